@@ -0,0 +1,35 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler, globals, hash, luminance}
+
+// получение значений из игрового движка
+struct AnalogStaticSettings {
+    intensity: f32,
+}
+@group(0) @binding(2) var<uniform> settings: AnalogStaticSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let strength: f32 = clamp(settings.intensity, 0.0, 1.0);
+    let frame: f32 = f32(globals.frame_count % 4096u);
+    let row: f32 = floor(in.position.y);
+
+    // срыв синхронизации отдельных строк
+    let tear_chance: f32 = hash(vec2<f32>(floor(row / 4.0), frame));
+    let tear: f32 = select(0.0, (hash(vec2<f32>(row, frame + 3.0)) - 0.5) * 0.1 * strength, tear_chance < strength * 0.3);
+
+    // сэмплирование со смещением строки
+    let uv: vec2<f32> = vec2<f32>(fract(in.uv.x + tear), in.uv.y);
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, uv);
+
+    // при слабом сигнале цвет пропадает раньше яркости
+    let gray: vec3<f32> = vec3<f32>(luminance(color.rgb));
+    let signal: vec3<f32> = mix(color.rgb, gray, smoothstep(0.2, 0.6, strength));
+
+    // снег и полное пропадание сигнала на некоторых кадрах
+    let snow: f32 = hash(in.position.xy + frame * 7.0);
+    let dropout: f32 = select(0.0, 1.0, hash(vec2<f32>(frame, 1.0)) < (strength - 0.7) * 2.0);
+    let snow_amount: f32 = clamp(strength * strength + dropout, 0.0, 1.0);
+
+    return vec4<f32>(mix(signal, vec3<f32>(snow), snow_amount), color.a);
+}
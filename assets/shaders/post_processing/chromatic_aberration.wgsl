@@ -0,0 +1,24 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler}
+
+// получение значений из игрового движка
+struct ChromaticAberrationSettings {
+    intensity: f32,
+    offset: f32,
+}
+@group(0) @binding(2) var<uniform> settings: ChromaticAberrationSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // сэмплирование
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+
+    // красный и синий каналы увеличиваются линзой по-разному
+    let from_center: vec2<f32> = in.uv - vec2<f32>(0.5, 0.5);
+    let offset: f32 = settings.offset * settings.intensity;
+    let red: f32 = textureSample(screen_texture, texture_sampler, vec2<f32>(0.5, 0.5) + from_center * (1.0 + offset)).r;
+    let blue: f32 = textureSample(screen_texture, texture_sampler, vec2<f32>(0.5, 0.5) + from_center * (1.0 - offset)).b;
+
+    return vec4<f32>(red, color.g, blue, color.a);
+}
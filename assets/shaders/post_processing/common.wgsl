@@ -0,0 +1,23 @@
+#define_import_path supersonic::post_processing
+
+#import bevy_render::globals::Globals
+
+// общие для всех эффектов значения из игрового движка
+// настройки эффекта находятся в @binding(2) и объявляются в шейдере самого эффекта
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+@group(0) @binding(3) var<uniform> globals: Globals;
+// предыдущий кадр, используется только эффектами, которые хранят историю
+@group(0) @binding(4) var history_texture: texture_2d<f32>;
+
+// яркость цвета с учетом восприятия глаза
+fn luminance(color: vec3<f32>) -> f32 {
+    return 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+}
+
+// псевдослучайное число в диапазоне [0, 1)
+fn hash(p: vec2<f32>) -> f32 {
+    let p3: vec3<f32> = fract(vec3<f32>(p.x, p.y, p.x) * 0.1031);
+    let d: f32 = dot(p3, vec3<f32>(p3.y, p3.z, p3.x) + 33.33);
+    return fract((p3.x + p3.y + d) * (p3.z + d));
+}
@@ -0,0 +1,29 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler}
+
+// получение значений из игрового движка
+struct CompressionSettings {
+    intensity: f32,
+    block_size: f32,
+    levels: f32,
+}
+@group(0) @binding(2) var<uniform> settings: CompressionSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let dimensions: vec2<f32> = vec2<f32>(textureDimensions(screen_texture));
+    let block_size: f32 = max(settings.block_size, 1.0);
+
+    // сэмплирование пикселя и центра его блока
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+    let block_center: vec2<f32> = (floor(in.position.xy / block_size) + 0.5) * block_size / dimensions;
+    let block_color: vec3<f32> = textureSample(screen_texture, texture_sampler, block_center).rgb;
+
+    // потеря деталей внутри блока и квантование цвета
+    let levels: f32 = max(settings.levels, 2.0);
+    let smoothed: vec3<f32> = mix(color.rgb, block_color, settings.intensity);
+    let quantized: vec3<f32> = floor(smoothed * levels + 0.5) / levels;
+
+    return vec4<f32>(mix(color.rgb, quantized, min(settings.intensity * 2.0, 1.0)), color.a);
+}
@@ -0,0 +1,29 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler}
+
+// получение значений из игрового движка
+struct LensDistortionSettings {
+    intensity: f32,
+    k1: f32,
+    k2: f32,
+}
+@group(0) @binding(2) var<uniform> settings: LensDistortionSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // координаты относительно оптической оси
+    let p: vec2<f32> = (in.uv - vec2<f32>(0.5, 0.5)) * 2.0;
+    let r2: f32 = dot(p, p);
+
+    // радиальная дисторсия Брауна-Конради
+    let factor: f32 = 1.0 + settings.k1 * r2 + settings.k2 * r2 * r2;
+    let distorted: vec2<f32> = mix(p, p * factor, settings.intensity);
+    let uv: vec2<f32> = distorted * 0.5 + vec2<f32>(0.5, 0.5);
+
+    // сэмплирование, за пределами кадра изображения нет
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, clamp(uv, vec2<f32>(0.0), vec2<f32>(1.0)));
+    let inside: bool = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+
+    return select(vec4<f32>(0.0, 0.0, 0.0, color.a), color, inside);
+}
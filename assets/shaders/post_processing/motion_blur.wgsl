@@ -0,0 +1,30 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler}
+
+// получение значений из игрового движка
+struct MotionBlurSettings {
+    intensity: f32,
+    samples: u32,
+    direction: vec2<f32>,
+}
+@group(0) @binding(2) var<uniform> settings: MotionBlurSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // сэмплирование
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+
+    // усреднение сэмплов вдоль направления движения за время экспозиции
+    let samples: u32 = max(settings.samples, 1u);
+    var sum: vec3<f32> = vec3<f32>(0.0);
+    for (var i: u32 = 0u; i < samples; i = i + 1u) {
+        let t: f32 = (f32(i) + 0.5) / f32(samples) - 0.5;
+        let uv: vec2<f32> = clamp(in.uv + settings.direction * t, vec2<f32>(0.0), vec2<f32>(1.0));
+        sum = sum + textureSample(screen_texture, texture_sampler, uv).rgb;
+    }
+    let blurred: vec3<f32> = sum / f32(samples);
+
+    // смешивание цветов с учетом интенсивности
+    return vec4<f32>(mix(color.rgb, blurred, settings.intensity), color.a);
+}
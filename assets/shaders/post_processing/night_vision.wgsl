@@ -0,0 +1,49 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler, globals, hash, luminance}
+
+// получение значений из игрового движка
+struct NightVisionSettings {
+    intensity: f32,
+    gain: f32,
+    bloom: f32,
+    scintillation: f32,
+    phosphor: vec3<f32>,
+}
+@group(0) @binding(2) var<uniform> settings: NightVisionSettings;
+
+// радиус свечения вокруг источников света в пикселях
+const BLOOM_RADIUS: f32 = 8.0;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let texel: vec2<f32> = 1.0 / vec2<f32>(textureDimensions(screen_texture));
+
+    // сэмплирование и усиление света
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+    let amplified: f32 = luminance(color.rgb) * settings.gain;
+
+    // свечение вокруг пересвеченных областей
+    var glow: f32 = 0.0;
+    for (var i: i32 = 0; i < 8; i = i + 1) {
+        let angle: f32 = f32(i) * 0.7853982;
+        let direction: vec2<f32> = vec2<f32>(cos(angle), sin(angle)) * texel;
+        let near: f32 = luminance(textureSample(screen_texture, texture_sampler, in.uv + direction * BLOOM_RADIUS * 0.5).rgb);
+        let far: f32 = luminance(textureSample(screen_texture, texture_sampler, in.uv + direction * BLOOM_RADIUS).rgb);
+        glow = glow + max(near * settings.gain - 1.0, 0.0) + 0.5 * max(far * settings.gain - 1.0, 0.0);
+    }
+    glow = glow / 12.0 * settings.bloom;
+
+    // мерцающие вспышки и зернистость усилителя
+    let pixel: vec2<f32> = floor(in.position.xy);
+    let frame: f32 = f32(globals.frame_count % 1024u);
+    let grain: f32 = (hash(pixel + frame * 5.0) - 0.5) * settings.scintillation * 0.3;
+    let sparkle: f32 = select(0.0, 1.0, hash(pixel * 1.7 + frame * 3.0) > 1.0 - settings.scintillation * 0.002);
+
+    // насыщение усилителя и цвет люминофора
+    let signal: f32 = 1.0 - exp(-max(amplified + glow + grain, 0.0)) + sparkle;
+    let result: vec3<f32> = settings.phosphor * min(signal, 1.0);
+
+    // смешивание цветов с учетом интенсивности
+    return vec4<f32>(mix(color.rgb, result, settings.intensity), color.a);
+}
@@ -0,0 +1,25 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler, globals, hash}
+
+// получение значений из игрового движка
+struct NoiseSettings {
+    intensity: f32,
+    grain_size: f32,
+}
+@group(0) @binding(2) var<uniform> settings: NoiseSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // сэмплирование
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+
+    // зерно шума, которое меняется каждый кадр
+    let pixel: vec2<f32> = floor(in.position.xy / max(settings.grain_size, 1.0));
+    let grain: f32 = hash(pixel + f32(globals.frame_count % 1024u) * 17.0) - 0.5;
+
+    // добавление шума с учетом интенсивности
+    let result: vec3<f32> = color.rgb + grain * settings.intensity;
+
+    return vec4<f32>(max(result, vec3<f32>(0.0)), color.a);
+}
@@ -0,0 +1,57 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler, luminance}
+
+// получение значений из игрового движка
+struct PaletteSettings {
+    intensity: f32,
+    palette: u32,
+}
+@group(0) @binding(2) var<uniform> settings: PaletteSettings;
+
+// градиент по трем опорным цветам
+fn gradient(t: f32, a: vec3<f32>, b: vec3<f32>, c: vec3<f32>) -> vec3<f32> {
+    if t < 0.5 {
+        return mix(a, b, t * 2.0);
+    }
+    return mix(b, c, (t - 0.5) * 2.0);
+}
+
+// отображение яркости в цвет палитры
+fn map_palette(t: f32) -> vec3<f32> {
+    switch settings.palette {
+        // black hot
+        case 1u: {
+            return vec3<f32>(1.0 - t);
+        }
+        // ironbow
+        case 2u: {
+            let cold: vec3<f32> = gradient(t * 2.0, vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(0.2, 0.0, 0.5), vec3<f32>(0.8, 0.0, 0.4));
+            let hot: vec3<f32> = gradient(t * 2.0 - 1.0, vec3<f32>(0.8, 0.0, 0.4), vec3<f32>(1.0, 0.5, 0.0), vec3<f32>(1.0, 1.0, 0.8));
+            return select(hot, cold, t < 0.5);
+        }
+        // rainbow
+        case 3u: {
+            let cold: vec3<f32> = gradient(t * 2.0, vec3<f32>(0.0, 0.0, 1.0), vec3<f32>(0.0, 1.0, 1.0), vec3<f32>(0.0, 1.0, 0.0));
+            let hot: vec3<f32> = gradient(t * 2.0 - 1.0, vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0));
+            return select(hot, cold, t < 0.5);
+        }
+        // white hot
+        default: {
+            return vec3<f32>(t);
+        }
+    }
+}
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // сэмплирование
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+
+    // отображение яркости в палитру
+    let t: f32 = clamp(luminance(color.rgb), 0.0, 1.0);
+    let mapped: vec4<f32> = vec4<f32>(map_palette(t), color.a);
+
+    // смешивание цветов с учетом интенсивности
+    return mix(color, mapped, settings.intensity);
+}
@@ -0,0 +1,19 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler}
+
+// получение значений из игрового движка
+struct RollingShutterSettings {
+    intensity: f32,
+    skew: vec2<f32>,
+}
+@group(0) @binding(2) var<uniform> settings: RollingShutterSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // строки считываются сверху вниз, поэтому смещение растет к нижней части кадра
+    let offset: vec2<f32> = settings.skew * (in.uv.y - 0.5) * settings.intensity;
+
+    // сэмплирование со смещением
+    return textureSample(screen_texture, texture_sampler, clamp(in.uv + offset, vec2<f32>(0.0), vec2<f32>(1.0)));
+}
@@ -0,0 +1,37 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler, globals, hash}
+
+// получение значений из игрового движка
+struct SensorNoiseSettings {
+    intensity: f32,
+    shot_noise: f32,
+    read_noise: f32,
+}
+@group(0) @binding(2) var<uniform> settings: SensorNoiseSettings;
+
+// нормально распределенное случайное число (преобразование Бокса-Мюллера)
+fn gaussian(p: vec2<f32>) -> f32 {
+    let u1: f32 = max(hash(p), 0.0001);
+    let u2: f32 = hash(p + 71.3);
+    return sqrt(-2.0 * log(u1)) * cos(6.2831853 * u2);
+}
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // сэмплирование
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+
+    // дробовой шум растет с корнем из сигнала, шум считывания постоянен
+    let signal: vec3<f32> = clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    let sigma: vec3<f32> = sqrt(settings.shot_noise * settings.shot_noise * signal + settings.read_noise * settings.read_noise);
+
+    // шум каждого канала независим и меняется каждый кадр
+    let seed: vec2<f32> = in.position.xy + f32(globals.frame_count % 1024u) * 13.0;
+    let noise: vec3<f32> = vec3<f32>(gaussian(seed), gaussian(seed + 19.1), gaussian(seed + 37.7)) * sigma;
+
+    // добавление шума с учетом интенсивности
+    let result: vec3<f32> = color.rgb + noise * settings.intensity;
+
+    return vec4<f32>(max(result, vec3<f32>(0.0)), color.a);
+}
@@ -0,0 +1,28 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler}
+
+// получение значений из игрового движка
+struct SharpenSettings {
+    intensity: f32,
+}
+@group(0) @binding(2) var<uniform> settings: SharpenSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // размер одного пикселя в uv координатах
+    let texel: vec2<f32> = 1.0 / vec2<f32>(textureDimensions(screen_texture));
+
+    // сэмплирование пикселя и его соседей
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+    let up: vec3<f32> = textureSample(screen_texture, texture_sampler, in.uv + vec2<f32>(0.0, -texel.y)).rgb;
+    let down: vec3<f32> = textureSample(screen_texture, texture_sampler, in.uv + vec2<f32>(0.0, texel.y)).rgb;
+    let left: vec3<f32> = textureSample(screen_texture, texture_sampler, in.uv + vec2<f32>(-texel.x, 0.0)).rgb;
+    let right: vec3<f32> = textureSample(screen_texture, texture_sampler, in.uv + vec2<f32>(texel.x, 0.0)).rgb;
+
+    // усиление разницы между пикселем и его соседями
+    let edges: vec3<f32> = 4.0 * color.rgb - up - down - left - right;
+    let result: vec3<f32> = color.rgb + edges * settings.intensity;
+
+    return vec4<f32>(max(result, vec3<f32>(0.0)), color.a);
+}
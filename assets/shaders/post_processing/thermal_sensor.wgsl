@@ -0,0 +1,59 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler, history_texture, globals, hash, luminance}
+
+// получение значений из игрового движка
+struct ThermalSensorSettings {
+    intensity: f32,
+    fixed_pattern_noise: f32,
+    column_striping: f32,
+    temporal_noise: f32,
+    vignetting: f32,
+    halo: f32,
+    drift: f32,
+    frozen: u32,
+}
+@group(0) @binding(2) var<uniform> settings: ThermalSensorSettings;
+
+// радиус ореола вокруг горячих объектов в пикселях
+const HALO_RADIUS: f32 = 6.0;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let texel: vec2<f32> = 1.0 / vec2<f32>(textureDimensions(screen_texture));
+
+    // сэмплирование текущего и предыдущего кадров
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+    let previous: vec4<f32> = textureSample(history_texture, texture_sampler, in.uv);
+
+    // средняя яркость вокруг пикселя
+    var around: f32 = 0.0;
+    for (var i: i32 = 0; i < 8; i = i + 1) {
+        let angle: f32 = f32(i) * 0.7853982;
+        let offset: vec2<f32> = vec2<f32>(cos(angle), sin(angle)) * HALO_RADIUS * texel;
+        around = around + luminance(textureSample(screen_texture, texture_sampler, in.uv + offset).rgb);
+    }
+    around = around / 8.0;
+
+    // темный ореол вокруг объектов, которые горячее пикселя
+    let halo: f32 = max(around - luminance(color.rgb), 0.0) * settings.halo;
+
+    // неоднородности матрицы растут с момента последней калибровки
+    let pixel: vec2<f32> = floor(in.position.xy);
+    let non_uniformity: f32 = 0.3 + settings.drift;
+    let fixed_pattern: f32 = (hash(pixel) - 0.5) * settings.fixed_pattern_noise * non_uniformity;
+    let column: f32 = (hash(vec2<f32>(pixel.x, 7.0)) - 0.5) * settings.column_striping * non_uniformity;
+
+    // временной шум меняется каждый кадр
+    let temporal: f32 = (hash(pixel + f32(globals.frame_count % 1024u) * 11.0) - 0.5) * settings.temporal_noise;
+
+    // радиальное падение сигнала
+    let from_center: vec2<f32> = in.uv - vec2<f32>(0.5, 0.5);
+    let vignette: f32 = 1.0 - settings.vignetting * dot(from_center, from_center) * 2.0;
+
+    let sensor: vec3<f32> = max(color.rgb * vignette - halo + fixed_pattern + column + temporal, vec3<f32>(0.0));
+    let result: vec4<f32> = vec4<f32>(mix(color.rgb, sensor, settings.intensity), color.a);
+
+    // во время калибровки затвор закрыт и изображение замирает
+    return select(result, previous, settings.frozen != 0u);
+}
@@ -0,0 +1,26 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import supersonic::post_processing::{screen_texture, texture_sampler}
+
+// получение значений из игрового движка
+struct VignetteSettings {
+    intensity: f32,
+    radius: f32,
+    smoothness: f32,
+}
+@group(0) @binding(2) var<uniform> settings: VignetteSettings;
+
+// фрагментный шейдер
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // сэмплирование
+    let color: vec4<f32> = textureSample(screen_texture, texture_sampler, in.uv);
+
+    // затемнение в зависимости от расстояния до центра экрана
+    let distance: f32 = length(in.uv - vec2<f32>(0.5, 0.5));
+    let vignette: f32 = smoothstep(settings.radius + settings.smoothness, settings.radius, distance);
+
+    // смешивание цветов с учетом интенсивности
+    let result: vec3<f32> = color.rgb * mix(1.0, vignette, settings.intensity);
+
+    return vec4<f32>(result, color.a);
+}
@@ -0,0 +1,55 @@
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+
+// поля расширения стандартного материала для рельефа
+@group(2) @binding(100) var<uniform> layer_colors: array<vec4<f32>, 4>;
+@group(2) @binding(101) var<uniform> tiling: f32;
+@group(2) @binding(102) var sand_texture: texture_2d<f32>;
+@group(2) @binding(103) var layer_sampler: sampler;
+@group(2) @binding(104) var grass_texture: texture_2d<f32>;
+@group(2) @binding(105) var rock_texture: texture_2d<f32>;
+@group(2) @binding(106) var snow_texture: texture_2d<f32>;
+
+// фрагментный шейдер материала
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+
+    // получение объекта
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    var out: FragmentOutput;
+
+    // веса слоев хранятся в цвете вершин: песок, трава, скала, снег
+#ifdef VERTEX_COLORS
+    let weights: vec4<f32> = in.color;
+#else
+    let weights: vec4<f32> = vec4<f32>(0.0, 1.0, 0.0, 0.0);
+#endif
+
+    // uv сетки задаются в метрах
+#ifdef VERTEX_UVS
+    let uv: vec2<f32> = in.uv * tiling;
+#else
+    let uv: vec2<f32> = in.world_position.xz * tiling;
+#endif
+
+    // смешивание слоев
+    let sand: vec4<f32> = layer_colors[0] * textureSample(sand_texture, layer_sampler, uv);
+    let grass: vec4<f32> = layer_colors[1] * textureSample(grass_texture, layer_sampler, uv);
+    let rock: vec4<f32> = layer_colors[2] * textureSample(rock_texture, layer_sampler, uv);
+    let snow: vec4<f32> = layer_colors[3] * textureSample(snow_texture, layer_sampler, uv);
+
+    let color: vec4<f32> = sand * weights.x + grass * weights.y + rock * weights.z + snow * weights.w;
+    pbr_input.material.base_color = vec4<f32>(color.rgb, 1.0);
+
+    // добавление эффекта освещенности, тумана и прочей обработки
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+
+    return out;
+}
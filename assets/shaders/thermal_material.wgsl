@@ -3,12 +3,14 @@
     pbr_functions::alpha_discard,
     forward_io::{VertexOutput, FragmentOutput},
     pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+    mesh_view_bindings::view,
 }
 
 // поля температурного расширения стандартного материала
 @group(2) @binding(100) var<uniform> temperature: f32;
 @group(2) @binding(101) var<uniform> intensity: f32;
 @group(2) @binding(103) var<uniform> is_infrared_mode_active: u32;
+@group(2) @binding(104) var<uniform> ir_extinction: f32;
 
 // фрагментный шейдер материала
 @fragment
@@ -31,8 +33,15 @@ fn fragment(
         var luminance: f32 = 0.2126 * out.color.r + 0.7152 * out.color.g + 0.0722 * out.color.b;
         var grayscale_color: vec4<f32> = vec4<f32>(luminance, luminance, luminance, out.color.a) * 0.2;
 
+        // ослабление инфракрасного излучения атмосферой с расстоянием
+        let distance: f32 = length(in.world_position.xyz - view.world_position.xyz);
+        let transmittance: f32 = exp(-ir_extinction * distance);
+
         // смешивание серого с определенной интенсивностью и с учетом температуры
-        out.color = mix(grayscale_color, grayscale_color * temperature, intensity);
+        out.color = mix(grayscale_color, grayscale_color * temperature, intensity * transmittance);
+    } else {
+        // туман и прочая обработка видимого изображения
+        out.color = main_pass_post_lighting_processing(pbr_input, out.color);
     }
 
     return out;
@@ -0,0 +1,574 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::{prelude::*, render::view::RenderLayers};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    fleet::Drone,
+    materials::{SolarHeating, Temperature, Thermal, ThermalMaterial, ThermalMaterialExtension},
+    player::Player,
+    sky::{SkyLighting, TimeOfDay},
+    terrain::Terrain,
+    world::generator::{EnvironmentSettings, GeneratedObject, GeneratorRng},
+};
+
+/// Plugin for the agents: people, animals and cars, which move through the world.
+pub struct AgentsPlugin;
+
+impl Plugin for AgentsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<AgentPopulation>()
+            .add_systems(Update, (populate_agents, move_agents, animate_agents).chain());
+    }
+}
+
+// resources
+/// Describes how many agents of each kind live in the world.
+///
+/// The same `seed` always gives the same agents. Changing the resource respawns them.
+#[derive(Resource, Debug, Clone)]
+pub struct AgentPopulation {
+    pub seed: u64,
+    pub pedestrians: u32,
+    pub animals: u32,
+    pub cars: u32,
+}
+
+impl Default for AgentPopulation {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            pedestrians: 8,
+            animals: 5,
+            cars: 4,
+        }
+    }
+}
+
+// components
+/// Kind of the agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentKind {
+    Pedestrian,
+    Animal,
+    Car,
+}
+
+/// What the agent does when nothing disturbs it.
+///
+/// `Wander` walks to random points within `radius` meters around `center`.
+/// `FollowPath` walks through the `waypoints` one by one and starts over if the path is `looped`.
+/// `FollowRoad` moves along the roads of the `EnvironmentSettings` and turns at random intersections.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentBehaviour {
+    Wander {
+        center: Vec2,
+        radius: f32,
+    },
+    FollowPath {
+        waypoints: Vec<Vec2>,
+        looped: bool,
+    },
+    FollowRoad,
+}
+
+/// What the agent does when a drone comes closer than `Agent::alert_distance`.
+///
+/// `Flee` runs away from the drone, `Hide` runs to the nearest tree and stays under it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DroneReaction {
+    #[default]
+    Ignore,
+    Flee,
+    Hide,
+}
+
+/// Describes an agent.
+///
+/// Speeds are in meters per second. After the drone goes away, the agent stays alert for `calm_down` seconds.
+#[derive(Component)]
+pub struct Agent {
+    pub kind: AgentKind,
+    pub behaviour: AgentBehaviour,
+    pub reaction: DroneReaction,
+    pub walk_speed: f32,
+    pub run_speed: f32,
+    pub alert_distance: f32,
+    pub calm_down: f32,
+    /// Seconds left until the agent calms down.
+    pub alarm: f32,
+    /// Point the agent goes to.
+    pub target: Option<Vec2>,
+    /// Direction along the road.
+    pub heading: Vec2,
+    /// Index of the next waypoint of the path.
+    pub waypoint: usize,
+    /// Meters travelled, they drive the animation.
+    pub travelled: f32,
+    rng: GeneratorRng,
+}
+
+impl Agent {
+    /// Creates agent with the default speeds and reaction of its kind.
+    pub fn new(kind: AgentKind, behaviour: AgentBehaviour, seed: u64) -> Self {
+        let (walk_speed, run_speed, reaction) = match kind {
+            AgentKind::Pedestrian => (1.4, 4.0, DroneReaction::Hide),
+            AgentKind::Animal => (1.0, 7.0, DroneReaction::Flee),
+            AgentKind::Car => (9.0, 9.0, DroneReaction::Ignore),
+        };
+
+        Self {
+            kind,
+            behaviour,
+            reaction,
+            walk_speed,
+            run_speed,
+            alert_distance: 30.0,
+            calm_down: 15.0,
+            alarm: 0.0,
+            target: None,
+            heading: Vec2::ZERO,
+            waypoint: 0,
+            travelled: 0.0,
+            rng: GeneratorRng::new(seed),
+        }
+    }
+
+    /// Sets the reaction to the drone.
+    pub fn with_reaction(mut self, reaction: DroneReaction) -> Self {
+        self.reaction = reaction;
+        self
+    }
+
+    /// Whether the agent is disturbed by a drone.
+    pub fn is_alarmed(&self) -> bool {
+        self.alarm > 0.0
+    }
+}
+
+/// Kind of the agent body part. `side` is -1.0 for the left part and 1.0 for the right one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BodyPartKind {
+    Head,
+    Torso,
+    Arm { side: f32 },
+    Leg { side: f32 },
+    Body,
+    Engine,
+    Exhaust,
+    Wheel,
+}
+
+/// Describes a body part of the agent. `rest` is its transform, when the agent stands still.
+#[derive(Component)]
+pub struct BodyPart {
+    pub kind: BodyPartKind,
+    pub rest: Transform,
+}
+
+// functions
+/// Spawns an agent with its body parts at the given point of the XZ plane.
+///
+/// Every body part is `Thermal` and has its own temperature: heads are warmer than clothes,
+/// exhausts are hotter than engines.
+pub fn spawn_agent(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    thermal_materials: &mut Assets<ThermalMaterial>,
+    agent: Agent,
+    position: Vec3,
+    air_temperature: f32,
+) -> Entity {
+    // (kind, mesh, transform, color, heating)
+    let parts: Vec<(BodyPartKind, Mesh, Transform, Color, SolarHeating)> = match agent.kind {
+        AgentKind::Pedestrian => {
+            let skin = SolarHeating { solar_gain: 2.0, internal_heat: 22.0, time_constant: 60.0 };
+            let clothes = || SolarHeating { solar_gain: 6.0, internal_heat: 14.0, time_constant: 60.0 };
+
+            let mut parts = vec![
+                (BodyPartKind::Head, Sphere::new(0.12).into(), Transform::from_xyz(0.0, 1.62, 0.0), Color::BISQUE, skin),
+                (BodyPartKind::Torso, Cuboid::new(0.4, 0.6, 0.25).into(), Transform::from_xyz(0.0, 1.15, 0.0), Color::MIDNIGHT_BLUE, clothes()),
+            ];
+
+            for side in [-1.0, 1.0] {
+                // limbs swing around their upper ends
+                let arm = Mesh::from(Cuboid::new(0.1, 0.6, 0.1)).translated_by(Vec3::new(0.0, -0.3, 0.0));
+                let leg = Mesh::from(Cuboid::new(0.14, 0.85, 0.14)).translated_by(Vec3::new(0.0, -0.425, 0.0));
+
+                parts.push((BodyPartKind::Arm { side }, arm, Transform::from_xyz(0.27 * side, 1.42, 0.0), Color::MIDNIGHT_BLUE, clothes()));
+                parts.push((BodyPartKind::Leg { side }, leg, Transform::from_xyz(0.1 * side, 0.85, 0.0), Color::DARK_GRAY, clothes()));
+            }
+
+            parts
+        },
+        AgentKind::Animal => {
+            let fur = || SolarHeating { solar_gain: 4.0, internal_heat: 16.0, time_constant: 120.0 };
+
+            let mut parts = vec![
+                (BodyPartKind::Head, Sphere::new(0.15).into(), Transform::from_xyz(0.0, 0.85, -0.55), Color::rgb(0.5, 0.35, 0.2), SolarHeating { internal_heat: 22.0, ..fur() }),
+                (BodyPartKind::Torso, Cuboid::new(0.35, 0.35, 0.9).into(), Transform::from_xyz(0.0, 0.65, 0.0), Color::rgb(0.5, 0.35, 0.2), fur()),
+            ];
+
+            for (side, end) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+                let leg = Mesh::from(Cuboid::new(0.08, 0.5, 0.08)).translated_by(Vec3::new(0.0, -0.25, 0.0));
+
+                // diagonal legs move together
+                parts.push((BodyPartKind::Leg { side: side * end }, leg, Transform::from_xyz(0.12 * side, 0.5, 0.35 * end), Color::rgb(0.4, 0.28, 0.15), fur()));
+            }
+
+            parts
+        },
+        AgentKind::Car => {
+            let metal = || SolarHeating { solar_gain: 20.0, internal_heat: 0.0, time_constant: 1800.0 };
+            let tire = || SolarHeating { solar_gain: 10.0, internal_heat: 15.0, time_constant: 300.0 };
+
+            let mut parts = vec![
+                (BodyPartKind::Body, Cuboid::new(1.8, 0.8, 4.2).into(), Transform::from_xyz(0.0, 0.75, 0.0), Color::SILVER, metal()),
+                (BodyPartKind::Body, Cuboid::new(1.6, 0.6, 2.2).into(), Transform::from_xyz(0.0, 1.45, 0.3), Color::SILVER, metal()),
+                (BodyPartKind::Engine, Cuboid::new(1.7, 0.05, 1.0).into(), Transform::from_xyz(0.0, 1.16, -1.55), Color::SILVER, SolarHeating { internal_heat: 45.0, ..metal() }),
+                (
+                    BodyPartKind::Exhaust,
+                    Cylinder::new(0.05, 0.3).into(),
+                    Transform::from_xyz(0.5, 0.4, 2.15).with_rotation(Quat::from_rotation_x(FRAC_PI_2)),
+                    Color::DARK_GRAY,
+                    SolarHeating { solar_gain: 0.0, internal_heat: 110.0, time_constant: 60.0 },
+                ),
+            ];
+
+            for (x, z) in [(-0.9, -1.35), (0.9, -1.35), (-0.9, 1.35), (0.9, 1.35)] {
+                let transform = Transform::from_xyz(x, 0.35, z).with_rotation(Quat::from_rotation_z(FRAC_PI_2));
+                parts.push((BodyPartKind::Wheel, Cylinder::new(0.35, 0.25).into(), transform, Color::BLACK, tire()));
+            }
+
+            parts
+        },
+    };
+
+    let collider = match agent.kind {
+        AgentKind::Pedestrian => Collider::compound(vec![(Vec3::Y * 0.9, Quat::IDENTITY, Collider::capsule_y(0.6, 0.25))]),
+        AgentKind::Animal => Collider::compound(vec![(Vec3::Y * 0.55, Quat::IDENTITY, Collider::cuboid(0.2, 0.35, 0.6))]),
+        AgentKind::Car => Collider::compound(vec![(Vec3::Y * 0.9, Quat::IDENTITY, Collider::cuboid(0.9, 0.6, 2.1))]),
+    };
+
+    let name = match agent.kind {
+        AgentKind::Pedestrian => "Pedestrian",
+        AgentKind::Animal => "Animal",
+        AgentKind::Car => "Car",
+    };
+
+    let children: Vec<Entity> = parts
+        .into_iter()
+        .map(|(kind, mesh, transform, color, heating)| {
+            let temperature = air_temperature + heating.internal_heat;
+
+            commands
+                .spawn((
+                    MaterialMeshBundle {
+                        mesh: meshes.add(mesh),
+                        material: thermal_materials.add(ThermalMaterial {
+                            base: StandardMaterial {
+                                base_color: color,
+                                ..default()
+                            },
+                            extension: ThermalMaterialExtension {
+                                temperature,
+                                intensity: 1.0,
+                                ..default()
+                            },
+                        }),
+                        transform,
+                        ..default()
+                    },
+                    BodyPart { kind, rest: transform },
+                    Thermal,
+                    Temperature(temperature),
+                    heating,
+                    RenderLayers::layer(1),
+                ))
+                .id()
+        })
+        .collect();
+
+    commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_translation(position)),
+            RigidBody::KinematicPositionBased,
+            collider,
+            agent,
+            Name::new(name),
+        ))
+        .push_children(&children)
+        .id()
+}
+
+/// Returns a point to the right of the `heading`, `offset` meters away from the `point`.
+fn keep_right(point: Vec2, heading: Vec2, offset: f32) -> Vec2 {
+    point + Vec2::new(-heading.y, heading.x) * offset
+}
+
+/// Returns index of the waypoint after the reached one.
+///
+/// Looped path starts over, otherwise the agent stays at the last waypoint.
+fn next_waypoint(agent: &Agent) -> usize {
+    match &agent.behaviour {
+        AgentBehaviour::FollowPath { waypoints, looped: true } => (agent.waypoint + 1) % waypoints.len().max(1),
+        AgentBehaviour::FollowPath { waypoints, looped: false } => {
+            (agent.waypoint + 1).min(waypoints.len().saturating_sub(1))
+        },
+        _ => agent.waypoint,
+    }
+}
+
+/// Returns the next intersection of the road after `from` in the direction close to `heading`.
+///
+/// Agents never turn back, unless it's a dead end.
+fn next_intersection(agent: &mut Agent, environment: &EnvironmentSettings, from: Vec2) -> (Vec2, Vec2) {
+    let spacing = environment.road_spacing();
+    let back = -agent.heading;
+
+    let mut options: Vec<Vec2> = [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y]
+        .into_iter()
+        .filter(|direction| *direction != back && (from + *direction * spacing).length() < environment.radius)
+        .collect();
+
+    if options.is_empty() {
+        options.push(back);
+    }
+
+    let direction = options[(agent.rng.next() * options.len() as f32) as usize % options.len()];
+
+    (from + direction * spacing, direction)
+}
+
+// systems
+/// System that spawns `AgentPopulation` when it changes.
+///
+/// Pedestrians walk around the town, animals wander in the countryside and cars drive along the roads.
+#[allow(clippy::too_many_arguments)]
+pub fn populate_agents(
+    mut commands: Commands,
+    population: Res<AgentPopulation>,
+    environment: Option<Res<EnvironmentSettings>>,
+    terrain: Option<Res<Terrain>>,
+    sky: Option<Res<SkyLighting>>,
+    time_of_day: Option<Res<TimeOfDay>>,
+    agents: Query<Entity, With<Agent>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut thermal_materials: ResMut<Assets<ThermalMaterial>>,
+) {
+    if !population.is_changed() {
+        return;
+    }
+
+    for entity in &agents {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let environment = environment.map_or_else(EnvironmentSettings::default, |environment| environment.clone());
+    let ground = |position: Vec2| {
+        Vec3::new(position.x, terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position)), position.y)
+    };
+    let air_temperature = match (sky, time_of_day) {
+        (Some(sky), Some(time_of_day)) => sky.air_temperature_at(time_of_day.hours),
+        _ => 15.0,
+    };
+
+    let mut rng = GeneratorRng::new(population.seed);
+    let mut random_point = |min: f32, max: f32| {
+        Vec2::from_angle(rng.range(0.0, TAU)) * rng.range(min, max)
+    };
+
+    let town = (environment.clearing_radius, environment.urban_radius.max(environment.clearing_radius));
+    let countryside = (environment.urban_radius, environment.radius.max(environment.urban_radius));
+
+    let mut seed = population.seed.wrapping_mul(1000);
+    let mut next_seed = || {
+        seed = seed.wrapping_add(1);
+        seed
+    };
+
+    for index in 0..population.pedestrians {
+        let position = random_point(town.0, town.1);
+        let behaviour = if index % 2 == 0 {
+            AgentBehaviour::Wander { center: position, radius: 30.0 }
+        } else {
+            AgentBehaviour::FollowRoad
+        };
+        let position = match behaviour {
+            AgentBehaviour::FollowRoad => environment.nearest_intersection(position),
+            _ => position,
+        };
+
+        let agent = Agent::new(AgentKind::Pedestrian, behaviour, next_seed());
+        spawn_agent(&mut commands, &mut meshes, &mut thermal_materials, agent, ground(position), air_temperature);
+    }
+
+    for _ in 0..population.animals {
+        let position = random_point(countryside.0, countryside.1);
+        let behaviour = AgentBehaviour::Wander { center: position, radius: 50.0 };
+
+        let agent = Agent::new(AgentKind::Animal, behaviour, next_seed());
+        spawn_agent(&mut commands, &mut meshes, &mut thermal_materials, agent, ground(position), air_temperature);
+    }
+
+    for _ in 0..population.cars {
+        let position = environment.nearest_intersection(random_point(town.0, countryside.1));
+
+        let agent = Agent::new(AgentKind::Car, AgentBehaviour::FollowRoad, next_seed());
+        spawn_agent(&mut commands, &mut meshes, &mut thermal_materials, agent, ground(position), air_temperature);
+    }
+}
+
+/// Query for the drones, which agents react to.
+type DroneTransformQuery<'w, 's> = Query<'w, 's, &'static Transform, (Or<(With<Player>, With<Drone>)>, Without<Agent>)>;
+
+/// System that moves agents according to their behaviours and reactions to the nearest drone.
+///
+/// Agents stick to the terrain and face the direction they move in.
+pub fn move_agents(
+    time: Res<Time>,
+    environment: Option<Res<EnvironmentSettings>>,
+    terrain: Option<Res<Terrain>>,
+    drones: DroneTransformQuery,
+    trees: Query<(&Transform, &GeneratedObject), Without<Agent>>,
+    mut agents: Query<(&mut Agent, &mut Transform)>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let environment = environment.map_or_else(EnvironmentSettings::default, |environment| environment.clone());
+
+    for (mut agent, mut transform) in &mut agents {
+        let position = transform.translation.xz();
+        let drone = drones
+            .iter()
+            .map(|drone| drone.translation)
+            .min_by(|a, b| a.distance_squared(transform.translation).total_cmp(&b.distance_squared(transform.translation)));
+
+        // reaction to the drone
+        let is_drone_close = drone.is_some_and(|drone| drone.distance(transform.translation) < agent.alert_distance);
+
+        if agent.reaction != DroneReaction::Ignore && is_drone_close {
+            if !agent.is_alarmed() {
+                agent.target = None;
+            }
+            agent.alarm = agent.calm_down;
+        } else if agent.is_alarmed() {
+            agent.alarm -= delta_seconds;
+
+            if !agent.is_alarmed() {
+                agent.target = None;
+            }
+        }
+
+        let hiding_place = if agent.is_alarmed() && agent.reaction == DroneReaction::Hide {
+            trees
+                .iter()
+                .filter(|(_, object)| **object == GeneratedObject::Tree)
+                .map(|(tree, _)| tree.translation.xz())
+                .min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+                .filter(|tree| tree.distance(position) < 60.0)
+        } else {
+            None
+        };
+
+        let (destination, speed) = match (agent.is_alarmed(), agent.reaction, drone) {
+            (true, DroneReaction::Hide, _) if hiding_place.is_some() => {
+                let tree = hiding_place.unwrap_or(position);
+                // stand next to the trunk, under the crown
+                (tree + (position - tree).normalize_or_zero() * 0.6, agent.run_speed)
+            },
+            (true, DroneReaction::Flee | DroneReaction::Hide, Some(drone)) => {
+                let away = (position - drone.xz()).try_normalize().unwrap_or(Vec2::X);
+                (position + away * 10.0, agent.run_speed)
+            },
+            _ => {
+                let target = match agent.target {
+                    Some(target) => target,
+                    None => {
+                        let target = match agent.behaviour.clone() {
+                            AgentBehaviour::Wander { center, radius } => {
+                                let angle = agent.rng.range(0.0, TAU);
+                                let distance = agent.rng.range(0.0, radius);
+                                center + Vec2::from_angle(angle) * distance
+                            },
+                            AgentBehaviour::FollowPath { waypoints, looped } => {
+                                if agent.waypoint >= waypoints.len() && looped {
+                                    agent.waypoint = 0;
+                                }
+                                waypoints.get(agent.waypoint).copied().unwrap_or(position)
+                            },
+                            AgentBehaviour::FollowRoad => {
+                                let from = environment.nearest_intersection(position);
+                                let (target, heading) = next_intersection(&mut agent, &environment, from);
+                                agent.heading = heading;
+                                target
+                            },
+                        };
+                        agent.target = Some(target);
+                        target
+                    },
+                };
+
+                let destination = match (&agent.behaviour, agent.kind) {
+                    (AgentBehaviour::FollowRoad, AgentKind::Car) => keep_right(target, agent.heading, environment.road_width / 4.0),
+                    (AgentBehaviour::FollowRoad, _) => keep_right(target, agent.heading, environment.road_width / 2.0 + 0.5),
+                    _ => target,
+                };
+
+                (destination, agent.walk_speed)
+            },
+        };
+
+        let offset = destination - position;
+        let step = speed * delta_seconds;
+
+        let moved = if offset.length() <= step.max(0.05) {
+            if !agent.is_alarmed() {
+                agent.target = None;
+                agent.waypoint = next_waypoint(&agent);
+            }
+            offset
+        } else {
+            offset.normalize() * step
+        };
+
+        let position = position + moved;
+        let height = terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position));
+
+        transform.translation = Vec3::new(position.x, height, position.y);
+        agent.travelled += moved.length();
+
+        if moved.length() > f32::EPSILON {
+            transform.look_to(Vec3::new(moved.x, 0.0, moved.y), Vec3::Y);
+        }
+    }
+}
+
+/// System that animates body parts of the moving agents.
+///
+/// Legs and arms swing with every step, wheels spin with every meter.
+pub fn animate_agents(
+    agents: Query<&Agent>,
+    mut parts: Query<(&BodyPart, &Parent, &mut Transform)>,
+) {
+    for (part, parent, mut transform) in &mut parts {
+        let Ok(agent) = agents.get(parent.get()) else {
+            continue;
+        };
+
+        let stride = match agent.kind {
+            AgentKind::Pedestrian => 1.4,
+            _ => 1.0,
+        };
+        let swing = (agent.travelled / stride * TAU).sin() * 0.5;
+
+        let rotation = match part.kind {
+            BodyPartKind::Leg { side } => Quat::from_rotation_x(swing * side),
+            BodyPartKind::Arm { side } => Quat::from_rotation_x(-swing * side),
+            BodyPartKind::Wheel => Quat::from_rotation_x(-agent.travelled / 0.35),
+            _ => Quat::IDENTITY,
+        };
+
+        transform.rotation = rotation * part.rest.rotation;
+    }
+}
@@ -0,0 +1,341 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    failsafe::{Failsafe, FailureInjection},
+    geofence::Geofence,
+    player::{Battery, Motors, Player, LANDED_HEIGHT},
+    terrain::Terrain,
+    ui::menu::AppState,
+};
+
+/// Plugin for arming and disarming the drone.
+///
+/// Pre-flight checks block arming, landed drones are disarmed automatically.
+pub struct ArmingPlugin;
+
+impl Plugin for ArmingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ArmingSettings>()
+            .add_event::<ArmingCommand>()
+            .add_systems(Startup, setup_arming_hud)
+            .add_systems(Update, (
+                arming_input.run_if(in_state(AppState::InFlight)),
+                process_arming_commands,
+                detect_landing,
+                update_arming_hud,
+            ).chain());
+    }
+}
+
+// resources
+/// Describes arming of the drone.
+///
+/// Press `switch_key` to arm or disarm, or hold `gesture_key` on the ground for `gesture_time` seconds.
+///
+/// The drone can't be armed with less than `min_battery` charge or tilted more than `max_tilt` radians.
+/// Armed drone, which didn't take off in `takeoff_timeout` seconds or stayed landed for `landed_time` seconds, is disarmed.
+#[derive(Resource, Debug, Clone)]
+pub struct ArmingSettings {
+    pub switch_key: KeyCode,
+    pub gesture_key: KeyCode,
+    pub gesture_time: f32,
+    pub min_battery: f32,
+    pub max_tilt: f32,
+    pub takeoff_timeout: f32,
+    pub landed_time: f32,
+}
+
+impl Default for ArmingSettings {
+    fn default() -> Self {
+        Self {
+            switch_key: KeyCode::KeyK,
+            gesture_key: KeyCode::ArrowDown,
+            gesture_time: 1.5,
+            min_battery: 0.3,
+            max_tilt: 10.0_f32.to_radians(),
+            takeoff_timeout: 10.0,
+            landed_time: 1.0,
+        }
+    }
+}
+
+/// Pre-flight check, which doesn't let the drone arm.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreflightFailure {
+    GpsUnhealthy,
+    RcLinkLost,
+    /// Battery charge from 0.0 to 1.0.
+    LowBattery(f32),
+    /// Tilt in radians.
+    NotLevel(f32),
+    OutsideGeofence,
+}
+
+impl fmt::Display for PreflightFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GpsUnhealthy => write!(f, "GPS is unhealthy"),
+            Self::RcLinkLost => write!(f, "RC link is lost"),
+            Self::LowBattery(charge) => write!(f, "battery is low: {:.0}%", charge * 100.0),
+            Self::NotLevel(tilt) => write!(f, "drone is not level: {:.0}°", tilt.to_degrees()),
+            Self::OutsideGeofence => write!(f, "drone is outside of the geofence"),
+        }
+    }
+}
+
+/// Runs pre-flight checks and returns the failed ones.
+pub fn preflight_checks(
+    settings: &ArmingSettings,
+    transform: &Transform,
+    battery: Option<&Battery>,
+    injection: Option<&FailureInjection>,
+    geofence: Option<&Geofence>,
+) -> Vec<PreflightFailure> {
+    let mut failures = Vec::new();
+
+    if injection.is_some_and(|injection| injection.gps_lost) {
+        failures.push(PreflightFailure::GpsUnhealthy);
+    }
+
+    if injection.is_some_and(|injection| injection.rc_link_lost) {
+        failures.push(PreflightFailure::RcLinkLost);
+    }
+
+    if let Some(charge) = battery.map(Battery::charge).filter(|charge| *charge < settings.min_battery) {
+        failures.push(PreflightFailure::LowBattery(charge));
+    }
+
+    let tilt = transform.up().angle_between(Vec3::Y);
+
+    if tilt > settings.max_tilt {
+        failures.push(PreflightFailure::NotLevel(tilt));
+    }
+
+    if geofence.is_some_and(|geofence| geofence.breach_at(transform.translation).is_some()) {
+        failures.push(PreflightFailure::OutsideGeofence);
+    }
+
+    failures
+}
+
+// events
+/// Command to arm or disarm the `Player`. Drones in the air are not disarmed.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmingCommand {
+    Arm,
+    Disarm,
+}
+
+// components
+/// Arming state of the drone besides its `Motors`.
+///
+/// `armed_for`, `landed_for` and `gesture` are in seconds.
+#[derive(Component, Debug, Default, Clone)]
+pub struct Arming {
+    pub is_airborne: bool,
+    pub armed_for: f32,
+    pub landed_for: f32,
+    pub gesture: f32,
+    /// Checks, which failed at the last arming.
+    pub failures: Vec<PreflightFailure>,
+}
+
+/// Describes the arming HUD text.
+#[derive(Component)]
+struct ArmingHudText;
+
+// systems
+/// Query for the `Player` drones, which are armed and disarmed.
+type ArmingDroneQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static mut Motors,
+        &'static mut Arming,
+        &'static Transform,
+        Option<&'static Battery>,
+        Option<&'static mut Failsafe>,
+    ),
+    With<Player>,
+>;
+
+/// System that turns the switch and the gesture into `ArmingCommand`s.
+fn arming_input(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<ArmingSettings>,
+    mut drones: Query<(&Motors, &mut Arming), With<Player>>,
+    mut commands: EventWriter<ArmingCommand>,
+) {
+    for (motors, mut arming) in &mut drones {
+        let command = if motors.is_armed { ArmingCommand::Disarm } else { ArmingCommand::Arm };
+
+        if keys.just_pressed(settings.switch_key) {
+            commands.send(command);
+        }
+
+        if !keys.pressed(settings.gesture_key) || arming.is_airborne {
+            arming.gesture = 0.0;
+            continue;
+        }
+
+        arming.gesture += time.delta_seconds();
+
+        if arming.gesture >= settings.gesture_time {
+            arming.gesture = f32::NEG_INFINITY;
+            commands.send(command);
+        }
+    }
+}
+
+/// System that arms and disarms the `Player`.
+///
+/// Drone is armed only if it passes pre-flight checks, arming resets its failsafe.
+pub fn process_arming_commands(
+    settings: Res<ArmingSettings>,
+    injection: Option<Res<FailureInjection>>,
+    geofence: Option<Res<Geofence>>,
+    mut commands: EventReader<ArmingCommand>,
+    mut drones: ArmingDroneQuery,
+) {
+    for command in commands.read() {
+        for (mut motors, mut arming, transform, battery, failsafe) in &mut drones {
+            match command {
+                ArmingCommand::Arm if !motors.is_armed => {
+                    arming.failures =
+                        preflight_checks(&settings, transform, battery, injection.as_deref(), geofence.as_deref());
+
+                    if !arming.failures.is_empty() {
+                        for failure in &arming.failures {
+                            warn!("can't arm: {failure}");
+                        }
+
+                        continue;
+                    }
+
+                    motors.is_armed = true;
+                    arming.armed_for = 0.0;
+                    arming.is_airborne = false;
+
+                    if let Some(mut failsafe) = failsafe {
+                        failsafe.reset();
+                    }
+                },
+                ArmingCommand::Disarm if motors.is_armed && !arming.is_airborne => {
+                    motors.is_armed = false;
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+/// System that detects takeoffs and landings of the armed drones.
+///
+/// The drone took off, when it climbed a meter above the ground.
+/// It landed, when it is on the ground and doesn't move.
+pub fn detect_landing(
+    time: Res<Time>,
+    settings: Res<ArmingSettings>,
+    terrain: Option<Res<Terrain>>,
+    mut drones: Query<(&mut Motors, &mut Arming, &Transform, Option<&Velocity>)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (mut motors, mut arming, transform, velocity) in &mut drones {
+        if !motors.is_armed {
+            arming.is_airborne = false;
+            continue;
+        }
+
+        arming.armed_for += delta;
+
+        let position = transform.translation;
+        let height = position.y - terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position.xz()));
+        let speed = velocity.map_or(0.0, |velocity| velocity.linvel.length());
+
+        if height > LANDED_HEIGHT + 1.0 {
+            arming.is_airborne = true;
+        }
+
+        if !arming.is_airborne {
+            if arming.armed_for > settings.takeoff_timeout {
+                info!("no takeoff, motors are disarmed");
+                motors.is_armed = false;
+            }
+
+            continue;
+        }
+
+        arming.landed_for = if height < LANDED_HEIGHT && speed < 0.5 { arming.landed_for + delta } else { 0.0 };
+
+        if arming.landed_for > settings.landed_time {
+            info!("landed, motors are disarmed");
+
+            motors.is_armed = false;
+            arming.is_airborne = false;
+            arming.landed_for = 0.0;
+        }
+    }
+}
+
+/// Arming HUD initialization.
+fn setup_arming_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(30.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                        font_size: 18.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                ArmingHudText,
+            ));
+        });
+}
+
+/// System that shows whether the drone is armed and why it can't be armed.
+fn update_arming_hud(
+    drones: Query<(&Motors, &Arming), With<Player>>,
+    mut query: Query<&mut Text, With<ArmingHudText>>,
+) {
+    let Ok((motors, arming)) = drones.get_single() else {
+        return;
+    };
+
+    let mut value = match (motors.is_armed, arming.is_airborne) {
+        (true, true) => "ARMED, in the air".to_owned(),
+        (true, false) => "ARMED".to_owned(),
+        (false, _) => "DISARMED".to_owned(),
+    };
+
+    if !motors.is_armed {
+        for failure in &arming.failures {
+            value.push_str(&format!("\n{failure}"));
+        }
+    }
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
@@ -0,0 +1,652 @@
+use std::{f32::consts::TAU, path::PathBuf};
+
+use bevy::{prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow};
+
+use crate::{
+    arming::Arming,
+    fleet::{Drone, DroneControl},
+    player::{FlightController, FlightSetpoint, Motors, Player, LANDED_HEIGHT},
+    terrain::Terrain,
+    ui::menu::AppState,
+};
+
+/// QGroundControl mission plans.
+pub mod plan;
+
+use plan::{read_qgc_plan_file, PlanError};
+
+/// Plugin for waypoint missions.
+///
+/// The autopilot flies the `Player` through the waypoints of the `WaypointMission` with its `FlightController`.
+/// Other drones fly their own `DroneMission`s.
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<WaypointMission>()
+            .init_resource::<AutopilotProgress>()
+            .add_event::<StartWaypointMission>()
+            .add_event::<StopWaypointMission>()
+            .add_event::<WaypointActionStarted>()
+            .add_systems(Startup, setup_autopilot_hud)
+            .add_systems(Update, (
+                plan_mission.run_if(in_state(AppState::InFlight)),
+                start_waypoint_mission,
+                navigate_waypoints,
+                navigate_drone_missions,
+                perform_waypoint_actions,
+                update_autopilot_hud,
+            ).chain());
+    }
+}
+
+// resources
+/// How the drone is turned while it flies to the waypoint. Yaw is the rotation around Y in radians.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum YawMode {
+    /// Look where the drone flies.
+    #[default]
+    FollowPath,
+    Fixed(f32),
+    /// Look at the point of the XZ plane.
+    PointOfInterest(Vec2),
+    /// Don't turn.
+    Keep,
+}
+
+/// Action, which is done when the drone reaches the waypoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaypointAction {
+    TakePhoto,
+    StartRecording,
+    StopRecording,
+    /// Fly `turns` circles of `radius` meters around the `center` of the XZ plane, looking at it.
+    Orbit { center: Vec2, radius: f32, turns: f32 },
+    /// Land under the waypoint, the mission ends there.
+    Land,
+}
+
+/// Describes a waypoint of the mission.
+///
+/// `position` is the point of the XZ plane, `altitude` is in meters above the launch pad.
+/// The drone flies to the waypoint at `speed` meters per second and stays there for `hold` seconds after its actions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    pub position: Vec2,
+    pub altitude: f32,
+    pub speed: f32,
+    pub hold: f32,
+    pub yaw: YawMode,
+    pub actions: Vec<WaypointAction>,
+}
+
+impl Waypoint {
+    /// Creates waypoint without actions, which is passed at 5 meters per second.
+    pub fn new(position: Vec2, altitude: f32) -> Self {
+        Self {
+            position,
+            altitude,
+            speed: 5.0,
+            hold: 0.0,
+            yaw: YawMode::default(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Sets the speed of the flight to the waypoint in meters per second.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Sets the seconds the drone stays at the waypoint after its actions.
+    pub fn with_hold(mut self, hold: f32) -> Self {
+        self.hold = hold;
+        self
+    }
+
+    /// Sets how the drone is turned on the way to the waypoint.
+    pub fn with_yaw(mut self, yaw: YawMode) -> Self {
+        self.yaw = yaw;
+        self
+    }
+
+    /// Adds the action, which is performed at the waypoint after the previous ones.
+    pub fn with_action(mut self, action: WaypointAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    /// Returns the point the drone flies to.
+    pub fn target(&self) -> Vec3 {
+        Vec3::new(self.position.x, self.altitude, self.position.y)
+    }
+
+    fn orbit(&self) -> Option<(Vec2, f32, f32)> {
+        self.actions.iter().find_map(|action| match action {
+            WaypointAction::Orbit { center, radius, turns } => Some((*center, *radius, *turns)),
+            _ => None,
+        })
+    }
+}
+
+/// Describes the waypoint mission.
+///
+/// A waypoint is reached, when the drone is closer than `acceptance_radius` meters to it.
+/// The drone lands at `landing_speed` meters per second.
+///
+/// Press `add_key` to add a waypoint at the `Player`, `remove_key` to remove the last one,
+/// `load_key` to load the mission from the QGroundControl `plan_path` file and `start_key` to start or stop the mission.
+#[derive(Resource, Debug, Clone)]
+pub struct WaypointMission {
+    pub waypoints: Vec<Waypoint>,
+    pub acceptance_radius: f32,
+    pub landing_speed: f32,
+    pub plan_path: PathBuf,
+    pub start_key: KeyCode,
+    pub add_key: KeyCode,
+    pub remove_key: KeyCode,
+    pub load_key: KeyCode,
+}
+
+impl Default for WaypointMission {
+    fn default() -> Self {
+        Self {
+            waypoints: Vec::new(),
+            acceptance_radius: 1.5,
+            landing_speed: 1.0,
+            plan_path: PathBuf::from("assets/missions/mission.plan"),
+            start_key: KeyCode::F6,
+            add_key: KeyCode::KeyO,
+            remove_key: KeyCode::Backspace,
+            load_key: KeyCode::F9,
+        }
+    }
+}
+
+impl WaypointMission {
+    /// Replaces the waypoints with the ones from the QGroundControl `.plan` file.
+    pub fn load_qgc_plan(&mut self, path: impl Into<PathBuf>) -> Result<(), PlanError> {
+        let path = path.into();
+
+        self.waypoints = read_qgc_plan_file(&path)?;
+        self.plan_path = path;
+
+        Ok(())
+    }
+}
+
+/// Status of the autopilot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AutopilotStatus {
+    #[default]
+    Idle,
+    Flying,
+    Completed,
+    /// The mission didn't start, because the `Player` is disarmed.
+    Disarmed,
+}
+
+/// What the autopilot does at the current waypoint.
+///
+/// `Orbit` keeps the angle in radians, which is already flown, `Hold` keeps seconds left.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WaypointPhase {
+    #[default]
+    Approach,
+    Orbit(f32),
+    Hold(f32),
+    Land,
+}
+
+/// Progress of the waypoint mission.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct AutopilotProgress {
+    pub status: AutopilotStatus,
+    pub current: usize,
+    pub phase: WaypointPhase,
+    pub photos: u32,
+    pub is_recording: bool,
+}
+
+impl AutopilotProgress {
+    /// Advances the mission of the drone at `position` above the `ground` and returns the setpoint,
+    /// which flies it through the `waypoints`. `None` means, that the mission is completed.
+    ///
+    /// The drone slows down near the waypoint, actions start when it is reached and are passed to `on_action`.
+    /// After the actions it orbits, holds and lands if the waypoint says so.
+    #[allow(clippy::too_many_arguments)]
+    pub fn advance(
+        &mut self,
+        waypoints: &[Waypoint],
+        acceptance_radius: f32,
+        landing_speed: f32,
+        position: Vec3,
+        ground: f32,
+        delta: f32,
+        mut on_action: impl FnMut(usize, WaypointAction),
+    ) -> Option<FlightSetpoint> {
+        let Some(waypoint) = waypoints.get(self.current) else {
+            self.status = AutopilotStatus::Completed;
+            return None;
+        };
+
+        let target = waypoint.target();
+        let to_target = target - position;
+
+        // position hold with the speed limit of the waypoint
+        let hold_velocity = (to_target * 1.0).clamp_length_max(waypoint.speed);
+        let look_at = |point: Vec2| {
+            let direction = point - position.xz();
+            (direction.length() > 0.5).then(|| (-direction.x).atan2(-direction.y))
+        };
+
+        let yaw = match waypoint.yaw {
+            YawMode::FollowPath => look_at(target.xz()),
+            YawMode::Fixed(yaw) => Some(yaw),
+            YawMode::PointOfInterest(point) => look_at(point),
+            YawMode::Keep => None,
+        };
+
+        let setpoint = match self.phase {
+            WaypointPhase::Approach => {
+                if to_target.length() <= acceptance_radius {
+                    for action in &waypoint.actions {
+                        on_action(self.current, *action);
+                    }
+
+                    self.phase = match waypoint.orbit() {
+                        Some(_) => WaypointPhase::Orbit(0.0),
+                        None => WaypointPhase::Hold(waypoint.hold),
+                    };
+                }
+
+                FlightSetpoint { velocity: hold_velocity, yaw }
+            },
+            WaypointPhase::Orbit(travelled) => {
+                let (center, radius, turns) = waypoint.orbit().unwrap_or((waypoint.position, 0.0, 0.0));
+
+                let offset = position.xz() - center;
+                let distance = offset.length().max(0.1);
+                let outward = offset / distance;
+
+                let horizontal = outward.perp() * waypoint.speed + outward * (radius - distance);
+                let travelled = travelled + waypoint.speed / distance.max(1.0) * delta;
+
+                self.phase = if travelled >= turns * TAU {
+                    WaypointPhase::Hold(waypoint.hold)
+                } else {
+                    WaypointPhase::Orbit(travelled)
+                };
+
+                FlightSetpoint {
+                    velocity: Vec3::new(horizontal.x, to_target.y, horizontal.y),
+                    yaw: look_at(center),
+                }
+            },
+            WaypointPhase::Hold(left) => {
+                let is_landing = waypoint.actions.contains(&WaypointAction::Land);
+
+                self.phase = match (left - delta, is_landing) {
+                    (left, _) if left > 0.0 => WaypointPhase::Hold(left),
+                    (_, true) => WaypointPhase::Land,
+                    (_, false) => {
+                        self.current += 1;
+                        WaypointPhase::Approach
+                    },
+                };
+
+                FlightSetpoint { velocity: hold_velocity, yaw }
+            },
+            WaypointPhase::Land => {
+                if position.y - ground < LANDED_HEIGHT {
+                    self.status = AutopilotStatus::Completed;
+                    return None;
+                }
+
+                FlightSetpoint {
+                    velocity: Vec3::new(to_target.x, -landing_speed, to_target.z),
+                    yaw: None,
+                }
+            },
+        };
+
+        Some(setpoint)
+    }
+
+    /// Counts photos and recordings of the action.
+    pub fn count_action(&mut self, action: WaypointAction) {
+        match action {
+            WaypointAction::TakePhoto => self.photos += 1,
+            WaypointAction::StartRecording => self.is_recording = true,
+            WaypointAction::StopRecording => self.is_recording = false,
+            WaypointAction::Orbit { .. } | WaypointAction::Land => {},
+        }
+    }
+}
+
+// events
+/// Event to start the waypoint mission from the first waypoint.
+#[derive(Event, Default)]
+pub struct StartWaypointMission;
+
+/// Event to stop the waypoint mission and give control back to the pilot.
+#[derive(Event, Default)]
+pub struct StopWaypointMission;
+
+/// Event of the action started at the waypoint.
+#[derive(Event)]
+pub struct WaypointActionStarted {
+    pub waypoint: usize,
+    pub action: WaypointAction,
+}
+
+// components
+/// Waypoint mission of the drone, which is flown by the autopilot while the drone is not piloted.
+#[derive(Component, Debug, Default, Clone)]
+pub struct DroneMission {
+    pub waypoints: Vec<Waypoint>,
+    pub progress: AutopilotProgress,
+}
+
+impl DroneMission {
+    pub fn new(waypoints: Vec<Waypoint>) -> Self {
+        Self {
+            waypoints,
+            progress: AutopilotProgress::default(),
+        }
+    }
+}
+
+/// Describes the autopilot HUD text.
+#[derive(Component)]
+struct AutopilotHudText;
+
+// systems
+/// System of the mission planner: waypoints are added at the `Player`, removed and loaded from the plan file.
+fn plan_mission(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut mission: ResMut<WaypointMission>,
+    progress: Res<AutopilotProgress>,
+    players: Query<&Transform, With<Player>>,
+    mut start: EventWriter<StartWaypointMission>,
+    mut stop: EventWriter<StopWaypointMission>,
+) {
+    if keys.just_pressed(mission.start_key) {
+        match progress.status {
+            AutopilotStatus::Flying => { stop.send_default(); },
+            _ => { start.send_default(); },
+        }
+    }
+
+    // the mission can't change while it is flown
+    if progress.status == AutopilotStatus::Flying {
+        return;
+    }
+
+    if keys.just_pressed(mission.add_key) {
+        if let Ok(transform) = players.get_single() {
+            let position = transform.translation;
+            mission.waypoints.push(Waypoint::new(position.xz(), position.y));
+        }
+    }
+
+    if keys.just_pressed(mission.remove_key) {
+        mission.waypoints.pop();
+    }
+
+    if keys.just_pressed(mission.load_key) {
+        let path = mission.plan_path.clone();
+
+        match mission.load_qgc_plan(&path) {
+            Ok(()) => info!("mission is loaded from {}", path.display()),
+            Err(error) => warn!("{error}"),
+        }
+    }
+}
+
+/// Query for the drones, whose missions are started with the mission of the `Player`.
+type DroneMissionQuery<'w, 's> = Query<'w, 's,
+    (&'static mut DroneMission, &'static Drone, Option<&'static mut Motors>, Option<&'static mut Arming>),
+    Without<Player>,
+>;
+
+/// System that starts and stops waypoint missions.
+///
+/// The mission of the `Player` starts only when it is armed. Missions of the drones flown by the autopilot start
+/// together with it, the autopilot arms them. They are not stopped, the operator takes over a drone by switching to it.
+pub fn start_waypoint_mission(
+    mission: Res<WaypointMission>,
+    mut progress: ResMut<AutopilotProgress>,
+    mut start: EventReader<StartWaypointMission>,
+    mut stop: EventReader<StopWaypointMission>,
+    mut controllers: Query<(&mut FlightController, Option<&Motors>), With<Player>>,
+    mut drone_missions: DroneMissionQuery,
+) {
+    if start.read().count() > 0 {
+        let is_armed = controllers.iter().all(|(_, motors)| motors.map_or(true, |motors| motors.is_armed));
+
+        if !mission.waypoints.is_empty() {
+            *progress = AutopilotProgress {
+                status: if is_armed { AutopilotStatus::Flying } else { AutopilotStatus::Disarmed },
+                photos: progress.photos,
+                ..default()
+            };
+        }
+
+        for (mut drone_mission, drone, motors, arming) in &mut drone_missions {
+            let is_idle = drone_mission.progress.status != AutopilotStatus::Flying;
+
+            if drone.control != DroneControl::Autopilot || !is_idle || drone_mission.waypoints.is_empty() {
+                continue;
+            }
+
+            drone_mission.progress = AutopilotProgress {
+                status: AutopilotStatus::Flying,
+                photos: drone_mission.progress.photos,
+                ..default()
+            };
+
+            if let Some(mut motors) = motors.filter(|motors| !motors.is_armed) {
+                motors.is_armed = true;
+
+                if let Some(mut arming) = arming {
+                    *arming = Arming::default();
+                }
+            }
+        }
+    }
+
+    if stop.read().count() > 0 && progress.status == AutopilotStatus::Flying {
+        progress.status = AutopilotStatus::Idle;
+
+        for (mut controller, _) in &mut controllers {
+            controller.setpoint = None;
+        }
+    }
+}
+
+/// System that flies the `Player` through the waypoints.
+pub fn navigate_waypoints(
+    time: Res<Time>,
+    mission: Res<WaypointMission>,
+    terrain: Option<Res<Terrain>>,
+    mut progress: ResMut<AutopilotProgress>,
+    mut drones: Query<(&Transform, &mut FlightController), With<Player>>,
+    mut actions: EventWriter<WaypointActionStarted>,
+) {
+    if progress.status != AutopilotStatus::Flying {
+        return;
+    }
+
+    let Ok((transform, mut controller)) = drones.get_single_mut() else {
+        return;
+    };
+
+    let position = transform.translation;
+    let ground = terrain.map_or(0.0, |terrain| terrain.height_at(position.xz()));
+
+    controller.setpoint = progress.advance(
+        &mission.waypoints,
+        mission.acceptance_radius,
+        mission.landing_speed,
+        position,
+        ground,
+        time.delta_seconds(),
+        |waypoint, action| { actions.send(WaypointActionStarted { waypoint, action }); },
+    );
+}
+
+/// System that flies the drones, which are not piloted, through the waypoints of their `DroneMission`s.
+///
+/// Missions of the swarm members are assigned by the swarm.
+///
+/// Their photos and recordings are only counted, screenshots show the view of the `Player`.
+pub fn navigate_drone_missions(
+    time: Res<Time>,
+    mission: Res<WaypointMission>,
+    terrain: Option<Res<Terrain>>,
+    mut drones: Query<(&mut DroneMission, &Transform, &mut FlightController, &Drone), Without<Player>>,
+) {
+    for (mut drone_mission, transform, mut controller, drone) in &mut drones {
+        let is_autonomous = matches!(drone.control, DroneControl::Autopilot | DroneControl::Swarm);
+
+        if !is_autonomous || drone_mission.progress.status != AutopilotStatus::Flying {
+            continue;
+        }
+
+        let position = transform.translation;
+        let ground = terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position.xz()));
+        let DroneMission { waypoints, progress } = drone_mission.as_mut();
+        let mut started = Vec::new();
+
+        controller.setpoint = progress.advance(
+            waypoints,
+            mission.acceptance_radius,
+            mission.landing_speed,
+            position,
+            ground,
+            time.delta_seconds(),
+            |_, action| started.push(action),
+        );
+
+        for action in started {
+            progress.count_action(action);
+        }
+    }
+}
+
+/// System that takes photos and starts recordings at the waypoints.
+///
+/// Photos are screenshots of the main window, saved to the `photos` directory.
+pub fn perform_waypoint_actions(
+    mut progress: ResMut<AutopilotProgress>,
+    mut actions: EventReader<WaypointActionStarted>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    screenshot_manager: Option<ResMut<ScreenshotManager>>,
+) {
+    let mut screenshot_manager = screenshot_manager;
+
+    for event in actions.read() {
+        progress.count_action(event.action);
+
+        if event.action != WaypointAction::TakePhoto {
+            continue;
+        }
+
+        let (Ok(window), Some(manager)) = (windows.get_single(), screenshot_manager.as_mut()) else {
+            continue;
+        };
+
+        let path = format!("photos/waypoint_{}_{}.png", event.waypoint, progress.photos);
+
+        if let Err(error) = manager.save_screenshot_to_disk(window, path) {
+            warn!("{error}");
+        }
+    }
+}
+
+/// Autopilot HUD initialization.
+fn setup_autopilot_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                font_size: 15.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        AutopilotHudText,
+    ));
+}
+
+/// System that shows the mission and its progress in the HUD.
+fn update_autopilot_hud(
+    mission: Res<WaypointMission>,
+    progress: Res<AutopilotProgress>,
+    mut query: Query<&mut Text, With<AutopilotHudText>>,
+) {
+    let mut value = String::new();
+
+    if !mission.waypoints.is_empty() {
+        let status = match (progress.status, progress.phase) {
+            (AutopilotStatus::Idle, _) => "ready".to_owned(),
+            (AutopilotStatus::Completed, _) => "completed".to_owned(),
+            (AutopilotStatus::Disarmed, _) => "arm first".to_owned(),
+            (AutopilotStatus::Flying, WaypointPhase::Approach) => "flying".to_owned(),
+            (AutopilotStatus::Flying, WaypointPhase::Orbit(travelled)) => format!("orbiting {:.0}°", travelled.to_degrees()),
+            (AutopilotStatus::Flying, WaypointPhase::Hold(left)) => format!("holding {left:.0} s"),
+            (AutopilotStatus::Flying, WaypointPhase::Land) => "landing".to_owned(),
+        };
+
+        value = format!(
+            "Mission: {status}{}\nPhotos: {}",
+            if progress.is_recording { "  [REC]" } else { "" },
+            progress.photos,
+        );
+
+        for (index, waypoint) in mission.waypoints.iter().enumerate() {
+            let marker = if progress.status == AutopilotStatus::Flying && index == progress.current { ">" } else { " " };
+
+            value.push_str(&format!(
+                "\n{marker} {}. ({:.0}, {:.0}) {:.0} m, {:.0} m/s",
+                index + 1,
+                waypoint.position.x,
+                waypoint.position.y,
+                waypoint.altitude,
+                waypoint.speed,
+            ));
+
+            if waypoint.hold > 0.0 {
+                value.push_str(&format!(", hold {:.0} s", waypoint.hold));
+            }
+
+            for action in &waypoint.actions {
+                value.push_str(match action {
+                    WaypointAction::TakePhoto => ", photo",
+                    WaypointAction::StartRecording => ", record",
+                    WaypointAction::StopRecording => ", stop recording",
+                    WaypointAction::Orbit { .. } => ", orbit",
+                    WaypointAction::Land => ", land",
+                });
+            }
+        }
+    }
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
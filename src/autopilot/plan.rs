@@ -0,0 +1,274 @@
+use std::{fmt, fs, io, path::Path};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{Waypoint, WaypointAction, YawMode};
+
+/// Mean radius of the Earth in meters.
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Error that can happen while reading a QGroundControl plan.
+#[derive(Debug)]
+pub enum PlanError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    NoWaypoints,
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't read plan: {error}"),
+            Self::Json(error) => write!(f, "can't parse plan: {error}"),
+            Self::NoWaypoints => write!(f, "plan has no waypoints"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+impl From<io::Error> for PlanError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for PlanError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+/// Part of the `.plan` file, which describes the mission.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlanFile {
+    mission: PlanMission,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlanMission {
+    #[serde(default)]
+    hover_speed: Option<f32>,
+    planned_home_position: [f64; 3],
+    items: Vec<PlanItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum PlanItem {
+    SimpleItem(SimpleItem),
+    ComplexItem(ComplexItem),
+}
+
+/// MAVLink mission item. Missing params are `null` in the file.
+#[derive(Deserialize)]
+struct SimpleItem {
+    command: u16,
+    #[serde(default)]
+    frame: u8,
+    params: [Option<f64>; 7],
+}
+
+/// Survey or corridor scan, it keeps the generated mission items.
+#[derive(Deserialize)]
+struct ComplexItem {
+    #[serde(rename = "TransectStyleComplexItem")]
+    transect: Option<TransectItem>,
+}
+
+#[derive(Deserialize)]
+struct TransectItem {
+    #[serde(rename = "Items")]
+    items: Vec<PlanItem>,
+}
+
+// MAVLink commands
+const NAV_WAYPOINT: u16 = 16;
+const NAV_LOITER_UNLIM: u16 = 17;
+const NAV_LOITER_TURNS: u16 = 18;
+const NAV_LOITER_TIME: u16 = 19;
+const NAV_RETURN_TO_LAUNCH: u16 = 20;
+const NAV_LAND: u16 = 21;
+const NAV_TAKEOFF: u16 = 22;
+const DO_CHANGE_SPEED: u16 = 178;
+const DO_SET_ROI_LOCATION: u16 = 195;
+const DO_SET_ROI_NONE: u16 = 197;
+const DO_SET_ROI: u16 = 201;
+const DO_DIGICAM_CONTROL: u16 = 203;
+const IMAGE_START_CAPTURE: u16 = 2000;
+const VIDEO_START_CAPTURE: u16 = 2500;
+const VIDEO_STOP_CAPTURE: u16 = 2501;
+
+// MAVLink frames with altitude above the mean sea level
+const FRAME_GLOBAL: u8 = 0;
+const FRAME_GLOBAL_INT: u8 = 5;
+
+/// Turns mission items into waypoints.
+struct PlanReader {
+    home: [f64; 3],
+    speed: f32,
+    yaw: YawMode,
+    waypoints: Vec<Waypoint>,
+    /// Actions before the first waypoint.
+    pending: Vec<WaypointAction>,
+}
+
+impl PlanReader {
+    /// Returns local position of the point, east is +X and north is -Z.
+    fn position(&self, latitude: f64, longitude: f64) -> Vec2 {
+        let [home_latitude, home_longitude, _] = self.home;
+
+        let north = (latitude - home_latitude).to_radians() * EARTH_RADIUS;
+        let east = (longitude - home_longitude).to_radians() * EARTH_RADIUS * home_latitude.to_radians().cos();
+
+        Vec2::new(east as f32, -north as f32)
+    }
+
+    /// Returns position of the item, items without coordinates stay where the previous waypoint is.
+    fn item_position(&self, item: &SimpleItem) -> Vec2 {
+        match (item.params[4], item.params[5]) {
+            (Some(latitude), Some(longitude)) if latitude != 0.0 || longitude != 0.0 => {
+                self.position(latitude, longitude)
+            },
+            _ => self.last_position(),
+        }
+    }
+
+    /// Returns altitude of the item above home.
+    fn item_altitude(&self, item: &SimpleItem) -> f32 {
+        let Some(altitude) = item.params[6] else {
+            return self.last_altitude();
+        };
+
+        match item.frame {
+            FRAME_GLOBAL | FRAME_GLOBAL_INT => (altitude - self.home[2]) as f32,
+            _ => altitude as f32,
+        }
+    }
+
+    fn last_position(&self) -> Vec2 {
+        self.waypoints.last().map_or(Vec2::ZERO, |waypoint| waypoint.position)
+    }
+
+    fn last_altitude(&self) -> f32 {
+        self.waypoints.last().map_or(0.0, |waypoint| waypoint.altitude)
+    }
+
+    fn push(&mut self, waypoint: Waypoint) {
+        let mut waypoint = waypoint.with_speed(self.speed);
+
+        if waypoint.yaw == YawMode::FollowPath {
+            waypoint.yaw = self.yaw;
+        }
+
+        waypoint.actions.splice(0..0, self.pending.drain(..));
+        self.waypoints.push(waypoint);
+    }
+
+    fn push_action(&mut self, action: WaypointAction) {
+        match self.waypoints.last_mut() {
+            Some(waypoint) => waypoint.actions.push(action),
+            None => self.pending.push(action),
+        }
+    }
+
+    fn read(&mut self, items: &[PlanItem]) {
+        for item in items {
+            match item {
+                PlanItem::SimpleItem(item) => self.read_simple(item),
+                PlanItem::ComplexItem(ComplexItem { transect: Some(transect) }) => self.read(&transect.items),
+                PlanItem::ComplexItem(_) => warn!("only survey and corridor scan complex items are supported"),
+            }
+        }
+    }
+
+    fn read_simple(&mut self, item: &SimpleItem) {
+        let param = |index: usize| item.params[index].filter(|value| value.is_finite()).map(|value| value as f32);
+
+        match item.command {
+            NAV_WAYPOINT | NAV_TAKEOFF => {
+                let mut waypoint = Waypoint::new(self.item_position(item), self.item_altitude(item))
+                    .with_hold(param(0).unwrap_or(0.0));
+
+                // yaw is a compass heading in degrees
+                if let Some(heading) = param(3).filter(|_| item.command == NAV_WAYPOINT) {
+                    waypoint = waypoint.with_yaw(YawMode::Fixed(-heading.to_radians()));
+                }
+
+                self.push(waypoint);
+            },
+            NAV_LOITER_UNLIM => {
+                self.push(Waypoint::new(self.item_position(item), self.item_altitude(item)).with_hold(f32::INFINITY));
+            },
+            NAV_LOITER_TURNS => {
+                let position = self.item_position(item);
+
+                self.push(Waypoint::new(position, self.item_altitude(item)).with_action(WaypointAction::Orbit {
+                    center: position,
+                    radius: param(2).unwrap_or(10.0).abs(),
+                    turns: param(0).unwrap_or(1.0),
+                }));
+            },
+            NAV_LOITER_TIME => {
+                self.push(
+                    Waypoint::new(self.item_position(item), self.item_altitude(item))
+                        .with_hold(param(0).unwrap_or(0.0)),
+                );
+            },
+            NAV_LAND => {
+                let altitude = self.last_altitude();
+                self.push(Waypoint::new(self.item_position(item), altitude).with_action(WaypointAction::Land));
+            },
+            NAV_RETURN_TO_LAUNCH => {
+                let altitude = self.last_altitude();
+                self.push(Waypoint::new(Vec2::ZERO, altitude).with_action(WaypointAction::Land));
+            },
+            DO_CHANGE_SPEED => {
+                if let Some(speed) = param(1).filter(|speed| *speed > 0.0) {
+                    self.speed = speed;
+                }
+            },
+            DO_SET_ROI_LOCATION | DO_SET_ROI => {
+                if let (Some(latitude), Some(longitude)) = (item.params[4], item.params[5]) {
+                    self.yaw = YawMode::PointOfInterest(self.position(latitude, longitude));
+                }
+            },
+            DO_SET_ROI_NONE => self.yaw = YawMode::FollowPath,
+            DO_DIGICAM_CONTROL | IMAGE_START_CAPTURE => self.push_action(WaypointAction::TakePhoto),
+            VIDEO_START_CAPTURE => self.push_action(WaypointAction::StartRecording),
+            VIDEO_STOP_CAPTURE => self.push_action(WaypointAction::StopRecording),
+            command => warn!("mission command {command} is not supported"),
+        }
+    }
+}
+
+/// Reads waypoints from a QGroundControl `.plan` file.
+///
+/// Positions are relative to the planned home position, which becomes the origin. Altitudes are above home.
+pub fn read_qgc_plan(json: &str) -> Result<Vec<Waypoint>, PlanError> {
+    let plan: PlanFile = serde_json::from_str(json)?;
+
+    let mut reader = PlanReader {
+        home: plan.mission.planned_home_position,
+        speed: plan.mission.hover_speed.filter(|speed| *speed > 0.0).unwrap_or(5.0),
+        yaw: YawMode::FollowPath,
+        waypoints: Vec::new(),
+        pending: Vec::new(),
+    };
+
+    reader.read(&plan.mission.items);
+
+    if reader.waypoints.is_empty() {
+        return Err(PlanError::NoWaypoints);
+    }
+
+    Ok(reader.waypoints)
+}
+
+/// Reads QGroundControl `.plan` file. See `read_qgc_plan`.
+pub fn read_qgc_plan_file(path: impl AsRef<Path>) -> Result<Vec<Waypoint>, PlanError> {
+    read_qgc_plan(&fs::read_to_string(path)?)
+}
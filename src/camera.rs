@@ -1,16 +1,25 @@
 #[cfg(not(test))]
-use bevy::pbr::ExtendedMaterial;
-#[cfg(not(test))]
-use crate::materials::{Thermal, ThermalMaterialExtension};
+use crate::materials::{Thermal, ThermalMaterial};
 
 use bevy::{
     core_pipeline::core_3d::Camera3dDepthLoadOp, 
     prelude::*, 
-    render::view::RenderLayers
+    render::view::RenderLayers,
+    ui::IsDefaultUiCamera,
 };
 use bevy_third_person_camera::{camera::Zoom, ThirdPersonCamera};
 
-use crate::post_processing::PostProcessSettings;
+use crate::{
+    camera_sensor::{AnalogVideoLink, CameraSensor, ThermalSensor},
+    post_processing::{
+        effects::{
+            AnalogStaticSettings, ChromaticAberrationSettings, CompressionSettings, GrayscaleSettings, 
+            LensDistortionSettings, MotionBlurSettings, NightVisionSettings, PostProcessEffect, PostProcessEffectKind, 
+            RollingShutterSettings, SensorNoiseSettings, ThermalSensorSettings, VignetteSettings
+        }, 
+        PostProcessStack
+    }
+};
 
 /// Plugin for a Camera.
 pub struct CameraPlugin;
@@ -18,15 +27,59 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<VisionModeBindings>()
+            .add_event::<SetVisionMode>()
             .add_systems(Startup, spawn_camera)
-            .add_systems(Update, (sync_cameras, update_post_processing));
+            .add_systems(Update, (sync_cameras, update_post_processing, set_vision_modes));
     }
 }
 
+// resources
+/// Key bindings for the vision modes.
+/// 
+/// `cycle` switches the camera to the next mode of `modes`. Modes that are not listed are skipped.
+#[derive(Resource)]
+pub struct VisionModeBindings {
+    pub cycle: KeyCode,
+    pub modes: Vec<VisionMode>,
+}
+
+impl Default for VisionModeBindings {
+    fn default() -> Self {
+        Self {
+            cycle: KeyCode::KeyJ,
+            modes: vec![VisionMode::Day, VisionMode::Thermal, VisionMode::NightVision],
+        }
+    }
+}
+
+impl VisionModeBindings {
+    /// Returns the mode that goes after the `current` one.
+    pub fn next(&self, current: VisionMode) -> VisionMode {
+        match self.modes.iter().position(|mode| *mode == current) {
+            Some(index) => self.modes[(index + 1) % self.modes.len()],
+            None => self.modes.first().copied().unwrap_or(current),
+        }
+    }
+}
+
+// events
+/// Switches the camera to the given vision mode.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetVisionMode(pub VisionMode);
+
 // components
-/// Describes whether the thermal post-processing is active or not.
-#[derive(Component)]
-pub struct IsPostProcessingActive(pub bool);
+/// Describes the vision mode of the camera.
+/// 
+/// `Day` is a regular camera, `Thermal` shows temperatures and `NightVision` is an image intensifier,
+/// which amplifies the low light.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VisionMode {
+    #[default]
+    Day,
+    Thermal,
+    NightVision,
+}
 
 /// Describes the main camera on which post-processing should be applied.
 #[derive(Component)]
@@ -40,6 +93,11 @@ pub struct ThermalMaterialCamera;
 /// System for spawning cameras. 
 /// 
 /// Note, that it uses `RenderLayers` and `Camera3dDepthLoadOp::Load` for `Camera3d`'s `depth_load_op`.
+/// 
+/// Main camera post-processing goes in the order light travels: scene, optics, sensor and video link.
+/// 
+/// Thermal material camera renders last, so its night vision and thermal sensor effects are applied to the whole image,
+/// hot objects included.
 fn spawn_camera(
     mut commands: Commands,
 ) {
@@ -56,15 +114,27 @@ fn spawn_camera(
                 .looking_at(Vec3::ZERO, Vec3::Y),
             ..default()
         },        
-        ThirdPersonCamera {
-            zoom: Zoom::new(1.0, 40.0),
-            ..default()
-        },
-        PostProcessSettings {
-            intensity: 0.0,
-            ..default()
-        },
-        IsPostProcessingActive(false),
+        third_person_camera(),
+        PostProcessStack::default()
+            .with(PostProcessEffect::Grayscale(GrayscaleSettings {
+                intensity: 0.0,
+                ..default()
+            }))
+            .with(PostProcessEffect::MotionBlur(MotionBlurSettings::default()))
+            .with(PostProcessEffect::RollingShutter(RollingShutterSettings::default()))
+            .with(PostProcessEffect::LensDistortion(LensDistortionSettings::default()))
+            .with(PostProcessEffect::ChromaticAberration(ChromaticAberrationSettings::default()))
+            .with(PostProcessEffect::Vignette(VignetteSettings {
+                intensity: 0.3,
+                radius: 0.6,
+                ..default()
+            }))
+            .with(PostProcessEffect::SensorNoise(SensorNoiseSettings::default()))
+            .with(PostProcessEffect::Compression(CompressionSettings::default()))
+            .with(PostProcessEffect::AnalogStatic(AnalogStaticSettings::default())),
+        CameraSensor::default(),
+        AnalogVideoLink::default(),
+        VisionMode::Day,
         MainCamera,
     ));
 
@@ -83,11 +153,31 @@ fn spawn_camera(
                 .looking_at(Vec3::ZERO, Vec3::Y),
             ..default()
         },
+        PostProcessStack::default()
+            .with(PostProcessEffect::NightVision(NightVisionSettings {
+                intensity: 0.0,
+                ..default()
+            }))
+            .with(PostProcessEffect::ThermalSensor(ThermalSensorSettings {
+                intensity: 0.0,
+                ..default()
+            })),
+        ThermalSensor::default(),
         ThermalMaterialCamera,
+        // UI is drawn over the whole image, not over the cameras with viewports, which render later
+        IsDefaultUiCamera,
         thermal_render_layer,
     ));
 }
 
+/// Returns the third person camera of the `MainCamera`, which follows the `Player`.
+pub fn third_person_camera() -> ThirdPersonCamera {
+    ThirdPersonCamera {
+        zoom: Zoom::new(1.0, 40.0),
+        ..default()
+    }
+}
+
 /// System that syncs `MainCamera` and `ThermalCamera` `Transform`s.
 fn sync_cameras(
     main_query: Query<&Transform, (With<MainCamera>, Without<ThermalMaterialCamera>)>,
@@ -100,66 +190,104 @@ fn sync_cameras(
     }
 }
 
-/// System that contains logic for switching visual modes.
+/// System that contains logic for switching vision modes.
 /// 
-/// It changes the intensity of `PostProcessSettings` in order to achive that.
+/// When `VisionModeBindings::cycle` is released, the `VisionMode` of the camera switches to the next one
+/// and the mode is applied to every `PostProcessStack`.
 /// 
 /// Additionally it changes the `is_infrared_mode_active` of `ThermalMaterialExtension`.
-/// 
-/// Also keep in mind that it basicly switches material as a whole.
 pub fn update_post_processing(
-    mut settings: Query<(&mut PostProcessSettings, &mut IsPostProcessingActive)>,
+    mut cameras: Query<(&mut PostProcessStack, &mut VisionMode)>,
+    mut other_stacks: Query<&mut PostProcessStack, Without<VisionMode>>,
     keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<VisionModeBindings>,
+
+    #[cfg(not(test))]
+    mat: Query<&Handle<ThermalMaterial>, With<Thermal>>,
+    #[cfg(not(test))]
+    mut ext_materials: ResMut<Assets<ThermalMaterial>>,
+) {
+    if !keys.just_released(bindings.cycle) {
+        return;
+    }
+
+    for (mut stack, mut mode) in &mut cameras {
+        *mode = bindings.next(*mode);
+
+        apply_vision_mode_everywhere(
+            &mut stack,
+            *mode,
+            &mut other_stacks,
+            #[cfg(not(test))]
+            &mat,
+            #[cfg(not(test))]
+            &mut ext_materials,
+        );
+    }
+}
+
+/// System that switches cameras to the modes of `SetVisionMode` events.
+pub fn set_vision_modes(
+    mut events: EventReader<SetVisionMode>,
+    mut cameras: Query<(&mut PostProcessStack, &mut VisionMode)>,
+    mut other_stacks: Query<&mut PostProcessStack, Without<VisionMode>>,
+
+    #[cfg(not(test))]
+    mat: Query<&Handle<ThermalMaterial>, With<Thermal>>,
+    #[cfg(not(test))]
+    mut ext_materials: ResMut<Assets<ThermalMaterial>>,
+) {
+    let Some(SetVisionMode(requested)) = events.read().last().copied() else {
+        return;
+    };
+
+    for (mut stack, mut mode) in &mut cameras {
+        *mode = requested;
+
+        apply_vision_mode_everywhere(
+            &mut stack,
+            *mode,
+            &mut other_stacks,
+            #[cfg(not(test))]
+            &mat,
+            #[cfg(not(test))]
+            &mut ext_materials,
+        );
+    }
+}
+
+/// Applies the mode to the camera `stack`, the `other_stacks` and the thermal materials.
+fn apply_vision_mode_everywhere(
+    stack: &mut PostProcessStack,
+    mode: VisionMode,
+    other_stacks: &mut Query<&mut PostProcessStack, Without<VisionMode>>,
 
     #[cfg(not(test))]
-    mut mat: Query<&mut Handle<ExtendedMaterial<StandardMaterial, ThermalMaterialExtension>>, With<Thermal>>,
+    mat: &Query<&Handle<ThermalMaterial>, With<Thermal>>,
     #[cfg(not(test))]
-    mut ext_materials: ResMut<Assets<ExtendedMaterial<StandardMaterial, ThermalMaterialExtension>>>,
+    ext_materials: &mut Assets<ThermalMaterial>,
 ) {
-    for (mut setting, mut is_active) in &mut settings {
-        if keys.just_released(KeyCode::KeyJ) {
-            match is_active.0 {
-                true => {
-                    is_active.0 = false;
-
-                    setting.intensity = 0.0;
-
-                    #[cfg(not(test))]
-                    for mut material in mat.iter_mut() {
-                        *material = ext_materials.add(ExtendedMaterial {
-                            base: StandardMaterial {
-                                base_color: Color::RED,
-                                ..default()
-                            },
-                            extension: ThermalMaterialExtension { 
-                                temperature: 15.0,
-                                intensity: 1.0,
-                                is_infrared_mode_active: 0,
-                            },
-                        });
-                    }
-                },
-                false => {
-                    is_active.0 = true;
-
-                    setting.intensity = 1.0;
-
-                    #[cfg(not(test))]
-                    for mut material in mat.iter_mut() {
-                        *material = ext_materials.add(ExtendedMaterial {
-                            base: StandardMaterial {
-                                base_color: Color::RED,
-                                ..default()
-                            },
-                            extension: ThermalMaterialExtension { 
-                                temperature: 15.0,
-                                intensity: 1.0,
-                                is_infrared_mode_active: 1,
-                            },
-                        });
-                    }
-                },
-            }
+    apply_vision_mode(stack, mode);
+
+    for mut other_stack in other_stacks.iter_mut() {
+        apply_vision_mode(&mut other_stack, mode);
+    }
+
+    #[cfg(not(test))]
+    for handle in mat.iter() {
+        if let Some(material) = ext_materials.get_mut(handle) {
+            material.extension.is_infrared_mode_active = (mode == VisionMode::Thermal).into();
         }
     }
 }
+
+/// Turns effects of the `PostProcessStack` on and off according to the `VisionMode`.
+fn apply_vision_mode(stack: &mut PostProcessStack, mode: VisionMode) {
+    if let Some(grayscale) = stack.get_mut(PostProcessEffectKind::Grayscale) {
+        grayscale.set_intensity(if mode == VisionMode::Thermal { 1.0 } else { 0.0 });
+    }
+
+    if let Some(night_vision) = stack.get_mut(PostProcessEffectKind::NightVision) {
+        night_vision.set_intensity(if mode == VisionMode::NightVision { 1.0 } else { 0.0 });
+    }
+}
@@ -0,0 +1,296 @@
+use std::f32::consts::FRAC_PI_3;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+
+use crate::{
+    camera::VisionMode,
+    player::Player,
+    post_processing::{
+        effects::{PostProcessEffect, PostProcessEffectKind},
+        PostProcessStack,
+    },
+};
+
+/// Plugin for the camera sensor simulation.
+///
+/// Drives post-processing effects, which depend on the drone state.
+pub struct CameraSensorPlugin;
+
+impl Plugin for CameraSensorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (update_camera_sensor_effects, update_thermal_sensor_effects));
+    }
+}
+
+/// Temperature in °C, which thermal cameras see in the sky.
+pub const SKY_TEMPERATURE: f32 = -20.0;
+
+/// Camera, which sees by casting rays into the physics world, so it works without rendering.
+///
+/// `fov` is the vertical field of view and `pitch` is the tilt below the horizon, both in radians.
+/// The camera sees `range` meters far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RayCastCamera {
+    pub width: usize,
+    pub height: usize,
+    pub fov: f32,
+    pub pitch: f32,
+    pub range: f32,
+}
+
+impl Default for RayCastCamera {
+    fn default() -> Self {
+        Self {
+            width: 32,
+            height: 24,
+            fov: FRAC_PI_3,
+            pitch: 45.0_f32.to_radians(),
+            range: 100.0,
+        }
+    }
+}
+
+impl RayCastCamera {
+    /// Returns the rotation of the camera on the drone with the given rotation.
+    pub fn rotation(&self, drone_rotation: Quat) -> Quat {
+        drone_rotation * Quat::from_rotation_x(-self.pitch)
+    }
+
+    /// Returns tangents of the half field of view horizontally and vertically.
+    pub fn half_view(&self) -> (f32, f32) {
+        let half_height = (self.fov / 2.0).tan();
+
+        (half_height * self.width as f32 / self.height as f32, half_height)
+    }
+
+    /// Returns directions of the rays through the pixels, row by row from the top left corner.
+    pub fn ray_directions(&self, rotation: Quat) -> impl Iterator<Item = Vec3> + '_ {
+        let (half_width, half_height) = self.half_view();
+
+        (0..self.height).flat_map(move |y| {
+            (0..self.width).map(move |x| {
+                rotation * Vec3::new(
+                    (2.0 * (x as f32 + 0.5) / self.width as f32 - 1.0) * half_width,
+                    (1.0 - 2.0 * (y as f32 + 0.5) / self.height as f32) * half_height,
+                    -1.0,
+                ).normalize()
+            })
+        })
+    }
+
+    /// Whether the point is in the field of view and the range of the camera at the position with the rotation.
+    pub fn is_in_view(&self, position: Vec3, rotation: Quat, point: Vec3) -> bool {
+        let (half_width, half_height) = self.half_view();
+        let local = rotation.inverse() * (point - position);
+        let depth = -local.z;
+
+        depth > 0.0
+            && local.x.abs() <= depth * half_width
+            && local.y.abs() <= depth * half_height
+            && local.length() <= self.range
+    }
+}
+
+// components
+/// Describes physical parameters of the camera sensor.
+///
+/// `exposure_time` is how long the shutter is open, it affects motion blur.
+/// `readout_time` is how long it takes to read all of the sensor rows, it affects rolling shutter.
+///
+/// `scene_depth` is a typical distance to the scene in meters. It is used to convert drone speed into image motion.
+#[derive(Component)]
+pub struct CameraSensor {
+    pub exposure_time: f32,
+    pub readout_time: f32,
+    pub scene_depth: f32,
+    /// Rotation of the camera during the previous frame.
+    pub previous_rotation: Option<Quat>,
+}
+
+impl Default for CameraSensor {
+    fn default() -> Self {
+        Self {
+            exposure_time: 1.0 / 120.0,
+            readout_time: 1.0 / 60.0,
+            scene_depth: 20.0,
+            previous_rotation: None,
+        }
+    }
+}
+
+/// Describes analog FPV video link between the drone and the pilot.
+///
+/// Link is clean up to `clean_range` meters from the pilot and fully breaks at `max_range` meters.
+#[derive(Component)]
+pub struct AnalogVideoLink {
+    pub pilot_position: Vec3,
+    pub clean_range: f32,
+    pub max_range: f32,
+}
+
+impl Default for AnalogVideoLink {
+    fn default() -> Self {
+        Self {
+            pilot_position: Vec3::ZERO,
+            clean_range: 300.0,
+            max_range: 1000.0,
+        }
+    }
+}
+
+impl AnalogVideoLink {
+    /// Returns how broken the link is at the given drone position.
+    ///
+    /// 0.0 means clean link, 1.0 means that there is only static.
+    pub fn breakup(&self, drone_position: Vec3) -> f32 {
+        let distance = drone_position.distance(self.pilot_position);
+        let range = (self.max_range - self.clean_range).max(f32::EPSILON);
+
+        ((distance - self.clean_range) / range).clamp(0.0, 1.0)
+    }
+}
+
+/// Describes uncooled thermal sensor of the camera.
+///
+/// Every `nuc_interval` seconds the sensor closes its shutter for `nuc_duration` seconds
+/// to perform non-uniformity correction (NUC). The image is frozen while the shutter is closed.
+#[derive(Component)]
+pub struct ThermalSensor {
+    pub nuc_interval: f32,
+    pub nuc_duration: f32,
+    /// Seconds passed since the end of the last NUC.
+    pub since_nuc: f32,
+}
+
+impl Default for ThermalSensor {
+    fn default() -> Self {
+        Self {
+            nuc_interval: 60.0,
+            nuc_duration: 0.5,
+            since_nuc: 0.0,
+        }
+    }
+}
+
+impl ThermalSensor {
+    /// Whether the NUC shutter is closed.
+    pub fn is_shutter_closed(&self) -> bool {
+        self.since_nuc >= self.nuc_interval
+    }
+
+    /// Returns non-uniformity drift since the last NUC from 0.0 to 1.0.
+    pub fn drift(&self) -> f32 {
+        (self.since_nuc / self.nuc_interval.max(f32::EPSILON)).min(1.0)
+    }
+}
+
+// systems
+/// Query for the cameras with `CameraSensor`.
+type CameraSensorQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static mut PostProcessStack,
+        &'static mut CameraSensor,
+        &'static Transform,
+        &'static Projection,
+        Option<&'static AnalogVideoLink>,
+    ),
+    Without<Player>,
+>;
+
+/// System that updates drone state dependent post-processing effects of the cameras with `CameraSensor`.
+///
+/// Motion blur follows the `Player` velocity, rolling shutter follows the camera rotation
+/// and analog static follows the distance between the `Player` and the pilot.
+/// Video link of the `Player` is used, if it has one.
+pub fn update_camera_sensor_effects(
+    time: Res<Time>,
+    players: Query<(&Transform, &Velocity, Option<&AnalogVideoLink>), With<Player>>,
+    mut cameras: CameraSensorQuery,
+) {
+    let Ok((player_transform, player_velocity, player_link)) = players.get_single() else {
+        return;
+    };
+
+    for (mut stack, mut sensor, camera_transform, projection, camera_link) in &mut cameras {
+        let link = player_link.or(camera_link);
+
+        let (horizontal_fov, vertical_fov) = match projection {
+            Projection::Perspective(perspective) => {
+                let horizontal_fov = 2.0 * ((perspective.fov / 2.0).tan() * perspective.aspect_ratio).atan();
+                (horizontal_fov, perspective.fov)
+            },
+            Projection::Orthographic(_) => (std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2),
+        };
+
+        // camera angular velocity in its local space
+        let delta_seconds = time.delta_seconds();
+        let angular_velocity = match sensor.previous_rotation {
+            Some(previous_rotation) if delta_seconds > 0.0 => {
+                let (axis, angle) = (previous_rotation.inverse() * camera_transform.rotation).to_axis_angle();
+                axis * angle / delta_seconds
+            },
+            _ => Vec3::ZERO,
+        };
+        sensor.previous_rotation = Some(camera_transform.rotation);
+
+        // image motion in uv per second, caused by camera rotation
+        let rotation_motion = Vec2::new(
+            angular_velocity.y / horizontal_fov,
+            angular_velocity.x / vertical_fov,
+        );
+
+        // image motion in uv per second, caused by drone movement
+        let local_velocity = camera_transform.rotation.inverse() * player_velocity.linvel;
+        let translation_motion = Vec2::new(
+            -local_velocity.x / (2.0 * sensor.scene_depth * (horizontal_fov / 2.0).tan()),
+            local_velocity.y / (2.0 * sensor.scene_depth * (vertical_fov / 2.0).tan()),
+        );
+
+        let blur = (rotation_motion + translation_motion) * sensor.exposure_time;
+        let skew = -rotation_motion * sensor.readout_time;
+
+        if let Some(PostProcessEffect::MotionBlur(settings)) = stack.get_mut(PostProcessEffectKind::MotionBlur) {
+            settings.direction = blur;
+            // there is no need in a pass if the blur is smaller than a pixel
+            settings.intensity = if blur.length() > 0.0005 { 1.0 } else { 0.0 };
+        }
+
+        if let Some(PostProcessEffect::RollingShutter(settings)) = stack.get_mut(PostProcessEffectKind::RollingShutter) {
+            settings.skew = skew;
+            settings.intensity = if skew.length() > 0.0005 { 1.0 } else { 0.0 };
+        }
+
+        if let Some(link) = link {
+            if let Some(analog_static) = stack.get_mut(PostProcessEffectKind::AnalogStatic) {
+                analog_static.set_intensity(link.breakup(player_transform.translation));
+            }
+        }
+    }
+}
+
+/// System that advances NUC cycle of the `ThermalSensor`s and updates their thermal sensor effects.
+///
+/// Thermal sensor effect is only visible while the thermal mode is active.
+pub fn update_thermal_sensor_effects(
+    time: Res<Time>,
+    modes: Query<&VisionMode>,
+    mut sensors: Query<(&mut PostProcessStack, &mut ThermalSensor)>,
+) {
+    let is_thermal_mode_active = modes.iter().any(|mode| *mode == VisionMode::Thermal);
+
+    for (mut stack, mut sensor) in &mut sensors {
+        sensor.since_nuc += time.delta_seconds();
+
+        if sensor.since_nuc >= sensor.nuc_interval + sensor.nuc_duration {
+            sensor.since_nuc = 0.0;
+        }
+
+        if let Some(PostProcessEffect::ThermalSensor(settings)) = stack.get_mut(PostProcessEffectKind::ThermalSensor) {
+            settings.intensity = if is_thermal_mode_active { 1.0 } else { 0.0 };
+            settings.drift = sensor.drift();
+            settings.frozen = sensor.is_shutter_closed().into();
+        }
+    }
+}
@@ -0,0 +1,703 @@
+use std::{
+    env, fmt,
+    io::{self, BufRead, BufReader, Write},
+    net::{AddrParseError, SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+use bevy::{
+    app::AppExit,
+    hierarchy::despawn_with_children_recursive,
+    prelude::*,
+    render::view::screenshot::ScreenshotManager,
+    time::TimeSystem,
+    window::PrimaryWindow,
+};
+use bevy_rapier3d::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    camera::{SetVisionMode, VisionMode},
+    camera_sensor::{AnalogVideoLink, ThermalSensor},
+    fleet::{Drone, DroneControl, DroneDefinition, DroneSetpoint, SpawnDrone},
+    materials::{Temperature, Thermal},
+    player::{Battery, FlightController, FlightSetpoint, Motors, Player},
+    replay::Replay,
+    sim_clock::{apply_sim_clock, SimClock},
+    swarm::HeatDetections,
+    terrain::Terrain,
+};
+
+/// Plugin for the control server: external programs drive the simulator over a local socket.
+///
+/// Clients send JSON-RPC 2.0 requests, one per line, and get responses the same way.
+/// The server is off, unless it is started with `supersonic --control-server <address>`.
+pub struct ControlServerPlugin;
+
+impl Plugin for ControlServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ControlServerSettings>();
+
+        match ControlServerSettings::address_from_args(env::args()) {
+            Some(Ok(address)) => app.world.resource_mut::<ControlServerSettings>().address = Some(address),
+            Some(Err(error)) => error!("wrong control server address: {error}"),
+            None => {},
+        }
+
+        app
+            .add_systems(Startup, start_control_server)
+            .add_systems(First, process_control_requests.before(TimeSystem).before(apply_sim_clock));
+    }
+}
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const SERVER_ERROR: i32 = -32000;
+
+// resources
+/// Settings of the control server.
+///
+/// The server listens on `address`, if it is set. Frames are saved as PNG files to `frame_directory`.
+#[derive(Resource, Debug, Clone)]
+pub struct ControlServerSettings {
+    pub address: Option<SocketAddr>,
+    pub frame_directory: PathBuf,
+}
+
+impl Default for ControlServerSettings {
+    fn default() -> Self {
+        Self {
+            address: None,
+            frame_directory: PathBuf::from("frames"),
+        }
+    }
+}
+
+impl ControlServerSettings {
+    /// Reads the address after `--control-server` from the command line arguments.
+    ///
+    /// Returns `None` if there is no `--control-server`, the port 7700 is used if the address has none.
+    pub fn address_from_args(args: impl IntoIterator<Item = String>) -> Option<Result<SocketAddr, AddrParseError>> {
+        let mut args = args.into_iter();
+
+        args.find(|arg| arg == "--control-server")?;
+
+        let address = args.next().unwrap_or_else(|| "127.0.0.1".to_owned());
+
+        Some(address.parse().or_else(|_| format!("{address}:7700").parse()))
+    }
+}
+
+/// Request of a client with the channel for its response.
+pub struct ControlRequest {
+    pub request: RpcRequest,
+    pub reply: Sender<RpcResponse>,
+}
+
+/// Running control server.
+///
+/// `address` is the address the server listens on. Pauses and steps go through the `SimClock`.
+#[derive(Resource)]
+pub struct ControlServer {
+    pub address: SocketAddr,
+    requests: Mutex<Receiver<ControlRequest>>,
+    /// Requests, which wait for the end of the steps.
+    step_replies: Vec<(Value, Sender<RpcResponse>)>,
+}
+
+impl ControlServer {
+    /// Starts listening on the address, clients are served in their own threads.
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+
+                thread::spawn(move || {
+                    if let Err(error) = serve_client(stream, sender) {
+                        warn!("control client: {error}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            address,
+            requests: Mutex::new(receiver),
+            step_replies: Vec::new(),
+        })
+    }
+}
+
+/// JSON-RPC request. Requests without `id` are notifications, which get no response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default)]
+    pub id: Option<Value>,
+}
+
+/// JSON-RPC response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl RpcResponse {
+    /// Creates the response with the result or the error.
+    pub fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        Self {
+            jsonrpc: "2.0".to_owned(),
+            result,
+            error,
+            id,
+        }
+    }
+}
+
+/// JSON-RPC error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl fmt::Display) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// State of a drone, which clients read.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroneStateMessage {
+    pub id: u32,
+    pub airframe: String,
+    pub control: DroneControl,
+    pub is_player: bool,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub battery_charge: f32,
+    pub is_armed: bool,
+    pub setpoint_velocity: Option<Vec3>,
+    pub setpoint_yaw: Option<f32>,
+}
+
+/// Parameters of the methods, which address a drone.
+#[derive(Deserialize)]
+struct DroneParams {
+    id: u32,
+}
+
+/// Parameters of `set_setpoint`.
+#[derive(Deserialize)]
+struct SetpointParams {
+    id: u32,
+    velocity: Vec3,
+    #[serde(default)]
+    yaw: Option<f32>,
+}
+
+/// Shape of the objects, which clients spawn.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ObjectShape {
+    Box { size: Vec3 },
+    Sphere { radius: f32 },
+}
+
+/// Parameters of `spawn_object`. Objects with a `temperature` are visible to the thermal camera.
+#[derive(Deserialize)]
+struct ObjectParams {
+    shape: ObjectShape,
+    position: Vec3,
+    #[serde(default)]
+    temperature: Option<f32>,
+}
+
+/// Parameters of `despawn_object`.
+#[derive(Deserialize)]
+struct EntityParams {
+    entity: u64,
+}
+
+/// Parameters of `set_vision_mode`.
+#[derive(Deserialize)]
+struct VisionModeParams {
+    mode: String,
+}
+
+/// Parameters of `step`.
+#[derive(Deserialize)]
+struct StepParams {
+    #[serde(default = "one_frame")]
+    frames: u32,
+}
+
+fn one_frame() -> u32 {
+    1
+}
+
+// components
+/// Describes an object, which a client spawned.
+#[derive(Component)]
+pub struct ControlledObject;
+
+// systems
+/// System that starts the control server, if it has an address.
+///
+/// The simulator exits if the server can't listen, e.g. the port is taken, as the clients can't reach it.
+fn start_control_server(
+    mut commands: Commands,
+    settings: Res<ControlServerSettings>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(address) = settings.address else {
+        return;
+    };
+
+    match ControlServer::listen(address) {
+        Ok(server) => {
+            info!("control server listens on {}", server.address);
+            commands.insert_resource(server);
+        },
+        Err(error) => {
+            error!("can't start control server on {address}: {error}");
+            exit.send(AppExit);
+        },
+    }
+}
+
+/// System that executes requests of the clients and pauses or steps the simulation.
+///
+/// Runs before the time update, so pausing stops the virtual time and the physics in the same frame.
+pub fn process_control_requests(world: &mut World) {
+    if !world.contains_resource::<ControlServer>() {
+        return;
+    }
+
+    world.resource_scope(|world, mut server: Mut<ControlServer>| {
+        let requests: Vec<ControlRequest> = server.requests.lock().unwrap().try_iter().collect();
+
+        for ControlRequest { request, reply } in requests {
+            let id = request.id.clone().unwrap_or(Value::Null);
+
+            if let Some(result) = execute(world, &mut server, request, &reply) {
+                let _ = reply.send(RpcResponse::new(id, result));
+            }
+        }
+
+        reply_to_steps(world, &mut server);
+    });
+}
+
+/// Answers the `step` requests, when the `SimClock` ran their frames.
+fn reply_to_steps(world: &World, server: &mut ControlServer) {
+    let Some(clock) = world.get_resource::<SimClock>() else {
+        return;
+    };
+
+    if clock.is_paused && clock.steps > 0 {
+        return;
+    }
+
+    for (id, reply) in server.step_replies.drain(..) {
+        let _ = reply.send(RpcResponse::new(id, Ok(json!({ "frame": clock.frame }))));
+    }
+}
+
+/// Returns the clock, which the simulation runs by.
+///
+/// A replay runs the frames of its log, so its clock can't be paused or stepped.
+fn sim_clock(world: &mut World) -> Result<Mut<'_, SimClock>, RpcError> {
+    if world.contains_resource::<Replay>() {
+        return Err(RpcError::new(SERVER_ERROR, "the replay runs the simulation"));
+    }
+
+    world
+        .get_resource_mut::<SimClock>()
+        .ok_or_else(|| RpcError::new(SERVER_ERROR, "the simulation has no clock"))
+}
+
+/// Executes the request, returns `None` if the response comes later.
+fn execute(
+    world: &mut World,
+    server: &mut ControlServer,
+    request: RpcRequest,
+    reply: &Sender<RpcResponse>,
+) -> Option<Result<Value, RpcError>> {
+    let id = request.id.clone().unwrap_or(Value::Null);
+    let params = request.params;
+
+    let result = match request.method.as_str() {
+        "get_state" => Ok(get_state(world)),
+        "get_sensors" => parse(params).and_then(|DroneParams { id }| get_sensors(world, id)),
+        "spawn_drone" => parse(params).and_then(|definition| spawn_drone(world, definition)),
+        "despawn_drone" => parse(params).and_then(|DroneParams { id }| despawn_drone(world, id)),
+        "spawn_object" => parse(params).map(|params| spawn_object(world, params)),
+        "despawn_object" => parse(params).and_then(|EntityParams { entity }| despawn_object(world, entity)),
+        "set_setpoint" => parse(params).and_then(|params| set_setpoint(world, params)),
+        "release_drone" => parse(params).and_then(|DroneParams { id }| release_drone(world, id)),
+        "set_vision_mode" => parse(params).and_then(|VisionModeParams { mode }| set_vision_mode(world, &mode)),
+        "pause" => sim_clock(world).map(|mut clock| {
+            clock.pause();
+            json!({ "frame": clock.frame })
+        }),
+        "resume" => sim_clock(world).map(|mut clock| {
+            clock.resume();
+            json!({ "frame": clock.frame })
+        }),
+        "step" => match parse(params).and_then(|StepParams { frames }| {
+            sim_clock(world).map(|mut clock| clock.step_frames(frames))
+        }) {
+            Ok(()) => {
+                server.step_replies.push((id, reply.clone()));
+                return None;
+            },
+            Err(error) => Err(error),
+        },
+        "grab_frame" => match grab_frame(world, id, reply) {
+            Ok(()) => return None,
+            Err(error) => Err(error),
+        },
+        method => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method {method}"))),
+    };
+
+    Some(result)
+}
+
+/// Reads the parameters of a method.
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+
+    serde_json::from_value(params).map_err(|error| RpcError::new(INVALID_PARAMS, error))
+}
+
+/// Returns the entity of the drone with the ID.
+fn find_drone(world: &mut World, id: u32) -> Result<Entity, RpcError> {
+    world
+        .query::<(Entity, &Drone)>()
+        .iter(world)
+        .find_map(|(entity, drone)| (drone.id == id).then_some(entity))
+        .ok_or_else(|| RpcError::new(SERVER_ERROR, format!("no drone {id}")))
+}
+
+/// Sends the event, if the app has it.
+fn send<E: Event>(world: &mut World, event: E) -> Result<(), RpcError> {
+    let mut events = world
+        .get_resource_mut::<Events<E>>()
+        .ok_or_else(|| RpcError::new(SERVER_ERROR, "the simulation doesn't support it"))?;
+
+    events.send(event);
+    Ok(())
+}
+
+/// Query for the drone states.
+type DroneStateQuery<'w, 's> = (
+    &'static Drone,
+    &'static Transform,
+    Option<&'static Velocity>,
+    Option<&'static Battery>,
+    Option<&'static Motors>,
+    Option<&'static FlightController>,
+    Has<Player>,
+);
+
+/// Returns states of all drones.
+fn get_state(world: &mut World) -> Value {
+    let mut drones: Vec<_> = world
+        .query::<DroneStateQuery>()
+        .iter(world)
+        .map(|(drone, transform, velocity, battery, motors, controller, is_player)| {
+            let setpoint = controller.and_then(|controller| controller.setpoint);
+
+            DroneStateMessage {
+                id: drone.id,
+                airframe: drone.airframe.clone(),
+                control: drone.control,
+                is_player,
+                position: transform.translation,
+                rotation: transform.rotation,
+                velocity: velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel),
+                angular_velocity: velocity.map_or(Vec3::ZERO, |velocity| velocity.angvel),
+                battery_charge: battery.map_or(1.0, Battery::charge),
+                is_armed: motors.map_or(true, |motors| motors.is_armed),
+                setpoint_velocity: setpoint.map(|setpoint| setpoint.velocity),
+                setpoint_yaw: setpoint.and_then(|setpoint| setpoint.yaw),
+            }
+        })
+        .collect();
+
+    drones.sort_by_key(|drone| drone.id);
+
+    let time = world.get_resource::<Time>().map_or(0.0, Time::elapsed_seconds);
+
+    json!({ "time": time, "drones": drones })
+}
+
+/// Returns sensor data of the drone: altitude, temperature, video link, camera and heat detections.
+fn get_sensors(world: &mut World, id: u32) -> Result<Value, RpcError> {
+    let entity = find_drone(world, id)?;
+    let drone = world.entity(entity);
+    let position = drone.get::<Transform>().map_or(Vec3::ZERO, |transform| transform.translation);
+    let ground = world.get_resource::<Terrain>().map_or(0.0, |terrain| terrain.height_at(position.xz()));
+
+    let temperature = drone.get::<Temperature>().map(|temperature| temperature.0);
+    let video_breakup = drone.get::<AnalogVideoLink>().map(|link| link.breakup(position));
+
+    let mut camera = world.query::<(&VisionMode, Option<&ThermalSensor>)>();
+    let camera = camera.iter(world).next().map(|(mode, thermal)| json!({
+        "vision_mode": vision_mode_name(*mode),
+        "is_shutter_closed": thermal.is_some_and(ThermalSensor::is_shutter_closed),
+    }));
+
+    let detections: Vec<_> = world
+        .get_resource::<HeatDetections>()
+        .map(|detections| {
+            detections.0
+                .iter()
+                .filter(|detection| detection.drone == id)
+                .map(|detection| json!({
+                    "position": detection.position,
+                    "temperature": detection.temperature,
+                    "time": detection.time,
+                }))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(json!({
+        "altitude": position.y - ground,
+        "temperature": temperature,
+        "video_breakup": video_breakup,
+        "camera": camera,
+        "heat_detections": detections,
+    }))
+}
+
+/// Spawns the drone, unless there is one with its ID.
+fn spawn_drone(world: &mut World, definition: DroneDefinition) -> Result<Value, RpcError> {
+    if find_drone(world, definition.id).is_ok() {
+        return Err(RpcError::new(SERVER_ERROR, format!("drone {} already exists", definition.id)));
+    }
+
+    let id = definition.id;
+    send(world, SpawnDrone(definition))?;
+
+    Ok(json!({ "id": id }))
+}
+
+/// Despawns the drone, the piloted one can't be despawned.
+fn despawn_drone(world: &mut World, id: u32) -> Result<Value, RpcError> {
+    let entity = find_drone(world, id)?;
+
+    if world.get::<Player>(entity).is_some() {
+        return Err(RpcError::new(SERVER_ERROR, format!("drone {id} is piloted")));
+    }
+
+    despawn_with_children_recursive(world, entity);
+
+    Ok(json!({ "id": id }))
+}
+
+/// Spawns an object with a collider, it has a mesh, if the app renders.
+fn spawn_object(world: &mut World, params: ObjectParams) -> Value {
+    let (collider, mesh) = match params.shape {
+        ObjectShape::Box { size } => {
+            (Collider::cuboid(size.x / 2.0, size.y / 2.0, size.z / 2.0), Mesh::from(Cuboid::from_size(size)))
+        },
+        ObjectShape::Sphere { radius } => (Collider::ball(radius), Mesh::from(Sphere::new(radius))),
+    };
+
+    let transform = Transform::from_translation(params.position);
+    let mut object = world.spawn((collider, TransformBundle::from(transform), ControlledObject));
+
+    if let Some(temperature) = params.temperature {
+        object.insert((Thermal, Temperature(temperature)));
+    }
+
+    let entity = object.id();
+    let mesh = world.get_resource_mut::<Assets<Mesh>>().map(|mut meshes| meshes.add(mesh));
+    let material = world.get_resource_mut::<Assets<StandardMaterial>>().map(|mut materials| materials.add(Color::GRAY));
+
+    if let (Some(mesh), Some(material)) = (mesh, material) {
+        world.entity_mut(entity).insert((mesh, material, VisibilityBundle::default()));
+    }
+
+    json!({ "entity": entity.to_bits() })
+}
+
+/// Despawns the object, which a client spawned.
+fn despawn_object(world: &mut World, bits: u64) -> Result<Value, RpcError> {
+    let entity = Entity::try_from_bits(bits)
+        .ok()
+        .filter(|&entity| world.get::<ControlledObject>(entity).is_some())
+        .ok_or_else(|| RpcError::new(SERVER_ERROR, format!("no object {bits}")))?;
+
+    despawn_with_children_recursive(world, entity);
+
+    Ok(json!({ "entity": bits }))
+}
+
+/// Gives the drone to the client and sets its setpoint. The piloted drone stays with the operator.
+fn set_setpoint(world: &mut World, params: SetpointParams) -> Result<Value, RpcError> {
+    let entity = find_drone(world, params.id)?;
+
+    if world.get::<Player>(entity).is_some() {
+        return Err(RpcError::new(SERVER_ERROR, format!("drone {} is piloted", params.id)));
+    }
+
+    world.get_mut::<Drone>(entity).unwrap().control = DroneControl::External;
+
+    send(world, DroneSetpoint {
+        id: params.id,
+        setpoint: Some(FlightSetpoint { velocity: params.velocity, yaw: params.yaw }),
+    })?;
+
+    Ok(json!({ "id": params.id }))
+}
+
+/// Releases the setpoint of the drone and gives it back to the autopilot.
+fn release_drone(world: &mut World, id: u32) -> Result<Value, RpcError> {
+    let entity = find_drone(world, id)?;
+    let mut drone = world.get_mut::<Drone>(entity).unwrap();
+
+    if drone.control == DroneControl::External {
+        drone.control = DroneControl::Autopilot;
+
+        if let Some(mut controller) = world.get_mut::<FlightController>(entity) {
+            controller.setpoint = None;
+        }
+
+        // the input log records the release
+        send(world, DroneSetpoint { id, setpoint: None })?;
+    }
+
+    Ok(json!({ "id": id }))
+}
+
+/// Returns the name of the vision mode, which clients use.
+fn vision_mode_name(mode: VisionMode) -> &'static str {
+    match mode {
+        VisionMode::Day => "day",
+        VisionMode::Thermal => "thermal",
+        VisionMode::NightVision => "night-vision",
+    }
+}
+
+/// Switches the camera to the vision mode with the name.
+fn set_vision_mode(world: &mut World, name: &str) -> Result<Value, RpcError> {
+    let mode = [VisionMode::Day, VisionMode::Thermal, VisionMode::NightVision]
+        .into_iter()
+        .find(|&mode| vision_mode_name(mode) == name)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("unknown vision mode {name}")))?;
+
+    send(world, SetVisionMode(mode))?;
+
+    Ok(json!({ "mode": name }))
+}
+
+/// Takes a screenshot of the window, the response comes when it is saved.
+fn grab_frame(
+    world: &mut World,
+    id: Value,
+    reply: &Sender<RpcResponse>,
+) -> Result<(), RpcError> {
+    let window = world
+        .query_filtered::<Entity, With<PrimaryWindow>>()
+        .iter(world)
+        .next()
+        .ok_or_else(|| RpcError::new(SERVER_ERROR, "the simulation has no window"))?;
+
+    let directory = world.resource::<ControlServerSettings>().frame_directory.clone();
+    let frame = world.get_resource::<SimClock>().map_or(0, |clock| clock.frame);
+    let path = directory.join(format!("frame_{frame:06}.png"));
+    let reply = reply.clone();
+
+    let mut screenshots = world
+        .get_resource_mut::<ScreenshotManager>()
+        .ok_or_else(|| RpcError::new(SERVER_ERROR, "the simulation doesn't render"))?;
+
+    screenshots
+        .take_screenshot(window, move |image| {
+            let (width, height) = (image.width(), image.height());
+
+            let result = std::fs::create_dir_all(&directory)
+                .map_err(|error| error.to_string())
+                .and_then(|()| image.try_into_dynamic().map_err(|error| error.to_string()))
+                .and_then(|image| image.to_rgb8().save(&path).map_err(|error| error.to_string()))
+                .map(|()| json!({ "path": path, "width": width, "height": height }))
+                .map_err(|error| RpcError::new(SERVER_ERROR, error));
+
+            let _ = reply.send(RpcResponse::new(id, result));
+        })
+        .map_err(|error| RpcError::new(SERVER_ERROR, error))
+}
+
+/// Reads requests of the client and writes responses to them.
+fn serve_client(stream: TcpStream, requests: Sender<ControlRequest>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let (reply, responses) = mpsc::channel();
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request = match serde_json::from_str::<RpcRequest>(&line) {
+            Ok(request) => request,
+            Err(error) => {
+                let response = RpcResponse::new(Value::Null, Err(RpcError::new(PARSE_ERROR, error)));
+                writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+                continue;
+            },
+        };
+
+        let is_notification = request.id.is_none();
+
+        if requests.send(ControlRequest { request, reply: reply.clone() }).is_err() {
+            break;
+        }
+
+        let Ok(response) = responses.recv() else {
+            break;
+        };
+
+        if !is_notification {
+            writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,429 @@
+use bevy::prelude::*;
+
+use crate::{
+    autopilot::{AutopilotProgress, AutopilotStatus, StopWaypointMission},
+    geofence::{BreachAction, Geofence, GeofenceStatus},
+    player::{Battery, FlightController, FlightSetpoint, HomePosition, Motors, Player, LANDED_HEIGHT},
+    terrain::Terrain,
+};
+
+/// Plugin for the drone failsafe.
+///
+/// Low battery, lost RC link, lost GPS and geofence breaches take control from the pilot and the autopilot,
+/// the drone hovers, lands, returns to launch or disarms.
+pub struct FailsafePlugin;
+
+impl Plugin for FailsafePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FailsafeSettings>()
+            .init_resource::<FailureInjection>()
+            .add_systems(Startup, setup_failsafe_hud)
+            .add_systems(Update, (
+                inject_failures,
+                trigger_failsafes,
+                run_failsafes,
+                update_failsafe_hud,
+            ).chain());
+    }
+}
+
+// resources
+/// What triggers the failsafe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailsafeTrigger {
+    LowBattery,
+    CriticalBattery,
+    RcLinkLoss,
+    GpsLoss,
+    GeofenceBreach,
+}
+
+/// What the failsafe does. Actions are ordered by severity, a more severe action overrides a less severe one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FailsafeAction {
+    Hover,
+    ReturnToLaunch,
+    Land,
+    Disarm,
+}
+
+/// Describes the failsafe.
+///
+/// Battery triggers are charges from 0.0 to 1.0. RC link is lost, when there was no pilot input for `rc_timeout` seconds,
+/// there is no timeout by default, because the keyboard is silent while the drone hovers.
+///
+/// Return to launch climbs to `return_altitude` meters above the launch pad, unless the drone is higher,
+/// and flies home at `return_speed` meters per second, keeping at least `terrain_clearance` meters above the terrain.
+///
+/// Press `rc_failure_key` or `gps_failure_key` to inject failures.
+#[derive(Resource, Debug, Clone)]
+pub struct FailsafeSettings {
+    pub low_battery: f32,
+    pub low_battery_action: FailsafeAction,
+    pub critical_battery: f32,
+    pub critical_battery_action: FailsafeAction,
+    pub rc_timeout: Option<f32>,
+    pub rc_loss_action: FailsafeAction,
+    pub gps_loss_action: FailsafeAction,
+    pub return_altitude: f32,
+    pub terrain_clearance: f32,
+    pub return_speed: f32,
+    pub climb_speed: f32,
+    pub landing_speed: f32,
+    pub rc_failure_key: KeyCode,
+    pub gps_failure_key: KeyCode,
+}
+
+impl Default for FailsafeSettings {
+    fn default() -> Self {
+        Self {
+            low_battery: 0.2,
+            low_battery_action: FailsafeAction::ReturnToLaunch,
+            critical_battery: 0.1,
+            critical_battery_action: FailsafeAction::Land,
+            rc_timeout: None,
+            rc_loss_action: FailsafeAction::ReturnToLaunch,
+            gps_loss_action: FailsafeAction::Land,
+            return_altitude: 30.0,
+            terrain_clearance: 10.0,
+            return_speed: 8.0,
+            climb_speed: 3.0,
+            landing_speed: 1.0,
+            rc_failure_key: KeyCode::F7,
+            gps_failure_key: KeyCode::F8,
+        }
+    }
+}
+
+/// Failures injected into the `Player` drone.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct FailureInjection {
+    pub rc_link_lost: bool,
+    pub gps_lost: bool,
+}
+
+// components
+/// Phase of the return to launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnPhase {
+    Climb,
+    Return,
+    Land,
+}
+
+/// State of the failsafe.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FailsafeState {
+    #[default]
+    Normal,
+    Hover { point: Vec3 },
+    ReturnToLaunch(ReturnPhase),
+    Land,
+    Disarmed,
+}
+
+impl FailsafeState {
+    /// Returns the action of the state.
+    pub fn action(&self) -> Option<FailsafeAction> {
+        match self {
+            Self::Normal => None,
+            Self::Hover { .. } => Some(FailsafeAction::Hover),
+            Self::ReturnToLaunch(_) => Some(FailsafeAction::ReturnToLaunch),
+            Self::Land => Some(FailsafeAction::Land),
+            Self::Disarmed => Some(FailsafeAction::Disarm),
+        }
+    }
+}
+
+/// Failsafe of the drone: its state and the trigger, which caused it.
+#[derive(Component, Debug, Default, Clone)]
+pub struct Failsafe {
+    pub state: FailsafeState,
+    pub trigger: Option<FailsafeTrigger>,
+    /// Time of the last pilot input in seconds of `Time::elapsed_seconds`.
+    pub last_input: f32,
+}
+
+impl Failsafe {
+    /// Starts the action, unless the current one is more severe.
+    ///
+    /// Without GPS the drone doesn't know where it is, so it can't hover in place or return, it lands instead.
+    pub fn engage(&mut self, trigger: FailsafeTrigger, action: FailsafeAction, hover_point: Vec3, has_gps: bool) {
+        let action = match action {
+            FailsafeAction::Hover | FailsafeAction::ReturnToLaunch if !has_gps => FailsafeAction::Land,
+            action => action,
+        };
+
+        if self.state.action().is_some_and(|current| current >= action) {
+            return;
+        }
+
+        warn!("failsafe {trigger:?}: {action:?}");
+
+        self.trigger = Some(trigger);
+        self.state = match action {
+            FailsafeAction::Hover => FailsafeState::Hover { point: hover_point },
+            FailsafeAction::ReturnToLaunch => FailsafeState::ReturnToLaunch(ReturnPhase::Climb),
+            FailsafeAction::Land => FailsafeState::Land,
+            FailsafeAction::Disarm => FailsafeState::Disarmed,
+        };
+    }
+
+    /// Gives control back to the pilot.
+    pub fn reset(&mut self) {
+        self.state = FailsafeState::Normal;
+        self.trigger = None;
+    }
+}
+
+/// Describes the failsafe HUD text.
+#[derive(Component)]
+struct FailsafeHudText;
+
+// systems
+/// System that injects failures by keys.
+fn inject_failures(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<FailsafeSettings>,
+    mut injection: ResMut<FailureInjection>,
+) {
+    if keys.just_pressed(settings.rc_failure_key) {
+        injection.rc_link_lost = !injection.rc_link_lost;
+    }
+
+    if keys.just_pressed(settings.gps_failure_key) {
+        injection.gps_lost = !injection.gps_lost;
+    }
+}
+
+/// System that engages failsafes of the `Player`s.
+///
+/// Hovering ends, when its trigger is gone, and the restored RC link gives control back to the pilot.
+/// Other actions go on until the drone is disarmed.
+#[allow(clippy::too_many_arguments)]
+pub fn trigger_failsafes(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<FailsafeSettings>,
+    injection: Res<FailureInjection>,
+    geofence: Option<Res<Geofence>>,
+    geofence_status: Option<Res<GeofenceStatus>>,
+    autopilot: Option<Res<AutopilotProgress>>,
+    mut drones: Query<(&mut Failsafe, &mut FlightController, &Transform, Option<&Battery>), With<Player>>,
+    mut stop_mission: EventWriter<StopWaypointMission>,
+) {
+    let now = time.elapsed_seconds();
+
+    let breach_action = geofence
+        .zip(geofence_status.as_ref())
+        .filter(|(_, status)| status.breach.is_some())
+        .and_then(|(geofence, _)| match geofence.action {
+            BreachAction::Warn => None,
+            BreachAction::Hold => Some(FailsafeAction::Hover),
+            BreachAction::ReturnToHome => Some(FailsafeAction::ReturnToLaunch),
+            BreachAction::Land => Some(FailsafeAction::Land),
+        });
+
+    for (mut failsafe, mut controller, transform, battery) in &mut drones {
+        if keys.get_pressed().next().is_some() {
+            failsafe.last_input = now;
+        }
+
+        let is_rc_lost = injection.rc_link_lost
+            || settings.rc_timeout.is_some_and(|timeout| now - failsafe.last_input > timeout);
+        let charge = battery.map_or(1.0, |battery| battery.charge());
+        let has_gps = !injection.gps_lost;
+
+        let mut triggers = Vec::new();
+
+        if charge <= settings.critical_battery {
+            triggers.push((FailsafeTrigger::CriticalBattery, settings.critical_battery_action));
+        } else if charge <= settings.low_battery {
+            triggers.push((FailsafeTrigger::LowBattery, settings.low_battery_action));
+        }
+
+        if is_rc_lost {
+            triggers.push((FailsafeTrigger::RcLinkLoss, settings.rc_loss_action));
+        }
+
+        if !has_gps {
+            triggers.push((FailsafeTrigger::GpsLoss, settings.gps_loss_action));
+        }
+
+        if let Some(action) = breach_action {
+            triggers.push((FailsafeTrigger::GeofenceBreach, action));
+        }
+
+        // the last point inside the fence is where the breach is safe to hold
+        let hover_point = |trigger: FailsafeTrigger| match trigger {
+            FailsafeTrigger::GeofenceBreach => geofence_status
+                .as_ref()
+                .and_then(|status| status.last_inside)
+                .unwrap_or(transform.translation),
+            _ => transform.translation,
+        };
+
+        // the trigger is gone
+        if let Some(trigger) = failsafe.trigger {
+            let is_active = triggers.iter().any(|(active, _)| *active == trigger);
+            let is_resolvable = matches!(failsafe.state, FailsafeState::Hover { .. }) || trigger == FailsafeTrigger::RcLinkLoss;
+
+            if !is_active && is_resolvable && failsafe.state != FailsafeState::Disarmed {
+                info!("failsafe {trigger:?} is over");
+
+                failsafe.reset();
+                controller.setpoint = None;
+            }
+        }
+
+        let was_normal = failsafe.state == FailsafeState::Normal;
+
+        for (trigger, action) in triggers {
+            failsafe.engage(trigger, action, hover_point(trigger), has_gps);
+        }
+
+        let is_mission_flying = autopilot.as_ref().is_some_and(|autopilot| autopilot.status == AutopilotStatus::Flying);
+
+        if was_normal && failsafe.state != FailsafeState::Normal && is_mission_flying {
+            stop_mission.send_default();
+        }
+    }
+}
+
+/// System that flies the drones with engaged failsafes.
+///
+/// Landed drones are disarmed.
+pub fn run_failsafes(
+    settings: Res<FailsafeSettings>,
+    terrain: Option<Res<Terrain>>,
+    mut drones: Query<(&mut Failsafe, &mut FlightController, &mut Motors, &Transform, Option<&HomePosition>)>,
+) {
+    for (mut failsafe, mut controller, mut motors, transform, home) in &mut drones {
+        let position = transform.translation;
+        let home = home.map_or(Vec3::ZERO, |home| home.0);
+        let ground = terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position.xz()));
+        let is_landed = position.y - ground < LANDED_HEIGHT;
+
+        let landing = Vec3::NEG_Y * settings.landing_speed;
+
+        let velocity = match failsafe.state {
+            FailsafeState::Normal => continue,
+            FailsafeState::Disarmed => {
+                motors.is_armed = false;
+                controller.setpoint = None;
+                continue;
+            },
+            FailsafeState::Hover { point } => ((point - position) * 1.0).clamp_length_max(5.0),
+            FailsafeState::ReturnToLaunch(phase) => {
+                let altitude = (home.y + settings.return_altitude).max(ground + settings.terrain_clearance);
+
+                match phase {
+                    ReturnPhase::Climb if position.y < altitude => Vec3::Y * settings.climb_speed,
+                    ReturnPhase::Climb => {
+                        failsafe.state = FailsafeState::ReturnToLaunch(ReturnPhase::Return);
+                        Vec3::ZERO
+                    },
+                    ReturnPhase::Return => {
+                        let to_home = home.xz() - position.xz();
+
+                        if to_home.length() < 1.5 {
+                            failsafe.state = FailsafeState::ReturnToLaunch(ReturnPhase::Land);
+                        }
+
+                        let horizontal = to_home.clamp_length_max(settings.return_speed);
+                        Vec3::new(horizontal.x, (altitude - position.y).max(0.0), horizontal.y)
+                    },
+                    ReturnPhase::Land => {
+                        let to_home = home.xz() - position.xz();
+                        Vec3::new(to_home.x, 0.0, to_home.y) + landing
+                    },
+                }
+            },
+            FailsafeState::Land => landing,
+        };
+
+        let is_landing = matches!(failsafe.state, FailsafeState::Land | FailsafeState::ReturnToLaunch(ReturnPhase::Land));
+
+        if is_landing && is_landed {
+            info!("landed, motors are disarmed");
+
+            failsafe.state = FailsafeState::Disarmed;
+            motors.is_armed = false;
+            controller.setpoint = None;
+            continue;
+        }
+
+        controller.setpoint = Some(FlightSetpoint { velocity, yaw: None });
+    }
+}
+
+/// Failsafe HUD initialization.
+fn setup_failsafe_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                        font_size: 18.0,
+                        color: Color::ORANGE,
+                    },
+                ),
+                FailsafeHudText,
+            ));
+        });
+}
+
+/// System that shows the failsafe state and the injected failures.
+fn update_failsafe_hud(
+    injection: Res<FailureInjection>,
+    drones: Query<&Failsafe, With<Player>>,
+    mut query: Query<&mut Text, With<FailsafeHudText>>,
+) {
+    let Ok(failsafe) = drones.get_single() else {
+        return;
+    };
+
+    let state = match failsafe.state {
+        FailsafeState::Normal => "OK".to_owned(),
+        FailsafeState::Hover { .. } => "hover".to_owned(),
+        FailsafeState::ReturnToLaunch(phase) => format!("return to launch ({phase:?})").to_lowercase(),
+        FailsafeState::Land => "land".to_owned(),
+        FailsafeState::Disarmed => "disarmed".to_owned(),
+    };
+
+    let mut value = format!("Failsafe: {state}");
+
+    if let Some(trigger) = failsafe.trigger {
+        value.push_str(&format!(" ({trigger:?})"));
+    }
+
+    if injection.rc_link_lost {
+        value.push_str("  [RC LINK LOST]");
+    }
+
+    if injection.gps_lost {
+        value.push_str("  [GPS LOST]");
+    }
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use std::{collections::VecDeque, fmt, fs, io, path::{Path, PathBuf}};
+
+use bevy::{pbr::ExtendedMaterial, prelude::*, render::view::RenderLayers};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arming::Arming,
+    autopilot::{plan::read_qgc_plan_file, AutopilotProgress, AutopilotStatus, DroneMission, StopWaypointMission},
+    camera_sensor::AnalogVideoLink,
+    failsafe::{Failsafe, FailsafeState},
+    materials::{SolarHeating, Temperature, Thermal, ThermalMaterialExtension},
+    player::{Battery, FlightController, FlightSetpoint, HomePosition, Motors, Player},
+    terrain::Terrain,
+    ui::menu::AppState,
+    weather::WindDrag,
+};
+
+/// Plugin for the fleet of drones.
+///
+/// Drones are spawned from airframe definitions. The operator pilots one of them, the `Player`,
+/// and switches between them. Other drones are flown by the autopilot or by external programs.
+pub struct FleetPlugin;
+
+impl Plugin for FleetPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Fleet>()
+            .init_resource::<FleetBindings>()
+            .add_event::<SpawnDrone>()
+            .add_event::<SelectDrone>()
+            .add_event::<DroneSetpoint>()
+            .add_systems(Startup, (spawn_fleet, setup_fleet_hud))
+            .add_systems(Update, (
+                switch_drone_input.run_if(in_state(AppState::InFlight)),
+                spawn_drones,
+                select_drones,
+                fly_unpiloted_drones,
+                record_telemetry,
+                update_fleet_hud,
+            ).chain());
+    }
+}
+
+/// Standard gravity in meters per second squared.
+const GRAVITY: f32 = 9.81;
+
+/// Error that can happen while reading an airframe or a fleet file.
+#[derive(Debug)]
+pub enum FleetError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for FleetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't access fleet file: {error}"),
+            Self::Parse(error) => write!(f, "can't parse fleet file: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for FleetError {}
+
+impl From<io::Error> for FleetError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for FleetError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+// resources
+/// Describes a drone model.
+///
+/// `dimensions` of the body are in meters, `mass` is in kilograms and `max_thrust` is the thrust in newtons,
+/// which the flight controller can add. Battery `capacity` is in watt-hours, it gives `hover_power` watts while hovering.
+/// `color` is the RGB color of the body, `internal_heat` is the heat of the electronics in degrees above the air.
+///
+/// Airframes are stored in RON files in `assets/airframes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Airframe {
+    pub name: String,
+    pub model: String,
+    pub dimensions: Vec3,
+    pub mass: f32,
+    pub max_thrust: f32,
+    pub battery_capacity: f32,
+    pub hover_power: f32,
+    pub internal_heat: f32,
+    pub color: [f32; 3],
+}
+
+impl Default for Airframe {
+    /// Quadcopter of the `Player`.
+    fn default() -> Self {
+        Self {
+            name: "quadcopter".to_owned(),
+            model: "models/drone-model.glb#Mesh0/Primitive0".to_owned(),
+            dimensions: Vec3::new(2.5, 1.0, 3.0),
+            mass: 7.5,
+            max_thrust: 60.0,
+            battery_capacity: 77.0,
+            hover_power: 180.0,
+            internal_heat: 10.0,
+            color: [1.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl Airframe {
+    /// Reads the airframe file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FleetError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Returns the file of the airframe with the given name.
+    pub fn file_path(name: &str) -> PathBuf {
+        PathBuf::from("assets/airframes").join(format!("{name}.ron"))
+    }
+
+    /// Returns thrust in newtons, which holds the drone in the air.
+    pub fn hover_thrust(&self) -> f32 {
+        self.mass * GRAVITY
+    }
+}
+
+/// Describes a drone of the fleet.
+///
+/// `airframe` is the name of the airframe file, `mission` is an optional QGroundControl plan,
+/// which the drone flies while it is not piloted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroneDefinition {
+    pub id: u32,
+    pub airframe: String,
+    pub position: Vec3,
+    #[serde(default)]
+    pub control: DroneControl,
+    #[serde(default)]
+    pub mission: Option<PathBuf>,
+}
+
+impl DroneDefinition {
+    /// Creates quadcopter definition, which is flown by the autopilot.
+    pub fn new(id: u32, position: Vec3) -> Self {
+        Self {
+            id,
+            airframe: "quadcopter".to_owned(),
+            position,
+            control: DroneControl::default(),
+            mission: None,
+        }
+    }
+}
+
+/// Describes drones, which are spawned at the start, and the drone the operator pilots first.
+///
+/// Fleets are stored in RON files.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fleet {
+    pub drones: Vec<DroneDefinition>,
+    pub selected: u32,
+}
+
+impl Default for Fleet {
+    /// Quadcopter of the `Player` on the launch pad and its swarm of two scouts next to it.
+    fn default() -> Self {
+        let scout = |id, position| DroneDefinition {
+            airframe: "scout".to_owned(),
+            control: DroneControl::Swarm,
+            ..DroneDefinition::new(id, position)
+        };
+
+        // disarmed on the launch pad
+        Self {
+            drones: vec![
+                DroneDefinition::new(0, Vec3::new(0.0, 0.55, 0.0)),
+                scout(1, Vec3::new(-6.0, 0.35, 0.0)),
+                scout(2, Vec3::new(6.0, 0.35, 0.0)),
+            ],
+            selected: 0,
+        }
+    }
+}
+
+impl Fleet {
+    /// Reads the fleet file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FleetError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Key bindings of the fleet.
+///
+/// `next` switches to the drone with the next ID, `select` keys switch to the drones in the order of their IDs.
+#[derive(Resource)]
+pub struct FleetBindings {
+    pub next: KeyCode,
+    pub select: Vec<KeyCode>,
+}
+
+impl Default for FleetBindings {
+    fn default() -> Self {
+        Self {
+            next: KeyCode::KeyC,
+            select: vec![
+                KeyCode::Digit1,
+                KeyCode::Digit2,
+                KeyCode::Digit3,
+                KeyCode::Digit4,
+                KeyCode::Digit5,
+                KeyCode::Digit6,
+                KeyCode::Digit7,
+                KeyCode::Digit8,
+                KeyCode::Digit9,
+            ],
+        }
+    }
+}
+
+// events
+/// Event to spawn a drone. The drone with the `Fleet::selected` ID becomes the `Player`, if there is none.
+#[derive(Event, Debug, Clone)]
+pub struct SpawnDrone(pub DroneDefinition);
+
+/// Event to give the operator control and view of the drone with the ID.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectDrone(pub u32);
+
+/// Event to set the setpoint of the drone, which is flown by an external program. `None` releases the drone.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DroneSetpoint {
+    pub id: u32,
+    pub setpoint: Option<FlightSetpoint>,
+}
+
+// components
+/// Who flies the drone, while the operator doesn't pilot it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DroneControl {
+    /// The drone flies its `DroneMission` or holds its position.
+    #[default]
+    Autopilot,
+    /// The drone holds `DroneSetpoint`s.
+    External,
+    /// The drone is a member of the `Swarm`.
+    Swarm,
+}
+
+/// Describes a drone of the fleet. The operator pilots the one with the `Player` marker.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Drone {
+    pub id: u32,
+    pub airframe: String,
+    pub control: DroneControl,
+}
+
+/// Sample of the drone state.
+///
+/// `time` is in seconds since the start, `altitude` is in meters above the ground and `charge` is from 0.0 to 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TelemetrySample {
+    pub time: f32,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub altitude: f32,
+    pub charge: f32,
+    pub is_armed: bool,
+    pub setpoint: Option<FlightSetpoint>,
+}
+
+/// Telemetry stream of the drone: `rate` samples per second, the last `capacity` of them are kept.
+#[derive(Component, Debug, Clone)]
+pub struct Telemetry {
+    pub rate: f32,
+    pub capacity: usize,
+    pub samples: VecDeque<TelemetrySample>,
+    /// Seconds since the last sample.
+    pub since_sample: f32,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            rate: 10.0,
+            capacity: 600,
+            samples: VecDeque::new(),
+            since_sample: 0.0,
+        }
+    }
+}
+
+impl Telemetry {
+    /// Returns the last sample.
+    pub fn latest(&self) -> Option<&TelemetrySample> {
+        self.samples.back()
+    }
+}
+
+/// Describes the fleet HUD text.
+#[derive(Component)]
+struct FleetHudText;
+
+// systems
+/// System that spawns drones of the `Fleet`.
+fn spawn_fleet(
+    fleet: Res<Fleet>,
+    mut spawn: EventWriter<SpawnDrone>,
+) {
+    for definition in &fleet.drones {
+        spawn.send(SpawnDrone(definition.clone()));
+    }
+}
+
+/// System that spawns drones from their airframes.
+///
+/// Missing airframe files are replaced by the default quadcopter.
+pub fn spawn_drones(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    fleet: Res<Fleet>,
+    mut spawn: EventReader<SpawnDrone>,
+    players: Query<(), With<Player>>,
+    mut ext_materials: ResMut<Assets<ExtendedMaterial<StandardMaterial, ThermalMaterialExtension>>>,
+) {
+    let mut has_player = !players.is_empty();
+
+    for SpawnDrone(definition) in spawn.read() {
+        let airframe = Airframe::load(Airframe::file_path(&definition.airframe)).unwrap_or_else(|error| {
+            warn!("airframe {}: {error}", definition.airframe);
+            Airframe::default()
+        });
+
+        let mission = definition.mission.as_ref().map(|path| {
+            DroneMission::new(read_qgc_plan_file(path).unwrap_or_else(|error| {
+                warn!("{error}");
+                Vec::new()
+            }))
+        });
+
+        let position = definition.position;
+        let dimensions = airframe.dimensions;
+        let [red, green, blue] = airframe.color;
+
+        let drone = (
+            MaterialMeshBundle {
+                mesh: server.load::<Mesh>(airframe.model.clone()),
+                material: ext_materials.add(ExtendedMaterial {
+                    base: StandardMaterial {
+                        base_color: Color::rgb(red, green, blue),
+                        ..default()
+                    },
+                    extension: ThermalMaterialExtension {
+                        temperature: 15.0,
+                        intensity: 1.0,
+                        is_infrared_mode_active: 0,
+                        ..default()
+                    },
+                }),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            Thermal,
+            Temperature(15.0),
+            SolarHeating {
+                internal_heat: airframe.internal_heat,
+                ..default()
+            },
+            RigidBody::Dynamic,
+            GravityScale(1.0),
+            ExternalForce::default(),
+            ExternalImpulse::default(),
+            Velocity::default(),
+            WindDrag::default(),
+            (
+                Motors {
+                    hover_thrust: airframe.hover_thrust(),
+                    ..default()
+                },
+                Battery {
+                    capacity: airframe.battery_capacity,
+                    remaining: airframe.battery_capacity,
+                    hover_power: airframe.hover_power,
+                    hover_thrust: airframe.hover_thrust(),
+                },
+                FlightController {
+                    mass: airframe.mass,
+                    max_thrust: airframe.max_thrust,
+                    ..default()
+                },
+                Failsafe::default(),
+                HomePosition(position),
+                Arming::default(),
+            ),
+            (
+                Drone {
+                    id: definition.id,
+                    airframe: airframe.name.clone(),
+                    control: definition.control,
+                },
+                Telemetry::default(),
+                // the pilot stands at the launch point
+                AnalogVideoLink {
+                    pilot_position: position,
+                    ..default()
+                },
+            ),
+            (
+                Collider::cuboid(dimensions.x / 2.0, dimensions.y / 2.0, dimensions.z / 2.0),
+                ColliderMassProperties::Mass(airframe.mass),
+            ),
+            Name::new(format!("Drone {}", definition.id)),
+            RenderLayers::layer(1),
+        );
+
+        let mut entity = commands.spawn(drone);
+
+        if let Some(mission) = mission {
+            entity.insert(mission);
+        }
+
+        if !has_player && definition.id == fleet.selected {
+            entity.insert(Player);
+            has_player = true;
+        }
+    }
+}
+
+/// System that turns the fleet key bindings into `SelectDrone` events.
+fn switch_drone_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<FleetBindings>,
+    drones: Query<(&Drone, Has<Player>)>,
+    mut select: EventWriter<SelectDrone>,
+) {
+    let mut ids: Vec<(u32, bool)> = drones.iter().map(|(drone, is_player)| (drone.id, is_player)).collect();
+    ids.sort_unstable();
+
+    if keys.just_pressed(bindings.next) {
+        let current = ids.iter().position(|(_, is_player)| *is_player);
+        let next = current.map_or(0, |index| index + 1) % ids.len().max(1);
+
+        if let Some((id, _)) = ids.get(next) {
+            select.send(SelectDrone(*id));
+        }
+    }
+
+    for (key, (id, _)) in bindings.select.iter().zip(&ids) {
+        if keys.just_pressed(*key) {
+            select.send(SelectDrone(*id));
+        }
+    }
+}
+
+/// Query for the drones, which can be selected.
+type SelectableDroneQuery<'w, 's> = Query<'w, 's,
+    (Entity, &'static Drone, &'static mut FlightController, Option<&'static Failsafe>, Has<Player>),
+>;
+
+/// System that gives the operator control and view of the selected drone.
+///
+/// The mission of the `Player` is stopped, the drone it flew is left to its `DroneControl`.
+/// The pilot takes over the selected drone, unless its failsafe is engaged.
+pub fn select_drones(
+    mut commands: Commands,
+    mut select: EventReader<SelectDrone>,
+    progress: Option<Res<AutopilotProgress>>,
+    mut drones: SelectableDroneQuery,
+    mut stop_mission: EventWriter<StopWaypointMission>,
+) {
+    let Some(SelectDrone(id)) = select.read().last().copied() else {
+        return;
+    };
+
+    let Some((selected, ..)) = drones.iter().find(|(_, drone, ..)| drone.id == id) else {
+        warn!("there is no drone {id}");
+        return;
+    };
+
+    for (entity, _, mut controller, failsafe, is_player) in &mut drones {
+        if entity == selected && !is_player {
+            commands.entity(entity).insert(Player);
+
+            if failsafe.map_or(true, |failsafe| failsafe.state == FailsafeState::Normal) {
+                controller.setpoint = None;
+            }
+        } else if entity != selected && is_player {
+            commands.entity(entity).remove::<Player>();
+
+            if progress.as_ref().is_some_and(|progress| progress.status == AutopilotStatus::Flying) {
+                stop_mission.send_default();
+            }
+        }
+    }
+}
+
+/// Query for the drones, which are not piloted.
+type UnpilotedDroneQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static Drone,
+        &'static mut FlightController,
+        Option<&'static Motors>,
+        Option<&'static Failsafe>,
+        Option<&'static DroneMission>,
+    ),
+    Without<Player>,
+>;
+
+/// System that flies the drones, which are not piloted.
+///
+/// Armed drones of the autopilot hold their position, when they don't fly their missions.
+/// Drones of external programs hold their `DroneSetpoint`s. Engaged failsafes fly the drones themselves.
+pub fn fly_unpiloted_drones(
+    mut setpoints: EventReader<DroneSetpoint>,
+    mut drones: UnpilotedDroneQuery,
+) {
+    let setpoints: Vec<DroneSetpoint> = setpoints.read().copied().collect();
+
+    for (drone, mut controller, motors, failsafe, mission) in &mut drones {
+        if failsafe.is_some_and(|failsafe| failsafe.state != FailsafeState::Normal) {
+            continue;
+        }
+
+        match drone.control {
+            DroneControl::Autopilot => {
+                let is_flying_mission = mission.is_some_and(|mission| mission.progress.status == AutopilotStatus::Flying);
+                let is_armed = motors.map_or(true, |motors| motors.is_armed);
+
+                if !is_flying_mission && is_armed && controller.setpoint.is_none() {
+                    controller.setpoint = Some(FlightSetpoint { velocity: Vec3::ZERO, yaw: None });
+                }
+            },
+            DroneControl::External => {
+                for command in setpoints.iter().filter(|command| command.id == drone.id) {
+                    controller.setpoint = command.setpoint;
+                }
+            },
+            DroneControl::Swarm => {},
+        }
+    }
+}
+
+/// Query for the drones with telemetry.
+type TelemetryQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static mut Telemetry,
+        &'static Transform,
+        Option<&'static Velocity>,
+        Option<&'static Battery>,
+        Option<&'static Motors>,
+        Option<&'static FlightController>,
+    ),
+>;
+
+/// System that records telemetry samples of the drones.
+pub fn record_telemetry(
+    time: Res<Time>,
+    terrain: Option<Res<Terrain>>,
+    mut drones: TelemetryQuery,
+) {
+    for (mut telemetry, transform, velocity, battery, motors, controller) in &mut drones {
+        telemetry.since_sample += time.delta_seconds();
+
+        if !telemetry.samples.is_empty() && telemetry.since_sample < 1.0 / telemetry.rate.max(f32::EPSILON) {
+            continue;
+        }
+
+        telemetry.since_sample = 0.0;
+
+        let position = transform.translation;
+        let ground = terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position.xz()));
+
+        let sample = TelemetrySample {
+            time: time.elapsed_seconds(),
+            position,
+            rotation: transform.rotation,
+            velocity: velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel),
+            angular_velocity: velocity.map_or(Vec3::ZERO, |velocity| velocity.angvel),
+            altitude: position.y - ground,
+            charge: battery.map_or(1.0, Battery::charge),
+            is_armed: motors.map_or(true, |motors| motors.is_armed),
+            setpoint: controller.and_then(|controller| controller.setpoint),
+        };
+
+        telemetry.samples.push_back(sample);
+
+        while telemetry.samples.len() > telemetry.capacity {
+            telemetry.samples.pop_front();
+        }
+    }
+}
+
+/// Fleet HUD initialization.
+fn setup_fleet_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                font_size: 15.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(130.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        FleetHudText,
+    ));
+}
+
+/// System that shows the drones of the fleet, the piloted one is marked.
+fn update_fleet_hud(
+    drones: Query<(&Drone, &Telemetry, Option<&DroneMission>, Has<Player>)>,
+    mut query: Query<&mut Text, With<FleetHudText>>,
+) {
+    let mut drones: Vec<_> = drones.iter().collect();
+    drones.sort_unstable_by_key(|(drone, ..)| drone.id);
+
+    let mut value = String::new();
+
+    // a single drone is not a fleet
+    if drones.len() > 1 {
+        value.push_str("Fleet:");
+
+        for (drone, telemetry, mission, is_player) in drones {
+            let control = match (is_player, drone.control, mission.map(|mission| &mission.progress)) {
+                (true, ..) => "piloted".to_owned(),
+                (false, DroneControl::Autopilot, Some(progress)) if progress.status == AutopilotStatus::Flying => {
+                    format!("mission {}/{}", progress.current + 1, mission.map_or(0, |mission| mission.waypoints.len()))
+                },
+                (false, DroneControl::Autopilot, _) => "autopilot".to_owned(),
+                (false, DroneControl::External, _) => "external".to_owned(),
+                (false, DroneControl::Swarm, _) => "swarm".to_owned(),
+            };
+
+            value.push_str(&format!("\n{} {}. {} {control}", if is_player { ">" } else { " " }, drone.id, drone.airframe));
+
+            if let Some(sample) = telemetry.latest() {
+                value.push_str(&format!(
+                    ", {:.0} m, {:.0}%{}",
+                    sample.altitude,
+                    sample.charge * 100.0,
+                    if sample.is_armed { "" } else { ", disarmed" },
+                ));
+            }
+        }
+    }
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
@@ -0,0 +1,545 @@
+use std::{f32::consts::TAU, fmt, fs, io, path::Path};
+
+use bevy::{
+    prelude::*,
+    render::{
+        camera::{ClearColorConfig, ScalingMode, Viewport},
+        view::RenderLayers,
+    },
+    window::PrimaryWindow,
+};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{player::Player, world::generator::GeneratedObject};
+
+/// Plugin for the geofence.
+///
+/// The drone should stay inside the inclusion fence and out of the exclusion zones.
+/// Fences are drawn in the world and on the map, breaches are logged. The failsafe answers them with the `BreachAction`.
+/// The `Geofence` comes with the scenario, scenarios without it have no fence.
+pub struct GeofencePlugin;
+
+impl Plugin for GeofencePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<GeofenceStatus>()
+            .init_resource::<GeofenceMap>()
+            .init_gizmo_group::<MapGizmos>()
+            .add_event::<GeofenceBreach>()
+            .add_systems(Startup, (setup_geofence_map, setup_geofence_hud))
+            .add_systems(Update, (
+                (fence_buildings, check_geofence, draw_geofence).run_if(resource_exists::<Geofence>),
+                update_geofence_map,
+                update_geofence_hud,
+            ).chain());
+    }
+}
+
+/// Distance of the map from the top of the window in pixels, the race HUD is above it.
+const MAP_TOP: f32 = 190.0;
+
+/// Distance of the breach warning from the top of the window in pixels, below the recorder and replay HUDs.
+const WARNING_TOP: f32 = 70.0;
+
+/// Error that can happen while reading or writing a geofence file.
+#[derive(Debug)]
+pub enum GeofenceError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for GeofenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't access geofence file: {error}"),
+            Self::Parse(error) => write!(f, "can't parse geofence file: {error}"),
+            Self::Serialize(error) => write!(f, "can't serialize geofence: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for GeofenceError {}
+
+impl From<io::Error> for GeofenceError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for GeofenceError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<ron::Error> for GeofenceError {
+    fn from(error: ron::Error) -> Self {
+        Self::Serialize(error)
+    }
+}
+
+// resources
+/// Shape of a fence on the XZ plane.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FenceShape {
+    Cylinder { center: Vec2, radius: f32 },
+    /// Vertices of the polygon in order, the last one is connected to the first one.
+    Polygon(Vec<Vec2>),
+}
+
+impl FenceShape {
+    /// Returns whether the point of the XZ plane is inside the shape.
+    pub fn contains(&self, point: Vec2) -> bool {
+        match self {
+            Self::Cylinder { center, radius } => point.distance(*center) <= *radius,
+            Self::Polygon(vertices) => {
+                // even-odd rule
+                let mut is_inside = false;
+
+                for (index, a) in vertices.iter().enumerate() {
+                    let b = vertices[(index + 1) % vertices.len()];
+
+                    if (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                        is_inside = !is_inside;
+                    }
+                }
+
+                is_inside
+            },
+        }
+    }
+
+    /// Returns the outline of the shape, cylinders are approximated with 32 segments.
+    pub fn outline(&self) -> Vec<Vec2> {
+        match self {
+            Self::Cylinder { center, radius } => (0..32)
+                .map(|index| {
+                    let angle = index as f32 / 32.0 * TAU;
+                    *center + Vec2::new(angle.cos(), angle.sin()) * *radius
+                })
+                .collect(),
+            Self::Polygon(vertices) => vertices.clone(),
+        }
+    }
+}
+
+/// Describes a fence: its shape and the altitudes in meters above the launch pad, where it works.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FenceZone {
+    pub name: String,
+    pub shape: FenceShape,
+    pub min_altitude: f32,
+    pub max_altitude: f32,
+}
+
+impl FenceZone {
+    /// Returns whether the point is inside the zone.
+    pub fn contains(&self, position: Vec3) -> bool {
+        (self.min_altitude..=self.max_altitude).contains(&position.y) && self.shape.contains(position.xz())
+    }
+}
+
+/// What the drone does when it breaches the geofence.
+///
+/// `Hold` brings the drone back to the last point inside the fence, the pilot gets control back there.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BreachAction {
+    #[default]
+    Warn,
+    Hold,
+    ReturnToHome,
+    Land,
+}
+
+/// Describes the geofence of the scenario.
+///
+/// The drone should stay inside the `inclusion` zone and out of the `exclusions`.
+/// If `building_margin` is set, generated buildings are wrapped into exclusion zones with this margin in meters.
+///
+/// Geofences are stored in RON files.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Geofence {
+    pub inclusion: Option<FenceZone>,
+    pub exclusions: Vec<FenceZone>,
+    pub action: BreachAction,
+    pub building_margin: Option<f32>,
+    /// Exclusion zones of the buildings.
+    #[serde(skip)]
+    pub building_zones: Vec<FenceZone>,
+}
+
+impl Default for Geofence {
+    /// 400 meters around the launch pad below 120 meters and a restricted area beyond the town.
+    fn default() -> Self {
+        Self {
+            inclusion: Some(FenceZone {
+                name: "Flight area".to_owned(),
+                shape: FenceShape::Cylinder { center: Vec2::ZERO, radius: 400.0 },
+                min_altitude: -100.0,
+                max_altitude: 120.0,
+            }),
+            exclusions: vec![FenceZone {
+                name: "Restricted area".to_owned(),
+                shape: FenceShape::Cylinder { center: Vec2::new(180.0, -180.0), radius: 40.0 },
+                min_altitude: -100.0,
+                max_altitude: 1000.0,
+            }],
+            action: BreachAction::Warn,
+            building_margin: None,
+            building_zones: Vec::new(),
+        }
+    }
+}
+
+impl Geofence {
+    /// Reads the geofence file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GeofenceError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the geofence file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GeofenceError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)?;
+        Ok(())
+    }
+
+    /// Returns the breach at the given position, if there is any.
+    pub fn breach_at(&self, position: Vec3) -> Option<BreachKind> {
+        if self.inclusion.as_ref().is_some_and(|inclusion| !inclusion.contains(position)) {
+            return Some(BreachKind::OutsideFence);
+        }
+
+        self.exclusions
+            .iter()
+            .chain(&self.building_zones)
+            .find(|zone| zone.contains(position))
+            .map(|zone| BreachKind::RestrictedZone(zone.name.clone()))
+    }
+}
+
+/// Kind of the geofence breach.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreachKind {
+    OutsideFence,
+    RestrictedZone(String),
+}
+
+impl fmt::Display for BreachKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutsideFence => write!(f, "outside of the fence"),
+            Self::RestrictedZone(name) => write!(f, "inside {name}"),
+        }
+    }
+}
+
+/// Breach in the geofence log. `time` is in seconds of `Time::elapsed_seconds`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreachRecord {
+    pub time: f32,
+    pub kind: BreachKind,
+    pub position: Vec3,
+    pub action: BreachAction,
+}
+
+/// State of the geofence: the current breach and the log of all breaches.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct GeofenceStatus {
+    pub breach: Option<BreachKind>,
+    pub last_inside: Option<Vec3>,
+    pub log: Vec<BreachRecord>,
+}
+
+/// Describes the map with the geofence.
+///
+/// The map is `size` pixels wide at the top right of the screen below the race HUD and shows `extent` meters
+/// around the `Player`.
+/// Press `toggle_key` to show or hide it.
+#[derive(Resource, Debug, Clone)]
+pub struct GeofenceMap {
+    pub enabled: bool,
+    pub size: f32,
+    pub extent: f32,
+    pub toggle_key: KeyCode,
+}
+
+impl Default for GeofenceMap {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            size: 200.0,
+            extent: 600.0,
+            toggle_key: KeyCode::F3,
+        }
+    }
+}
+
+/// Gizmos, which are only drawn on the map.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct MapGizmos;
+
+// events
+/// Event of the drone breaching the geofence.
+#[derive(Event)]
+pub struct GeofenceBreach {
+    pub kind: BreachKind,
+    pub position: Vec3,
+}
+
+// components
+/// Describes the camera of the geofence map.
+#[derive(Component)]
+pub struct GeofenceMapCamera;
+
+/// Describes the geofence warning text.
+#[derive(Component)]
+struct GeofenceHudText;
+
+// systems
+/// System that wraps generated buildings into exclusion zones, when they are spawned or despawned.
+fn fence_buildings(
+    mut geofence: ResMut<Geofence>,
+    objects: Query<(&GeneratedObject, &Transform, &Collider)>,
+    added: Query<(), Added<GeneratedObject>>,
+    mut removed: RemovedComponents<GeneratedObject>,
+) {
+    let is_changed = !added.is_empty() || removed.read().count() > 0;
+
+    if !is_changed && !geofence.is_changed() {
+        return;
+    }
+
+    let Some(margin) = geofence.building_margin else {
+        if !geofence.building_zones.is_empty() {
+            geofence.building_zones.clear();
+        }
+
+        return;
+    };
+
+    let zones = objects
+        .iter()
+        .filter(|(object, _, _)| **object == GeneratedObject::Building)
+        .filter_map(|(_, transform, collider)| {
+            let half_size = collider.as_cuboid()?.half_extents() + Vec3::splat(margin);
+
+            let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .map(|(x, z)| (transform.translation + transform.rotation * (half_size * Vec3::new(x, 0.0, z))).xz());
+
+            Some(FenceZone {
+                name: "Building".to_owned(),
+                shape: FenceShape::Polygon(corners.to_vec()),
+                min_altitude: transform.translation.y - half_size.y,
+                max_altitude: transform.translation.y + half_size.y,
+            })
+        })
+        .collect();
+
+    geofence.bypass_change_detection().building_zones = zones;
+}
+
+/// System that finds geofence breaches of the `Player`. Every new breach is logged.
+pub fn check_geofence(
+    time: Res<Time>,
+    geofence: Res<Geofence>,
+    mut status: ResMut<GeofenceStatus>,
+    players: Query<&Transform, With<Player>>,
+    mut breaches: EventWriter<GeofenceBreach>,
+) {
+    let Ok(transform) = players.get_single() else {
+        return;
+    };
+
+    let position = transform.translation;
+    let breach = geofence.breach_at(position);
+
+    if breach.is_none() {
+        status.last_inside = Some(position);
+    }
+
+    if breach == status.breach {
+        return;
+    }
+
+    status.breach = breach.clone();
+
+    let Some(kind) = breach else {
+        return;
+    };
+
+    warn!("geofence breach at {position}: {kind}");
+
+    status.log.push(BreachRecord {
+        time: time.elapsed_seconds(),
+        kind: kind.clone(),
+        position,
+        action: geofence.action,
+    });
+
+    breaches.send(GeofenceBreach { kind, position });
+}
+
+/// System that draws the fences in the world and on the map.
+///
+/// Inclusion fence is green, exclusion zones are red and buildings are orange.
+fn draw_geofence(
+    geofence: Res<Geofence>,
+    players: Query<&Transform, With<Player>>,
+    mut gizmos: Gizmos,
+    mut map_gizmos: Gizmos<MapGizmos>,
+) {
+    let zones = geofence
+        .inclusion
+        .iter()
+        .map(|zone| (zone, Color::GREEN))
+        .chain(geofence.exclusions.iter().map(|zone| (zone, Color::RED)))
+        .chain(geofence.building_zones.iter().map(|zone| (zone, Color::ORANGE)));
+
+    for (zone, color) in zones {
+        let outline = zone.shape.outline();
+
+        for (index, a) in outline.iter().enumerate() {
+            let b = outline[(index + 1) % outline.len()];
+
+            let (floor, ceiling) = (zone.min_altitude.max(0.0), zone.max_altitude);
+
+            gizmos.line(Vec3::new(a.x, floor, a.y), Vec3::new(b.x, floor, b.y), color);
+            gizmos.line(Vec3::new(a.x, ceiling, a.y), Vec3::new(b.x, ceiling, b.y), color);
+            gizmos.line(Vec3::new(a.x, floor, a.y), Vec3::new(a.x, ceiling, a.y), color.with_a(0.3));
+
+            map_gizmos.line(Vec3::new(a.x, 200.0, a.y), Vec3::new(b.x, 200.0, b.y), color);
+        }
+    }
+
+    for transform in &players {
+        let position = Vec3::new(transform.translation.x, 200.0, transform.translation.z);
+        map_gizmos.circle(position, Direction3d::Y, 5.0, Color::WHITE);
+    }
+}
+
+/// Map initialization: the camera looks down at the `Player` from above.
+///
+/// Map gizmos are on their own render layer, so only the map camera sees them.
+fn setup_geofence_map(
+    mut commands: Commands,
+    map: Res<GeofenceMap>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    let map_layer = RenderLayers::layer(2);
+
+    config_store.config_mut::<MapGizmos>().0.render_layers = map_layer;
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                order: 2,
+                clear_color: ClearColorConfig::Custom(Color::rgb(0.1, 0.12, 0.1)),
+                is_active: map.enabled,
+                ..default()
+            },
+            projection: Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed { width: map.extent, height: map.extent },
+                far: 1000.0,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, 500.0, 0.0).looking_at(Vec3::ZERO, Vec3::NEG_Z),
+            ..default()
+        },
+        GeofenceMapCamera,
+        RenderLayers::layer(0).with(2),
+        Name::new("Geofence Map"),
+    ));
+}
+
+/// System that keeps the map above the `Player` and at the top right of the window.
+fn update_geofence_map(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut map: ResMut<GeofenceMap>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    players: Query<&Transform, (With<Player>, Without<GeofenceMapCamera>)>,
+    mut cameras: Query<(&mut Camera, &mut Transform), With<GeofenceMapCamera>>,
+) {
+    if keys.just_pressed(map.toggle_key) {
+        map.enabled = !map.enabled;
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    let scale = window.scale_factor();
+    let size = (map.size * scale) as u32;
+    let (top, margin) = ((MAP_TOP * scale) as u32, (5.0 * scale) as u32);
+    let width = window.physical_width();
+
+    for (mut camera, mut transform) in &mut cameras {
+        camera.is_active = map.enabled && width > size + margin && window.physical_height() > top + size;
+
+        if !camera.is_active {
+            continue;
+        }
+
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::new(width - size - margin, top),
+            physical_size: UVec2::splat(size),
+            ..default()
+        });
+
+        if let Ok(player) = players.get_single() {
+            transform.translation = Vec3::new(player.translation.x, 500.0, player.translation.z);
+        }
+    }
+}
+
+/// Geofence warning initialization.
+fn setup_geofence_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(WARNING_TOP),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                        font_size: 18.0,
+                        color: Color::RED,
+                    },
+                ),
+                GeofenceHudText,
+            ));
+        });
+}
+
+/// System that warns about the breach.
+fn update_geofence_hud(
+    status: Res<GeofenceStatus>,
+    mut query: Query<&mut Text, With<GeofenceHudText>>,
+) {
+    let value = status.breach.as_ref().map_or(String::new(), |kind| format!("Geofence breach: {kind}"));
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
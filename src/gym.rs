@@ -0,0 +1,606 @@
+use std::{thread, time::Duration};
+
+use bevy::{
+    ecs::schedule::ExecutorKind,
+    prelude::*,
+    time::{TimePlugin, TimeUpdateStrategy},
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera_sensor::{RayCastCamera, SKY_TEMPERATURE},
+    fleet::Airframe,
+    materials::{Temperature, Thermal},
+    player::{drain_battery, run_flight_controller, run_motors, Battery, FlightController, FlightSetpoint, Motors},
+    world::generator::GeneratorRng,
+};
+
+/// Number of values in the observation vector.
+pub const OBSERVATION_SIZE: usize = 14;
+
+/// Speed in meters per second, above which touching anything is a crash.
+const CRASH_SPEED: f32 = 3.0;
+/// Body temperature of the people to search for, in °C.
+const PERSON_TEMPERATURE: f32 = 36.6;
+/// Reaction torque of a rotor in newton-meters per newton of its thrust.
+const ROTOR_TORQUE: f32 = 0.05;
+
+/// Kind of the camera frame.
+///
+/// `Depth` pixels are distances in meters, `Thermal` pixels are temperatures in °C.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Depth,
+    Thermal,
+}
+
+/// Frame of the drone camera, pixels go row by row from the top left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<f32>,
+}
+
+impl Frame {
+    /// Returns the pixel in the given column and row.
+    pub fn pixel(&self, x: usize, y: usize) -> f32 {
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Settings of an environment.
+///
+/// Every step repeats the action for `action_repeat` physics steps of `physics_step`.
+/// The drone starts `start_height` meters above a random point within `start_radius` meters of the origin,
+/// `targets` people stand within `area` meters of it. The episode ends when the drone crashes, its battery is empty
+/// or it flies `bounds` meters away from the origin, and it is cut after `max_steps`.
+/// Steps return a camera `frame` of the given kind, if there is one.
+#[derive(Debug, Clone)]
+pub struct GymSettings {
+    pub airframe: Airframe,
+    pub physics_step: Duration,
+    pub action_repeat: u32,
+    pub start_height: f32,
+    pub start_radius: f32,
+    pub targets: usize,
+    pub area: f32,
+    pub bounds: f32,
+    pub max_steps: u32,
+    pub air_temperature: f32,
+    pub camera: RayCastCamera,
+    pub frame: Option<FrameKind>,
+    /// Speed in meters per second and yaw rate in radians per second of full stick inputs.
+    pub max_speed: f32,
+    pub max_yaw_rate: f32,
+}
+
+impl Default for GymSettings {
+    fn default() -> Self {
+        Self {
+            airframe: Airframe::default(),
+            physics_step: Duration::from_secs_f64(1.0 / 60.0),
+            action_repeat: 3,
+            start_height: 10.0,
+            start_radius: 0.0,
+            targets: 3,
+            area: 50.0,
+            bounds: 200.0,
+            max_steps: 1000,
+            air_temperature: 15.0,
+            camera: RayCastCamera::default(),
+            frame: None,
+            max_speed: 10.0,
+            max_yaw_rate: 90.0_f32.to_radians(),
+        }
+    }
+}
+
+/// Action of a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Commands of the rotors from 0.0 to 1.0: front left, front right, rear left, rear right.
+    ///
+    /// Each rotor gives half of the hover thrust at 0.5. Rotors replace the motors and the flight controller,
+    /// so the policy balances the drone itself.
+    Rotors([f32; 4]),
+    /// Stick inputs from -1.0 to 1.0, the flight controller holds the velocity they give.
+    ///
+    /// Positive `throttle` climbs, `roll` flies right, `pitch` flies forward and `yaw` turns right.
+    Sticks { throttle: f32, roll: f32, pitch: f32, yaw: f32 },
+}
+
+impl Default for Action {
+    /// Hovering in place.
+    fn default() -> Self {
+        Self::Sticks { throttle: 0.0, roll: 0.0, pitch: 0.0, yaw: 0.0 }
+    }
+}
+
+/// State of the environment drone.
+///
+/// `visible_targets` are indices of the people the camera sees now, `found_targets` is the number of people
+/// it has seen during the episode.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DroneState {
+    pub time: f32,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub battery_charge: f32,
+    pub is_crashed: bool,
+    pub visible_targets: Vec<usize>,
+    pub found_targets: usize,
+    pub target_count: usize,
+}
+
+impl DroneState {
+    /// Returns the observation vector: position, velocity, rotation quaternion, angular velocity and battery charge.
+    pub fn observation(&self) -> Vec<f32> {
+        let mut observation = Vec::with_capacity(OBSERVATION_SIZE);
+
+        observation.extend(self.position.to_array());
+        observation.extend(self.velocity.to_array());
+        observation.extend(self.rotation.to_array());
+        observation.extend(self.angular_velocity.to_array());
+        observation.push(self.battery_charge);
+
+        observation
+    }
+}
+
+/// Reward function of an environment.
+pub trait RewardFunction: Send + Sync {
+    /// Returns the reward of the step from the `previous` state to the `state`.
+    fn reward(&mut self, previous: &DroneState, state: &DroneState) -> f32;
+
+    /// Returns `true` if the task is done.
+    fn is_done(&self, _state: &DroneState) -> bool {
+        false
+    }
+}
+
+/// Reward for holding the drone at the `target` position.
+///
+/// The reward decreases with the distance and the spin, crashes cost `crash_penalty`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoverReward {
+    pub target: Vec3,
+    pub crash_penalty: f32,
+}
+
+impl RewardFunction for HoverReward {
+    fn reward(&mut self, _previous: &DroneState, state: &DroneState) -> f32 {
+        let penalty = if state.is_crashed { self.crash_penalty } else { 0.0 };
+
+        -state.position.distance(self.target) - 0.1 * state.angular_velocity.length() - penalty
+    }
+}
+
+/// Reward for finding people.
+///
+/// Every found person gives `target_reward`, every step costs `step_penalty`. The search is done, when all are found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchReward {
+    pub target_reward: f32,
+    pub step_penalty: f32,
+}
+
+impl Default for SearchReward {
+    fn default() -> Self {
+        Self {
+            target_reward: 1.0,
+            step_penalty: 0.01,
+        }
+    }
+}
+
+impl RewardFunction for SearchReward {
+    fn reward(&mut self, previous: &DroneState, state: &DroneState) -> f32 {
+        (state.found_targets - previous.found_targets) as f32 * self.target_reward - self.step_penalty
+    }
+
+    fn is_done(&self, state: &DroneState) -> bool {
+        state.found_targets == state.target_count
+    }
+}
+
+/// Result of a step.
+///
+/// `is_done` ends the episode, `is_truncated` means that it was cut after the maximum number of steps.
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub observation: Vec<f32>,
+    pub frame: Option<Frame>,
+    pub reward: f32,
+    pub is_done: bool,
+    pub is_truncated: bool,
+    pub state: DroneState,
+}
+
+// components
+/// Control of the environment drone by actions.
+#[derive(Component, Debug, Clone, Default)]
+pub struct GymControl {
+    pub action: Action,
+    pub max_speed: f32,
+    pub max_yaw_rate: f32,
+    /// Distance from the center to the rotors in meters.
+    pub arm: f32,
+    pub hover_thrust: f32,
+    /// Force and torque of the rotors, which are currently added to the `ExternalForce`.
+    pub applied: (Vec3, Vec3),
+}
+
+// systems
+/// Query for the drones controlled by actions.
+type GymDroneQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static mut GymControl,
+        &'static mut Motors,
+        &'static mut FlightController,
+        &'static mut ExternalForce,
+        &'static Transform,
+    ),
+>;
+
+/// System that flies the drones by their actions.
+pub fn run_gym_control(
+    mut drones: GymDroneQuery,
+) {
+    for (mut control, mut motors, mut controller, mut external_force, transform) in &mut drones {
+        let (force, torque) = match control.action {
+            Action::Rotors(commands) => {
+                motors.is_armed = false;
+                controller.setpoint = None;
+
+                let rotor_max = control.hover_thrust / 2.0;
+                let [front_left, front_right, rear_left, rear_right] =
+                    commands.map(|command| command.clamp(0.0, 1.0) * rotor_max);
+
+                // the front is -Z and the right is +X, front left and rear right rotors spin counterclockwise
+                let torque = Vec3::new(
+                    control.arm * (front_left + front_right - rear_left - rear_right),
+                    ROTOR_TORQUE * (front_right + rear_left - front_left - rear_right),
+                    control.arm * (front_right + rear_right - front_left - rear_left),
+                );
+                let thrust = front_left + front_right + rear_left + rear_right;
+
+                (transform.rotation * Vec3::Y * thrust, transform.rotation * torque)
+            },
+            Action::Sticks { throttle, roll, pitch, yaw } => {
+                motors.is_armed = true;
+
+                let (heading, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+                let sticks = Vec3::new(roll, throttle, -pitch).clamp(-Vec3::ONE, Vec3::ONE);
+
+                controller.setpoint = Some(FlightSetpoint {
+                    velocity: Quat::from_rotation_y(heading) * sticks * control.max_speed,
+                    // the controller turns at twice the heading error
+                    yaw: Some(heading - yaw.clamp(-1.0, 1.0) * control.max_yaw_rate / 2.0),
+                });
+
+                (Vec3::ZERO, Vec3::ZERO)
+            },
+        };
+
+        let (applied_force, applied_torque) = control.applied;
+
+        external_force.force += force - applied_force;
+        external_force.torque += torque - applied_torque;
+        control.applied = (force, torque);
+    }
+}
+
+/// Environment, which simulates a drone with the physics of the simulator.
+///
+/// Each environment runs its own headless app with a fixed time step, so environments are independent
+/// and run as fast as the processor allows.
+pub struct DroneEnv {
+    pub settings: GymSettings,
+    pub reward: Box<dyn RewardFunction>,
+    app: App,
+    drone: Entity,
+    targets: Vec<Entity>,
+    found: Vec<bool>,
+    steps: u32,
+    state: DroneState,
+}
+
+impl DroneEnv {
+    /// Creates the environment and starts its episode with the seed 0.
+    pub fn new(settings: GymSettings, reward: Box<dyn RewardFunction>) -> Self {
+        let (app, drone, targets) = build_app(&settings, 0);
+
+        let mut env = Self {
+            settings,
+            reward,
+            app,
+            drone,
+            found: vec![false; targets.len()],
+            targets,
+            steps: 0,
+            state: DroneState::default(),
+        };
+
+        env.start_episode();
+        env
+    }
+
+    /// Starts a new episode and returns the first observation.
+    ///
+    /// The same `seed` gives the same start position and people.
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        let (app, drone, targets) = build_app(&self.settings, seed);
+
+        self.app = app;
+        self.drone = drone;
+        self.found = vec![false; targets.len()];
+        self.targets = targets;
+
+        self.start_episode()
+    }
+
+    /// Runs the first frame of the new app and returns the first observation.
+    fn start_episode(&mut self) -> Vec<f32> {
+        self.steps = 0;
+
+        // creates the physics bodies
+        self.app.update();
+        self.state = self.read_state(false);
+
+        self.state.observation()
+    }
+
+    /// Applies the action for a step and returns its result.
+    pub fn step(&mut self, action: Action) -> StepResult {
+        self.app.world.get_mut::<GymControl>(self.drone).unwrap().action = action;
+
+        let mut is_crashed = false;
+
+        for _ in 0..self.settings.action_repeat {
+            let speed = self.app.world.get::<Velocity>(self.drone).unwrap().linvel.length();
+
+            self.app.update();
+
+            let context = self.app.world.resource::<RapierContext>();
+            let is_touching = context.contact_pairs_with(self.drone).any(|contact| contact.has_any_active_contacts());
+
+            is_crashed |= is_touching && speed > CRASH_SPEED;
+        }
+
+        self.steps += 1;
+
+        let state = self.read_state(is_crashed);
+        let previous = std::mem::replace(&mut self.state, state);
+        let state = &self.state;
+
+        let is_out = state.position.xz().length() > self.settings.bounds;
+        let is_done = state.is_crashed || is_out || state.battery_charge <= 0.0 || self.reward.is_done(state);
+
+        StepResult {
+            observation: state.observation(),
+            frame: self.settings.frame.map(|kind| self.render(kind)),
+            reward: self.reward.reward(&previous, state),
+            is_done,
+            is_truncated: !is_done && self.steps >= self.settings.max_steps,
+            state: state.clone(),
+        }
+    }
+
+    /// Returns the current observation.
+    pub fn observe(&self) -> Vec<f32> {
+        self.state.observation()
+    }
+
+    /// Returns the current state of the drone.
+    pub fn state(&self) -> &DroneState {
+        &self.state
+    }
+
+    /// Returns positions of the people to search for.
+    pub fn targets(&self) -> Vec<Vec3> {
+        self.targets.iter().map(|&target| self.app.world.get::<Transform>(target).unwrap().translation).collect()
+    }
+
+    /// Renders the camera frame by casting a ray through every pixel.
+    pub fn render(&self, kind: FrameKind) -> Frame {
+        let camera = &self.settings.camera;
+        let (origin, rotation) = self.camera_pose();
+        let context = self.app.world.resource::<RapierContext>();
+        let filter = QueryFilter::default().exclude_collider(self.drone);
+
+        let pixels = camera
+            .ray_directions(rotation)
+            .map(|direction| match (kind, context.cast_ray(origin, direction, camera.range, true, filter)) {
+                (FrameKind::Depth, Some((_, distance))) => distance,
+                (FrameKind::Depth, None) => camera.range,
+                (FrameKind::Thermal, Some((entity, _))) => self.app.world
+                    .get::<Temperature>(entity)
+                    .map_or(self.settings.air_temperature, |temperature| temperature.0),
+                (FrameKind::Thermal, None) => SKY_TEMPERATURE,
+            })
+            .collect();
+
+        Frame { kind, width: camera.width, height: camera.height, pixels }
+    }
+
+    /// Returns the position and the rotation of the camera.
+    fn camera_pose(&self) -> (Vec3, Quat) {
+        let transform = self.app.world.get::<Transform>(self.drone).unwrap();
+
+        (transform.translation, self.settings.camera.rotation(transform.rotation))
+    }
+
+    /// Returns indices of the people, which the camera sees.
+    fn visible_targets(&self) -> Vec<usize> {
+        let camera = &self.settings.camera;
+        let (origin, rotation) = self.camera_pose();
+        let context = self.app.world.resource::<RapierContext>();
+        let filter = QueryFilter::default().exclude_collider(self.drone);
+
+        self.targets
+            .iter()
+            .enumerate()
+            .filter(|&(_, &target)| {
+                let position = self.app.world.get::<Transform>(target).unwrap().translation;
+
+                camera.is_in_view(origin, rotation, position) && context
+                    .cast_ray(origin, (position - origin).normalize(), camera.range, true, filter)
+                    .is_some_and(|(entity, _)| entity == target)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Reads the state of the drone and marks the visible people as found.
+    fn read_state(&mut self, is_crashed: bool) -> DroneState {
+        let visible_targets = self.visible_targets();
+
+        for &index in &visible_targets {
+            self.found[index] = true;
+        }
+
+        let world = &self.app.world;
+        let transform = world.get::<Transform>(self.drone).unwrap();
+        let velocity = world.get::<Velocity>(self.drone).unwrap();
+
+        DroneState {
+            time: world.resource::<Time>().elapsed_seconds(),
+            position: transform.translation,
+            rotation: transform.rotation,
+            velocity: velocity.linvel,
+            angular_velocity: velocity.angvel,
+            battery_charge: world.get::<Battery>(self.drone).unwrap().charge(),
+            is_crashed,
+            visible_targets,
+            found_targets: self.found.iter().filter(|&&is_found| is_found).count(),
+            target_count: self.targets.len(),
+        }
+    }
+}
+
+/// Many independent environments, which step in parallel.
+pub struct VecDroneEnv {
+    pub envs: Vec<DroneEnv>,
+}
+
+impl VecDroneEnv {
+    /// Creates `count` environments with rewards of the given function.
+    pub fn new(settings: GymSettings, count: usize, reward: impl Fn() -> Box<dyn RewardFunction>) -> Self {
+        Self {
+            envs: (0..count).map(|_| DroneEnv::new(settings.clone(), reward())).collect(),
+        }
+    }
+
+    /// Resets the environments with consecutive seeds and returns their observations.
+    pub fn reset(&mut self, seed: u64) -> Vec<Vec<f32>> {
+        self.envs.iter_mut().zip(seed..).map(|(env, seed)| env.reset(seed)).collect()
+    }
+
+    /// Steps every environment with its action.
+    ///
+    /// Panics if the number of actions differs from the number of environments.
+    pub fn step(&mut self, actions: &[Action]) -> Vec<StepResult> {
+        assert_eq!(actions.len(), self.envs.len(), "every environment should get an action");
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = self.envs
+                .iter_mut()
+                .zip(actions)
+                .map(|(env, &action)| scope.spawn(move || env.step(action)))
+                .collect();
+
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        })
+    }
+}
+
+/// Creates the headless app of an environment with the drone and the people.
+fn build_app(settings: &GymSettings, seed: u64) -> (App, Entity, Vec<Entity>) {
+    let mut app = App::new();
+    let airframe = &settings.airframe;
+    let step = settings.physics_step;
+
+    app.add_plugins((TaskPoolPlugin::default(), TimePlugin));
+    app.init_resource::<Assets<Mesh>>();
+    app.init_resource::<Assets<Scene>>();
+    app.init_resource::<SceneSpawner>();
+    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default());
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(step));
+    app.insert_resource(RapierConfiguration {
+        timestep_mode: TimestepMode::Fixed { dt: step.as_secs_f32(), substeps: 1 },
+        ..RapierConfiguration::new(1.0)
+    });
+    app.add_systems(Update, (run_gym_control, run_motors, run_flight_controller, drain_battery).chain());
+
+    let mut rng = GeneratorRng::new(seed);
+    let mut random_point = |radius: f32| Vec3::new(rng.range(-radius, radius), 0.0, rng.range(-radius, radius));
+
+    app.world.spawn((
+        Collider::cuboid(settings.bounds, 0.5, settings.bounds),
+        TransformBundle::from(Transform::from_xyz(0.0, -0.5, 0.0)),
+        Temperature(settings.air_temperature),
+    ));
+
+    let start = random_point(settings.start_radius) + Vec3::Y * settings.start_height;
+    let dimensions = airframe.dimensions;
+
+    let drone = app.world
+        .spawn((
+            TransformBundle::from(Transform::from_translation(start)),
+            RigidBody::Dynamic,
+            ExternalForce::default(),
+            Velocity::default(),
+            Collider::cuboid(dimensions.x / 2.0, dimensions.y / 2.0, dimensions.z / 2.0),
+            ColliderMassProperties::Mass(airframe.mass),
+            Motors {
+                hover_thrust: airframe.hover_thrust(),
+                ..default()
+            },
+            Battery {
+                capacity: airframe.battery_capacity,
+                remaining: airframe.battery_capacity,
+                hover_power: airframe.hover_power,
+                hover_thrust: airframe.hover_thrust(),
+            },
+            FlightController {
+                mass: airframe.mass,
+                max_thrust: airframe.max_thrust,
+                ..default()
+            },
+            GymControl {
+                max_speed: settings.max_speed,
+                max_yaw_rate: settings.max_yaw_rate,
+                arm: dimensions.x.min(dimensions.z) / 2.0,
+                hover_thrust: airframe.hover_thrust(),
+                ..default()
+            },
+        ))
+        .id();
+
+    let targets = (0..settings.targets)
+        .map(|_| {
+            let position = Vec3::new(start.x, 0.85, start.z) + random_point(settings.area);
+
+            app.world
+                .spawn((
+                    Collider::capsule_y(0.6, 0.25),
+                    TransformBundle::from(Transform::from_translation(position)),
+                    Thermal,
+                    Temperature(PERSON_TEMPERATURE),
+                ))
+                .id()
+        })
+        .collect();
+
+    // environments already run in parallel
+    for (_, schedule) in app.world.resource_mut::<Schedules>().iter_mut() {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+
+    (app, drone, targets)
+}
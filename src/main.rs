@@ -13,10 +13,48 @@ mod tests;
 
 /// Drone models and Player logic.
 pub mod player;
+/// Fleet of drones: airframes, switching between drones and telemetry.
+pub mod fleet;
+/// Swarm behaviours: formations, flocking, area coverage and collision avoidance.
+pub mod swarm;
 /// Camera logic.
 pub mod camera;
+/// Camera sensor simulation.
+pub mod camera_sensor;
 /// All additional objects and their logic.
 pub mod world;
+/// Terrain, its chunks and colliders.
+pub mod terrain;
+/// People, animals and cars moving through the world.
+pub mod agents;
+/// Search-and-rescue mission and its scoring.
+pub mod search_and_rescue;
+/// Race gates, lap timing and the track editor.
+pub mod racing;
+/// Waypoint missions and the autopilot.
+pub mod autopilot;
+/// Geofence and no-fly zones.
+pub mod geofence;
+/// Failsafes: return to launch, landing and disarming.
+pub mod failsafe;
+/// Arming, pre-flight checks and landing detection.
+pub mod arming;
+/// Flight data recorder and its logs.
+pub mod recorder;
+/// Input recording and deterministic replay.
+pub mod replay;
+/// Reinforcement learning environments.
+pub mod gym;
+/// Control server for external programs.
+pub mod control_server;
+/// ROS 2 bridge over rosbridge-style WebSocket.
+pub mod ros_bridge;
+/// Simulation clock: pause, speed and single steps.
+pub mod sim_clock;
+/// Time of day, sun and moon.
+pub mod sky;
+/// Weather and its influence on sensors.
+pub mod weather;
 /// Post-processing logic.
 pub mod post_processing;
 /// Contains all of the materials.
@@ -25,8 +63,26 @@ pub mod materials;
 pub mod ui;
 
 use player::PlayerPlugin;
+use fleet::FleetPlugin;
+use swarm::SwarmPlugin;
 use camera::CameraPlugin;
+use camera_sensor::CameraSensorPlugin;
 use world::WorldPlugin;
+use terrain::TerrainPlugin;
+use agents::AgentsPlugin;
+use search_and_rescue::SearchAndRescuePlugin;
+use racing::RacingPlugin;
+use autopilot::AutopilotPlugin;
+use geofence::GeofencePlugin;
+use failsafe::FailsafePlugin;
+use arming::ArmingPlugin;
+use recorder::RecorderPlugin;
+use replay::ReplayPlugin;
+use control_server::ControlServerPlugin;
+use ros_bridge::RosBridgePlugin;
+use sim_clock::SimClockPlugin;
+use sky::SkyPlugin;
+use weather::WeatherPlugin;
 use post_processing::PostProcessPlugin;
 use materials::DefinedMaterialsPlugin;
 use ui::UIPlugin;
@@ -51,13 +107,34 @@ fn main() {
 
     app.add_plugins((
         PlayerPlugin,
+        FleetPlugin,
         CameraPlugin,
+        CameraSensorPlugin,
         WorldPlugin,
+        TerrainPlugin,
+        AgentsPlugin,
+        SimClockPlugin,
+        SkyPlugin,
+        WeatherPlugin,
         ThirdPersonCameraPlugin,
         PostProcessPlugin,
         DefinedMaterialsPlugin,
         UIPlugin,
     ));
 
+    app.add_plugins((
+        SearchAndRescuePlugin,
+        RacingPlugin,
+        AutopilotPlugin,
+        GeofencePlugin,
+        FailsafePlugin,
+        ArmingPlugin,
+        SwarmPlugin,
+        RecorderPlugin,
+        ReplayPlugin,
+        ControlServerPlugin,
+        RosBridgePlugin,
+    ));
+
     app.run();
 }
@@ -11,7 +11,9 @@ pub struct DefinedMaterialsPlugin;
 impl Plugin for DefinedMaterialsPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_plugins(MaterialPlugin::<ExtendedMaterial<StandardMaterial, ThermalMaterialExtension>>::default());
+            .add_plugins(MaterialPlugin::<ExtendedMaterial<StandardMaterial, ThermalMaterialExtension>>::default())
+            .add_plugins(MaterialPlugin::<TerrainMaterial>::default())
+            .add_systems(PostUpdate, sync_temperature);
     }
 }
 
@@ -19,12 +21,45 @@ impl Plugin for DefinedMaterialsPlugin {
 #[derive(Component)]
 pub struct Thermal;
 
+/// Temperature of the `Thermal` entity in degrees Celsius.
+/// 
+/// It's sent to the `temperature` of the entity's `ThermalMaterialExtension`.
+#[derive(Component)]
+pub struct Temperature(pub f32);
+
+/// Describes how the entity is heated.
+/// 
+/// `solar_gain` is how many degrees the entity gains in the sun at zenith,
+/// `internal_heat` is how many degrees warmer than the air it is by itself, e.g. because of the running motors.
+/// `time_constant` is how many seconds it takes to get most of the way to the new temperature.
+#[derive(Component)]
+pub struct SolarHeating {
+    pub solar_gain: f32,
+    pub internal_heat: f32,
+    pub time_constant: f32,
+}
+
+impl Default for SolarHeating {
+    fn default() -> Self {
+        Self {
+            solar_gain: 15.0,
+            internal_heat: 0.0,
+            time_constant: 3600.0,
+        }
+    }
+}
+
 // materials
+/// `StandardMaterial` extended with `ThermalMaterialExtension`.
+pub type ThermalMaterial = ExtendedMaterial<StandardMaterial, ThermalMaterialExtension>;
+
 /// Thermal `MaterialExtension`.
 /// 
 /// In order to use it, you need to specify `temperature`, `intensity` and `is_infrared_mode_active`.
 /// This values will be sent to a material's fragment shader.
 /// 
+/// `ir_extinction` is an infrared extinction coefficient of the air per meter, it lowers the contrast of distant objects.
+/// 
 /// Use `is_infrared_mode_active` to switch into infrared mode and back.
 /// 
 /// Keep in mind, that infrared white glow only will be applied if `is_infrared_mode_active` is set to 1.
@@ -36,6 +71,8 @@ pub struct ThermalMaterialExtension {
     pub intensity: f32,
     #[uniform(103)]
     pub is_infrared_mode_active: u32,
+    #[uniform(104)]
+    pub ir_extinction: f32,
 }
 
 impl MaterialExtension for ThermalMaterialExtension {
@@ -43,3 +80,68 @@ impl MaterialExtension for ThermalMaterialExtension {
         "shaders/thermal_material.wgsl".into()
     }   
 }
+
+/// `StandardMaterial` extended with `TerrainMaterialExtension`.
+pub type TerrainMaterial = ExtendedMaterial<StandardMaterial, TerrainMaterialExtension>;
+
+/// Terrain splatting `MaterialExtension`.
+/// 
+/// Blends four layers: sand, grass, rock and snow. Layer weights are taken from the mesh vertex colors,
+/// so the mesh should have `Mesh::ATTRIBUTE_COLOR`.
+/// 
+/// Each layer is its color multiplied by its texture. Missing textures are white.
+/// `tiling` is how many times textures repeat per meter, mesh UVs should be in meters.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct TerrainMaterialExtension {
+    #[uniform(100)]
+    pub layer_colors: [Vec4; 4],
+    #[uniform(101)]
+    pub tiling: f32,
+    #[texture(102)]
+    #[sampler(103)]
+    pub sand_texture: Option<Handle<Image>>,
+    #[texture(104)]
+    pub grass_texture: Option<Handle<Image>>,
+    #[texture(105)]
+    pub rock_texture: Option<Handle<Image>>,
+    #[texture(106)]
+    pub snow_texture: Option<Handle<Image>>,
+}
+
+impl Default for TerrainMaterialExtension {
+    fn default() -> Self {
+        Self {
+            layer_colors: [Vec4::ONE; 4],
+            tiling: 0.25,
+            sand_texture: None,
+            grass_texture: None,
+            rock_texture: None,
+            snow_texture: None,
+        }
+    }
+}
+
+impl MaterialExtension for TerrainMaterialExtension {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/terrain_material.wgsl".into()
+    }
+}
+
+/// System that sends `Temperature` of the entities to their thermal materials.
+fn sync_temperature(
+    query: Query<(&Temperature, &Handle<ThermalMaterial>), With<Thermal>>,
+    mut ext_materials: ResMut<Assets<ThermalMaterial>>,
+) {
+    for (temperature, handle) in &query {
+        // material is only touched when the difference is visible, so it isn't reuploaded every frame
+        let is_outdated = ext_materials
+            .get(handle)
+            .is_some_and(|material| (material.extension.temperature - temperature.0).abs() > 0.01);
+
+        if is_outdated {
+            if let Some(material) = ext_materials.get_mut(handle) {
+                material.extension.temperature = temperature.0;
+            }
+        }
+    }
+}
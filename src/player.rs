@@ -1,17 +1,26 @@
-use bevy::{pbr::ExtendedMaterial, prelude::*, render::view::RenderLayers};
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy_third_person_camera::ThirdPersonCameraTarget;
+use serde::{Deserialize, Serialize};
 
-use crate::materials::{Thermal, ThermalMaterialExtension};
+use crate::{ui::menu::AppState, weather::WindDrag};
 
-/// Plugin for the drone models and a Player.
+/// Plugin for the drone physics and the Player.
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Startup, spawn_player)
-            .add_systems(Update, player_movement);
+            .add_systems(Startup, spawn_player_camera_target)
+            .add_systems(Update, (
+                player_movement.run_if(in_state(AppState::InFlight)),
+                run_motors,
+                run_flight_controller,
+                drain_battery,
+                follow_player,
+            ));
     }
 }
 
@@ -21,23 +30,135 @@ impl Plugin for PlayerPlugin {
 #[derive(Component)]
 struct PlayerCameraTarget;
 
-/// Describes Player.
+/// Describes Player: the drone, which the operator pilots and views.
 #[derive(Component)]
 pub struct Player;
 
+/// Describes the drone battery.
+/// 
+/// `capacity` and `remaining` are in watt-hours. The drone draws `hover_power` watts while its thrust is `hover_thrust` newtons,
+/// power grows with thrust to the power of 1.5, as it does for propellers.
+#[derive(Component, Debug, Clone)]
+pub struct Battery {
+    pub capacity: f32,
+    pub remaining: f32,
+    pub hover_power: f32,
+    pub hover_thrust: f32,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self {
+            capacity: 77.0,
+            remaining: 77.0,
+            hover_power: 180.0,
+            hover_thrust: 73.8,
+        }
+    }
+}
+
+impl Battery {
+    /// Returns the remaining charge from 0.0 to 1.0.
+    pub fn charge(&self) -> f32 {
+        (self.remaining / self.capacity.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    /// Returns used energy in watt-hours.
+    pub fn used(&self) -> f32 {
+        self.capacity - self.remaining
+    }
+
+    /// Returns power in watts needed for the given thrust.
+    pub fn power(&self, thrust: f32) -> f32 {
+        self.hover_power * (thrust.max(0.0) / self.hover_thrust.max(f32::EPSILON)).powf(1.5)
+    }
+}
+
+/// Height of the drone center above the ground in meters, below which the drone is on the ground.
+///
+/// The drone is one meter tall.
+pub const LANDED_HEIGHT: f32 = 0.8;
+
+/// Describes the drone motors.
+///
+/// Armed motors hold `hover_thrust` newtons, which compensates the gravity, disarmed motors produce no thrust.
+#[derive(Component, Debug, Clone)]
+pub struct Motors {
+    pub is_armed: bool,
+    pub hover_thrust: f32,
+    /// Thrust, which is currently added to the `ExternalForce`.
+    pub applied: f32,
+}
+
+impl Default for Motors {
+    fn default() -> Self {
+        Self {
+            is_armed: false,
+            hover_thrust: 73.8,
+            applied: 0.0,
+        }
+    }
+}
+
+/// Position the drone took off from, it returns there.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct HomePosition(pub Vec3);
+
+/// Velocity and heading the `FlightController` should hold.
+///
+/// `yaw` is the rotation around Y in radians, `None` keeps the current heading.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FlightSetpoint {
+    pub velocity: Vec3,
+    pub yaw: Option<f32>,
+}
+
+/// Onboard flight controller of the drone.
+///
+/// While there is a `setpoint`, the controller adds thrust to the `ExternalForce` to hold its velocity,
+/// otherwise the pilot flies the drone. Added thrust is limited by `max_thrust` newtons,
+/// `velocity_gain` is how fast velocity errors are corrected, in 1/s.
+#[derive(Component, Debug, Clone)]
+pub struct FlightController {
+    pub setpoint: Option<FlightSetpoint>,
+    /// Velocity in meters per second, which the collision avoidance adds to the setpoint.
+    pub avoidance: Vec3,
+    pub mass: f32,
+    pub max_thrust: f32,
+    pub velocity_gain: f32,
+    pub max_yaw_rate: f32,
+    /// Thrust, which is currently added to the `ExternalForce`.
+    pub applied: Vec3,
+}
+
+impl Default for FlightController {
+    fn default() -> Self {
+        Self {
+            setpoint: None,
+            avoidance: Vec3::ZERO,
+            mass: 7.5,
+            max_thrust: 60.0,
+            velocity_gain: 2.0,
+            max_yaw_rate: 90.0_f32.to_radians(),
+            applied: Vec3::ZERO,
+        }
+    }
+}
+
 /// System that contains logic for Player movement.
 /// 
-/// Currently Player can move Up and Down by applying `ExternalImpulse`s.
+/// Currently Player can move Up and Down by applying `ExternalImpulse`s. Disarmed `Motors` don't move it.
 pub fn player_movement(
     keys: Res<ButtonInput<KeyCode>>,
-    mut controllers: Query<&mut ExternalImpulse, With<Player>>,
+    mut controllers: Query<(&mut ExternalImpulse, Option<&Motors>), With<Player>>,
 ) {
-    for mut ext_imp in controllers.iter_mut() {
+    for (mut ext_imp, motors) in controllers.iter_mut() {
+        if motors.is_some_and(|motors| !motors.is_armed) {
+            continue;
+        }
+
         if keys.pressed(KeyCode::ArrowUp) {
             ext_imp.impulse = Vec3::new(0.0, 5.0, 0.0);
-        }
-        if keys.pressed(KeyCode::KeyJ) {
-            
         }
         if keys.pressed(KeyCode::ArrowDown) {
             ext_imp.impulse = -Vec3::new(0.0, 5.0, 0.0);
@@ -45,73 +166,94 @@ pub fn player_movement(
     }
 }
 
-// fn update_player_camera_target_position(
-//     player_position: Query<&Transform, (With<Player>, Without<PlayerCameraTarget>)>,
-//     mut player_camera_target_position: Query<&mut Transform, (With<PlayerCameraTarget>, Without<Player>)>,
-// ) {
-//     if let Ok(player_transform) = player_position.get_single() {
-//         if let Ok(mut player_camera_target_transform) = player_camera_target_position.get_single_mut() {
-//             *player_camera_target_transform = *player_transform;
-//         }
-//     }
-// }
-
-/// System for initializing Player and other drone models.
-fn spawn_player(
-    mut commands: Commands,
-    server: Res<AssetServer>,
-    mut _meshes: ResMut<Assets<Mesh>>,
-    mut ext_materials: ResMut<Assets<ExtendedMaterial<StandardMaterial, ThermalMaterialExtension>>>,
+/// System that applies the thrust of the `Motors`.
+pub fn run_motors(
+    mut drones: Query<(&mut Motors, &mut ExternalForce)>,
 ) {
-    let thermal_render_layer = RenderLayers::layer(1);
+    for (mut motors, mut external_force) in &mut drones {
+        let thrust = if motors.is_armed { motors.hover_thrust } else { 0.0 };
 
-    let player_dimensions = Vec3::new(2.5, 1.0, 3.0);
-    let player_position = Vec3::new(0.0, 10.0, 0.0);
-    let player_name = "Player";
+        if thrust != motors.applied {
+            external_force.force.y += thrust - motors.applied;
+            motors.applied = thrust;
+        }
+    }
+}
+
+/// System that makes `FlightController`s hold their setpoints.
+///
+/// Disarmed `Motors` can't hold anything.
+pub fn run_flight_controller(
+    mut drones: Query<(&mut FlightController, &mut ExternalForce, &mut Velocity, &Transform, Option<&Motors>)>,
+) {
+    for (mut controller, mut external_force, mut velocity, transform, motors) in &mut drones {
+        let setpoint = controller.setpoint.filter(|_| motors.map_or(true, |motors| motors.is_armed));
+
+        let Some(setpoint) = setpoint else {
+            external_force.force -= controller.applied;
+            controller.applied = Vec3::ZERO;
+            continue;
+        };
+
+        let target_velocity = setpoint.velocity + controller.avoidance;
+        let thrust = (controller.mass * controller.velocity_gain * (target_velocity - velocity.linvel))
+            .clamp_length_max(controller.max_thrust);
+
+        external_force.force += thrust - controller.applied;
+        controller.applied = thrust;
+
+        if let Some(yaw) = setpoint.yaw {
+            let (current, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            // the shortest way around
+            let error = (yaw - current + PI).rem_euclid(TAU) - PI;
+
+            velocity.angvel.y = (error * 2.0).clamp(-controller.max_yaw_rate, controller.max_yaw_rate);
+        }
+    }
+}
+
+/// System that drains `Battery`s according to the thrust of the motors.
+/// 
+/// Wind force is not a thrust, so it is subtracted from the `ExternalForce`.
+pub fn drain_battery(
+    time: Res<Time>,
+    mut drones: Query<(&mut Battery, &ExternalForce, Option<&WindDrag>)>,
+) {
+    for (mut battery, external_force, wind) in &mut drones {
+        let thrust = (external_force.force - wind.map_or(Vec3::ZERO, |wind| wind.applied)).length();
+        let energy = battery.power(thrust) * time.delta_seconds() / 3600.0;
+
+        battery.remaining = (battery.remaining - energy).max(0.0);
+    }
+}
 
+/// System that keeps the camera target at the `Player`, so the camera follows the piloted drone.
+///
+/// Only the translation is copied, the camera doesn't turn with the drone.
+fn follow_player(
+    players: Query<&Transform, (With<Player>, Without<PlayerCameraTarget>)>,
+    mut targets: Query<&mut Transform, (With<PlayerCameraTarget>, Without<Player>)>,
+) {
+    let Ok(player_transform) = players.get_single() else {
+        return;
+    };
+
+    for mut target_transform in &mut targets {
+        target_transform.translation = player_transform.translation;
+    }
+}
+
+/// System for initializing the Player camera target. Drones are spawned by the fleet.
+fn spawn_player_camera_target(
+    mut commands: Commands,
+) {
     commands.spawn((
         PbrBundle {
-            transform: Transform::from_xyz(player_position.x, player_position.y, player_position.z),
+            // the launch pad
+            transform: Transform::from_xyz(0.0, 0.55, 0.0),
             ..default()
         },
         PlayerCameraTarget,
         ThirdPersonCameraTarget
     ));
-
-    let player_mesh: Handle<Mesh> = server.load("models/drone-model.glb#Mesh0/Primitive0");
-
-    let player = (
-        MaterialMeshBundle {
-            mesh: player_mesh,
-            material: ext_materials.add(ExtendedMaterial {
-                base: StandardMaterial {
-                    base_color: Color::RED,
-                    ..default()
-                },
-                extension: ThermalMaterialExtension { 
-                    temperature: 15.0,
-                    intensity: 1.0,
-                    is_infrared_mode_active: 0,
-                },
-            }),
-            transform: Transform::from_xyz(player_position.x, player_position.y, player_position.z),
-            ..default()
-        },
-        Thermal,
-        Player,
-        RigidBody::Dynamic,
-        GravityScale(1.0),
-        ExternalForce {
-            force: Vec3::new(0.0, 73.8, 0.0),
-            ..default()
-        },
-        ExternalImpulse {
-            ..default()
-        },
-        Collider::cuboid(player_dimensions.x / 2.0, player_dimensions.y / 2.0, player_dimensions.z / 2.0),
-        Name::new(player_name),
-        thermal_render_layer,
-    );
-
-    commands.spawn(player);
 }
@@ -6,32 +6,36 @@ use bevy::{
     ecs::query::QueryItem,
     prelude::*,
     render::{
-        extract_component::{
-            ComponentUniforms, ExtractComponent, ExtractComponentPlugin, UniformComponentPlugin,
-        },
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        globals::{GlobalsBuffer, GlobalsUniform},
         render_graph::{
             NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
         },
         render_resource::{
-            binding_types::{sampler, texture_2d, uniform_buffer},
+            binding_types::{sampler, texture_2d, uniform_buffer, uniform_buffer_sized},
             *,
         },
-        renderer::{RenderContext, RenderDevice},
-        texture::BevyDefault,
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        texture::{BevyDefault, FallbackImage},
         view::ViewTarget,
-        RenderApp,
+        Render, RenderApp, RenderSet,
     },
+    utils::{HashMap, HashSet},
 };
 
+/// Built-in post-processing effects and their settings.
+pub mod effects;
+
+use effects::{PostProcessEffect, PostProcessEffectKind};
+
 /// Post-processing plugin.
+/// 
+/// Runs effects from the camera's `PostProcessStack` one after another, in the order they are stored.
 pub struct PostProcessPlugin;
 
 impl Plugin for PostProcessPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((
-            ExtractComponentPlugin::<PostProcessSettings>::default(),
-            UniformComponentPlugin::<PostProcessSettings>::default(),
-        ));
+        app.add_plugins(ExtractComponentPlugin::<PostProcessStack>::default());
 
         let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
@@ -49,7 +53,13 @@ impl Plugin for PostProcessPlugin {
                     PostProcessLabel,
                     Node3d::EndMainPassPostProcessing,
                 ),
-            );
+            )
+            .init_resource::<PostProcessHistoryTextures>()
+            .init_resource::<PostProcessSettingsBuffer>()
+            .add_systems(Render, (
+                prepare_post_process_history,
+                prepare_post_process_settings,
+            ).in_set(RenderSet::PrepareResources));
     }
 
     fn finish(&self, app: &mut App) {
@@ -58,7 +68,7 @@ impl Plugin for PostProcessPlugin {
         };
 
         render_app
-            .init_resource::<PostProcessPipeline>();
+            .init_resource::<PostProcessPipelines>();
     }
 }
 
@@ -71,75 +81,127 @@ struct PostProcessNode;
 impl ViewNode for PostProcessNode {
     type ViewQuery = (
         &'static ViewTarget,
-        &'static PostProcessSettings,
+        &'static PostProcessStack,
     );
 
     fn run(
         &self,
-        _graph: &mut RenderGraphContext,
+        graph: &mut RenderGraphContext,
         render_context: &mut RenderContext,
-        (view_target, _post_process_settings): QueryItem<Self::ViewQuery>,
+        (view_target, post_process_stack): QueryItem<Self::ViewQuery>,
         world: &World,
     ) -> Result<(), NodeRunError> {
-        let post_process_pipeline = world.resource::<PostProcessPipeline>();
+        let post_process_pipelines = world.resource::<PostProcessPipelines>();
 
         let pipeline_cache = world.resource::<PipelineCache>();
 
-        let Some(pipeline) = pipeline_cache.get_render_pipeline(post_process_pipeline.pipeline_id)
-        else {
+        let Some(globals_binding) = world.resource::<GlobalsBuffer>().buffer.binding() else {
             return Ok(());
         };
 
-        let settings_uniforms = world.resource::<ComponentUniforms<PostProcessSettings>>();
-        let Some(settings_binding) = settings_uniforms.uniforms().binding() else {
+        let settings = world.resource::<PostProcessSettingsBuffer>();
+
+        let (Some(settings_buffer), Some(settings_offset)) = (
+            settings.buffer.as_ref(),
+            settings.offsets.get(&graph.view_entity()),
+        ) else {
             return Ok(());
         };
 
-        let post_process = view_target.post_process_write();
-
-        let bind_group = render_context
-            .render_device()
-            .create_bind_group(
-                "post_process_bind_group",
-                &post_process_pipeline.layout,
-                &BindGroupEntries::sequential((
-                    post_process.source,
-                    &post_process_pipeline.sampler,
-                    settings_binding.clone(),
-                )),
-        );
-
-        let mut render_pass = render_context
-            .begin_tracked_render_pass(
-                RenderPassDescriptor {
-                    label: Some("post_process_pass"),
-                    color_attachments: &[Some(RenderPassColorAttachment {
-                        view: post_process.destination,
-                        resolve_target: None,
-                        ops: Operations::default(),
-                    })],
-                    depth_stencil_attachment: None,
-                    timestamp_writes: None,
-                    occlusion_query_set: None,
-                }
-        );
+        let history_texture = world
+            .resource::<PostProcessHistoryTextures>()
+            .0
+            .get(&graph.view_entity());
+
+        let history_view = match history_texture {
+            Some(history_texture) => &history_texture.view,
+            None => &world.resource::<FallbackImage>().d2.texture_view,
+        };
 
-        render_pass.set_render_pipeline(pipeline);
-        render_pass.set_bind_group(0, &bind_group, &[]);
-        render_pass.draw(0..3, 0..1);
+        for (index, effect) in post_process_stack.effects.iter().enumerate() {
+            // effect with zero intensity doesn't change anything, so there is no need in a pass
+            if effect.intensity() == 0.0 {
+                continue;
+            }
+
+            let Some(pipeline) = post_process_pipelines
+                .pipeline_ids
+                .get(&effect.kind())
+                .and_then(|pipeline_id| pipeline_cache.get_render_pipeline(*pipeline_id))
+            else {
+                continue;
+            };
+
+            let post_process = view_target.post_process_write();
+
+            let bind_group = render_context
+                .render_device()
+                .create_bind_group(
+                    "post_process_bind_group",
+                    &post_process_pipelines.layout,
+                    &BindGroupEntries::sequential((
+                        post_process.source,
+                        &post_process_pipelines.sampler,
+                        BufferBinding {
+                            buffer: settings_buffer,
+                            offset: 0,
+                            size: BufferSize::new(SETTINGS_STRIDE),
+                        },
+                        globals_binding.clone(),
+                        history_view,
+                    )),
+            );
+
+            let mut render_pass = render_context
+                .begin_tracked_render_pass(
+                    RenderPassDescriptor {
+                        label: Some("post_process_pass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: post_process.destination,
+                            resolve_target: None,
+                            ops: Operations::default(),
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    }
+            );
+
+            render_pass.set_render_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[settings_offset + index as u32 * SETTINGS_STRIDE as u32]);
+            render_pass.draw(0..3, 0..1);
+            drop(render_pass);
+
+            // frozen effect keeps showing the same frame, so the history shouldn't be overwritten
+            if let Some(history_texture) = history_texture {
+                if effect.kind().keeps_history() && !effect.is_frozen() {
+                    render_context.command_encoder().copy_texture_to_texture(
+                        view_target.main_texture().as_image_copy(),
+                        history_texture.texture.as_image_copy(),
+                        history_texture.texture.size(),
+                    );
+                }
+            }
+        }
 
         Ok(())
     }
 }
 
+/// Render pipelines for every `PostProcessEffectKind`.
+/// 
+/// All of the effects share the same bind group layout:
+/// screen texture, sampler, effect settings, globals and previous frame.
 #[derive(Resource)]
-struct PostProcessPipeline {
+struct PostProcessPipelines {
     layout: BindGroupLayout,
     sampler: Sampler,
-    pipeline_id: CachedRenderPipelineId,
+    pipeline_ids: HashMap<PostProcessEffectKind, CachedRenderPipelineId>,
+    /// Shader with shared bindings, which is imported by every effect.
+    _common_shader: Handle<Shader>,
 }
 
-impl FromWorld for PostProcessPipeline {
+impl FromWorld for PostProcessPipelines {
     fn from_world(world: &mut World) -> Self {
         let render_device = world.resource::<RenderDevice>();
 
@@ -151,53 +213,239 @@ impl FromWorld for PostProcessPipeline {
                     (
                         texture_2d(TextureSampleType::Float { filterable: true }),
                         sampler(SamplerBindingType::Filtering),
-                        uniform_buffer::<PostProcessSettings>(false),
+                        uniform_buffer_sized(true, BufferSize::new(SETTINGS_STRIDE)),
+                        uniform_buffer::<GlobalsUniform>(false),
+                        texture_2d(TextureSampleType::Float { filterable: true }),
                     ),
                 ),
             );
 
         let sampler = render_device.create_sampler(&SamplerDescriptor::default());
 
-        let shader = world
-            .resource::<AssetServer>()
-            .load("shaders/post_processing.wgsl");
-
-        let pipeline_id = world
-            .resource_mut::<PipelineCache>()
-            .queue_render_pipeline(RenderPipelineDescriptor {
-                label: Some("post_process_pipeline".into()),
-                layout: vec![layout.clone()],
-                vertex: fullscreen_shader_vertex_state(),
-                fragment: Some(FragmentState {
-                    shader,
-                    shader_defs: vec![],
-                    entry_point: "fragment".into(),
-                    targets: vec![Some(ColorTargetState {
-                        format: TextureFormat::bevy_default(),
-                        blend: None,
-                        write_mask: ColorWrites::ALL,
-                    })],
-                }),
-                primitive: PrimitiveState::default(),
-                depth_stencil: None,
-                multisample: MultisampleState::default(),
-                push_constant_ranges: vec![],
-            });
+        let asset_server = world.resource::<AssetServer>();
+
+        let common_shader = asset_server.load("shaders/post_processing/common.wgsl");
+
+        let shaders: Vec<(PostProcessEffectKind, Handle<Shader>)> = PostProcessEffectKind::ALL
+            .iter()
+            .map(|kind| (*kind, asset_server.load(kind.shader_path())))
+            .collect();
+
+        let pipeline_cache = world.resource_mut::<PipelineCache>();
+
+        let pipeline_ids = shaders
+            .into_iter()
+            .map(|(kind, shader)| {
+                let pipeline_id = pipeline_cache.queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("post_process_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![Some(ColorTargetState {
+                            format: TextureFormat::bevy_default(),
+                            blend: None,
+                            write_mask: ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    push_constant_ranges: vec![],
+                });
+
+                (kind, pipeline_id)
+            })
+            .collect();
 
         Self {
             layout,
             sampler,
-            pipeline_id,
+            pipeline_ids,
+            _common_shader: common_shader,
         }
     }
 }
 
-/// Post-processing settings.
+/// Space taken by the settings of a single effect in the `PostProcessSettingsBuffer`.
+///
+/// It is the largest uniform offset alignment allowed by wgpu and more than the settings of any effect take.
+const SETTINGS_STRIDE: u64 = 256;
+
+/// Settings of the effects of all views, which are written once per frame.
+///
+/// Settings of every effect take `SETTINGS_STRIDE` bytes, `offsets` point to the first effect of each view.
+#[derive(Resource, Default)]
+struct PostProcessSettingsBuffer {
+    data: Vec<u8>,
+    buffer: Option<Buffer>,
+    offsets: HashMap<Entity, u32>,
+}
+
+/// System that writes settings of the effects to the `PostProcessSettingsBuffer`.
+///
+/// Buffer is recreated only if it is too small for the effects of all the views.
+fn prepare_post_process_settings(
+    mut settings: ResMut<PostProcessSettingsBuffer>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    views: Query<(Entity, &PostProcessStack), With<ViewTarget>>,
+) {
+    let settings = &mut *settings;
+
+    settings.data.clear();
+    settings.offsets.clear();
+
+    for (entity, stack) in &views {
+        settings.offsets.insert(entity, settings.data.len() as u32);
+
+        for effect in stack.effects.iter() {
+            let start = settings.data.len();
+            settings.data.extend(effect.uniform_bytes());
+            settings.data.resize(start + SETTINGS_STRIDE as usize, 0);
+        }
+    }
+
+    if settings.data.is_empty() {
+        return;
+    }
+
+    let is_large_enough = settings.buffer
+        .as_ref()
+        .is_some_and(|buffer| buffer.size() >= settings.data.len() as u64);
+
+    if !is_large_enough {
+        settings.buffer = Some(render_device.create_buffer(&BufferDescriptor {
+            label: Some("post_process_settings_buffer"),
+            size: settings.data.len() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+    }
+
+    if let Some(buffer) = &settings.buffer {
+        render_queue.write_buffer(buffer, 0, &settings.data);
+    }
+}
+
+/// Previous frame of a view, which is used by effects that keep history.
+struct PostProcessHistoryTexture {
+    texture: Texture,
+    view: TextureView,
+}
+
+/// Previous frames of the views, which post-processing stacks have effects that keep history.
+#[derive(Resource, Default)]
+struct PostProcessHistoryTextures(HashMap<Entity, PostProcessHistoryTexture>);
+
+/// System that creates history textures for the views, which need them.
 /// 
-/// Use `intensity` to control the intensity of post-processing effect.
+/// Texture is recreated if the view is resized. Textures of the views that are gone are dropped.
+fn prepare_post_process_history(
+    mut history_textures: ResMut<PostProcessHistoryTextures>,
+    render_device: Res<RenderDevice>,
+    views: Query<(Entity, &ViewTarget, &PostProcessStack)>,
+) {
+    let mut views_with_history = HashSet::new();
+
+    for (entity, view_target, stack) in &views {
+        if !stack.effects.iter().any(|effect| effect.kind().keeps_history()) {
+            continue;
+        }
+
+        views_with_history.insert(entity);
+
+        let size = view_target.main_texture().size();
+        let format = view_target.main_texture_format();
+
+        let is_valid = history_textures.0
+            .get(&entity)
+            .is_some_and(|history| history.texture.size() == size && history.texture.format() == format);
+
+        if is_valid {
+            continue;
+        }
+
+        let texture = render_device.create_texture(&TextureDescriptor {
+            label: Some("post_process_history_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        history_textures.0.insert(entity, PostProcessHistoryTexture { texture, view });
+    }
+
+    history_textures.0.retain(|entity, _| views_with_history.contains(entity));
+}
+
+/// Ordered stack of post-processing effects of a camera.
 /// 
-/// Generally, if `intensity` is set to 0.0, the effect is not applyed. If it's set to 1.0, the effect is fully applied.
-#[derive(Component, Default, Clone, Copy, ExtractComponent, ShaderType)]
-pub struct PostProcessSettings {
-    pub intensity: f32,
+/// Effects are applied from the first to the last. Each of them is rendered in its own pass,
+/// so the order matters: e.g. noise applied before palette mapping will be colored by the palette.
+/// 
+/// Effect with `intensity` set to 0.0 is skipped.
+#[derive(Component, Default, Clone, ExtractComponent)]
+pub struct PostProcessStack {
+    pub effects: Vec<PostProcessEffect>,
+}
+
+impl PostProcessStack {
+    /// Creates a new stack with a given effect on top of it.
+    pub fn with(mut self, effect: PostProcessEffect) -> Self {
+        self.push(effect);
+        self
+    }
+
+    /// Adds effect to the end of the stack.
+    pub fn push(&mut self, effect: PostProcessEffect) {
+        self.effects.push(effect);
+    }
+
+    /// Inserts effect at the `index`. If `index` is out of bounds, effect is added to the end of the stack.
+    pub fn insert(&mut self, index: usize, effect: PostProcessEffect) {
+        let index = index.min(self.effects.len());
+        self.effects.insert(index, effect);
+    }
+
+    /// Removes the first effect of a given kind.
+    pub fn remove(&mut self, kind: PostProcessEffectKind) -> Option<PostProcessEffect> {
+        let index = self.position(kind)?;
+        Some(self.effects.remove(index))
+    }
+
+    /// Moves the first effect of a given kind to the `index`.
+    /// 
+    /// Returns `false` if there is no such effect in the stack.
+    pub fn move_to(&mut self, kind: PostProcessEffectKind, index: usize) -> bool {
+        match self.remove(kind) {
+            Some(effect) => {
+                self.insert(index, effect);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns position of the first effect of a given kind.
+    pub fn position(&self, kind: PostProcessEffectKind) -> Option<usize> {
+        self.effects.iter().position(|effect| effect.kind() == kind)
+    }
+
+    /// Returns the first effect of a given kind.
+    pub fn get(&self, kind: PostProcessEffectKind) -> Option<&PostProcessEffect> {
+        self.effects.iter().find(|effect| effect.kind() == kind)
+    }
+
+    /// Returns the first effect of a given kind.
+    pub fn get_mut(&mut self, kind: PostProcessEffectKind) -> Option<&mut PostProcessEffect> {
+        self.effects.iter_mut().find(|effect| effect.kind() == kind)
+    }
 }
@@ -0,0 +1,190 @@
+use bevy::render::render_resource::encase::UniformBuffer;
+
+/// Settings of the effects, which are sent to their shaders.
+// `ShaderType` derive generates checks of the field types, which are never called.
+#[allow(dead_code)]
+mod settings;
+
+pub use settings::*;
+
+/// Kind of the post-processing effect.
+///
+/// Every kind has its own shader and render pipeline.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum PostProcessEffectKind {
+    Grayscale,
+    Palette,
+    Vignette,
+    Sharpen,
+    Noise,
+    SensorNoise,
+    RollingShutter,
+    MotionBlur,
+    ChromaticAberration,
+    LensDistortion,
+    Compression,
+    AnalogStatic,
+    ThermalSensor,
+    NightVision,
+}
+
+impl PostProcessEffectKind {
+    /// All of the effect kinds. Pipelines are queued for each of them.
+    pub const ALL: [PostProcessEffectKind; 14] = [
+        PostProcessEffectKind::Grayscale,
+        PostProcessEffectKind::Palette,
+        PostProcessEffectKind::Vignette,
+        PostProcessEffectKind::Sharpen,
+        PostProcessEffectKind::Noise,
+        PostProcessEffectKind::SensorNoise,
+        PostProcessEffectKind::RollingShutter,
+        PostProcessEffectKind::MotionBlur,
+        PostProcessEffectKind::ChromaticAberration,
+        PostProcessEffectKind::LensDistortion,
+        PostProcessEffectKind::Compression,
+        PostProcessEffectKind::AnalogStatic,
+        PostProcessEffectKind::ThermalSensor,
+        PostProcessEffectKind::NightVision,
+    ];
+
+    /// Path to the effect's fragment shader.
+    pub fn shader_path(&self) -> &'static str {
+        match self {
+            PostProcessEffectKind::Grayscale => "shaders/post_processing/grayscale.wgsl",
+            PostProcessEffectKind::Palette => "shaders/post_processing/palette.wgsl",
+            PostProcessEffectKind::Vignette => "shaders/post_processing/vignette.wgsl",
+            PostProcessEffectKind::Sharpen => "shaders/post_processing/sharpen.wgsl",
+            PostProcessEffectKind::Noise => "shaders/post_processing/noise.wgsl",
+            PostProcessEffectKind::SensorNoise => "shaders/post_processing/sensor_noise.wgsl",
+            PostProcessEffectKind::RollingShutter => "shaders/post_processing/rolling_shutter.wgsl",
+            PostProcessEffectKind::MotionBlur => "shaders/post_processing/motion_blur.wgsl",
+            PostProcessEffectKind::ChromaticAberration => "shaders/post_processing/chromatic_aberration.wgsl",
+            PostProcessEffectKind::LensDistortion => "shaders/post_processing/lens_distortion.wgsl",
+            PostProcessEffectKind::Compression => "shaders/post_processing/compression.wgsl",
+            PostProcessEffectKind::AnalogStatic => "shaders/post_processing/analog_static.wgsl",
+            PostProcessEffectKind::ThermalSensor => "shaders/post_processing/thermal_sensor.wgsl",
+            PostProcessEffectKind::NightVision => "shaders/post_processing/night_vision.wgsl",
+        }
+    }
+
+    /// Whether the effect needs the previous frame, e.g. to freeze the image.
+    pub fn keeps_history(&self) -> bool {
+        matches!(self, PostProcessEffectKind::ThermalSensor)
+    }
+}
+
+/// Single post-processing effect with its settings.
+///
+/// Settings of the effect are sent to its shader as a uniform.
+#[derive(Debug, Clone, Copy)]
+pub enum PostProcessEffect {
+    Grayscale(GrayscaleSettings),
+    Palette(PaletteSettings),
+    Vignette(VignetteSettings),
+    Sharpen(SharpenSettings),
+    Noise(NoiseSettings),
+    SensorNoise(SensorNoiseSettings),
+    RollingShutter(RollingShutterSettings),
+    MotionBlur(MotionBlurSettings),
+    ChromaticAberration(ChromaticAberrationSettings),
+    LensDistortion(LensDistortionSettings),
+    Compression(CompressionSettings),
+    AnalogStatic(AnalogStaticSettings),
+    ThermalSensor(ThermalSensorSettings),
+    NightVision(NightVisionSettings),
+}
+
+impl PostProcessEffect {
+    /// Returns kind of the effect.
+    pub fn kind(&self) -> PostProcessEffectKind {
+        match self {
+            PostProcessEffect::Grayscale(_) => PostProcessEffectKind::Grayscale,
+            PostProcessEffect::Palette(_) => PostProcessEffectKind::Palette,
+            PostProcessEffect::Vignette(_) => PostProcessEffectKind::Vignette,
+            PostProcessEffect::Sharpen(_) => PostProcessEffectKind::Sharpen,
+            PostProcessEffect::Noise(_) => PostProcessEffectKind::Noise,
+            PostProcessEffect::SensorNoise(_) => PostProcessEffectKind::SensorNoise,
+            PostProcessEffect::RollingShutter(_) => PostProcessEffectKind::RollingShutter,
+            PostProcessEffect::MotionBlur(_) => PostProcessEffectKind::MotionBlur,
+            PostProcessEffect::ChromaticAberration(_) => PostProcessEffectKind::ChromaticAberration,
+            PostProcessEffect::LensDistortion(_) => PostProcessEffectKind::LensDistortion,
+            PostProcessEffect::Compression(_) => PostProcessEffectKind::Compression,
+            PostProcessEffect::AnalogStatic(_) => PostProcessEffectKind::AnalogStatic,
+            PostProcessEffect::ThermalSensor(_) => PostProcessEffectKind::ThermalSensor,
+            PostProcessEffect::NightVision(_) => PostProcessEffectKind::NightVision,
+        }
+    }
+
+    /// Returns intensity of the effect.
+    pub fn intensity(&self) -> f32 {
+        match self {
+            PostProcessEffect::Grayscale(settings) => settings.intensity,
+            PostProcessEffect::Palette(settings) => settings.intensity,
+            PostProcessEffect::Vignette(settings) => settings.intensity,
+            PostProcessEffect::Sharpen(settings) => settings.intensity,
+            PostProcessEffect::Noise(settings) => settings.intensity,
+            PostProcessEffect::SensorNoise(settings) => settings.intensity,
+            PostProcessEffect::RollingShutter(settings) => settings.intensity,
+            PostProcessEffect::MotionBlur(settings) => settings.intensity,
+            PostProcessEffect::ChromaticAberration(settings) => settings.intensity,
+            PostProcessEffect::LensDistortion(settings) => settings.intensity,
+            PostProcessEffect::Compression(settings) => settings.intensity,
+            PostProcessEffect::AnalogStatic(settings) => settings.intensity,
+            PostProcessEffect::ThermalSensor(settings) => settings.intensity,
+            PostProcessEffect::NightVision(settings) => settings.intensity,
+        }
+    }
+
+    /// Sets intensity of the effect.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        match self {
+            PostProcessEffect::Grayscale(settings) => settings.intensity = intensity,
+            PostProcessEffect::Palette(settings) => settings.intensity = intensity,
+            PostProcessEffect::Vignette(settings) => settings.intensity = intensity,
+            PostProcessEffect::Sharpen(settings) => settings.intensity = intensity,
+            PostProcessEffect::Noise(settings) => settings.intensity = intensity,
+            PostProcessEffect::SensorNoise(settings) => settings.intensity = intensity,
+            PostProcessEffect::RollingShutter(settings) => settings.intensity = intensity,
+            PostProcessEffect::MotionBlur(settings) => settings.intensity = intensity,
+            PostProcessEffect::ChromaticAberration(settings) => settings.intensity = intensity,
+            PostProcessEffect::LensDistortion(settings) => settings.intensity = intensity,
+            PostProcessEffect::Compression(settings) => settings.intensity = intensity,
+            PostProcessEffect::AnalogStatic(settings) => settings.intensity = intensity,
+            PostProcessEffect::ThermalSensor(settings) => settings.intensity = intensity,
+            PostProcessEffect::NightVision(settings) => settings.intensity = intensity,
+        }
+    }
+
+    /// Whether the effect shows the previous frame instead of the current one.
+    pub fn is_frozen(&self) -> bool {
+        match self {
+            PostProcessEffect::ThermalSensor(settings) => settings.frozen != 0,
+            _ => false,
+        }
+    }
+
+    /// Encodes settings of the effect with the uniform memory layout.
+    pub fn uniform_bytes(&self) -> Vec<u8> {
+        let mut buffer = UniformBuffer::new(Vec::new());
+
+        match self {
+            PostProcessEffect::Grayscale(settings) => buffer.write(settings),
+            PostProcessEffect::Palette(settings) => buffer.write(settings),
+            PostProcessEffect::Vignette(settings) => buffer.write(settings),
+            PostProcessEffect::Sharpen(settings) => buffer.write(settings),
+            PostProcessEffect::Noise(settings) => buffer.write(settings),
+            PostProcessEffect::SensorNoise(settings) => buffer.write(settings),
+            PostProcessEffect::RollingShutter(settings) => buffer.write(settings),
+            PostProcessEffect::MotionBlur(settings) => buffer.write(settings),
+            PostProcessEffect::ChromaticAberration(settings) => buffer.write(settings),
+            PostProcessEffect::LensDistortion(settings) => buffer.write(settings),
+            PostProcessEffect::Compression(settings) => buffer.write(settings),
+            PostProcessEffect::AnalogStatic(settings) => buffer.write(settings),
+            PostProcessEffect::ThermalSensor(settings) => buffer.write(settings),
+            PostProcessEffect::NightVision(settings) => buffer.write(settings),
+        }
+        .expect("post-processing settings should always be encodable");
+
+        buffer.into_inner()
+    }
+}
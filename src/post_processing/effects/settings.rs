@@ -0,0 +1,306 @@
+use bevy::{math::{Vec2, Vec3}, render::render_resource::ShaderType};
+
+/// Grayscale effect settings.
+///
+/// `strength` describes how dark the result is. The bigger it is, the darker the image.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GrayscaleSettings {
+    pub intensity: f32,
+    pub strength: f32,
+}
+
+impl Default for GrayscaleSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            strength: 30.0,
+        }
+    }
+}
+
+/// Color palettes for the `Palette` effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    #[default]
+    WhiteHot,
+    BlackHot,
+    Ironbow,
+    Rainbow,
+}
+
+impl From<Palette> for u32 {
+    fn from(palette: Palette) -> Self {
+        match palette {
+            Palette::WhiteHot => 0,
+            Palette::BlackHot => 1,
+            Palette::Ironbow => 2,
+            Palette::Rainbow => 3,
+        }
+    }
+}
+
+/// Palette mapping effect settings.
+///
+/// Maps luminance of the image to one of the `Palette`s. Use `Palette` to set `palette`.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct PaletteSettings {
+    pub intensity: f32,
+    pub palette: u32,
+}
+
+impl Default for PaletteSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            palette: Palette::default().into(),
+        }
+    }
+}
+
+/// Vignette effect settings.
+///
+/// `radius` is a distance from the center of the screen where darkening starts,
+/// `smoothness` is a width of the transition.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct VignetteSettings {
+    pub intensity: f32,
+    pub radius: f32,
+    pub smoothness: f32,
+}
+
+impl Default for VignetteSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            radius: 0.5,
+            smoothness: 0.4,
+        }
+    }
+}
+
+/// Sharpen effect settings.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct SharpenSettings {
+    pub intensity: f32,
+}
+
+impl Default for SharpenSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Noise effect settings.
+///
+/// Noise is animated, `grain_size` is a size of the single noise grain in pixels.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct NoiseSettings {
+    pub intensity: f32,
+    pub grain_size: f32,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.1,
+            grain_size: 1.0,
+        }
+    }
+}
+
+/// Camera sensor noise settings.
+///
+/// Noise has two components: photon shot noise, which grows with a square root of the signal,
+/// and read noise, which is constant. Both are standard deviations relative to the full white signal.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct SensorNoiseSettings {
+    pub intensity: f32,
+    pub shot_noise: f32,
+    pub read_noise: f32,
+}
+
+impl Default for SensorNoiseSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            shot_noise: 0.02,
+            read_noise: 0.005,
+        }
+    }
+}
+
+/// Rolling shutter settings.
+///
+/// Rows of the sensor are read one after another, so the rotating camera skews the image.
+/// `skew` is a uv offset of the last row relative to the first one.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct RollingShutterSettings {
+    pub intensity: f32,
+    pub skew: Vec2,
+}
+
+impl Default for RollingShutterSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            skew: Vec2::ZERO,
+        }
+    }
+}
+
+/// Motion blur settings.
+///
+/// `direction` is a uv distance the image moves while the shutter is open.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct MotionBlurSettings {
+    pub intensity: f32,
+    pub samples: u32,
+    pub direction: Vec2,
+}
+
+impl Default for MotionBlurSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            samples: 8,
+            direction: Vec2::ZERO,
+        }
+    }
+}
+
+/// Chromatic aberration settings.
+///
+/// `offset` is a relative difference of the red and blue channels magnification.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct ChromaticAberrationSettings {
+    pub intensity: f32,
+    pub offset: f32,
+}
+
+impl Default for ChromaticAberrationSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            offset: 0.004,
+        }
+    }
+}
+
+/// Lens distortion settings.
+///
+/// Radial Brown-Conrady distortion with `k1` and `k2` coefficients.
+/// Negative values give barrel distortion, which is typical for wide FPV lenses.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct LensDistortionSettings {
+    pub intensity: f32,
+    pub k1: f32,
+    pub k2: f32,
+}
+
+impl Default for LensDistortionSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            k1: -0.08,
+            k2: 0.01,
+        }
+    }
+}
+
+/// Video compression artifacts settings.
+///
+/// `block_size` is a size of the compression block in pixels, `levels` is a number of color levels per channel.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct CompressionSettings {
+    pub intensity: f32,
+    pub block_size: f32,
+    pub levels: f32,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.3,
+            block_size: 8.0,
+            levels: 32.0,
+        }
+    }
+}
+
+/// Analog FPV video link static settings.
+///
+/// `intensity` describes how broken the link is: snow, torn lines and signal dropouts grow with it.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct AnalogStaticSettings {
+    pub intensity: f32,
+}
+
+impl Default for AnalogStaticSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.0,
+        }
+    }
+}
+
+/// Uncooled thermal sensor (microbolometer) imperfections settings.
+///
+/// `fixed_pattern_noise` and `column_striping` are non-uniformities of the sensor. They grow with `drift`,
+/// which is 0.0 right after the non-uniformity correction (NUC) and 1.0 right before the next one.
+/// `temporal_noise` changes every frame. `vignetting` is a radial falloff, `halo` is a dark ring around hot objects.
+///
+/// While `frozen` is not 0, the NUC shutter is closed and the previous frame is shown.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct ThermalSensorSettings {
+    pub intensity: f32,
+    pub fixed_pattern_noise: f32,
+    pub column_striping: f32,
+    pub temporal_noise: f32,
+    pub vignetting: f32,
+    pub halo: f32,
+    pub drift: f32,
+    pub frozen: u32,
+}
+
+impl Default for ThermalSensorSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            fixed_pattern_noise: 0.04,
+            column_striping: 0.03,
+            temporal_noise: 0.02,
+            vignetting: 0.25,
+            halo: 0.5,
+            drift: 0.0,
+            frozen: 0,
+        }
+    }
+}
+
+/// Night vision (image intensifier) settings.
+///
+/// `gain` is an amplification of the incoming light, `bloom` is a glow around bright light sources
+/// and `scintillation` is an amount of sparkling noise of the intensifier tube.
+/// Amplified image is shown with the `phosphor` color.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct NightVisionSettings {
+    pub intensity: f32,
+    pub gain: f32,
+    pub bloom: f32,
+    pub scintillation: f32,
+    pub phosphor: Vec3,
+}
+
+impl Default for NightVisionSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            gain: 8.0,
+            bloom: 0.6,
+            scintillation: 0.3,
+            phosphor: Vec3::new(0.35, 1.0, 0.4),
+        }
+    }
+}
@@ -0,0 +1,714 @@
+use std::{
+    f32::consts::{FRAC_PI_2, TAU},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{player::Player, ui::menu::AppState};
+
+/// Plugin for drone racing: gates, lap timing, ghost of the best lap and the track editor.
+pub struct RacingPlugin;
+
+impl Plugin for RacingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RaceTrack>()
+            .init_resource::<RaceState>()
+            .init_resource::<TrackEditor>()
+            .init_resource::<TrackEditorBindings>()
+            .add_event::<GatePassed>()
+            .add_systems(Startup, setup_race_hud)
+            .add_systems(Update, (
+                edit_track.run_if(in_state(AppState::InFlight)),
+                spawn_track,
+                detect_gate_passes,
+                process_gate_passes,
+                record_lap,
+                move_ghost,
+                color_gates,
+                update_race_hud,
+            ).chain());
+    }
+}
+
+/// Error that can happen while reading or writing a track file.
+#[derive(Debug)]
+pub enum TrackError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for TrackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't access track file: {error}"),
+            Self::Parse(error) => write!(f, "can't parse track file: {error}"),
+            Self::Serialize(error) => write!(f, "can't serialize track: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for TrackError {}
+
+impl From<io::Error> for TrackError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for TrackError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<ron::Error> for TrackError {
+    fn from(error: ron::Error) -> Self {
+        Self::Serialize(error)
+    }
+}
+
+// resources
+/// Describes a race gate.
+///
+/// `position` is the center of the opening, `yaw` is the rotation around Y in radians.
+/// Drones should fly through the gate along its forward direction, which is -Z rotated by `yaw`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GateDefinition {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl GateDefinition {
+    /// Creates a gate of the default size.
+    pub fn new(position: Vec3, yaw: f32) -> Self {
+        Self {
+            position,
+            yaw,
+            width: 3.0,
+            height: 2.5,
+        }
+    }
+
+    /// Returns the transform of the gate.
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position).with_rotation(Quat::from_rotation_y(self.yaw))
+    }
+}
+
+/// Describes the race track: its gates in the order they should be passed.
+///
+/// Gate 0 is the start and finish line. Tracks are stored in RON files,
+/// best laps are stored in `.best.ron` files in `logs/tracks`.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaceTrack {
+    pub name: String,
+    pub gates: Vec<GateDefinition>,
+    /// File the track was loaded from.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl Default for RaceTrack {
+    /// Small loop around the launch pad.
+    fn default() -> Self {
+        let gates = (0..4)
+            .map(|index| {
+                let angle = index as f32 * FRAC_PI_2;
+                let position = Vec3::new(-12.0 * angle.cos(), 4.0 + (index % 2) as f32, -12.0 * angle.sin());
+                // gates look along the loop
+                GateDefinition::new(position, -angle)
+            })
+            .collect();
+
+        Self {
+            name: "default".to_owned(),
+            gates,
+            path: None,
+        }
+    }
+}
+
+impl RaceTrack {
+    /// Reads the track file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TrackError> {
+        let path = path.as_ref();
+        let mut track: Self = ron::from_str(&fs::read_to_string(path)?)?;
+        track.path = Some(path.to_owned());
+
+        Ok(track)
+    }
+
+    /// Writes the track file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TrackError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)?;
+        Ok(())
+    }
+
+    /// Returns the file, where the track should be saved.
+    pub fn file_path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("assets/tracks").join(format!("{}.ron", self.name)))
+    }
+
+    /// Returns the file of the best lap of the track, it is kept with the logs out of the assets.
+    pub fn best_lap_path(&self) -> PathBuf {
+        PathBuf::from("logs/tracks").join(format!("{}.best.ron", self.name))
+    }
+}
+
+/// Sample of the drone pose during the lap. `time` is in seconds since the start of the lap.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GhostSample {
+    pub time: f32,
+    pub translation: Vec3,
+    pub rotation: Quat,
+}
+
+/// Best lap of the track: its time, split times at every gate and the flight to replay as a ghost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BestLap {
+    pub time: f32,
+    pub splits: Vec<f32>,
+    pub samples: Vec<GhostSample>,
+}
+
+impl BestLap {
+    /// Reads the best lap file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TrackError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the best lap file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TrackError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, ron::ser::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the ghost pose at the given time since the start of the lap.
+    pub fn pose_at(&self, time: f32) -> Option<Transform> {
+        let next = self.samples.iter().position(|sample| sample.time >= time)?;
+
+        let sample = self.samples[next];
+        let Some(previous) = next.checked_sub(1).map(|index| self.samples[index]) else {
+            return Some(Transform::from_translation(sample.translation).with_rotation(sample.rotation));
+        };
+
+        let blend = ((time - previous.time) / (sample.time - previous.time).max(f32::EPSILON)).clamp(0.0, 1.0);
+
+        Some(
+            Transform::from_translation(previous.translation.lerp(sample.translation, blend))
+                .with_rotation(previous.rotation.slerp(sample.rotation, blend)),
+        )
+    }
+}
+
+/// State of the race.
+///
+/// Times are in seconds of `Time::elapsed_seconds`. `splits` are times since the start of the lap at every passed gate.
+///
+/// A lap with missed gates still counts, but it can't be the best one.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct RaceState {
+    pub next_gate: usize,
+    pub lap_started_at: Option<f32>,
+    pub laps: u32,
+    pub splits: Vec<f32>,
+    pub last_lap: Option<f32>,
+    pub best_lap: Option<BestLap>,
+    pub missed_gates: u32,
+    pub is_lap_clean: bool,
+    pub lap_samples: Vec<GhostSample>,
+    /// Whether the best lap was beaten and should be saved.
+    pub is_best_lap_new: bool,
+}
+
+impl RaceState {
+    /// Registers that the drone passed the gate at the given time.
+    pub fn pass_gate(&mut self, gate: usize, now: f32, gate_count: usize) {
+        if gate_count == 0 || gate >= gate_count {
+            return;
+        }
+
+        if gate == 0 {
+            if let Some(started_at) = self.lap_started_at {
+                if self.next_gate == 0 {
+                    self.finish_lap(now - started_at);
+                } else {
+                    // the lap is cut short
+                    self.missed_gates += (gate_count - self.next_gate) as u32;
+                }
+            }
+
+            self.lap_started_at = Some(now);
+            self.splits.clear();
+            self.lap_samples.clear();
+            self.is_lap_clean = true;
+            self.next_gate = 1 % gate_count;
+            return;
+        }
+
+        let Some(started_at) = self.lap_started_at else {
+            return;
+        };
+
+        // gates behind are passed again, they don't count
+        if gate < self.next_gate || self.next_gate == 0 {
+            return;
+        }
+
+        if gate > self.next_gate {
+            self.missed_gates += (gate - self.next_gate) as u32;
+            self.is_lap_clean = false;
+        }
+
+        self.splits.push(now - started_at);
+        self.next_gate = (gate + 1) % gate_count;
+    }
+
+    /// Returns current lap time.
+    pub fn lap_time(&self, now: f32) -> Option<f32> {
+        self.lap_started_at.map(|started_at| now - started_at)
+    }
+
+    fn finish_lap(&mut self, time: f32) {
+        self.laps += 1;
+        self.last_lap = Some(time);
+
+        let is_best = self.best_lap.as_ref().map_or(true, |best| time < best.time);
+
+        if self.is_lap_clean && is_best {
+            self.best_lap = Some(BestLap {
+                time,
+                splits: self.splits.clone(),
+                samples: std::mem::take(&mut self.lap_samples),
+            });
+            self.is_best_lap_new = true;
+        }
+    }
+}
+
+/// State of the track editor. `selected` is the index of the selected gate.
+#[derive(Resource, Debug, Default)]
+pub struct TrackEditor {
+    pub enabled: bool,
+    pub selected: Option<usize>,
+}
+
+/// Key bindings of the track editor.
+///
+/// `place` puts a new gate at the `Player`, looking where it looks. Other keys work with the selected gate.
+#[derive(Resource)]
+pub struct TrackEditorBindings {
+    pub toggle: KeyCode,
+    pub place: KeyCode,
+    pub select_next: KeyCode,
+    pub rotate_left: KeyCode,
+    pub rotate_right: KeyCode,
+    pub remove: KeyCode,
+    pub save: KeyCode,
+}
+
+impl Default for TrackEditorBindings {
+    fn default() -> Self {
+        Self {
+            toggle: KeyCode::KeyG,
+            place: KeyCode::KeyP,
+            select_next: KeyCode::Tab,
+            rotate_left: KeyCode::BracketLeft,
+            rotate_right: KeyCode::BracketRight,
+            remove: KeyCode::Delete,
+            save: KeyCode::F5,
+        }
+    }
+}
+
+// events
+/// Event of the drone passing a gate.
+#[derive(Event)]
+pub struct GatePassed {
+    pub gate: usize,
+}
+
+// components
+/// Describes a race gate. The entity has a sensor collider in the opening of the gate.
+#[derive(Component)]
+pub struct RaceGate {
+    pub index: usize,
+    pub material: Handle<StandardMaterial>,
+}
+
+/// Describes the ghost of the best lap.
+#[derive(Component)]
+pub struct Ghost;
+
+/// Describes the race HUD text.
+#[derive(Component)]
+pub struct RaceHudText;
+
+// systems
+/// Query for the entities spawned for the track.
+type TrackEntityQuery<'w, 's> = Query<'w, 's, Entity, Or<(With<RaceGate>, With<Ghost>)>>;
+
+/// System that spawns the gates and the ghost when the `RaceTrack` changes.
+///
+/// The race starts over and the best lap is loaded from the track's best lap file.
+pub fn spawn_track(
+    mut commands: Commands,
+    track: Res<RaceTrack>,
+    mut state: ResMut<RaceState>,
+    track_entities: TrackEntityQuery,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !track.is_changed() {
+        return;
+    }
+
+    for entity in &track_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let best_lap = BestLap::load(track.best_lap_path()).ok();
+    *state = RaceState {
+        best_lap,
+        ..default()
+    };
+
+    let bar = 0.15;
+
+    for (index, gate) in track.gates.iter().enumerate() {
+        let material = materials.add(Color::ORANGE);
+        let (width, height) = (gate.width, gate.height);
+
+        // bars around the opening
+        let bars = [
+            (Vec3::new(0.0, (height + bar) / 2.0, 0.0), Vec3::new(width + bar * 2.0, bar, bar)),
+            (Vec3::new(0.0, -(height + bar) / 2.0, 0.0), Vec3::new(width + bar * 2.0, bar, bar)),
+            (Vec3::new((width + bar) / 2.0, 0.0, 0.0), Vec3::new(bar, height, bar)),
+            (Vec3::new(-(width + bar) / 2.0, 0.0, 0.0), Vec3::new(bar, height, bar)),
+        ];
+
+        let children: Vec<Entity> = bars
+            .into_iter()
+            .map(|(offset, size)| {
+                commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: meshes.add(Cuboid::from_size(size)),
+                            material: material.clone(),
+                            transform: Transform::from_translation(offset),
+                            ..default()
+                        },
+                        Collider::cuboid(size.x / 2.0, size.y / 2.0, size.z / 2.0),
+                    ))
+                    .id()
+            })
+            .collect();
+
+        commands
+            .spawn((
+                SpatialBundle::from_transform(gate.transform()),
+                Collider::cuboid(width / 2.0, height / 2.0, 0.25),
+                Sensor,
+                ActiveEvents::COLLISION_EVENTS,
+                RaceGate { index, material },
+                Name::new(format!("Gate {index}")),
+            ))
+            .push_children(&children);
+    }
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Cuboid::new(2.5, 1.0, 3.0)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(0.3, 0.8, 1.0, 0.35),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Ghost,
+        Name::new("Ghost"),
+    ));
+}
+
+/// System that turns intersections of the `Player` with the gate sensors into `GatePassed` events.
+///
+/// Gates only count when they are passed along their forward direction.
+pub fn detect_gate_passes(
+    mut collisions: EventReader<CollisionEvent>,
+    gates: Query<(&RaceGate, &GlobalTransform)>,
+    players: Query<Option<&Velocity>, With<Player>>,
+    mut passes: EventWriter<GatePassed>,
+) {
+    for collision in collisions.read() {
+        let CollisionEvent::Started(first, second, _) = collision else {
+            continue;
+        };
+
+        let (gate, player) = if gates.contains(*first) { (*first, *second) } else { (*second, *first) };
+
+        let (Ok((gate, gate_transform)), Ok(velocity)) = (gates.get(gate), players.get(player)) else {
+            continue;
+        };
+
+        let is_forward = velocity.map_or(true, |velocity| velocity.linvel.dot(gate_transform.forward()) >= 0.0);
+
+        if is_forward {
+            passes.send(GatePassed { gate: gate.index });
+        }
+    }
+}
+
+/// System that times the laps from `GatePassed` events and saves the new best lap.
+pub fn process_gate_passes(
+    time: Res<Time>,
+    track: Res<RaceTrack>,
+    mut state: ResMut<RaceState>,
+    mut passes: EventReader<GatePassed>,
+) {
+    for pass in passes.read() {
+        state.pass_gate(pass.gate, time.elapsed_seconds(), track.gates.len());
+    }
+
+    if state.is_best_lap_new {
+        state.is_best_lap_new = false;
+
+        if let Some(best_lap) = &state.best_lap {
+            if let Err(error) = best_lap.save(track.best_lap_path()) {
+                warn!("{error}");
+            }
+        }
+    }
+}
+
+/// System that records the `Player` pose during the lap for the ghost.
+pub fn record_lap(
+    time: Res<Time>,
+    mut state: ResMut<RaceState>,
+    players: Query<&Transform, With<Player>>,
+) {
+    let (Some(lap_time), Ok(transform)) = (state.lap_time(time.elapsed_seconds()), players.get_single()) else {
+        return;
+    };
+
+    // 30 samples per second are enough to replay the flight smoothly
+    let is_due = state.lap_samples.last().map_or(true, |sample| lap_time - sample.time >= 1.0 / 30.0);
+
+    if is_due {
+        state.lap_samples.push(GhostSample {
+            time: lap_time,
+            translation: transform.translation,
+            rotation: transform.rotation,
+        });
+    }
+}
+
+/// System that flies the ghost of the best lap along with the current one.
+fn move_ghost(
+    time: Res<Time>,
+    state: Res<RaceState>,
+    mut ghosts: Query<(&mut Transform, &mut Visibility), With<Ghost>>,
+) {
+    let pose = state
+        .lap_time(time.elapsed_seconds())
+        .zip(state.best_lap.as_ref())
+        .and_then(|(lap_time, best_lap)| best_lap.pose_at(lap_time));
+
+    for (mut transform, mut visibility) in &mut ghosts {
+        match pose {
+            Some(pose) => {
+                *transform = pose;
+                *visibility = Visibility::Visible;
+            },
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// System that colors the gates: the next one is green, the selected one in the editor is blue.
+fn color_gates(
+    state: Res<RaceState>,
+    editor: Res<TrackEditor>,
+    gates: Query<&RaceGate>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !state.is_changed() && !editor.is_changed() {
+        return;
+    }
+
+    for gate in &gates {
+        let color = if editor.enabled && editor.selected == Some(gate.index) {
+            Color::BLUE
+        } else if state.lap_started_at.is_some() && gate.index == state.next_gate {
+            Color::GREEN
+        } else {
+            Color::ORANGE
+        };
+
+        if let Some(material) = materials.get_mut(&gate.material) {
+            material.base_color = color;
+        }
+    }
+}
+
+/// System of the track editor.
+///
+/// Gates are placed at the `Player`, rotated by 15 degrees and saved to the track file.
+pub fn edit_track(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<TrackEditorBindings>,
+    mut editor: ResMut<TrackEditor>,
+    mut track: ResMut<RaceTrack>,
+    players: Query<&Transform, With<Player>>,
+) {
+    if keys.just_pressed(bindings.toggle) {
+        editor.enabled = !editor.enabled;
+    }
+
+    if !editor.enabled {
+        return;
+    }
+
+    let gate_count = track.gates.len();
+
+    if keys.just_pressed(bindings.place) {
+        if let Ok(transform) = players.get_single() {
+            let forward = transform.forward();
+            let yaw = (-forward.x).atan2(-forward.z);
+
+            track.gates.push(GateDefinition::new(transform.translation, yaw));
+            editor.selected = Some(track.gates.len() - 1);
+        }
+    }
+
+    if keys.just_pressed(bindings.select_next) && gate_count > 0 {
+        editor.selected = Some(editor.selected.map_or(0, |selected| (selected + 1) % gate_count));
+    }
+
+    if let Some(selected) = editor.selected.filter(|selected| *selected < track.gates.len()) {
+        let step = TAU / 24.0;
+
+        if keys.just_pressed(bindings.rotate_left) {
+            track.gates[selected].yaw += step;
+        }
+
+        if keys.just_pressed(bindings.rotate_right) {
+            track.gates[selected].yaw -= step;
+        }
+
+        if keys.just_pressed(bindings.remove) {
+            track.gates.remove(selected);
+            editor.selected = None;
+        }
+    }
+
+    if keys.just_pressed(bindings.save) {
+        let path = track.file_path();
+
+        match track.save(&path) {
+            Ok(()) => {
+                info!("track is saved to {}", path.display());
+                track.path = Some(path);
+            },
+            Err(error) => warn!("{error}"),
+        }
+    }
+}
+
+/// Race HUD initialization.
+fn setup_race_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                font_size: 15.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+        RaceHudText,
+    ));
+}
+
+/// System that shows lap times in the HUD.
+///
+/// Split delta compares the last split with the same split of the best lap.
+pub fn update_race_hud(
+    time: Res<Time>,
+    track: Res<RaceTrack>,
+    state: Res<RaceState>,
+    editor: Res<TrackEditor>,
+    mut query: Query<&mut Text, With<RaceHudText>>,
+) {
+    let format_time = |time: Option<f32>| time.map_or("--.--".to_owned(), |time| format!("{time:.2}"));
+
+    let mut value = String::new();
+
+    if let Some(lap_time) = state.lap_time(time.elapsed_seconds()) {
+        let best_splits = state.best_lap.as_ref().map(|best_lap| &best_lap.splits);
+        let best_split = best_splits.and_then(|splits| state.splits.len().checked_sub(1).and_then(|i| splits.get(i)));
+        let delta = state.splits.last().zip(best_split);
+
+        value = format!(
+            "Lap {}  {}\nGate {}/{}\nLast: {}\nBest: {}\nMissed gates: {}",
+            state.laps + 1,
+            format_time(Some(lap_time)),
+            state.next_gate,
+            track.gates.len(),
+            format_time(state.last_lap),
+            format_time(state.best_lap.as_ref().map(|best_lap| best_lap.time)),
+            state.missed_gates,
+        );
+
+        if let Some((split, best_split)) = delta {
+            value.push_str(&format!("\nSplit: {:+.2}", split - best_split));
+        }
+    }
+
+    if editor.enabled {
+        value.push_str(&format!(
+            "\n\nTrack editor: {}\nSelected gate: {}",
+            track.name,
+            editor.selected.map_or("-".to_owned(), |selected| selected.to_string()),
+        ));
+    }
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    autopilot::AutopilotProgress,
+    camera::{MainCamera, VisionMode},
+    camera_sensor::AnalogVideoLink,
+    fleet::Drone,
+    materials::Temperature,
+    player::{Battery, FlightController, Motors},
+    terrain::Terrain,
+};
+
+/// Flight log formats: CSV and compact binary.
+pub mod flight_log;
+/// Export of flight logs to PX4 ULog and Betaflight blackbox CSV.
+pub mod export;
+
+use export::{export_betaflight_csv, export_ulog};
+use flight_log::{
+    BinaryLogWriter, CsvLogWriter, FlightLog, FlightRecord, LogError, LogWriter, SessionMetadata, FORMAT_VERSION,
+};
+
+/// Plugin for the flight data recorder.
+///
+/// The recorder logs the state of every drone into flight logs, which are read with `FlightLog::read`.
+pub struct RecorderPlugin;
+
+impl Plugin for RecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<RecorderSettings>()
+            .init_resource::<FlightRecorder>()
+            .add_event::<StartFlightRecording>()
+            .add_event::<StopFlightRecording>()
+            .add_systems(Startup, setup_recorder_hud)
+            .add_systems(Update, (
+                recorder_input,
+                process_recorder_commands,
+                record_flight,
+                update_recorder_hud,
+            ).chain());
+    }
+}
+
+// resources
+/// Describes the flight data recorder.
+///
+/// Press `toggle_key` to start or stop recording, the autopilot also records when the mission says so.
+/// Drones are sampled `rate` times per second into `directory`, as CSV and/or binary logs.
+/// Every `records_per_file` records the log is rotated: the next part of the session is started
+/// and only the last `max_parts` parts are kept.
+///
+/// When the recording stops, the kept parts are exported as PX4 `ulog` and Betaflight blackbox CSV files of every drone.
+#[derive(Resource, Debug, Clone)]
+pub struct RecorderSettings {
+    pub toggle_key: KeyCode,
+    pub rate: f32,
+    pub directory: PathBuf,
+    pub csv: bool,
+    pub binary: bool,
+    pub records_per_file: usize,
+    pub max_parts: usize,
+    pub ulog: bool,
+    pub betaflight: bool,
+}
+
+impl Default for RecorderSettings {
+    fn default() -> Self {
+        Self {
+            toggle_key: KeyCode::KeyL,
+            rate: 20.0,
+            directory: PathBuf::from("logs"),
+            csv: true,
+            binary: true,
+            records_per_file: 36000,
+            max_parts: 10,
+            ulog: false,
+            betaflight: false,
+        }
+    }
+}
+
+/// Recording session, which is split into parts by the log rotation.
+pub struct RecordingSession {
+    pub metadata: SessionMetadata,
+    /// Records in the current part.
+    pub records: usize,
+    /// Files of the kept parts, the oldest first.
+    pub parts: VecDeque<Vec<PathBuf>>,
+    writers: Vec<Box<dyn LogWriter>>,
+}
+
+impl RecordingSession {
+    /// Starts the first part of the session in the directory of the `settings`.
+    pub fn start(settings: &RecorderSettings, drones: Vec<(u32, String)>) -> Result<Self, LogError> {
+        fs::create_dir_all(&settings.directory)?;
+
+        let created = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+
+        let mut session = Self {
+            metadata: SessionMetadata {
+                version: FORMAT_VERSION,
+                session: format!("flight_{created}"),
+                part: 0,
+                created,
+                rate: settings.rate,
+                drones,
+            },
+            records: 0,
+            parts: VecDeque::new(),
+            writers: Vec::new(),
+        };
+
+        session.open_part(settings)?;
+        Ok(session)
+    }
+
+    /// Returns the path of the current part file with the given extension.
+    pub fn part_path(&self, directory: &Path, extension: &str) -> PathBuf {
+        directory.join(format!("{}_{:03}.{extension}", self.metadata.session, self.metadata.part))
+    }
+
+    /// Opens writers of the current part and removes the oldest parts.
+    fn open_part(&mut self, settings: &RecorderSettings) -> Result<(), LogError> {
+        let mut files = Vec::new();
+        self.writers.clear();
+
+        if settings.csv {
+            let path = self.part_path(&settings.directory, "csv");
+            self.writers.push(Box::new(CsvLogWriter::create(&path, &self.metadata)?));
+            files.push(path);
+        }
+
+        if settings.binary {
+            let path = self.part_path(&settings.directory, "bin");
+            self.writers.push(Box::new(BinaryLogWriter::create(&path, &self.metadata)?));
+            files.push(path);
+        }
+
+        self.parts.push_back(files);
+        self.records = 0;
+
+        while self.parts.len() > settings.max_parts.max(1) {
+            for path in self.parts.pop_front().unwrap_or_default() {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the record, rotating the log when the part is full.
+    pub fn write(&mut self, settings: &RecorderSettings, record: &FlightRecord) -> Result<(), LogError> {
+        if self.records >= settings.records_per_file.max(1) {
+            self.flush()?;
+            self.metadata.part += 1;
+            self.open_part(settings)?;
+        }
+
+        for writer in &mut self.writers {
+            writer.write_record(record)?;
+        }
+
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Exports the kept parts as ULog and Betaflight CSV files of every drone, returns paths of the files.
+    pub fn export(&self, settings: &RecorderSettings) -> Result<Vec<PathBuf>, LogError> {
+        let mut log = FlightLog {
+            metadata: self.metadata.clone(),
+            records: Vec::new(),
+        };
+
+        for files in &self.parts {
+            if let Some(path) = files.first() {
+                log.records.extend(FlightLog::read(path)?.records);
+            }
+        }
+
+        let mut paths = Vec::new();
+
+        for (drone, _) in &log.metadata.drones {
+            let name = format!("{}_drone{drone}", log.metadata.session);
+
+            if settings.ulog {
+                let path = settings.directory.join(format!("{name}.ulg"));
+                let mut bytes = Vec::new();
+                export_ulog(&log, *drone, &mut bytes)?;
+                fs::write(&path, bytes)?;
+                paths.push(path);
+            }
+
+            if settings.betaflight {
+                let path = settings.directory.join(format!("{name}_blackbox.csv"));
+                let mut bytes = Vec::new();
+                export_betaflight_csv(&log, *drone, &mut bytes)?;
+                fs::write(&path, bytes)?;
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Writes buffered records into the files.
+    pub fn flush(&mut self) -> Result<(), LogError> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// State of the flight data recorder.
+///
+/// `since_sample` is in seconds.
+#[derive(Resource, Default)]
+pub struct FlightRecorder {
+    pub session: Option<RecordingSession>,
+    pub since_sample: f32,
+}
+
+impl FlightRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.session.is_some()
+    }
+}
+
+// events
+/// Starts the new recording session.
+#[derive(Event, Debug, Default, Clone, Copy)]
+pub struct StartFlightRecording;
+
+/// Stops the recording session and writes its files.
+#[derive(Event, Debug, Default, Clone, Copy)]
+pub struct StopFlightRecording;
+
+// components
+/// Describes the recorder HUD text.
+#[derive(Component)]
+struct RecorderHudText;
+
+// systems
+/// Query for the recorded drones.
+type RecordedDroneQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static Drone,
+        &'static Transform,
+        Option<&'static Velocity>,
+        Option<&'static Motors>,
+        Option<&'static FlightController>,
+        Option<&'static Battery>,
+        Option<&'static Temperature>,
+        Option<&'static AnalogVideoLink>,
+    ),
+>;
+
+/// System that starts and stops recording with the key and the autopilot recording actions.
+fn recorder_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    settings: Res<RecorderSettings>,
+    recorder: Res<FlightRecorder>,
+    progress: Option<Res<AutopilotProgress>>,
+    mut was_autopilot_recording: Local<bool>,
+    mut starts: EventWriter<StartFlightRecording>,
+    mut stops: EventWriter<StopFlightRecording>,
+) {
+    if keys.just_pressed(settings.toggle_key) {
+        if recorder.is_recording() {
+            stops.send(StopFlightRecording);
+        } else {
+            starts.send(StartFlightRecording);
+        }
+    }
+
+    let is_autopilot_recording = progress.is_some_and(|progress| progress.is_recording);
+
+    if is_autopilot_recording != *was_autopilot_recording {
+        *was_autopilot_recording = is_autopilot_recording;
+
+        if is_autopilot_recording {
+            starts.send(StartFlightRecording);
+        } else {
+            stops.send(StopFlightRecording);
+        }
+    }
+}
+
+/// System that starts and stops recording sessions.
+pub fn process_recorder_commands(
+    settings: Res<RecorderSettings>,
+    mut recorder: ResMut<FlightRecorder>,
+    mut starts: EventReader<StartFlightRecording>,
+    mut stops: EventReader<StopFlightRecording>,
+    drones: Query<&Drone>,
+) {
+    if stops.read().count() > 0 {
+        if let Some(mut session) = recorder.session.take() {
+            match session.flush().and_then(|()| session.export(&settings)) {
+                Ok(_) => info!("flight log {} is written", session.metadata.session),
+                Err(error) => warn!("{error}"),
+            }
+        }
+    }
+
+    if starts.read().count() > 0 && !recorder.is_recording() {
+        let mut ids: Vec<_> = drones.iter().map(|drone| (drone.id, drone.airframe.clone())).collect();
+        ids.sort();
+
+        match RecordingSession::start(&settings, ids) {
+            Ok(session) => {
+                info!("recording flight log {}", session.metadata.session);
+
+                recorder.session = Some(session);
+                // the first sample is taken right away
+                recorder.since_sample = 1.0 / settings.rate.max(f32::EPSILON);
+            },
+            Err(error) => warn!("{error}"),
+        }
+    }
+}
+
+/// System that samples the drones into the flight log at the recorder rate.
+///
+/// Only the entities with the `Drone` component are sampled, its ID identifies them in the log.
+///
+/// Recording stops if the log can't be written.
+pub fn record_flight(
+    time: Res<Time>,
+    settings: Res<RecorderSettings>,
+    terrain: Option<Res<Terrain>>,
+    mut recorder: ResMut<FlightRecorder>,
+    drones: RecordedDroneQuery,
+    cameras: Query<&VisionMode, With<MainCamera>>,
+) {
+    let recorder = &mut *recorder;
+
+    let Some(session) = recorder.session.as_mut() else {
+        return;
+    };
+
+    let interval = 1.0 / settings.rate.max(f32::EPSILON);
+
+    recorder.since_sample += time.delta_seconds();
+
+    if recorder.since_sample < interval {
+        return;
+    }
+
+    // the remainder keeps the rate, a long frame doesn't make a burst of samples
+    recorder.since_sample %= interval;
+
+    let vision_mode = cameras.get_single().copied().unwrap_or_default();
+
+    for (drone, transform, velocity, motors, controller, battery, temperature, link) in &drones {
+        let position = transform.translation;
+        let velocity = velocity.copied().unwrap_or_default();
+        let setpoint = controller.and_then(|controller| controller.setpoint);
+
+        let record = FlightRecord {
+            time: time.elapsed_seconds(),
+            drone: drone.id,
+            position,
+            rotation: transform.rotation,
+            velocity: velocity.linvel,
+            angular_velocity: velocity.angvel,
+            motor_thrust: motors.map_or(0.0, |motors| motors.applied),
+            thrust: controller.map_or(Vec3::ZERO, |controller| controller.applied),
+            setpoint_velocity: setpoint.map(|setpoint| setpoint.velocity),
+            setpoint_yaw: setpoint.and_then(|setpoint| setpoint.yaw),
+            velocity_error: setpoint.map(|setpoint| setpoint.velocity - velocity.linvel),
+            altitude: position.y - terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position.xz())),
+            temperature: temperature.map_or(0.0, |temperature| temperature.0),
+            video_breakup: link.map_or(0.0, |link| link.breakup(position)),
+            battery_remaining: battery.map_or(0.0, |battery| battery.remaining),
+            battery_charge: battery.map_or(0.0, Battery::charge),
+            is_armed: motors.is_some_and(|motors| motors.is_armed),
+            vision_mode,
+        };
+
+        if let Err(error) = session.write(&settings, &record) {
+            warn!("{error}, recording is stopped");
+            recorder.session = None;
+            return;
+        }
+    }
+}
+
+/// Recorder HUD initialization.
+fn setup_recorder_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(5.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                        font_size: 15.0,
+                        color: Color::RED,
+                    },
+                ),
+                RecorderHudText,
+            ));
+        });
+}
+
+/// System that shows the recording session in the HUD.
+fn update_recorder_hud(
+    recorder: Res<FlightRecorder>,
+    mut query: Query<&mut Text, With<RecorderHudText>>,
+) {
+    if !recorder.is_changed() {
+        return;
+    }
+
+    let value = recorder.session.as_ref().map_or_else(String::new, |session| {
+        format!("REC {} part {}", session.metadata.session, session.metadata.part)
+    });
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
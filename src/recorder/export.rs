@@ -0,0 +1,539 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Read, Write},
+};
+
+use bevy::prelude::*;
+
+use super::flight_log::{FlightLog, FlightRecord, LogError};
+
+/// First bytes of the ULog file: the magic and the version.
+const ULOG_MAGIC: [u8; 8] = [0x55, 0x4c, 0x6f, 0x67, 0x01, 0x12, 0x35, 0x01];
+
+/// Cells of the simulated LiPo battery.
+const BATTERY_CELLS: f32 = 4.0;
+
+/// Acceleration of 1 g in the raw units of the Betaflight accelerometer.
+const BETAFLIGHT_ACC_1G: f32 = 2048.0;
+
+/// Standard gravity in m/s².
+const GRAVITY: f32 = 9.81;
+
+/// Returns the battery voltage of the charge from 0.0 to 1.0: a cell has 3.3 V empty and 4.2 V full.
+pub fn battery_voltage(charge: f32) -> f32 {
+    BATTERY_CELLS * (3.3 + 0.9 * charge)
+}
+
+/// Converts the world vector into the north, east and down (NED) frame.
+///
+/// North is -Z, east is +X.
+pub fn to_ned(vector: Vec3) -> Vec3 {
+    Vec3::new(-vector.z, vector.x, -vector.y)
+}
+
+/// Converts the world vector into the forward, right and down (FRD) frame of the drone.
+///
+/// The drone looks along -Z.
+pub fn to_frd(rotation: Quat, vector: Vec3) -> Vec3 {
+    to_ned(rotation.inverse() * vector)
+}
+
+/// Converts the drone rotation into the rotation from its FRD frame to the NED frame.
+pub fn to_ned_rotation(rotation: Quat) -> Quat {
+    let axis = to_ned(Vec3::new(rotation.x, rotation.y, rotation.z));
+
+    Quat::from_xyzw(axis.x, axis.y, axis.z, rotation.w)
+}
+
+/// Returns the heading in radians: clockwise from the north.
+pub fn heading(rotation: Quat) -> f32 {
+    let forward = to_ned(rotation * Vec3::NEG_Z);
+
+    forward.y.atan2(forward.x)
+}
+
+/// Returns the thrust of the record from 0.0 to 1.0, the drone hovers at the half throttle.
+pub fn throttle(record: &FlightRecord) -> f32 {
+    if !record.is_armed || record.motor_thrust <= 0.0 {
+        return 0.0;
+    }
+
+    let thrust = (Vec3::Y * record.motor_thrust + record.thrust).y;
+
+    (thrust / (2.0 * record.motor_thrust)).clamp(0.0, 1.0)
+}
+
+/// Returns the specific force in the FRD frame in m/s²: what the accelerometer of the drone measures.
+///
+/// Acceleration is the difference of the velocities of the `previous` and the current record.
+fn specific_force(previous: Option<&FlightRecord>, record: &FlightRecord) -> Vec3 {
+    let acceleration = previous
+        .map(|previous| (record.velocity - previous.velocity) / (record.time - previous.time).max(f32::EPSILON))
+        .unwrap_or_default();
+
+    to_frd(record.rotation, acceleration + Vec3::Y * GRAVITY)
+}
+
+/// Returns time of the record in microseconds.
+fn microseconds(time: f32) -> u64 {
+    (f64::from(time) * 1e6).round() as u64
+}
+
+/// Table of the decoded values.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DataTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+}
+
+impl DataTable {
+    /// Returns values of the column.
+    pub fn column(&self, name: &str) -> Option<Vec<f64>> {
+        let index = self.columns.iter().position(|column| column == name)?;
+
+        Some(self.rows.iter().map(|row| row[index]).collect())
+    }
+}
+
+/// Value of the ULog field.
+#[derive(Debug, Clone, Copy)]
+enum ULogValue {
+    U64(u64),
+    U8(u8),
+    F32(f32),
+}
+
+/// ULog topic: its name, format and values.
+type ULogTopic = (&'static str, &'static str, Vec<ULogValue>);
+
+/// Returns the ULog topics of the record.
+fn ulog_topics(previous: Option<&FlightRecord>, record: &FlightRecord) -> Vec<ULogTopic> {
+    use ULogValue::*;
+
+    let timestamp = U64(microseconds(record.time));
+    let position = to_ned(record.position);
+    let velocity = to_ned(record.velocity);
+    let rotation = to_ned_rotation(record.rotation);
+    let angular_velocity = to_frd(record.rotation, record.angular_velocity);
+    let setpoint = record.setpoint_velocity.map_or(Vec3::NAN, to_ned);
+    let force = specific_force(previous, record);
+
+    vec![
+        (
+            "vehicle_local_position",
+            "uint64_t timestamp;float x;float y;float z;float vx;float vy;float vz;float heading;float dist_bottom;",
+            vec![
+                timestamp,
+                F32(position.x),
+                F32(position.y),
+                F32(position.z),
+                F32(velocity.x),
+                F32(velocity.y),
+                F32(velocity.z),
+                F32(heading(record.rotation)),
+                F32(record.altitude),
+            ],
+        ),
+        (
+            "vehicle_attitude",
+            "uint64_t timestamp;float[4] q;",
+            vec![timestamp, F32(rotation.w), F32(rotation.x), F32(rotation.y), F32(rotation.z)],
+        ),
+        (
+            "vehicle_angular_velocity",
+            "uint64_t timestamp;uint64_t timestamp_sample;float[3] xyz;",
+            vec![timestamp, timestamp, F32(angular_velocity.x), F32(angular_velocity.y), F32(angular_velocity.z)],
+        ),
+        (
+            "sensor_combined",
+            "uint64_t timestamp;float[3] gyro_rad;float[3] accelerometer_m_s2;",
+            vec![
+                timestamp,
+                F32(angular_velocity.x),
+                F32(angular_velocity.y),
+                F32(angular_velocity.z),
+                F32(force.x),
+                F32(force.y),
+                F32(force.z),
+            ],
+        ),
+        (
+            "vehicle_local_position_setpoint",
+            "uint64_t timestamp;float vx;float vy;float vz;float yaw;",
+            vec![
+                timestamp,
+                F32(setpoint.x),
+                F32(setpoint.y),
+                F32(setpoint.z),
+                // yaw of the world turns counterclockwise
+                F32(record.setpoint_yaw.map_or(f32::NAN, |yaw| -yaw)),
+            ],
+        ),
+        (
+            "vehicle_thrust_setpoint",
+            "uint64_t timestamp;uint64_t timestamp_sample;float[3] xyz;",
+            vec![timestamp, timestamp, F32(0.0), F32(0.0), F32(-throttle(record))],
+        ),
+        (
+            "battery_status",
+            "uint64_t timestamp;float voltage_v;float remaining;",
+            vec![timestamp, F32(battery_voltage(record.battery_charge)), F32(record.battery_charge)],
+        ),
+        (
+            "vehicle_status",
+            "uint64_t timestamp;uint8_t arming_state;",
+            // ARMING_STATE_DISARMED and ARMING_STATE_ARMED
+            vec![timestamp, U8(if record.is_armed { 2 } else { 1 })],
+        ),
+    ]
+}
+
+/// Writes the ULog message.
+fn write_ulog_message(writer: &mut impl Write, kind: u8, body: &[u8]) -> Result<(), LogError> {
+    let size = u16::try_from(body.len()).map_err(|_| LogError::Format("ULog message is too long".to_owned()))?;
+
+    writer.write_all(&size.to_le_bytes())?;
+    writer.write_all(&[kind])?;
+    writer.write_all(body)?;
+    Ok(())
+}
+
+/// Exports records of the drone as PX4 ULog, which is opened by PlotJuggler and Flight Review.
+///
+/// Positions and velocities are in meters and m/s in the NED frame, rotations are from the FRD frame of the drone,
+/// angular velocities are in rad/s.
+pub fn export_ulog(log: &FlightLog, drone: u32, writer: &mut impl Write) -> Result<(), LogError> {
+    writer.write_all(&ULOG_MAGIC)?;
+    writer.write_all(&(log.metadata.created * 1_000_000).to_le_bytes())?;
+
+    // flag bits: no compatible or incompatible flags and no appended data
+    write_ulog_message(writer, b'B', &[0; 40])?;
+
+    let airframe = log
+        .metadata
+        .drones
+        .iter()
+        .find(|(id, _)| *id == drone)
+        .map_or("", |(_, airframe)| airframe.as_str());
+
+    for (key, value) in [("sys_name", "supersonic"), ("ver_hw", airframe), ("sys_uuid", log.metadata.session.as_str())] {
+        let key = format!("char[{}] {key}", value.len());
+
+        let mut body = vec![key.len() as u8];
+        body.extend(key.as_bytes());
+        body.extend(value.as_bytes());
+
+        write_ulog_message(writer, b'I', &body)?;
+    }
+
+    let records: Vec<_> = log.drone_records(drone).collect();
+
+    let Some(first) = records.first() else {
+        return Ok(());
+    };
+
+    let topics = ulog_topics(None, first);
+
+    for (name, format, _) in &topics {
+        write_ulog_message(writer, b'F', format!("{name}:{format}").as_bytes())?;
+    }
+
+    for (id, (name, _, _)) in topics.iter().enumerate() {
+        let mut body = vec![0];
+        body.extend((id as u16).to_le_bytes());
+        body.extend(name.as_bytes());
+
+        write_ulog_message(writer, b'A', &body)?;
+    }
+
+    for (index, record) in records.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|previous| records[previous]);
+
+        for (id, (_, _, values)) in ulog_topics(previous, record).into_iter().enumerate() {
+            let mut body = (id as u16).to_le_bytes().to_vec();
+
+            for value in values {
+                match value {
+                    ULogValue::U64(value) => body.extend(value.to_le_bytes()),
+                    ULogValue::U8(value) => body.push(value),
+                    ULogValue::F32(value) => body.extend(value.to_le_bytes()),
+                }
+            }
+
+            write_ulog_message(writer, b'D', &body)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parsed ULog file.
+///
+/// `timestamp` is in microseconds, `info` has the info messages without types, `topics` have the data of every topic.
+/// Arrays are split into columns: `q[0]`, `q[1]`...
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ULog {
+    pub timestamp: u64,
+    pub info: BTreeMap<String, String>,
+    pub topics: BTreeMap<String, DataTable>,
+}
+
+/// Returns the size of the ULog type in bytes.
+fn ulog_type_size(kind: &str) -> Option<usize> {
+    match kind {
+        "int8_t" | "uint8_t" | "bool" | "char" => Some(1),
+        "int16_t" | "uint16_t" => Some(2),
+        "int32_t" | "uint32_t" | "float" => Some(4),
+        "int64_t" | "uint64_t" | "double" => Some(8),
+        _ => None,
+    }
+}
+
+/// Decodes the ULog value of the type.
+fn decode_ulog_value(kind: &str, bytes: &[u8]) -> f64 {
+    let array = |size: usize| {
+        let mut array = [0; 8];
+        array[..size].copy_from_slice(&bytes[..size]);
+        array
+    };
+
+    match kind {
+        "int8_t" => f64::from(bytes[0] as i8),
+        "uint8_t" | "bool" | "char" => f64::from(bytes[0]),
+        "int16_t" => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])),
+        "uint16_t" => f64::from(u16::from_le_bytes([bytes[0], bytes[1]])),
+        "int32_t" => f64::from(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        "uint32_t" => f64::from(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        "float" => f64::from(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        "int64_t" => i64::from_le_bytes(array(8)) as f64,
+        "uint64_t" => u64::from_le_bytes(array(8)) as f64,
+        _ => f64::from_le_bytes(array(8)),
+    }
+}
+
+/// Reads the ULog file.
+///
+/// Only the messages, which describe and hold the data, are read. Nested types are not supported.
+pub fn read_ulog(reader: &mut impl Read) -> Result<ULog, LogError> {
+    let mut header = [0; 16];
+    reader.read_exact(&mut header)?;
+
+    if header[..7] != ULOG_MAGIC[..7] {
+        return Err(LogError::Format("it is not a ULog file".to_owned()));
+    }
+
+    let mut ulog = ULog {
+        timestamp: u64::from_le_bytes([
+            header[8], header[9], header[10], header[11], header[12], header[13], header[14], header[15],
+        ]),
+        ..default()
+    };
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    // fields of the formats and the topics of the subscriptions
+    let mut formats: BTreeMap<String, Vec<(String, String, usize)>> = BTreeMap::new();
+    let mut subscriptions: BTreeMap<u16, String> = BTreeMap::new();
+
+    let mut offset = 0;
+
+    while offset + 3 <= bytes.len() {
+        let size = usize::from(u16::from_le_bytes([bytes[offset], bytes[offset + 1]]));
+        let kind = bytes[offset + 2];
+        let body = bytes
+            .get(offset + 3..offset + 3 + size)
+            .ok_or_else(|| LogError::Format("the last ULog message is cut".to_owned()))?;
+
+        offset += 3 + size;
+
+        match kind {
+            b'F' => {
+                let format = String::from_utf8_lossy(body);
+                let (name, fields) = format
+                    .split_once(':')
+                    .ok_or_else(|| LogError::Format(format!("wrong ULog format {format}")))?;
+
+                let mut parsed = Vec::new();
+
+                for field in fields.split(';').filter(|field| !field.is_empty()) {
+                    let (kind, field) = field
+                        .split_once(' ')
+                        .ok_or_else(|| LogError::Format(format!("wrong ULog field {field}")))?;
+
+                    let (kind, count) = match kind.split_once('[') {
+                        Some((kind, count)) => (kind, count.trim_end_matches(']').parse().unwrap_or(0)),
+                        None => (kind, 1),
+                    };
+
+                    ulog_type_size(kind)
+                        .ok_or_else(|| LogError::Format(format!("ULog type {kind} is not supported")))?;
+
+                    parsed.push((kind.to_owned(), field.to_owned(), count));
+                }
+
+                formats.insert(name.to_owned(), parsed);
+            },
+            b'I' if !body.is_empty() => {
+                let key_length = usize::from(body[0]);
+                let key = String::from_utf8_lossy(&body[1..(1 + key_length).min(body.len())]);
+                let name = key.split_once(' ').map_or(key.as_ref(), |(_, name)| name).to_owned();
+                let value = String::from_utf8_lossy(body.get(1 + key_length..).unwrap_or_default()).into_owned();
+
+                ulog.info.insert(name, value);
+            },
+            b'A' if body.len() >= 3 => {
+                let id = u16::from_le_bytes([body[1], body[2]]);
+                let name = String::from_utf8_lossy(&body[3..]).into_owned();
+
+                let columns = formats
+                    .get(&name)
+                    .ok_or_else(|| LogError::Format(format!("there is no ULog format of {name}")))?
+                    .iter()
+                    .flat_map(|(_, field, count)| match count {
+                        1 => vec![field.clone()],
+                        _ => (0..*count).map(|index| format!("{field}[{index}]")).collect(),
+                    })
+                    .collect();
+
+                ulog.topics.entry(name.clone()).or_insert(DataTable { columns, rows: Vec::new() });
+                subscriptions.insert(id, name);
+            },
+            b'D' if body.len() >= 2 => {
+                let id = u16::from_le_bytes([body[0], body[1]]);
+                let Some(name) = subscriptions.get(&id) else {
+                    continue;
+                };
+
+                let mut row = Vec::new();
+                let mut position = 2;
+
+                for (kind, _, count) in &formats[name] {
+                    let size = ulog_type_size(kind).unwrap_or(1);
+
+                    for _ in 0..*count {
+                        let value = body
+                            .get(position..position + size)
+                            .ok_or_else(|| LogError::Format(format!("ULog data of {name} is cut")))?;
+
+                        row.push(decode_ulog_value(kind, value));
+                        position += size;
+                    }
+                }
+
+                if let Some(topic) = ulog.topics.get_mut(name) {
+                    topic.rows.push(row);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    Ok(ulog)
+}
+
+/// Columns of the Betaflight blackbox CSV, as `blackbox_decode` writes them.
+const BETAFLIGHT_COLUMNS: [&str; 20] = [
+    "loopIteration",
+    "time (us)",
+    "setpoint[3]",
+    "vbatLatest (V)",
+    "gyroADC[0] (deg/s)",
+    "gyroADC[1] (deg/s)",
+    "gyroADC[2] (deg/s)",
+    "accSmooth[0]",
+    "accSmooth[1]",
+    "accSmooth[2]",
+    "motor[0]",
+    "motor[1]",
+    "motor[2]",
+    "motor[3]",
+    "heading[0] (rad)",
+    "heading[1] (rad)",
+    "heading[2] (rad)",
+    "flightModeFlags",
+    "stateFlags",
+    "failsafePhase",
+];
+
+/// Exports records of the drone as the Betaflight blackbox CSV, which is opened by Blackbox Explorer and PIDtoolbox.
+///
+/// Axes are Betaflight ones: X forward, Y left, Z up. Gyro is in deg/s, the accelerometer reads 2048 per 1 g,
+/// `heading` is roll, pitch and yaw in radians. Throttle `setpoint[3]` is from 0 to 1000,
+/// DShot `motor` commands are from 48 to 2047, 0 stops the motor.
+pub fn export_betaflight_csv(log: &FlightLog, drone: u32, writer: &mut impl Write) -> Result<(), LogError> {
+    writeln!(writer, "{}", BETAFLIGHT_COLUMNS.map(|column| format!("\"{column}\"")).join(","))?;
+
+    let records: Vec<_> = log.drone_records(drone).collect();
+
+    for (index, record) in records.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|previous| records[previous]);
+
+        let flu = |frd: Vec3| Vec3::new(frd.x, -frd.y, -frd.z);
+        let gyro = flu(to_frd(record.rotation, record.angular_velocity)) * 180.0 / std::f32::consts::PI;
+        let acc = flu(specific_force(previous, record)) * BETAFLIGHT_ACC_1G / GRAVITY;
+
+        let throttle = throttle(record);
+        let motor = if record.is_armed { (48.0 + throttle * 1999.0).round() } else { 0.0 };
+
+        let (yaw, pitch, roll) = to_ned_rotation(record.rotation).to_euler(EulerRot::ZYX);
+
+        let values = [
+            index.to_string(),
+            microseconds(record.time).to_string(),
+            (throttle * 1000.0).round().to_string(),
+            format!("{:.2}", battery_voltage(record.battery_charge)),
+            format!("{:.2}", gyro.x),
+            format!("{:.2}", gyro.y),
+            format!("{:.2}", gyro.z),
+            format!("{:.0}", acc.x),
+            format!("{:.0}", acc.y),
+            format!("{:.0}", acc.z),
+            motor.to_string(),
+            motor.to_string(),
+            motor.to_string(),
+            motor.to_string(),
+            format!("{roll:.4}"),
+            format!("{pitch:.4}"),
+            format!("{:.4}", yaw.rem_euclid(std::f32::consts::TAU)),
+            // ANGLE_MODE while the flight controller holds the setpoint
+            if record.setpoint_velocity.is_some() { "1" } else { "0" }.to_owned(),
+            // ARMED
+            u8::from(record.is_armed).to_string(),
+            "0".to_owned(),
+        ];
+
+        writeln!(writer, "{}", values.join(","))?;
+    }
+
+    Ok(())
+}
+
+/// Reads the Betaflight blackbox CSV.
+pub fn read_betaflight_csv(reader: impl BufRead) -> Result<DataTable, LogError> {
+    let mut lines = reader.lines();
+
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let columns = header.split(',').map(|column| column.trim().trim_matches('"').to_owned()).collect();
+
+    let mut table = DataTable {
+        columns,
+        rows: Vec::new(),
+    };
+
+    for (index, line) in lines.enumerate() {
+        let row = line?
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| LogError::Format(format!("wrong Betaflight line {}", index + 2)))?;
+
+        if row.len() != table.columns.len() {
+            return Err(LogError::Format(format!("wrong Betaflight line {}", index + 2)));
+        }
+
+        table.rows.push(row);
+    }
+
+    Ok(table)
+}
@@ -0,0 +1,451 @@
+use std::{
+    fmt, fs,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::VisionMode;
+
+/// First bytes of the binary flight log.
+const BINARY_MAGIC: &[u8; 4] = b"SSFR";
+
+/// Version of the log formats.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Columns of the CSV flight log.
+const CSV_COLUMNS: &str = "time,drone,x,y,z,qx,qy,qz,qw,vx,vy,vz,wx,wy,wz,motor_thrust,thrust_x,thrust_y,thrust_z,\
+setpoint_vx,setpoint_vy,setpoint_vz,setpoint_yaw,error_vx,error_vy,error_vz,\
+altitude,temperature,video_breakup,battery_remaining,battery_charge,armed,vision_mode";
+
+/// Error that can happen while writing or reading a flight log.
+#[derive(Debug)]
+pub enum LogError {
+    Io(io::Error),
+    Metadata(serde_json::Error),
+    /// The file is not a flight log or its version is not supported.
+    Format(String),
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't access flight log: {error}"),
+            Self::Metadata(error) => write!(f, "can't read flight log metadata: {error}"),
+            Self::Format(error) => write!(f, "wrong flight log: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for LogError {}
+
+impl From<io::Error> for LogError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for LogError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Metadata(error)
+    }
+}
+
+/// Header of the flight log.
+///
+/// `session` is the name of the recording session, which is split into `part`s. `created` is in seconds since the Unix epoch,
+/// `rate` is in samples per second. `drones` are the IDs and airframes of the recorded drones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    pub version: u16,
+    pub session: String,
+    pub part: u32,
+    pub created: u64,
+    pub rate: f32,
+    pub drones: Vec<(u32, String)>,
+}
+
+/// Sample of the drone state in the flight log.
+///
+/// `time` is in seconds since the start of the simulation. Thrusts are in newtons: `motor_thrust` is the hover thrust
+/// of the motors, `thrust` is added by the flight controller. `velocity_error` is the setpoint velocity minus the velocity.
+/// `altitude` is in meters above the ground, `temperature` is the drone temperature in degrees Celsius,
+/// `video_breakup` is from 0.0 for a clean video link to 1.0 for static. Battery `remaining` is in watt-hours.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlightRecord {
+    pub time: f32,
+    pub drone: u32,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub motor_thrust: f32,
+    pub thrust: Vec3,
+    pub setpoint_velocity: Option<Vec3>,
+    pub setpoint_yaw: Option<f32>,
+    pub velocity_error: Option<Vec3>,
+    pub altitude: f32,
+    pub temperature: f32,
+    pub video_breakup: f32,
+    pub battery_remaining: f32,
+    pub battery_charge: f32,
+    pub is_armed: bool,
+    pub vision_mode: VisionMode,
+}
+
+/// Flight log: its header and records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlightLog {
+    pub metadata: SessionMetadata,
+    pub records: Vec<FlightRecord>,
+}
+
+impl FlightLog {
+    /// Reads the flight log in the format of its extension: `.csv` or binary.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, LogError> {
+        let path = path.as_ref();
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => read_csv_log(path),
+            _ => read_binary_log(path),
+        }
+    }
+
+    /// Returns records of the drone.
+    pub fn drone_records(&self, drone: u32) -> impl Iterator<Item = &FlightRecord> {
+        self.records.iter().filter(move |record| record.drone == drone)
+    }
+}
+
+fn vision_mode_code(mode: VisionMode) -> u8 {
+    match mode {
+        VisionMode::Day => 0,
+        VisionMode::Thermal => 1,
+        VisionMode::NightVision => 2,
+    }
+}
+
+fn vision_mode_from_code(code: u8) -> Result<VisionMode, LogError> {
+    match code {
+        0 => Ok(VisionMode::Day),
+        1 => Ok(VisionMode::Thermal),
+        2 => Ok(VisionMode::NightVision),
+        _ => Err(LogError::Format(format!("unknown vision mode {code}"))),
+    }
+}
+
+/// Writer of the flight log.
+pub trait LogWriter: Send + Sync {
+    fn write_record(&mut self, record: &FlightRecord) -> Result<(), LogError>;
+
+    fn flush(&mut self) -> Result<(), LogError>;
+}
+
+/// Writer of the CSV flight log.
+///
+/// The first line is a comment with the JSON metadata, the second one has the column names.
+/// Missing setpoints are empty cells.
+pub struct CsvLogWriter<W: Write> {
+    writer: W,
+}
+
+impl CsvLogWriter<BufWriter<fs::File>> {
+    /// Creates the log file.
+    pub fn create(path: impl AsRef<Path>, metadata: &SessionMetadata) -> Result<Self, LogError> {
+        Self::new(BufWriter::new(fs::File::create(path)?), metadata)
+    }
+}
+
+impl<W: Write> CsvLogWriter<W> {
+    /// Writes the header.
+    pub fn new(mut writer: W, metadata: &SessionMetadata) -> Result<Self, LogError> {
+        writeln!(writer, "# {}", serde_json::to_string(metadata)?)?;
+        writeln!(writer, "{CSV_COLUMNS}")?;
+
+        Ok(Self { writer })
+    }
+}
+
+impl<W: Write + Send + Sync> LogWriter for CsvLogWriter<W> {
+    fn write_record(&mut self, record: &FlightRecord) -> Result<(), LogError> {
+        let optional = |value: Option<f32>| value.map(|value| value.to_string()).unwrap_or_default();
+        let setpoint = record.setpoint_velocity;
+        let error = record.velocity_error;
+
+        let cells = [
+            record.time.to_string(),
+            record.drone.to_string(),
+            record.position.x.to_string(),
+            record.position.y.to_string(),
+            record.position.z.to_string(),
+            record.rotation.x.to_string(),
+            record.rotation.y.to_string(),
+            record.rotation.z.to_string(),
+            record.rotation.w.to_string(),
+            record.velocity.x.to_string(),
+            record.velocity.y.to_string(),
+            record.velocity.z.to_string(),
+            record.angular_velocity.x.to_string(),
+            record.angular_velocity.y.to_string(),
+            record.angular_velocity.z.to_string(),
+            record.motor_thrust.to_string(),
+            record.thrust.x.to_string(),
+            record.thrust.y.to_string(),
+            record.thrust.z.to_string(),
+            optional(setpoint.map(|setpoint| setpoint.x)),
+            optional(setpoint.map(|setpoint| setpoint.y)),
+            optional(setpoint.map(|setpoint| setpoint.z)),
+            optional(record.setpoint_yaw),
+            optional(error.map(|error| error.x)),
+            optional(error.map(|error| error.y)),
+            optional(error.map(|error| error.z)),
+            record.altitude.to_string(),
+            record.temperature.to_string(),
+            record.video_breakup.to_string(),
+            record.battery_remaining.to_string(),
+            record.battery_charge.to_string(),
+            u8::from(record.is_armed).to_string(),
+            vision_mode_code(record.vision_mode).to_string(),
+        ];
+
+        writeln!(self.writer, "{}", cells.join(","))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), LogError> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Reads the CSV flight log.
+pub fn read_csv_log(path: impl AsRef<Path>) -> Result<FlightLog, LogError> {
+    let mut lines = BufReader::new(fs::File::open(path)?).lines();
+
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let metadata = header
+        .strip_prefix("# ")
+        .ok_or_else(|| LogError::Format("there is no metadata".to_owned()))?;
+    let metadata: SessionMetadata = serde_json::from_str(metadata)?;
+
+    if lines.next().transpose()?.as_deref() != Some(CSV_COLUMNS) {
+        return Err(LogError::Format("unknown columns".to_owned()));
+    }
+
+    let lines = lines.collect::<Result<Vec<_>, _>>()?;
+    let mut records = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        match read_csv_record(line, index + 3) {
+            Ok(record) => records.push(record),
+            // the recording was cut in the middle of the last line
+            Err(error) if index + 1 == lines.len() => warn!("{error}, the last record is dropped"),
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(FlightLog { metadata, records })
+}
+
+/// Reads the record from the CSV line, `line_number` counts from one.
+fn read_csv_record(line: &str, line_number: usize) -> Result<FlightRecord, LogError> {
+    let wrong_line = || LogError::Format(format!("wrong line {line_number}"));
+
+    let cells: Vec<&str> = line.split(',').collect();
+
+    if cells.len() != CSV_COLUMNS.split(',').count() {
+        return Err(wrong_line());
+    }
+
+    let number = |column: usize| cells[column].parse::<f32>().map_err(|_| wrong_line());
+    let optional = |column: usize| match cells[column] {
+        "" => Ok(None),
+        cell => cell.parse::<f32>().map(Some).map_err(|_| wrong_line()),
+    };
+    let vector = |column: usize| -> Result<Vec3, LogError> {
+        Ok(Vec3::new(number(column)?, number(column + 1)?, number(column + 2)?))
+    };
+    let optional_vector = |column: usize| -> Result<Option<Vec3>, LogError> {
+        match (optional(column)?, optional(column + 1)?, optional(column + 2)?) {
+            (Some(x), Some(y), Some(z)) => Ok(Some(Vec3::new(x, y, z))),
+            _ => Ok(None),
+        }
+    };
+
+    Ok(FlightRecord {
+        time: number(0)?,
+        drone: cells[1].parse().map_err(|_| wrong_line())?,
+        position: vector(2)?,
+        rotation: Quat::from_xyzw(number(5)?, number(6)?, number(7)?, number(8)?),
+        velocity: vector(9)?,
+        angular_velocity: vector(12)?,
+        motor_thrust: number(15)?,
+        thrust: vector(16)?,
+        setpoint_velocity: optional_vector(19)?,
+        setpoint_yaw: optional(22)?,
+        velocity_error: optional_vector(23)?,
+        altitude: number(26)?,
+        temperature: number(27)?,
+        video_breakup: number(28)?,
+        battery_remaining: number(29)?,
+        battery_charge: number(30)?,
+        is_armed: cells[31] == "1",
+        vision_mode: vision_mode_from_code(cells[32].parse().map_err(|_| wrong_line())?)?,
+    })
+}
+
+/// Writer of the compact binary flight log.
+///
+/// The file starts with the `SSFR` magic, the format version and the length of the JSON metadata, which follows them.
+/// Records start with the `f32` time and the `u32` drone ID, the rest are `f32`s, missing setpoints are NaNs.
+/// Numbers are little-endian. Every record ends with the drone flags and the vision mode.
+pub struct BinaryLogWriter<W: Write> {
+    writer: W,
+}
+
+impl BinaryLogWriter<BufWriter<fs::File>> {
+    /// Creates the log file.
+    pub fn create(path: impl AsRef<Path>, metadata: &SessionMetadata) -> Result<Self, LogError> {
+        Self::new(BufWriter::new(fs::File::create(path)?), metadata)
+    }
+}
+
+impl<W: Write> BinaryLogWriter<W> {
+    /// Writes the header.
+    pub fn new(mut writer: W, metadata: &SessionMetadata) -> Result<Self, LogError> {
+        let metadata = serde_json::to_vec(metadata)?;
+
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(metadata.len() as u32).to_le_bytes())?;
+        writer.write_all(&metadata)?;
+
+        Ok(Self { writer })
+    }
+}
+
+impl<W: Write + Send + Sync> LogWriter for BinaryLogWriter<W> {
+    fn write_record(&mut self, record: &FlightRecord) -> Result<(), LogError> {
+        let setpoint = record.setpoint_velocity.unwrap_or(Vec3::NAN);
+        let error = record.velocity_error.unwrap_or(Vec3::NAN);
+
+        self.writer.write_all(&record.time.to_le_bytes())?;
+        self.writer.write_all(&record.drone.to_le_bytes())?;
+
+        let mut values = record.position.to_array().to_vec();
+        values.extend(record.rotation.to_array());
+        values.extend(record.velocity.to_array());
+        values.extend(record.angular_velocity.to_array());
+        values.push(record.motor_thrust);
+        values.extend(record.thrust.to_array());
+        values.extend(setpoint.to_array());
+        values.push(record.setpoint_yaw.unwrap_or(f32::NAN));
+        values.extend(error.to_array());
+        values.extend([
+            record.altitude,
+            record.temperature,
+            record.video_breakup,
+            record.battery_remaining,
+            record.battery_charge,
+        ]);
+
+        for value in values {
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+
+        self.writer.write_all(&[u8::from(record.is_armed), vision_mode_code(record.vision_mode)])?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), LogError> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Number of `f32`s after the time and the drone ID in the binary record.
+const BINARY_VALUES: usize = 29;
+
+/// Size of the time and the drone ID, which start the binary record.
+const BINARY_HEADER_SIZE: usize = 8;
+
+/// Reads the binary flight log.
+pub fn read_binary_log(path: impl AsRef<Path>) -> Result<FlightLog, LogError> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+
+    if &magic != BINARY_MAGIC {
+        return Err(LogError::Format("it is not a flight log".to_owned()));
+    }
+
+    let mut version = [0; 2];
+    reader.read_exact(&mut version)?;
+
+    if u16::from_le_bytes(version) != FORMAT_VERSION {
+        return Err(LogError::Format(format!("version {} is not supported", u16::from_le_bytes(version))));
+    }
+
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+
+    // the length isn't trusted, a broken one must not allocate more than the file has
+    let length = u64::from(u32::from_le_bytes(length));
+    let mut metadata = Vec::new();
+    reader.by_ref().take(length).read_to_end(&mut metadata)?;
+
+    if metadata.len() as u64 != length {
+        return Err(LogError::Format("the metadata is cut".to_owned()));
+    }
+
+    let metadata: SessionMetadata = serde_json::from_slice(&metadata)?;
+
+    let mut body = Vec::new();
+    reader.read_to_end(&mut body)?;
+
+    let record_size = BINARY_HEADER_SIZE + BINARY_VALUES * 4 + 2;
+
+    // the recording was cut in the middle of the last record
+    if body.len() % record_size != 0 {
+        warn!("the last record is cut, it is dropped");
+    }
+
+    let records = body
+        .chunks_exact(record_size)
+        .map(|chunk| {
+            let flags = BINARY_HEADER_SIZE + BINARY_VALUES * 4;
+            let values: Vec<f32> = chunk[BINARY_HEADER_SIZE..flags]
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+
+            let vector = |index: usize| Vec3::new(values[index], values[index + 1], values[index + 2]);
+            let optional = |value: f32| (!value.is_nan()).then_some(value);
+            let optional_vector = |index: usize| Some(vector(index)).filter(|vector| !vector.is_nan());
+
+            Ok(FlightRecord {
+                time: f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                drone: u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]),
+                position: vector(0),
+                rotation: Quat::from_xyzw(values[3], values[4], values[5], values[6]),
+                velocity: vector(7),
+                angular_velocity: vector(10),
+                motor_thrust: values[13],
+                thrust: vector(14),
+                setpoint_velocity: optional_vector(17),
+                setpoint_yaw: optional(values[20]),
+                velocity_error: optional_vector(21),
+                altitude: values[24],
+                temperature: values[25],
+                video_breakup: values[26],
+                battery_remaining: values[27],
+                battery_charge: values[28],
+                is_armed: chunk[flags] == 1,
+                vision_mode: vision_mode_from_code(chunk[flags + 1])?,
+            })
+        })
+        .collect::<Result<_, LogError>>()?;
+
+    Ok(FlightLog { metadata, records })
+}
@@ -0,0 +1,976 @@
+use std::{
+    env, fmt, fs, io, mem,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    app::{AppExit, MainScheduleOrder, RunFixedMainLoop},
+    ecs::schedule::ScheduleLabel,
+    input::{keyboard::KeyboardInput, ButtonState, InputSystem},
+    prelude::*,
+    time::TimeUpdateStrategy,
+    transform::TransformSystem,
+};
+use bevy_rapier3d::prelude::*;
+use bevy_third_person_camera::ThirdPersonCamera;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agents::AgentPopulation,
+    camera::{third_person_camera, MainCamera, SetVisionMode, ThermalMaterialCamera, VisionMode, VisionModeBindings},
+    fleet::{Drone, DroneControl, DroneSetpoint},
+    player::FlightController,
+    post_processing::{
+        effects::{Palette, PaletteSettings, PostProcessEffect, PostProcessEffectKind},
+        PostProcessStack,
+    },
+    recorder::{FlightRecorder, RecorderSettings},
+    search_and_rescue::{SearchMission, TargetMark},
+    sim_clock::{physics_timestep, SimClock},
+    ui::menu::{AppState, Scenarios},
+    world::generator::EnvironmentSettings,
+};
+
+/// Plugin for recording the operator input and replaying it.
+///
+/// The input of every flight frame is recorded with the frame time, so the replay steps the simulation
+/// exactly as it was stepped. The mouse and the gamepad only aim the camera, so the target marks they make
+/// are recorded instead of them, as are the setpoints of external programs.
+/// Run `supersonic --replay <file> [--vision-mode <mode>] [--palette <palette>]` to replay the input log.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match Replay::from_args(env::args()) {
+            Some(Ok(replay)) => {
+                app.insert_resource(replay);
+            },
+            Some(Err(error)) => warn!("{error}"),
+            None => {},
+        }
+
+        install_replay_step(app);
+
+        app
+            .init_resource::<ReplayBindings>()
+            .init_resource::<InputRecorder>()
+            .init_resource::<OperatorInput>()
+            .add_systems(PreStartup, start_replay)
+            .add_systems(Startup, setup_replay_hud)
+            .add_systems(PostStartup, apply_replay_palette)
+            .add_systems(OnTransition { from: AppState::Loading, to: AppState::InFlight }, start_input_recording)
+            // the frame, which enters the flight, runs its systems too
+            .add_systems(OnEnter(AppState::InFlight), (replay_input, record_input))
+            .add_systems(PreUpdate, (
+                read_operator_input,
+                replay_controls,
+                rewind_replay,
+                (replay_input, record_input).run_if(in_state(AppState::InFlight)),
+            ).chain().after(InputSystem))
+            .add_systems(PostUpdate, fly_free_camera.before(TransformSystem::TransformPropagate))
+            .add_systems(Last, (schedule_replay_frame, record_commands, save_input_log, update_replay_hud));
+    }
+}
+
+/// Schedule, which runs `Update` unless the replay holds the simulation.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplayStep;
+
+/// Runs `Update` in the `ReplayStep` schedule instead of the main schedule, when the app has a `Replay`.
+pub fn install_replay_step(app: &mut App) {
+    if !app.world.contains_resource::<Replay>() {
+        return;
+    }
+
+    app.add_systems(ReplayStep, run_replayed_update);
+
+    let mut order = app.world.resource_mut::<MainScheduleOrder>();
+    order.labels.retain(|label| *label != Update.intern());
+    order.insert_after(RunFixedMainLoop, ReplayStep);
+}
+
+/// Replay speeds, which are switched by the `ReplayBindings`.
+const REPLAY_SPEEDS: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 10.0];
+
+/// Error that can happen while reading or writing an input log.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// Wrong command line argument.
+    Argument(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't access input log: {error}"),
+            Self::Parse(error) => write!(f, "can't parse input log: {error}"),
+            Self::Serialize(error) => write!(f, "can't serialize input log: {error}"),
+            Self::Argument(error) => write!(f, "wrong replay argument: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for ReplayError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<ron::Error> for ReplayError {
+    fn from(error: ron::Error) -> Self {
+        Self::Serialize(error)
+    }
+}
+
+/// Seeds of the generated scenario: the environment, the agents and the search targets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioSeed {
+    pub environment: u64,
+    pub agents: u64,
+    pub search: u64,
+}
+
+/// Keyboard state of one frame and the virtual time, which passed since the previous frame.
+///
+/// `speed` is the speed of the simulation clock in the frame, it is zero while the physics was paused.
+/// `marks` are the points of the `TargetMark`s and `setpoints` are the `DroneSetpoint`s sent in the frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputFrame {
+    pub delta: Duration,
+    #[serde(default = "normal_speed", skip_serializing_if = "is_normal_speed")]
+    pub speed: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pressed: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub just_pressed: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub just_released: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marks: Vec<Vec3>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setpoints: Vec<DroneSetpoint>,
+}
+
+/// Returns the speed of the frames, which don't record it.
+fn normal_speed() -> f32 {
+    1.0
+}
+
+/// Whether the frame runs at the normal speed, so its speed isn't saved.
+fn is_normal_speed(speed: &f32) -> bool {
+    *speed == normal_speed()
+}
+
+impl InputFrame {
+    /// Captures the keyboard state.
+    pub fn capture(delta: Duration, keys: &ButtonInput<KeyCode>) -> Self {
+        Self {
+            delta,
+            speed: normal_speed(),
+            pressed: keys.get_pressed().copied().collect(),
+            just_pressed: keys.get_just_pressed().copied().collect(),
+            just_released: keys.get_just_released().copied().collect(),
+            marks: Vec::new(),
+            setpoints: Vec::new(),
+        }
+    }
+
+    /// Restores the keyboard state, `ignored` keys stay released.
+    pub fn restore(&self, keys: &mut ButtonInput<KeyCode>, ignored: &[KeyCode]) {
+        keys.reset_all();
+
+        let all = self.pressed.iter().chain(&self.just_pressed).chain(&self.just_released);
+
+        for key in all.filter(|key| !ignored.contains(key)) {
+            let is_just_pressed = self.just_pressed.contains(key);
+
+            // the key could be pressed and released in the same frame
+            if self.just_released.contains(key) {
+                keys.press(*key);
+                keys.release(*key);
+            }
+
+            if self.pressed.contains(key) {
+                keys.press(*key);
+            } else if is_just_pressed {
+                keys.press(*key);
+                keys.release(*key);
+            }
+
+            if !is_just_pressed {
+                keys.clear_just_pressed(*key);
+            }
+        }
+    }
+}
+
+/// Recorded input of the scenario from the start of the flight.
+///
+/// `scenario` is the name of the flown scenario and `timestep` is the longest physics step in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputLog {
+    #[serde(default)]
+    pub scenario: String,
+    pub seed: ScenarioSeed,
+    #[serde(default = "default_timestep")]
+    pub timestep: f32,
+    pub frames: Vec<InputFrame>,
+}
+
+/// Returns the physics step of the logs, which don't record it.
+fn default_timestep() -> f32 {
+    SimClock::default().physics_step
+}
+
+impl Default for InputLog {
+    fn default() -> Self {
+        Self {
+            scenario: String::new(),
+            seed: ScenarioSeed::default(),
+            timestep: default_timestep(),
+            frames: Vec::new(),
+        }
+    }
+}
+
+impl InputLog {
+    /// Reads the input log.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the input log.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the time of the frame in seconds since the start.
+    pub fn time_at(&self, frame: usize) -> f32 {
+        self.frames.iter().take(frame + 1).map(|frame| frame.delta.as_secs_f32()).sum()
+    }
+
+    /// Returns the first frame at the time.
+    pub fn frame_at(&self, time: f32) -> usize {
+        let mut elapsed = 0.0;
+
+        for (index, frame) in self.frames.iter().enumerate() {
+            elapsed += frame.delta.as_secs_f32();
+
+            if elapsed >= time {
+                return index;
+            }
+        }
+
+        self.frames.len()
+    }
+
+    /// Returns the duration of the log in seconds.
+    pub fn duration(&self) -> f32 {
+        self.time_at(self.frames.len())
+    }
+}
+
+// resources
+/// Replay of the input log.
+///
+/// The replay plays `frame` after `frame` at `speed`, seeks to the `seek` frame as fast as it can and holds while paused.
+/// Seeking back loads the scenario again and plays it from the first frame.
+///
+/// `vision_mode` and `palette` re-render the replay: the vision mode keys of the log are ignored.
+#[derive(Resource, Debug, Clone)]
+pub struct Replay {
+    pub log: InputLog,
+    pub frame: usize,
+    pub is_paused: bool,
+    pub speed: f32,
+    pub seek: Option<usize>,
+    pub vision_mode: Option<VisionMode>,
+    pub palette: Option<Palette>,
+    pub is_free_camera: bool,
+    /// Whether the simulation steps in the current frame.
+    pub is_playing_frame: bool,
+    /// Real time in seconds, which wasn't played yet.
+    since_frame: f32,
+}
+
+impl Replay {
+    pub fn new(log: InputLog) -> Self {
+        Self {
+            log,
+            frame: 0,
+            is_paused: false,
+            speed: 1.0,
+            seek: None,
+            vision_mode: None,
+            palette: None,
+            is_free_camera: false,
+            is_playing_frame: true,
+            since_frame: 0.0,
+        }
+    }
+
+    /// Reads the replay from the command line arguments, returns `None` if there is no `--replay`.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Result<Self, ReplayError>> {
+        let mut args = args.into_iter();
+        let mut path = None;
+        let mut vision_mode = None;
+        let mut palette = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--replay" => path = args.next(),
+                "--vision-mode" => vision_mode = args.next(),
+                "--palette" => palette = args.next(),
+                _ => {},
+            }
+        }
+
+        let log = match InputLog::load(path?) {
+            Ok(log) => log,
+            Err(error) => return Some(Err(error)),
+        };
+
+        let mut replay = Self::new(log);
+
+        replay.vision_mode = match vision_mode.as_deref() {
+            None => None,
+            Some("day") => Some(VisionMode::Day),
+            Some("thermal") => Some(VisionMode::Thermal),
+            Some("night-vision") => Some(VisionMode::NightVision),
+            Some(mode) => return Some(Err(ReplayError::Argument(format!("unknown vision mode {mode}")))),
+        };
+
+        replay.palette = match palette.as_deref() {
+            None => None,
+            Some("white-hot") => Some(Palette::WhiteHot),
+            Some("black-hot") => Some(Palette::BlackHot),
+            Some("ironbow") => Some(Palette::Ironbow),
+            Some("rainbow") => Some(Palette::Rainbow),
+            Some(palette) => return Some(Err(ReplayError::Argument(format!("unknown palette {palette}")))),
+        };
+
+        Some(Ok(replay))
+    }
+
+    /// Returns the replay time in seconds.
+    pub fn time(&self) -> f32 {
+        self.log.time_at(self.frame.saturating_sub(1))
+    }
+
+    /// Seeks to the time in seconds.
+    pub fn seek_to(&mut self, time: f32) {
+        self.seek = Some(self.log.frame_at(time));
+    }
+
+    /// Returns whether all frames are played.
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.log.frames.len()
+    }
+}
+
+/// Key bindings of the replay.
+///
+/// `seek_back` and `seek_forward` seek by `seek_step` seconds. `free_camera` detaches the camera from the drone,
+/// then `camera_forward`, `camera_back`, `camera_left`, `camera_right`, `camera_down` and `camera_up` move it
+/// `camera_speed` meters per second and arrows turn it.
+///
+/// The keys of the `SimClock` are off in the replay, so `pause`, `slower` and `faster` take them over.
+#[derive(Resource, Debug, Clone)]
+pub struct ReplayBindings {
+    pub pause: KeyCode,
+    pub slower: KeyCode,
+    pub faster: KeyCode,
+    pub seek_back: KeyCode,
+    pub seek_forward: KeyCode,
+    pub seek_step: f32,
+    pub free_camera: KeyCode,
+    pub camera_forward: KeyCode,
+    pub camera_back: KeyCode,
+    pub camera_left: KeyCode,
+    pub camera_right: KeyCode,
+    pub camera_down: KeyCode,
+    pub camera_up: KeyCode,
+    pub camera_speed: f32,
+}
+
+impl Default for ReplayBindings {
+    fn default() -> Self {
+        Self {
+            pause: KeyCode::Space,
+            slower: KeyCode::Minus,
+            faster: KeyCode::Equal,
+            seek_back: KeyCode::Comma,
+            seek_forward: KeyCode::Period,
+            seek_step: 5.0,
+            free_camera: KeyCode::KeyF,
+            camera_forward: KeyCode::KeyW,
+            camera_back: KeyCode::KeyS,
+            camera_left: KeyCode::KeyA,
+            camera_right: KeyCode::KeyD,
+            camera_down: KeyCode::KeyQ,
+            camera_up: KeyCode::KeyE,
+            camera_speed: 15.0,
+        }
+    }
+}
+
+/// Records the input of the scenario, unless it is replayed.
+///
+/// The log is saved to the recorder directory, when the flight ends: next to the flight log, if the flight
+/// was recorded, otherwise as `<name>.replay.ron`, where `name` is given by the start of the flight.
+#[derive(Resource, Debug, Clone)]
+pub struct InputRecorder {
+    pub is_enabled: bool,
+    pub name: String,
+    pub log: InputLog,
+}
+
+impl Default for InputRecorder {
+    fn default() -> Self {
+        Self {
+            is_enabled: true,
+            name: "flight".to_owned(),
+            log: InputLog::default(),
+        }
+    }
+}
+
+/// Keyboard of the operator, while the replay drives the `ButtonInput<KeyCode>`.
+#[derive(Resource, Default)]
+pub struct OperatorInput(pub ButtonInput<KeyCode>);
+
+// components
+/// Describes the replay HUD text.
+#[derive(Component)]
+struct ReplayHudText;
+
+// systems
+/// Returns the seed of the current scenario.
+fn scenario_seed(
+    environment: Option<&EnvironmentSettings>,
+    population: Option<&AgentPopulation>,
+    mission: Option<&SearchMission>,
+) -> ScenarioSeed {
+    ScenarioSeed {
+        environment: environment.map_or(0, |environment| environment.seed),
+        agents: population.map_or(0, |population| population.seed),
+        search: mission.map_or(0, |mission| mission.seed),
+    }
+}
+
+/// System that starts the input log of the flight with its scenario, seed and physics step.
+fn start_input_recording(
+    environment: Option<Res<EnvironmentSettings>>,
+    population: Option<Res<AgentPopulation>>,
+    mission: Option<Res<SearchMission>>,
+    scenarios: Option<Res<Scenarios>>,
+    clock: Option<Res<SimClock>>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+
+    recorder.name = format!("flight_{started}");
+    recorder.log = InputLog {
+        scenario: scenarios.and_then(|scenarios| scenarios.current().map(|scenario| scenario.name.clone()))
+            .unwrap_or_default(),
+        seed: scenario_seed(environment.as_deref(), population.as_deref(), mission.as_deref()),
+        timestep: clock.map_or_else(default_timestep, |clock| clock.physics_step),
+        frames: Vec::new(),
+    };
+}
+
+/// System that sets up the replayed scenario: its seed, the held time until the flight and the vision mode.
+pub fn start_replay(
+    replay: Option<Res<Replay>>,
+    environment: Option<ResMut<EnvironmentSettings>>,
+    population: Option<ResMut<AgentPopulation>>,
+    mission: Option<ResMut<SearchMission>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut commands: Commands,
+    mut vision_modes: EventWriter<SetVisionMode>,
+) {
+    let Some(replay) = replay else {
+        return;
+    };
+
+    // the recorded frames are already limited and sped up
+    virtual_time.set_max_delta(Duration::MAX);
+
+    let seed = replay.log.seed;
+
+    if let Some(mut environment) = environment {
+        environment.seed = seed.environment;
+    }
+
+    if let Some(mut population) = population {
+        population.seed = seed.agents;
+    }
+
+    if let Some(mut mission) = mission {
+        mission.seed = seed.search;
+    }
+
+    commands.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+
+    if let Some(mode) = replay.vision_mode {
+        vision_modes.send(SetVisionMode(mode));
+    }
+}
+
+/// System that maps the replay image to the palette.
+fn apply_replay_palette(
+    replay: Option<Res<Replay>>,
+    mut stacks: Query<&mut PostProcessStack, With<ThermalMaterialCamera>>,
+) {
+    let Some(palette) = replay.and_then(|replay| replay.palette) else {
+        return;
+    };
+
+    for mut stack in &mut stacks {
+        stack.remove(PostProcessEffectKind::Palette);
+        stack.push(PostProcessEffect::Palette(PaletteSettings {
+            palette: palette.into(),
+            ..default()
+        }));
+    }
+}
+
+/// System that tracks the keyboard of the operator from the keyboard events.
+fn read_operator_input(
+    mut events: EventReader<KeyboardInput>,
+    mut input: ResMut<OperatorInput>,
+) {
+    input.0.clear();
+
+    for event in events.read() {
+        match event.state {
+            ButtonState::Pressed => input.0.press(event.key_code),
+            ButtonState::Released => input.0.release(event.key_code),
+        }
+    }
+}
+
+/// System that pauses, slows down, speeds up and seeks the replay.
+fn replay_controls(
+    bindings: Res<ReplayBindings>,
+    input: Res<OperatorInput>,
+    replay: Option<ResMut<Replay>>,
+    mut cameras: Query<Entity, With<MainCamera>>,
+    mut commands: Commands,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+
+    let keys = &input.0;
+
+    if keys.just_pressed(bindings.pause) {
+        replay.is_paused = !replay.is_paused;
+    }
+
+    let speed = REPLAY_SPEEDS.iter().position(|speed| *speed >= replay.speed).unwrap_or(REPLAY_SPEEDS.len() - 1);
+
+    if keys.just_pressed(bindings.slower) {
+        replay.speed = REPLAY_SPEEDS[speed.saturating_sub(1)];
+    }
+
+    if keys.just_pressed(bindings.faster) {
+        replay.speed = REPLAY_SPEEDS[(speed + 1).min(REPLAY_SPEEDS.len() - 1)];
+    }
+
+    if keys.just_pressed(bindings.seek_back) {
+        let time = replay.time() - bindings.seek_step;
+        replay.seek_to(time.max(0.0));
+    }
+
+    if keys.just_pressed(bindings.seek_forward) {
+        let time = replay.time() + bindings.seek_step;
+        replay.seek_to(time);
+    }
+
+    if keys.just_pressed(bindings.free_camera) {
+        replay.is_free_camera = !replay.is_free_camera;
+
+        for camera in &mut cameras {
+            if replay.is_free_camera {
+                commands.entity(camera).remove::<ThirdPersonCamera>();
+            } else {
+                commands.entity(camera).insert(third_person_camera());
+            }
+        }
+    }
+}
+
+/// System that loads the scenario again, when the replay seeks back, so it is played from the first frame.
+///
+/// The simulation holds in the frame, which leaves the flight.
+pub fn rewind_replay(
+    replay: Option<ResMut<Replay>>,
+    rapier: Option<ResMut<RapierConfiguration>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+
+    if replay.seek.map_or(true, |seek| seek >= replay.frame) {
+        return;
+    }
+
+    replay.frame = 0;
+    replay.is_playing_frame = false;
+    next_state.set(AppState::Loading);
+
+    if let Some(mut rapier) = rapier {
+        rapier.physics_pipeline_active = false;
+    }
+}
+
+/// System that feeds the recorded keyboard state of the played frame into the `ButtonInput<KeyCode>`
+/// and sends its marks and setpoints. Setpoints give the drones to the external program and `None` takes them back,
+/// as the control server and the ROS bridge do.
+pub fn replay_input(
+    replay: Option<ResMut<Replay>>,
+    vision_bindings: Option<Res<VisionModeBindings>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut marks: EventWriter<TargetMark>,
+    mut setpoints: EventWriter<DroneSetpoint>,
+    mut drones: Query<(&mut Drone, Option<&mut FlightController>)>,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+
+    if !replay.is_playing_frame || replay.is_finished() {
+        return;
+    }
+
+    let frame = replay.frame;
+
+    // the replay is re-rendered in its own vision mode
+    let ignored: Vec<KeyCode> = vision_bindings
+        .filter(|_| replay.vision_mode.is_some())
+        .map(|bindings| vec![bindings.cycle])
+        .unwrap_or_default();
+
+    let frame = &replay.log.frames[frame];
+    frame.restore(&mut keys, &ignored);
+
+    for point in &frame.marks {
+        marks.send(TargetMark { point: *point });
+    }
+
+    for command in &frame.setpoints {
+        let drone = drones.iter_mut().find(|(drone, _)| drone.id == command.id);
+
+        if let Some((mut drone, controller)) = drone {
+            if command.setpoint.is_some() {
+                drone.control = DroneControl::External;
+            } else if drone.control == DroneControl::External {
+                drone.control = DroneControl::Autopilot;
+
+                if let Some(mut controller) = controller {
+                    controller.setpoint = None;
+                }
+            }
+        }
+
+        setpoints.send(*command);
+    }
+
+    replay.frame += 1;
+}
+
+/// System that records the keyboard state and the speed of every flight frame.
+pub fn record_input(
+    time: Res<Time>,
+    virtual_time: Res<Time<Virtual>>,
+    rapier: Option<Res<RapierConfiguration>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    replay: Option<Res<Replay>>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    if !recorder.is_enabled || replay.is_some() {
+        return;
+    }
+
+    // the clock has applied its speed and pause to the frame already
+    let is_running = rapier.map_or(true, |rapier| rapier.physics_pipeline_active);
+
+    recorder.log.frames.push(InputFrame {
+        speed: if is_running { virtual_time.relative_speed() } else { 0.0 },
+        ..InputFrame::capture(time.delta(), &keys)
+    });
+}
+
+/// System that records the marks and the setpoints of the flight frame.
+pub fn record_commands(
+    state: Res<State<AppState>>,
+    replay: Option<Res<Replay>>,
+    mut marks: EventReader<TargetMark>,
+    mut setpoints: EventReader<DroneSetpoint>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    // the events are read in every frame, so the ones sent outside the flight aren't recorded later
+    let marks: Vec<Vec3> = marks.read().map(|mark| mark.point).collect();
+    let setpoints: Vec<DroneSetpoint> = setpoints.read().copied().collect();
+
+    if !recorder.is_enabled || replay.is_some() || *state.get() != AppState::InFlight {
+        return;
+    }
+
+    if let Some(frame) = recorder.log.frames.last_mut() {
+        frame.marks.extend(marks);
+        frame.setpoints.extend(setpoints);
+    }
+}
+
+/// System that runs `Update`, when the simulation steps.
+fn run_replayed_update(world: &mut World) {
+    if world.get_resource::<Replay>().map_or(true, |replay| replay.is_playing_frame) {
+        world.run_schedule(Update);
+    }
+}
+
+/// System that decides, whether the simulation steps in the next frame.
+///
+/// The stepped frame lasts as long as the recorded one and its physics runs at the recorded speed. Otherwise
+/// the simulation holds: `Update` doesn't run and the physics is paused, so the world doesn't change while
+/// the replay is paused or slowed down.
+/// Until the flight starts, `Update` runs and the time holds as it does behind the menus.
+pub fn schedule_replay_frame(
+    replay: Option<ResMut<Replay>>,
+    state: Res<State<AppState>>,
+    rapier: Option<ResMut<RapierConfiguration>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut last_frame: Local<Option<Instant>>,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+
+    let now = Instant::now();
+    let real_delta = last_frame.map_or(0.0, |last| (now - last).as_secs_f32());
+    *last_frame = Some(now);
+
+    let replay = &mut *replay;
+    let next = replay.log.frames.get(replay.frame).map(|frame| (frame.delta, frame.speed));
+
+    if *state.get() != AppState::InFlight {
+        replay.is_playing_frame = true;
+        *strategy = TimeUpdateStrategy::ManualDuration(Duration::ZERO);
+
+        if let Some(mut rapier) = rapier {
+            if rapier.physics_pipeline_active {
+                rapier.physics_pipeline_active = false;
+            }
+        }
+
+        return;
+    }
+
+    let is_playing = match next {
+        None => {
+            replay.seek = None;
+            false
+        },
+        Some(_) if replay.seek.is_some_and(|seek| replay.frame < seek) => true,
+        Some((delta, _)) => {
+            replay.seek = None;
+
+            if replay.is_paused {
+                false
+            } else {
+                // the replay doesn't catch up, when frames take longer than they did
+                replay.since_frame = (replay.since_frame + real_delta * replay.speed).min(0.25);
+
+                let is_due = replay.since_frame >= delta.as_secs_f32();
+
+                if is_due {
+                    replay.since_frame -= delta.as_secs_f32();
+                }
+
+                is_due
+            }
+        },
+    };
+
+    replay.is_playing_frame = is_playing;
+    let (delta, speed) = next.filter(|_| is_playing).unwrap_or((Duration::ZERO, 0.0));
+    *strategy = TimeUpdateStrategy::ManualDuration(delta);
+
+    let Some(mut rapier) = rapier else {
+        return;
+    };
+
+    let is_running = speed > 0.0;
+
+    if rapier.physics_pipeline_active != is_running {
+        rapier.physics_pipeline_active = is_running;
+    }
+
+    // the fixed timestep belongs to its owner
+    let timestep = physics_timestep(replay.log.timestep, speed);
+
+    if matches!(rapier.timestep_mode, TimestepMode::Variable { .. }) && rapier.timestep_mode != timestep {
+        rapier.timestep_mode = timestep;
+    }
+}
+
+/// System that saves the input log, when the flight ends: the scenario is left or the app quits.
+///
+/// The log is named after the last recording session of the flight, so it is next to its flight log.
+pub fn save_input_log(
+    state: Res<State<AppState>>,
+    mut exits: EventReader<AppExit>,
+    recorder: Option<Res<FlightRecorder>>,
+    settings: Option<Res<RecorderSettings>>,
+    input_recorder: Res<InputRecorder>,
+    mut session: Local<Option<String>>,
+    mut is_flying: Local<bool>,
+) {
+    if let Some(current) = recorder.as_ref().and_then(|recorder| recorder.session.as_ref()) {
+        *session = Some(current.metadata.session.clone());
+    }
+
+    let is_quitting = exits.read().count() > 0;
+
+    if matches!(state.get(), AppState::InFlight | AppState::Paused) && !is_quitting {
+        *is_flying = true;
+        return;
+    }
+
+    if !mem::take(&mut *is_flying) {
+        return;
+    }
+
+    let name = session.take().unwrap_or_else(|| input_recorder.name.clone());
+
+    if input_recorder.log.frames.is_empty() {
+        return;
+    }
+
+    let directory = settings
+        .map_or_else(|| RecorderSettings::default().directory, |settings| settings.directory.clone());
+    let path = directory.join(format!("{name}.replay.ron"));
+
+    match input_recorder.log.save(&path) {
+        Ok(()) => info!("input log is saved to {}", path.display()),
+        Err(error) => warn!("{error}"),
+    }
+}
+
+/// System that flies the free camera with the operator keys.
+fn fly_free_camera(
+    bindings: Res<ReplayBindings>,
+    input: Res<OperatorInput>,
+    replay: Option<Res<Replay>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+    mut last_frame: Local<Option<Instant>>,
+) {
+    // the virtual time stops while the replay holds
+    let now = Instant::now();
+    let delta = last_frame.map_or(0.0, |last| (now - last).as_secs_f32()).min(0.1);
+    *last_frame = Some(now);
+
+    if !replay.is_some_and(|replay| replay.is_free_camera) {
+        return;
+    }
+
+    let keys = &input.0;
+    let axis = |negative: KeyCode, positive: KeyCode| {
+        f32::from(keys.pressed(positive)) - f32::from(keys.pressed(negative))
+    };
+
+    for mut transform in &mut cameras {
+        let movement = transform.forward() * axis(bindings.camera_back, bindings.camera_forward)
+            + transform.right() * axis(bindings.camera_left, bindings.camera_right)
+            + Vec3::Y * axis(bindings.camera_down, bindings.camera_up);
+
+        transform.translation += movement * bindings.camera_speed * delta;
+
+        let yaw = axis(KeyCode::ArrowRight, KeyCode::ArrowLeft) * delta;
+        let pitch = axis(KeyCode::ArrowDown, KeyCode::ArrowUp) * delta;
+
+        transform.rotate_y(yaw);
+        transform.rotate_local_x(pitch);
+    }
+}
+
+/// Replay HUD initialization.
+fn setup_replay_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(25.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                        font_size: 15.0,
+                        color: Color::YELLOW,
+                    },
+                ),
+                ReplayHudText,
+            ));
+        });
+}
+
+/// System that shows the replay time, speed and controls.
+fn update_replay_hud(
+    replay: Option<Res<Replay>>,
+    mut query: Query<&mut Text, With<ReplayHudText>>,
+) {
+    let value = replay.map_or_else(String::new, |replay| {
+        let state = match (replay.is_finished(), replay.is_paused, replay.seek.is_some()) {
+            (true, _, _) => "finished".to_owned(),
+            (_, _, true) => "seeking".to_owned(),
+            (_, true, _) => "paused".to_owned(),
+            _ => format!("x{}", replay.speed),
+        };
+
+        format!(
+            "REPLAY {:.1}/{:.1} s, {state}{}\nSpace pause, -/= speed, ,/. seek, F free camera",
+            replay.time(),
+            replay.log.duration(),
+            if replay.is_free_camera { ", free camera" } else { "" },
+        )
+    });
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
@@ -0,0 +1,721 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    f32::consts::{FRAC_PI_2, TAU},
+    io,
+    net::{AddrParseError, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bevy::{prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow};
+use bevy_rapier3d::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use tungstenite::{
+    accept_with_config,
+    protocol::{frame::coding::CloseCode, CloseFrame, WebSocketConfig},
+    HandshakeError, Message,
+};
+
+use crate::{
+    camera_sensor::{RayCastCamera, SKY_TEMPERATURE},
+    fleet::{fly_unpiloted_drones, Drone, DroneControl, DroneSetpoint},
+    materials::Temperature,
+    player::{FlightSetpoint, Player},
+    sky::{SkyLighting, TimeOfDay},
+    weather::Weather,
+    world::generator::GeneratorRng,
+};
+
+/// ROS 2 message layouts and frame conversions.
+pub mod messages;
+
+use messages::{
+    from_enu, from_flu, to_enu, to_enu_rotation, to_flu, Clock, Header, Image, Imu, Odometry, PointCloud2, PointField,
+    PoseStamped, PoseWithCovariance, RosTime, RosTransform, TfMessage, TransformStamped, Twist, TwistWithCovariance,
+};
+
+/// Plugin for the ROS 2 bridge.
+///
+/// Clients speak the rosbridge v2 JSON protocol over WebSocket, so ROS 2 nodes connect through `rosbridge_client`
+/// style tools and scripts need no ROS install. Messages have the layouts of `sensor_msgs`, `geometry_msgs`,
+/// `nav_msgs` and `tf2_msgs`, the map frame is ENU and drone frames are FLU.
+///
+/// Every drone publishes `/drone{id}/odom`, `/drone{id}/imu`, `/drone{id}/thermal/image_raw` and
+/// `/drone{id}/lidar/points`, the piloted one also `/drone{id}/camera/image_raw`. The bridge publishes `/tf` and
+/// `/clock` and subscribes to `/drone{id}/cmd_vel` and `/drone{id}/setpoint_position`.
+///
+/// The bridge is off, unless it is started with `supersonic --ros-bridge <address>`.
+pub struct RosBridgePlugin;
+
+impl Plugin for RosBridgePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RosBridgeSettings>();
+
+        match RosBridgeSettings::address_from_args(env::args()) {
+            Some(Ok(address)) => app.world.resource_mut::<RosBridgeSettings>().address = Some(address),
+            Some(Err(error)) => error!("wrong ROS bridge address: {error}"),
+            None => {},
+        }
+
+        app
+            .add_systems(Startup, start_ros_bridge)
+            // setpoints are flown in the frame they are sent, so the input log replays them exactly
+            .add_systems(Update, (receive_ros_messages, fly_to_ros_setpoints)
+                .chain()
+                .before(fly_unpiloted_drones))
+            .add_systems(Last, publish_ros_topics);
+    }
+}
+
+/// Gravity in m/s², which the IMU measures at rest.
+const GRAVITY: f32 = 9.81;
+
+/// Largest message, which is accepted from a client, with all of its fragments.
+pub const MAX_MESSAGE_SIZE: usize = 16 << 20;
+
+/// How long a client thread waits for a message, before it writes the queued ones.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Lidar of the drones: `channels` rings over the `vertical_fov` and a point every `horizontal_resolution` radians.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RosLidar {
+    pub channels: usize,
+    pub vertical_fov: f32,
+    pub horizontal_resolution: f32,
+    pub range: f32,
+}
+
+impl Default for RosLidar {
+    fn default() -> Self {
+        Self {
+            channels: 16,
+            vertical_fov: 30.0_f32.to_radians(),
+            horizontal_resolution: 2.0_f32.to_radians(),
+            range: 100.0,
+        }
+    }
+}
+
+// resources
+/// Settings of the ROS bridge.
+///
+/// The bridge listens on `address`, if it is set. State topics are published `state_rate` times
+/// per second, sensor topics `sensor_rate` times. Setpoint positions are flown at up to `max_speed` m/s.
+#[derive(Resource, Debug, Clone)]
+pub struct RosBridgeSettings {
+    pub address: Option<SocketAddr>,
+    pub state_rate: f32,
+    pub sensor_rate: f32,
+    pub thermal_camera: RayCastCamera,
+    pub lidar: RosLidar,
+    pub max_speed: f32,
+}
+
+impl Default for RosBridgeSettings {
+    fn default() -> Self {
+        Self {
+            address: None,
+            state_rate: 20.0,
+            sensor_rate: 5.0,
+            thermal_camera: RayCastCamera {
+                width: 64,
+                height: 48,
+                ..default()
+            },
+            lidar: RosLidar::default(),
+            max_speed: 5.0,
+        }
+    }
+}
+
+impl RosBridgeSettings {
+    /// Reads the address after `--ros-bridge` from the command line arguments.
+    ///
+    /// Returns `None` if there is no `--ros-bridge`, the port 9090 of rosbridge is used if the address has none.
+    pub fn address_from_args(args: impl IntoIterator<Item = String>) -> Option<Result<SocketAddr, AddrParseError>> {
+        let mut args = args.into_iter();
+
+        args.find(|arg| arg == "--ros-bridge")?;
+
+        let address = args.next().unwrap_or_else(|| "127.0.0.1".to_owned());
+
+        Some(address.parse().or_else(|_| format!("{address}:9090").parse()))
+    }
+}
+
+/// Message from a client thread.
+pub enum BridgeMessage {
+    Connected(u32, Sender<Message>),
+    Text(u32, String),
+    Disconnected(u32),
+}
+
+/// Connected client: the channel of its messages and its topics.
+struct BridgeClient {
+    outgoing: Sender<Message>,
+    subscriptions: HashSet<String>,
+}
+
+/// Running ROS bridge.
+#[derive(Resource)]
+pub struct RosBridge {
+    pub address: SocketAddr,
+    messages: Mutex<Receiver<BridgeMessage>>,
+    clients: HashMap<u32, BridgeClient>,
+    /// Seconds since the state and the sensor topics were published.
+    since_state: f32,
+    since_sensors: f32,
+    /// Velocities of the drones, when the IMU was published.
+    imu_velocities: HashMap<u32, Vec3>,
+    scans: u64,
+}
+
+impl RosBridge {
+    /// Starts listening on the address, clients are served in their own threads.
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (id, stream) in (0..).zip(listener.incoming().flatten()) {
+                let sender = sender.clone();
+
+                thread::spawn(move || {
+                    if let Err(error) = serve_client(id, stream, &sender) {
+                        warn!("ROS bridge client: {error}");
+                    }
+
+                    let _ = sender.send(BridgeMessage::Disconnected(id));
+                });
+            }
+        });
+
+        Ok(Self {
+            address,
+            messages: Mutex::new(receiver),
+            clients: HashMap::new(),
+            since_state: f32::INFINITY,
+            since_sensors: f32::INFINITY,
+            imu_velocities: HashMap::new(),
+            scans: 0,
+        })
+    }
+
+    /// Whether any client subscribed to the topic.
+    pub fn is_subscribed(&self, topic: &str) -> bool {
+        self.clients.values().any(|client| client.subscriptions.contains(topic))
+    }
+
+    /// Returns message channels of the clients subscribed to the topic.
+    fn subscribers(&self, topic: &str) -> Vec<Sender<Message>> {
+        self.clients
+            .values()
+            .filter(|client| client.subscriptions.contains(topic))
+            .map(|client| client.outgoing.clone())
+            .collect()
+    }
+
+    /// Publishes the message to the subscribers of the topic.
+    pub fn publish(&self, topic: &str, message: &impl Serialize) {
+        let subscribers = self.subscribers(topic);
+
+        if !subscribers.is_empty() {
+            send_publish(&subscribers, topic, message);
+        }
+    }
+
+    /// Sends the status message to the client, as rosbridge reports errors.
+    fn report(&self, client: u32, level: &str, message: impl Into<String>) {
+        let message = message.into();
+        warn!("ROS bridge client {client}: {message}");
+
+        if let Some(client) = self.clients.get(&client) {
+            let status = json!({ "op": "status", "level": level, "msg": message });
+            let _ = client.outgoing.send(Message::Text(status.to_string()));
+        }
+    }
+}
+
+/// Sends the rosbridge `publish` operation to the clients.
+fn send_publish(subscribers: &[Sender<Message>], topic: &str, message: &impl Serialize) {
+    let text = json!({ "op": "publish", "topic": topic, "msg": message }).to_string();
+
+    for outgoing in subscribers {
+        let _ = outgoing.send(Message::Text(text.clone()));
+    }
+}
+
+// components
+/// Position in the world and heading, which the drone flies to for a ROS client.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct RosPositionSetpoint {
+    pub position: Vec3,
+    pub yaw: f32,
+}
+
+// systems
+/// System that starts the ROS bridge, if it has an address.
+fn start_ros_bridge(
+    mut commands: Commands,
+    settings: Res<RosBridgeSettings>,
+) {
+    let Some(address) = settings.address else {
+        return;
+    };
+
+    match RosBridge::listen(address) {
+        Ok(bridge) => {
+            info!("ROS bridge listens on ws://{}", bridge.address);
+            commands.insert_resource(bridge);
+        },
+        Err(error) => warn!("can't start ROS bridge on {address}: {error}"),
+    }
+}
+
+/// Returns the ID of the drone and the topic name within its namespace.
+fn drone_topic(topic: &str) -> Option<(u32, &str)> {
+    let (id, name) = topic.strip_prefix("/drone")?.split_once('/')?;
+
+    Some((id.parse().ok()?, name))
+}
+
+/// Reads the field of the rosbridge operation.
+fn field<T: DeserializeOwned>(operation: &Value, name: &str) -> Option<T> {
+    serde_json::from_value(operation.get(name)?.clone()).ok()
+}
+
+/// System that handles operations of the clients: subscriptions and published commands.
+///
+/// Commands give drones to the clients, the piloted drone stays with the operator.
+pub fn receive_ros_messages(
+    mut commands: Commands,
+    bridge: Option<ResMut<RosBridge>>,
+    mut setpoints: EventWriter<DroneSetpoint>,
+    mut drones: Query<(Entity, &mut Drone, &Transform, Has<Player>)>,
+) {
+    let Some(mut bridge) = bridge else {
+        return;
+    };
+
+    let messages: Vec<BridgeMessage> = bridge.messages.lock().unwrap().try_iter().collect();
+
+    for message in messages {
+        let (client, text) = match message {
+            BridgeMessage::Connected(client, outgoing) => {
+                bridge.clients.insert(client, BridgeClient { outgoing, subscriptions: HashSet::new() });
+                continue;
+            },
+            BridgeMessage::Disconnected(client) => {
+                bridge.clients.remove(&client);
+                continue;
+            },
+            BridgeMessage::Text(client, text) => (client, text),
+        };
+
+        let Ok(operation) = serde_json::from_str::<Value>(&text) else {
+            bridge.report(client, "error", "operation is not JSON");
+            continue;
+        };
+
+        let op = field::<String>(&operation, "op").unwrap_or_default();
+        let topic = field::<String>(&operation, "topic").unwrap_or_default();
+
+        match op.as_str() {
+            "subscribe" => {
+                if let Some(client) = bridge.clients.get_mut(&client) {
+                    client.subscriptions.insert(topic);
+                }
+            },
+            "unsubscribe" => {
+                if let Some(client) = bridge.clients.get_mut(&client) {
+                    client.subscriptions.remove(&topic);
+                }
+            },
+            "advertise" | "unadvertise" => {},
+            "publish" => {
+                let Some((id, name)) = drone_topic(&topic) else {
+                    bridge.report(client, "warning", format!("can't publish to {topic}"));
+                    continue;
+                };
+
+                let drone = drones.iter_mut().find(|(_, drone, ..)| drone.id == id);
+
+                let Some((entity, mut drone, transform, is_player)) = drone else {
+                    bridge.report(client, "warning", format!("no drone {id}"));
+                    continue;
+                };
+
+                if is_player {
+                    bridge.report(client, "warning", format!("drone {id} is piloted"));
+                    continue;
+                }
+
+                let (heading, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+
+                match name {
+                    "cmd_vel" => {
+                        let Some(twist) = field::<Twist>(&operation, "msg") else {
+                            bridge.report(client, "error", format!("{topic} expects geometry_msgs/Twist"));
+                            continue;
+                        };
+
+                        drone.control = DroneControl::External;
+                        commands.entity(entity).remove::<RosPositionSetpoint>();
+
+                        // the controller turns at twice the heading error
+                        setpoints.send(DroneSetpoint {
+                            id,
+                            setpoint: Some(FlightSetpoint {
+                                velocity: Quat::from_rotation_y(heading) * from_flu(twist.linear.into()),
+                                yaw: Some(heading + twist.angular.z as f32 / 2.0),
+                            }),
+                        });
+                    },
+                    "setpoint_position" => {
+                        let Some(pose) = field::<PoseStamped>(&operation, "msg") else {
+                            bridge.report(client, "error", format!("{topic} expects geometry_msgs/PoseStamped"));
+                            continue;
+                        };
+
+                        let (yaw, _, _) = Quat::from(pose.pose.orientation).to_euler(EulerRot::ZYX);
+
+                        drone.control = DroneControl::External;
+                        // ENU yaw is counterclockwise from the east, the drone looks north without rotation
+                        commands.entity(entity).insert(RosPositionSetpoint {
+                            position: from_enu(pose.pose.position.into()),
+                            yaw: yaw - FRAC_PI_2,
+                        });
+                    },
+                    _ => bridge.report(client, "warning", format!("can't publish to {topic}")),
+                }
+            },
+            op => bridge.report(client, "error", format!("unsupported operation {op}")),
+        }
+    }
+}
+
+/// System that flies the drones to their `RosPositionSetpoint`s.
+pub fn fly_to_ros_setpoints(
+    settings: Res<RosBridgeSettings>,
+    mut setpoints: EventWriter<DroneSetpoint>,
+    drones: Query<(&Drone, &Transform, &RosPositionSetpoint), Without<Player>>,
+) {
+    for (drone, transform, target) in &drones {
+        if drone.control != DroneControl::External {
+            continue;
+        }
+
+        let velocity = (target.position - transform.translation).clamp_length_max(settings.max_speed);
+
+        setpoints.send(DroneSetpoint {
+            id: drone.id,
+            setpoint: Some(FlightSetpoint { velocity, yaw: Some(target.yaw) }),
+        });
+    }
+}
+
+/// Query for the drones, which the bridge publishes.
+type PublishedDroneQuery<'w, 's> = Query<'w, 's,
+    (Entity, &'static Drone, &'static Transform, Option<&'static Velocity>, Has<Player>),
+>;
+
+/// System that publishes the topics, which the clients subscribed to.
+#[allow(clippy::too_many_arguments)]
+pub fn publish_ros_topics(
+    time: Res<Time>,
+    settings: Res<RosBridgeSettings>,
+    bridge: Option<ResMut<RosBridge>>,
+    rapier: Option<Res<RapierContext>>,
+    weather: Option<Res<Weather>>,
+    sky: Option<Res<SkyLighting>>,
+    time_of_day: Option<Res<TimeOfDay>>,
+    mut screenshots: Option<ResMut<ScreenshotManager>>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    drones: PublishedDroneQuery,
+    temperatures: Query<&Temperature>,
+) {
+    let Some(mut bridge) = bridge else {
+        return;
+    };
+
+    if bridge.clients.is_empty() {
+        return;
+    }
+
+    bridge.since_state += time.delta_seconds();
+    bridge.since_sensors += time.delta_seconds();
+
+    let stamp = RosTime::from_seconds(time.elapsed_seconds_f64());
+
+    if bridge.since_state >= 1.0 / settings.state_rate {
+        let period = bridge.since_state;
+        bridge.since_state = 0.0;
+
+        bridge.publish("/clock", &Clock { clock: stamp });
+
+        let mut transforms = Vec::new();
+
+        for (_, drone, transform, velocity, _) in &drones {
+            let base = format!("drone{}/base_link", drone.id);
+            let rotation = to_enu_rotation(transform.rotation);
+            let linear = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+            let angular = velocity.map_or(Vec3::ZERO, |velocity| velocity.angvel);
+            let body_linear = to_flu(transform.rotation.inverse() * linear);
+            let body_angular = to_flu(transform.rotation.inverse() * angular);
+
+            let pose = PoseWithCovariance {
+                pose: messages::Pose {
+                    position: to_enu(transform.translation).into(),
+                    orientation: rotation.into(),
+                },
+                covariance: vec![0.0; 36],
+            };
+
+            bridge.publish(&format!("/drone{}/odom", drone.id), &Odometry {
+                header: Header::new(stamp, "map"),
+                child_frame_id: base.clone(),
+                pose,
+                twist: TwistWithCovariance {
+                    twist: Twist { linear: body_linear.into(), angular: body_angular.into() },
+                    covariance: vec![0.0; 36],
+                },
+            });
+
+            let previous = bridge.imu_velocities.insert(drone.id, linear).unwrap_or(linear);
+            let acceleration = (linear - previous) / period.max(f32::EPSILON);
+
+            bridge.publish(&format!("/drone{}/imu", drone.id), &Imu {
+                header: Header::new(stamp, base.clone()),
+                orientation: rotation.into(),
+                angular_velocity: body_angular.into(),
+                // the accelerometer measures the specific force
+                linear_acceleration: to_flu(transform.rotation.inverse() * (acceleration + Vec3::Y * GRAVITY)).into(),
+                ..default()
+            });
+
+            transforms.push(TransformStamped {
+                header: Header::new(stamp, "map"),
+                child_frame_id: base.clone(),
+                transform: RosTransform {
+                    translation: to_enu(transform.translation).into(),
+                    rotation: rotation.into(),
+                },
+            });
+            transforms.push(TransformStamped {
+                header: Header::new(stamp, base),
+                child_frame_id: format!("drone{}/thermal", drone.id),
+                transform: RosTransform {
+                    translation: Vec3::ZERO.into(),
+                    // pitched down around the left axis
+                    rotation: Quat::from_rotation_y(settings.thermal_camera.pitch).into(),
+                },
+            });
+        }
+
+        bridge.publish("/tf", &TfMessage { transforms });
+    }
+
+    if bridge.since_sensors < 1.0 / settings.sensor_rate {
+        return;
+    }
+
+    bridge.since_sensors = 0.0;
+    bridge.scans += 1;
+
+    let air_temperature = match (sky, time_of_day) {
+        (Some(sky), Some(time_of_day)) => sky.air_temperature_at(time_of_day.hours),
+        _ => 15.0,
+    };
+
+    for (entity, drone, transform, _, is_player) in &drones {
+        let thermal_topic = format!("/drone{}/thermal/image_raw", drone.id);
+        let lidar_topic = format!("/drone{}/lidar/points", drone.id);
+        let camera_topic = format!("/drone{}/camera/image_raw", drone.id);
+        let filter = QueryFilter::default().exclude_collider(entity);
+
+        if let (Some(rapier), true) = (&rapier, bridge.is_subscribed(&thermal_topic)) {
+            let camera = &settings.thermal_camera;
+
+            let pixels: Vec<u8> = camera
+                .ray_directions(camera.rotation(transform.rotation))
+                .map(|direction| match rapier.cast_ray(transform.translation, direction, camera.range, true, filter) {
+                    Some((hit, _)) => temperatures.get(hit).map_or(air_temperature, |temperature| temperature.0),
+                    None => SKY_TEMPERATURE,
+                })
+                .flat_map(f32::to_le_bytes)
+                .collect();
+
+            bridge.publish(&thermal_topic, &Image {
+                header: Header::new(stamp, format!("drone{}/thermal", drone.id)),
+                height: camera.height as u32,
+                width: camera.width as u32,
+                // temperatures in °C
+                encoding: "32FC1".to_owned(),
+                is_bigendian: 0,
+                step: camera.width as u32 * 4,
+                data: BASE64.encode(&pixels),
+            });
+        }
+
+        if let (Some(rapier), true) = (&rapier, bridge.is_subscribed(&lidar_topic)) {
+            let mut rng = GeneratorRng::new(bridge.scans ^ (u64::from(drone.id) << 32));
+            let points = scan_lidar(&settings.lidar, rapier, transform, filter, weather.as_deref(), &mut rng);
+
+            let data: Vec<u8> = points.iter().flat_map(|point| point.to_array()).flat_map(f32::to_le_bytes).collect();
+
+            bridge.publish(&lidar_topic, &PointCloud2 {
+                header: Header::new(stamp, format!("drone{}/base_link", drone.id)),
+                height: 1,
+                width: points.len() as u32,
+                fields: vec![
+                    PointField::float32("x", 0),
+                    PointField::float32("y", 4),
+                    PointField::float32("z", 8),
+                    PointField::float32("intensity", 12),
+                ],
+                is_bigendian: false,
+                point_step: 16,
+                row_step: points.len() as u32 * 16,
+                data: BASE64.encode(&data),
+                is_dense: true,
+            });
+        }
+
+        let window = windows.iter().next();
+
+        if let (true, Some(window), Some(screenshots)) = (is_player, window, screenshots.as_mut()) {
+            let subscribers = bridge.subscribers(&camera_topic);
+
+            if subscribers.is_empty() {
+                continue;
+            }
+
+            let frame_id = format!("drone{}/camera", drone.id);
+
+            // the frame of the previous screenshot is still being read
+            let _ = screenshots.take_screenshot(window, move |image| {
+                let Ok(image) = image.try_into_dynamic() else {
+                    return;
+                };
+
+                let image = image.to_rgb8();
+
+                send_publish(&subscribers, &camera_topic, &Image {
+                    header: Header::new(stamp, frame_id),
+                    height: image.height(),
+                    width: image.width(),
+                    encoding: "rgb8".to_owned(),
+                    is_bigendian: 0,
+                    step: image.width() * 3,
+                    data: BASE64.encode(image.as_raw()),
+                });
+            });
+        }
+    }
+}
+
+/// Scans the surroundings of the drone with the lidar.
+///
+/// Returns points in the FLU frame of the drone with their intensity. Fog, rain and snow lose pulses and add
+/// range noise.
+fn scan_lidar(
+    lidar: &RosLidar,
+    rapier: &RapierContext,
+    transform: &Transform,
+    filter: QueryFilter,
+    weather: Option<&Weather>,
+    rng: &mut GeneratorRng,
+) -> Vec<Vec4> {
+    let steps = (TAU / lidar.horizontal_resolution).round() as usize;
+    let mut points = Vec::new();
+
+    for channel in 0..lidar.channels {
+        let elevation = if lidar.channels > 1 {
+            lidar.vertical_fov * (channel as f32 / (lidar.channels - 1) as f32 - 0.5)
+        } else {
+            0.0
+        };
+
+        for step in 0..steps {
+            let azimuth = step as f32 * lidar.horizontal_resolution;
+            let local = Quat::from_euler(EulerRot::YXZ, azimuth, elevation, 0.0) * Vec3::NEG_Z;
+            let direction = transform.rotation * local;
+
+            let hit = rapier.cast_ray(transform.translation, direction, lidar.range, true, filter);
+
+            let Some((_, distance)) = hit else {
+                continue;
+            };
+
+            let probability = weather.map_or(1.0, |weather| weather.lidar_return_probability(distance));
+
+            if rng.next() >= probability {
+                continue;
+            }
+
+            // uniform noise with the standard deviation of the weather
+            let deviation = weather.map_or(0.0, Weather::lidar_range_noise);
+            let noise = deviation * 3.0_f32.sqrt() * rng.range(-1.0, 1.0);
+
+            points.push(to_flu(local * (distance + noise)).extend(probability));
+        }
+    }
+
+    points
+}
+
+/// Answers the handshake of the client, then reads its operations and writes its messages.
+///
+/// A message larger than `MAX_MESSAGE_SIZE` closes the connection with the 1009 code.
+fn serve_client(id: u32, stream: TcpStream, messages: &Sender<BridgeMessage>) -> Result<(), Box<tungstenite::Error>> {
+    let config = WebSocketConfig {
+        max_message_size: Some(MAX_MESSAGE_SIZE),
+        max_frame_size: Some(MAX_MESSAGE_SIZE),
+        ..default()
+    };
+
+    let mut socket = accept_with_config(stream, Some(config)).map_err(|error| match error {
+        HandshakeError::Failure(error) => error,
+        HandshakeError::Interrupted(_) => io::Error::from(io::ErrorKind::WouldBlock).into(),
+    })?;
+
+    // reads time out, so the queued messages are written while the client is silent
+    socket.get_mut().set_read_timeout(Some(POLL_INTERVAL)).map_err(tungstenite::Error::Io)?;
+
+    let (outgoing, queued) = mpsc::channel();
+    let _ = messages.send(BridgeMessage::Connected(id, outgoing));
+
+    loop {
+        for message in queued.try_iter() {
+            socket.write(message)?;
+        }
+
+        socket.flush()?;
+
+        // pings are answered and closes are confirmed by the socket itself
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let _ = messages.send(BridgeMessage::Text(id, text));
+            },
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(error))
+                if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {},
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(tungstenite::Error::Capacity(error)) => {
+                let _ = socket.close(Some(CloseFrame { code: CloseCode::Size, reason: error.to_string().into() }));
+                let _ = socket.flush();
+
+                return Err(Box::new(tungstenite::Error::Capacity(error)));
+            },
+            Err(error) => return Err(Box::new(error)),
+        }
+    }
+}
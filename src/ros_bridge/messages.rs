@@ -0,0 +1,264 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Converts the world vector into the east, north and up (ENU) frame of ROS maps.
+///
+/// North is -Z, east is +X.
+pub fn to_enu(vector: Vec3) -> Vec3 {
+    Vec3::new(vector.x, -vector.z, vector.y)
+}
+
+/// Converts the ENU vector into the world frame.
+pub fn from_enu(vector: Vec3) -> Vec3 {
+    Vec3::new(vector.x, vector.z, -vector.y)
+}
+
+/// Converts the vector of the drone into its forward, left and up (FLU) frame of ROS bodies.
+///
+/// The drone looks along -Z.
+pub fn to_flu(vector: Vec3) -> Vec3 {
+    Vec3::new(-vector.z, -vector.x, vector.y)
+}
+
+/// Converts the FLU vector into the frame of the drone.
+pub fn from_flu(vector: Vec3) -> Vec3 {
+    Vec3::new(-vector.y, vector.z, -vector.x)
+}
+
+/// Converts the drone rotation into the rotation from its FLU frame to the ENU frame.
+pub fn to_enu_rotation(rotation: Quat) -> Quat {
+    let world = Mat3::from_cols(to_enu(Vec3::X), to_enu(Vec3::Y), to_enu(Vec3::Z));
+    let body = Mat3::from_cols(to_flu(Vec3::X), to_flu(Vec3::Y), to_flu(Vec3::Z));
+
+    Quat::from_mat3(&(world * Mat3::from_quat(rotation) * body.transpose())).normalize()
+}
+
+/// `builtin_interfaces/Time`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RosTime {
+    pub sec: i32,
+    pub nanosec: u32,
+}
+
+impl RosTime {
+    /// Creates the time from seconds since the start.
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self {
+            sec: seconds.floor() as i32,
+            nanosec: (seconds.fract() * 1e9) as u32,
+        }
+    }
+}
+
+/// `std_msgs/Header`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Header {
+    pub stamp: RosTime,
+    pub frame_id: String,
+}
+
+impl Header {
+    /// Creates the header of the message in the frame.
+    pub fn new(stamp: RosTime, frame_id: impl Into<String>) -> Self {
+        Self {
+            stamp,
+            frame_id: frame_id.into(),
+        }
+    }
+}
+
+/// `geometry_msgs/Vector3` and `geometry_msgs/Point`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Vector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<Vec3> for Vector3 {
+    fn from(vector: Vec3) -> Self {
+        Self {
+            x: vector.x.into(),
+            y: vector.y.into(),
+            z: vector.z.into(),
+        }
+    }
+}
+
+impl From<Vector3> for Vec3 {
+    fn from(vector: Vector3) -> Self {
+        Vec3::new(vector.x as f32, vector.y as f32, vector.z as f32)
+    }
+}
+
+/// `geometry_msgs/Quaternion`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quaternion {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub w: f64,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quat::IDENTITY.into()
+    }
+}
+
+impl From<Quat> for Quaternion {
+    fn from(rotation: Quat) -> Self {
+        Self {
+            x: rotation.x.into(),
+            y: rotation.y.into(),
+            z: rotation.z.into(),
+            w: rotation.w.into(),
+        }
+    }
+}
+
+impl From<Quaternion> for Quat {
+    fn from(rotation: Quaternion) -> Self {
+        Quat::from_xyzw(rotation.x as f32, rotation.y as f32, rotation.z as f32, rotation.w as f32)
+    }
+}
+
+/// `geometry_msgs/Pose`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pose {
+    pub position: Vector3,
+    pub orientation: Quaternion,
+}
+
+/// `geometry_msgs/PoseStamped`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoseStamped {
+    pub header: Header,
+    pub pose: Pose,
+}
+
+/// `geometry_msgs/Twist`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Twist {
+    pub linear: Vector3,
+    pub angular: Vector3,
+}
+
+/// `geometry_msgs/PoseWithCovariance`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoseWithCovariance {
+    pub pose: Pose,
+    pub covariance: Vec<f64>,
+}
+
+/// `geometry_msgs/TwistWithCovariance`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TwistWithCovariance {
+    pub twist: Twist,
+    pub covariance: Vec<f64>,
+}
+
+/// `nav_msgs/Odometry`: the pose in the map frame and the twist in the body frame.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Odometry {
+    pub header: Header,
+    pub child_frame_id: String,
+    pub pose: PoseWithCovariance,
+    pub twist: TwistWithCovariance,
+}
+
+/// `sensor_msgs/Imu`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Imu {
+    pub header: Header,
+    pub orientation: Quaternion,
+    pub orientation_covariance: [f64; 9],
+    pub angular_velocity: Vector3,
+    pub angular_velocity_covariance: [f64; 9],
+    pub linear_acceleration: Vector3,
+    pub linear_acceleration_covariance: [f64; 9],
+}
+
+/// `sensor_msgs/Image`, `data` is base64 as rosbridge encodes `uint8[]`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Image {
+    pub header: Header,
+    pub height: u32,
+    pub width: u32,
+    pub encoding: String,
+    pub is_bigendian: u8,
+    pub step: u32,
+    pub data: String,
+}
+
+/// `sensor_msgs/PointField`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointField {
+    pub name: String,
+    pub offset: u32,
+    pub datatype: u8,
+    pub count: u32,
+}
+
+impl PointField {
+    /// `PointField.FLOAT32`.
+    pub const FLOAT32: u8 = 7;
+
+    /// Creates the float field at the offset in bytes.
+    pub fn float32(name: &str, offset: u32) -> Self {
+        Self {
+            name: name.to_owned(),
+            offset,
+            datatype: Self::FLOAT32,
+            count: 1,
+        }
+    }
+}
+
+/// `sensor_msgs/PointCloud2`, `data` is base64 as rosbridge encodes `uint8[]`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointCloud2 {
+    pub header: Header,
+    pub height: u32,
+    pub width: u32,
+    pub fields: Vec<PointField>,
+    pub is_bigendian: bool,
+    pub point_step: u32,
+    pub row_step: u32,
+    pub data: String,
+    pub is_dense: bool,
+}
+
+/// `geometry_msgs/Transform`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RosTransform {
+    pub translation: Vector3,
+    pub rotation: Quaternion,
+}
+
+/// `geometry_msgs/TransformStamped`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransformStamped {
+    pub header: Header,
+    pub child_frame_id: String,
+    pub transform: RosTransform,
+}
+
+/// `tf2_msgs/TFMessage`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TfMessage {
+    pub transforms: Vec<TransformStamped>,
+}
+
+/// `rosgraph_msgs/Clock`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Clock {
+    pub clock: RosTime,
+}
@@ -0,0 +1,627 @@
+use bevy::{prelude::*, utils::HashSet, window::PrimaryWindow};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    agents::{spawn_agent, Agent, AgentBehaviour, AgentKind, DroneReaction},
+    camera::MainCamera,
+    fleet::Drone,
+    materials::ThermalMaterial,
+    player::{Battery, Player},
+    replay::Replay,
+    sky::{SkyLighting, TimeOfDay},
+    terrain::Terrain,
+    ui::menu::AppState,
+    world::generator::{GeneratedObject, GeneratorRng},
+};
+
+/// Plugin for the search-and-rescue mission.
+///
+/// Targets with heat signatures are hidden in the search area, the operator finds them with the drone camera
+/// and marks them. When every target is found or the time is over, the debrief screen shows the score.
+pub struct SearchAndRescuePlugin;
+
+impl Plugin for SearchAndRescuePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SearchMission>()
+            .init_resource::<SearchMissionProgress>()
+            .add_event::<StartSearchMission>()
+            .add_event::<TargetMark>()
+            .add_systems(Startup, setup_search_hud)
+            .add_systems(Update, (
+                search_mission_input.run_if(in_state(AppState::InFlight)),
+                start_search_mission,
+                process_target_marks,
+                track_search_progress,
+                update_search_hud,
+                show_debrief,
+            ).chain());
+    }
+}
+
+// resources
+/// Describes the search-and-rescue mission.
+///
+/// `target_count` targets are hidden within `area_radius` meters around `area_center`, `hidden_share` of them under the trees.
+/// The mission lasts up to `time_limit` seconds.
+///
+/// A mark counts as a detection if it is closer than `mark_tolerance` meters to a target.
+/// The area is split into `coverage_cell` meter cells, a cell is covered when it was in the camera footprint,
+/// which is a circle under the drone with `footprint_half_angle` radians half-angle.
+///
+/// Press `start_key` to start the mission, `mark_key` to mark the point at the center of the camera
+/// or click to mark the point under the cursor.
+#[derive(Resource, Debug, Clone)]
+pub struct SearchMission {
+    pub seed: u64,
+    pub area_center: Vec2,
+    pub area_radius: f32,
+    pub target_count: u32,
+    pub hidden_share: f32,
+    pub time_limit: f32,
+    pub mark_tolerance: f32,
+    pub coverage_cell: f32,
+    pub footprint_half_angle: f32,
+    pub start_key: KeyCode,
+    pub mark_key: KeyCode,
+}
+
+impl Default for SearchMission {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            area_center: Vec2::ZERO,
+            area_radius: 150.0,
+            target_count: 5,
+            hidden_share: 0.5,
+            time_limit: 600.0,
+            mark_tolerance: 6.0,
+            coverage_cell: 10.0,
+            footprint_half_angle: 30.0_f32.to_radians(),
+            start_key: KeyCode::KeyN,
+            mark_key: KeyCode::KeyM,
+        }
+    }
+}
+
+impl SearchMission {
+    /// Returns cells of the search area.
+    pub fn area_cells(&self) -> HashSet<IVec2> {
+        let cell = self.coverage_cell.max(f32::EPSILON);
+        let count = (self.area_radius / cell).ceil() as i32;
+
+        (-count..=count)
+            .flat_map(|z| (-count..=count).map(move |x| IVec2::new(x, z)))
+            .map(|offset| self.cell_at(self.area_center) + offset)
+            .filter(|cell| self.cell_center(*cell).distance(self.area_center) <= self.area_radius)
+            .collect()
+    }
+
+    /// Returns the cell, which contains the point.
+    pub fn cell_at(&self, position: Vec2) -> IVec2 {
+        (position / self.coverage_cell.max(f32::EPSILON)).floor().as_ivec2()
+    }
+
+    /// Returns the center of the cell.
+    pub fn cell_center(&self, cell: IVec2) -> Vec2 {
+        (cell.as_vec2() + 0.5) * self.coverage_cell
+    }
+}
+
+/// Status of the mission.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MissionStatus {
+    #[default]
+    Idle,
+    Active,
+    Debrief,
+}
+
+/// Progress of the current search-and-rescue mission.
+///
+/// Times are in seconds since the start of the mission, distances are in meters.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SearchMissionProgress {
+    pub status: MissionStatus,
+    pub started_at: f32,
+    pub elapsed: f32,
+    pub target_count: u32,
+    /// Times and positions of the detections in the order they happened.
+    pub detections: Vec<(f32, Vec2)>,
+    pub false_marks: u32,
+    pub area_cells: HashSet<IVec2>,
+    pub covered_cells: HashSet<IVec2>,
+    pub start_position: Vec2,
+    pub last_position: Option<Vec3>,
+    pub path_length: f32,
+    pub battery_at_start: f32,
+    pub battery_used: f32,
+}
+
+impl SearchMissionProgress {
+    /// Returns the score of the mission.
+    pub fn score(&self) -> SearchScore {
+        let times: Vec<f32> = self.detections.iter().map(|(time, _)| *time).collect();
+
+        // the shortest path visits the targets in the order they were found
+        let ideal_length: f32 = std::iter::once(self.start_position)
+            .chain(self.detections.iter().map(|(_, position)| *position))
+            .collect::<Vec<Vec2>>()
+            .windows(2)
+            .map(|pair| pair[0].distance(pair[1]))
+            .sum();
+
+        let path_efficiency = if self.detections.is_empty() {
+            0.0
+        } else {
+            (ideal_length / self.path_length.max(f32::EPSILON)).clamp(0.0, 1.0)
+        };
+
+        SearchScore {
+            found: self.detections.len() as u32,
+            target_count: self.target_count,
+            mean_time_to_detect: if times.is_empty() { None } else { Some(times.iter().sum::<f32>() / times.len() as f32) },
+            last_detection: times.last().copied(),
+            false_marks: self.false_marks,
+            area_covered: self.covered_cells.len() as f32 / self.area_cells.len().max(1) as f32,
+            battery_used: self.battery_used,
+            path_efficiency,
+        }
+    }
+}
+
+/// Score of the search-and-rescue mission.
+///
+/// `area_covered` and `path_efficiency` are from 0.0 to 1.0, `battery_used` is in watt-hours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchScore {
+    pub found: u32,
+    pub target_count: u32,
+    pub mean_time_to_detect: Option<f32>,
+    pub last_detection: Option<f32>,
+    pub false_marks: u32,
+    pub area_covered: f32,
+    pub battery_used: f32,
+    pub path_efficiency: f32,
+}
+
+impl SearchScore {
+    /// Returns total points from 0 to 100.
+    ///
+    /// Found targets give up to 60 points, coverage and path efficiency give up to 20 points each,
+    /// every false mark takes 5 points.
+    pub fn points(&self) -> f32 {
+        let found = self.found as f32 / self.target_count.max(1) as f32;
+        let points = found * 60.0 + self.area_covered * 20.0 + self.path_efficiency * 20.0 - self.false_marks as f32 * 5.0;
+
+        points.clamp(0.0, 100.0)
+    }
+}
+
+// events
+/// Event that starts a new search-and-rescue mission.
+#[derive(Event, Default)]
+pub struct StartSearchMission;
+
+/// Event of the operator marking a point as a target.
+#[derive(Event)]
+pub struct TargetMark {
+    pub point: Vec3,
+}
+
+// components
+/// Describes a target of the search-and-rescue mission.
+#[derive(Component, Default)]
+pub struct SearchTarget {
+    pub found: bool,
+}
+
+/// Describes an entity of the mission, which is removed when the next mission starts.
+#[derive(Component)]
+pub struct MissionEntity;
+
+/// Describes the mission HUD text.
+#[derive(Component)]
+struct SearchHudText;
+
+/// Describes the debrief screen.
+#[derive(Component)]
+struct DebriefScreen;
+
+// systems
+/// System that starts missions and turns operator input into `TargetMark`s.
+///
+/// Marked point is the first hit of the ray from the camera through the cursor or through the center of the screen.
+/// Replays play the recorded marks instead.
+#[allow(clippy::too_many_arguments)]
+fn search_mission_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    replay: Option<Res<Replay>>,
+    mission: Res<SearchMission>,
+    progress: Res<SearchMissionProgress>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    players: Query<Entity, With<Player>>,
+    rapier_context: Res<RapierContext>,
+    mut start: EventWriter<StartSearchMission>,
+    mut marks: EventWriter<TargetMark>,
+) {
+    if keys.just_pressed(mission.start_key) && progress.status != MissionStatus::Active {
+        start.send_default();
+    }
+
+    if progress.status != MissionStatus::Active || replay.is_some() {
+        return;
+    }
+
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+
+    let screen_point = if mouse.just_pressed(MouseButton::Left) {
+        windows.get_single().ok().and_then(|window| window.cursor_position())
+    } else if keys.just_pressed(mission.mark_key) {
+        camera.logical_viewport_size().map(|size| size / 2.0)
+    } else {
+        None
+    };
+
+    let Some(ray) = screen_point.and_then(|point| camera.viewport_to_world(camera_transform, point)) else {
+        return;
+    };
+
+    let mut filter = QueryFilter::default().exclude_sensors();
+    if let Ok(player) = players.get_single() {
+        filter = filter.exclude_collider(player);
+    }
+
+    if let Some((_, toi)) = rapier_context.cast_ray(ray.origin, *ray.direction, 5000.0, true, filter) {
+        marks.send(TargetMark { point: ray.get_point(toi) });
+    }
+}
+
+/// System that hides the targets and resets the progress when a mission starts.
+///
+/// Targets are people, who need help, so they don't move and don't react to the drone.
+#[allow(clippy::too_many_arguments)]
+pub fn start_search_mission(
+    mut commands: Commands,
+    mut events: EventReader<StartSearchMission>,
+    time: Res<Time>,
+    mission: Res<SearchMission>,
+    mut progress: ResMut<SearchMissionProgress>,
+    terrain: Option<Res<Terrain>>,
+    sky: Option<Res<SkyLighting>>,
+    time_of_day: Option<Res<TimeOfDay>>,
+    mission_entities: Query<Entity, With<MissionEntity>>,
+    trees: Query<(&Transform, &GeneratedObject)>,
+    players: Query<(&Transform, Option<&Battery>), With<Player>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut thermal_materials: ResMut<Assets<ThermalMaterial>>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    for entity in &mission_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let mut rng = GeneratorRng::new(mission.seed);
+
+    let trees: Vec<Vec2> = trees
+        .iter()
+        .filter(|(transform, object)| {
+            **object == GeneratedObject::Tree
+                && transform.translation.xz().distance(mission.area_center) <= mission.area_radius
+        })
+        .map(|(transform, _)| transform.translation.xz())
+        .collect();
+
+    let air_temperature = match (sky, time_of_day) {
+        (Some(sky), Some(time_of_day)) => sky.air_temperature_at(time_of_day.hours),
+        _ => 15.0,
+    };
+
+    for index in 0..mission.target_count {
+        let is_hidden = !trees.is_empty() && rng.chance(mission.hidden_share);
+
+        let position = if is_hidden {
+            let tree = trees[(rng.next() * trees.len() as f32) as usize % trees.len()];
+            tree + Vec2::from_angle(rng.range(0.0, std::f32::consts::TAU)) * 0.6
+        } else {
+            let angle = rng.range(0.0, std::f32::consts::TAU);
+            // uniform over the area, not crowded in the center
+            mission.area_center + Vec2::from_angle(angle) * mission.area_radius * rng.next().sqrt()
+        };
+
+        let height = terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position));
+        let behaviour = AgentBehaviour::FollowPath { waypoints: vec![position], looped: false };
+        let agent = Agent::new(AgentKind::Pedestrian, behaviour, mission.seed.wrapping_add(index as u64))
+            .with_reaction(DroneReaction::Ignore);
+
+        let entity = spawn_agent(
+            &mut commands,
+            &mut meshes,
+            &mut thermal_materials,
+            agent,
+            Vec3::new(position.x, height, position.y),
+            air_temperature,
+        );
+
+        commands.entity(entity).insert((SearchTarget::default(), MissionEntity, Name::new("Search target")));
+    }
+
+    let (start_position, battery) = players
+        .get_single()
+        .map_or((Vec3::ZERO, 0.0), |(transform, battery)| {
+            (transform.translation, battery.map_or(0.0, |battery| battery.remaining))
+        });
+
+    *progress = SearchMissionProgress {
+        status: MissionStatus::Active,
+        started_at: time.elapsed_seconds(),
+        target_count: mission.target_count,
+        area_cells: mission.area_cells(),
+        start_position: start_position.xz(),
+        last_position: Some(start_position),
+        battery_at_start: battery,
+        ..default()
+    };
+}
+
+/// System that checks `TargetMark`s of the active mission.
+///
+/// Every mark leaves a marker: green for a found target and red for a false mark.
+pub fn process_target_marks(
+    mut commands: Commands,
+    mut events: EventReader<TargetMark>,
+    mission: Res<SearchMission>,
+    mut progress: ResMut<SearchMissionProgress>,
+    mut targets: Query<(&mut SearchTarget, &Transform)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for mark in events.read() {
+        if progress.status != MissionStatus::Active {
+            continue;
+        }
+
+        let point = mark.point.xz();
+
+        let nearest = targets
+            .iter_mut()
+            .filter(|(target, transform)| !target.found && transform.translation.xz().distance(point) <= mission.mark_tolerance)
+            .min_by(|(_, a), (_, b)| {
+                a.translation.xz().distance_squared(point).total_cmp(&b.translation.xz().distance_squared(point))
+            });
+
+        let color = match nearest {
+            Some((mut target, transform)) => {
+                target.found = true;
+                let elapsed = progress.elapsed;
+                progress.detections.push((elapsed, transform.translation.xz()));
+                Color::GREEN
+            },
+            None => {
+                progress.false_marks += 1;
+                Color::RED
+            },
+        };
+
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Cylinder::new(1.0, 0.2)),
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    emissive: color,
+                    ..default()
+                }),
+                transform: Transform::from_translation(mark.point + Vec3::Y * 0.1),
+                ..default()
+            },
+            MissionEntity,
+            Name::new("Target mark"),
+        ));
+    }
+}
+
+/// System that measures the flight of the `Player` during the mission and finishes it.
+///
+/// The area is searched cooperatively: cells under every drone of the fleet are covered.
+/// The mission is finished when all of the targets are found or the time is over.
+pub fn track_search_progress(
+    time: Res<Time>,
+    mission: Res<SearchMission>,
+    mut progress: ResMut<SearchMissionProgress>,
+    terrain: Option<Res<Terrain>>,
+    players: Query<(&Transform, Option<&Battery>), With<Player>>,
+    drones: Query<&Transform, (With<Drone>, Without<Player>)>,
+) {
+    if progress.status != MissionStatus::Active {
+        return;
+    }
+
+    progress.elapsed = time.elapsed_seconds() - progress.started_at;
+
+    if let Ok((transform, battery)) = players.get_single() {
+        let position = transform.translation;
+
+        if let Some(last_position) = progress.last_position {
+            progress.path_length += last_position.distance(position);
+        }
+        progress.last_position = Some(position);
+
+        if let Some(battery) = battery {
+            progress.battery_used = (progress.battery_at_start - battery.remaining).max(0.0);
+        }
+    }
+
+    let positions: Vec<Vec3> = players
+        .iter()
+        .map(|(transform, _)| transform.translation)
+        .chain(drones.iter().map(|transform| transform.translation))
+        .collect();
+
+    for position in positions {
+        // camera footprint on the ground
+        let ground = terrain.as_ref().map_or(0.0, |terrain| terrain.height_at(position.xz()));
+        let footprint = (position.y - ground).max(0.0) * mission.footprint_half_angle.tan();
+        let reach = (footprint / mission.coverage_cell.max(f32::EPSILON)).ceil() as i32;
+        let center = mission.cell_at(position.xz());
+
+        for z in -reach..=reach {
+            for x in -reach..=reach {
+                let cell = center + IVec2::new(x, z);
+
+                if mission.cell_center(cell).distance(position.xz()) <= footprint && progress.area_cells.contains(&cell) {
+                    progress.covered_cells.insert(cell);
+                }
+            }
+        }
+    }
+
+    let is_all_found = progress.detections.len() as u32 >= progress.target_count;
+
+    if is_all_found || progress.elapsed >= mission.time_limit {
+        progress.status = MissionStatus::Debrief;
+    }
+}
+
+/// Mission HUD initialization.
+fn setup_search_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                font_size: 15.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        SearchHudText,
+    ));
+}
+
+/// System that shows the mission progress in the HUD.
+fn update_search_hud(
+    mission: Res<SearchMission>,
+    progress: Res<SearchMissionProgress>,
+    mut query: Query<&mut Text, With<SearchHudText>>,
+) {
+    let value = match progress.status {
+        MissionStatus::Active => {
+            let score = progress.score();
+            let left = (mission.time_limit - progress.elapsed).max(0.0);
+
+            format!(
+                "Search and rescue\nTargets: {}/{}\nTime left: {:02}:{:02}\nCovered: {:.0}%\nFalse marks: {}",
+                score.found,
+                score.target_count,
+                left as u32 / 60,
+                left as u32 % 60,
+                score.area_covered * 100.0,
+                score.false_marks,
+            )
+        },
+        _ => String::new(),
+    };
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
+
+/// System that shows the debrief screen when the mission is finished and hides it when the next one starts.
+fn show_debrief(
+    mut commands: Commands,
+    mission: Res<SearchMission>,
+    progress: Res<SearchMissionProgress>,
+    screens: Query<Entity, With<DebriefScreen>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !progress.is_changed() {
+        return;
+    }
+
+    let is_shown = !screens.is_empty();
+
+    if progress.status != MissionStatus::Debrief {
+        for entity in &screens {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    if is_shown {
+        return;
+    }
+
+    let score = progress.score();
+    let format_time = |time: Option<f32>| time.map_or("-".to_owned(), |time| format!("{time:.1} s"));
+
+    let text = format!(
+        "Debrief\n\nTargets found: {}/{}\nMean time to detect: {}\nLast detection: {}\nFalse marks: {}\nArea covered: {:.0}%\nBattery used: {:.1} Wh\nPath efficiency: {:.0}%\n\nScore: {:.0}/100\n\nPress {:?} to start again",
+        score.found,
+        score.target_count,
+        format_time(score.mean_time_to_detect),
+        format_time(score.last_detection),
+        score.false_marks,
+        score.area_covered * 100.0,
+        score.battery_used,
+        score.path_efficiency * 100.0,
+        score.points(),
+        mission.start_key,
+    );
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            DebriefScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(15.0)),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.15, 0.15, 0.15, 0.9).into(),
+                    border_color: Color::WHITE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        text,
+                        TextStyle {
+                            font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        });
+}
@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeSystem};
+use bevy_rapier3d::prelude::*;
+
+use crate::{replay::Replay, ui::menu::AppState};
+
+/// Plugin for the simulation clock.
+///
+/// `SimClock` drives the virtual time and the Rapier timestep together, so every system using `Time` pauses,
+/// slows down and speeds up with the physics. Replays play the recorded speed of every frame instead, so the keys
+/// work only in the flight, which isn't replayed.
+pub struct SimClockPlugin;
+
+impl Plugin for SimClockPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SimClock>()
+            .init_resource::<SimClockBindings>()
+            .add_systems(First, (
+                apply_sim_clock.before(TimeSystem),
+                advance_sim_step.after(TimeSystem),
+            ).run_if(not(resource_exists::<Replay>)))
+            .add_systems(Update, sim_clock_controls
+                .run_if(in_state(AppState::InFlight))
+                .run_if(not(resource_exists::<Replay>)));
+    }
+}
+
+/// Slowest speed of the simulation.
+pub const MIN_SPEED: f32 = 0.1;
+/// Fastest speed of the simulation.
+pub const MAX_SPEED: f32 = 10.0;
+
+/// Speeds, which are switched by the `SimClockBindings`.
+const SPEEDS: [f32; 7] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 10.0];
+
+// resources
+/// Clock of the simulation.
+///
+/// The simulation runs `speed` times as fast as the real time. While it `is_paused`, it runs `steps` more
+/// frames of `step` each. `frame` counts the frames, which the simulation ran.
+///
+/// Physics steps last at most `physics_step` seconds of the simulation, so the fast-forward takes more of them.
+#[derive(Resource, Debug, Clone)]
+pub struct SimClock {
+    pub is_paused: bool,
+    pub speed: f32,
+    pub steps: u32,
+    pub step: Duration,
+    pub physics_step: f32,
+    pub frame: u64,
+    /// Whether the current frame is a single step of the paused simulation.
+    is_stepping: bool,
+    /// Running state and speed, which were applied to the time and the physics.
+    applied: Option<(bool, f32)>,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self {
+            is_paused: false,
+            speed: 1.0,
+            steps: 0,
+            step: Duration::from_secs_f64(1.0 / 60.0),
+            physics_step: 1.0 / 60.0,
+            frame: 0,
+            is_stepping: false,
+            applied: None,
+        }
+    }
+}
+
+impl SimClock {
+    /// Pauses the simulation, cancelling the remaining steps.
+    pub fn pause(&mut self) {
+        self.is_paused = true;
+        self.steps = 0;
+    }
+
+    /// Resumes the simulation.
+    pub fn resume(&mut self) {
+        self.is_paused = false;
+        self.steps = 0;
+    }
+
+    /// Pauses the running simulation or resumes the paused one.
+    pub fn toggle_pause(&mut self) {
+        if self.is_paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    /// Pauses the simulation and runs the frames one by one.
+    pub fn step_frames(&mut self, frames: u32) {
+        self.is_paused = true;
+        self.steps += frames;
+    }
+
+    /// Sets the speed between `MIN_SPEED` and `MAX_SPEED`.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// Switches to the next faster speed.
+    pub fn faster(&mut self) {
+        let speed = SPEEDS.into_iter().find(|speed| *speed > self.speed).unwrap_or(MAX_SPEED);
+        self.set_speed(speed);
+    }
+
+    /// Switches to the next slower speed.
+    pub fn slower(&mut self) {
+        let speed = SPEEDS.into_iter().rev().find(|speed| *speed < self.speed).unwrap_or(MIN_SPEED);
+        self.set_speed(speed);
+    }
+
+    /// Whether the simulation runs in the current frame.
+    pub fn is_running(&self) -> bool {
+        !self.is_paused || self.is_stepping
+    }
+}
+
+/// Returns the variable timestep of the physics at the speed: the fast-forward takes more steps of `physics_step`.
+pub fn physics_timestep(physics_step: f32, speed: f32) -> TimestepMode {
+    let speed = speed.max(1.0);
+
+    TimestepMode::Variable {
+        max_dt: physics_step * speed,
+        time_scale: 1.0,
+        substeps: speed.ceil() as usize,
+    }
+}
+
+/// Key bindings of the simulation clock.
+#[derive(Resource, Debug, Clone)]
+pub struct SimClockBindings {
+    pub pause: KeyCode,
+    pub step: KeyCode,
+    pub slower: KeyCode,
+    pub faster: KeyCode,
+}
+
+impl Default for SimClockBindings {
+    fn default() -> Self {
+        Self {
+            pause: KeyCode::Space,
+            step: KeyCode::Period,
+            slower: KeyCode::Minus,
+            faster: KeyCode::Equal,
+        }
+    }
+}
+
+// systems
+/// System that pauses the virtual time and the physics or runs them at the speed of the clock.
+///
+/// Runs before the time update, so the change applies in the same frame.
+pub fn apply_sim_clock(
+    mut clock: ResMut<SimClock>,
+    mut time: ResMut<Time<Virtual>>,
+    rapier: Option<ResMut<RapierConfiguration>>,
+) {
+    let clock = &mut *clock;
+
+    clock.is_stepping = clock.is_paused && clock.steps > 0;
+
+    if clock.is_stepping {
+        clock.steps -= 1;
+    }
+
+    let is_running = clock.is_running();
+
+    if is_running {
+        clock.frame += 1;
+    }
+
+    if clock.applied == Some((is_running, clock.speed)) {
+        return;
+    }
+
+    clock.applied = Some((is_running, clock.speed));
+
+    // steps are advanced by their own duration after the time update
+    if clock.is_paused {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+
+    time.set_relative_speed(clock.speed);
+
+    let Some(mut rapier) = rapier else {
+        return;
+    };
+
+    rapier.physics_pipeline_active = is_running;
+
+    // the fixed timestep belongs to its owner
+    if let TimestepMode::Variable { .. } = rapier.timestep_mode {
+        rapier.timestep_mode = physics_timestep(clock.physics_step, clock.speed);
+    }
+}
+
+/// System that advances the virtual time of the paused simulation by a single step.
+pub fn advance_sim_step(
+    clock: Res<SimClock>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut time: ResMut<Time>,
+) {
+    if clock.is_stepping {
+        virtual_time.advance_by(clock.step);
+        *time = virtual_time.as_generic();
+    }
+}
+
+/// System that pauses, steps and changes the speed of the simulation with the `SimClockBindings`.
+fn sim_clock_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<SimClockBindings>,
+    mut clock: ResMut<SimClock>,
+) {
+    if keys.just_pressed(bindings.pause) {
+        clock.toggle_pause();
+    }
+
+    if keys.just_pressed(bindings.step) {
+        clock.step_frames(1);
+    }
+
+    if keys.just_pressed(bindings.slower) {
+        clock.slower();
+    }
+
+    if keys.just_pressed(bindings.faster) {
+        clock.faster();
+    }
+}
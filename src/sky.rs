@@ -0,0 +1,249 @@
+use std::f32::consts::TAU;
+
+use bevy::{pbr::light_consts, prelude::*, render::view::RenderLayers};
+
+use crate::{
+    materials::{SolarHeating, Temperature},
+    weather::Weather,
+};
+
+/// Plugin for the sky: time of day, sun, moon and their influence on the world.
+pub struct SkyPlugin;
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<TimeOfDay>()
+            .init_resource::<SkyLighting>()
+            .add_systems(Startup, spawn_sky)
+            .add_systems(Update, (advance_time_of_day, update_sky, solar_heating).chain());
+    }
+}
+
+// resources
+/// Describes date, place and time of the scenario.
+///
+/// `latitude` is in degrees, `hours` is a local solar time from 0.0 to 24.0.
+///
+/// Use `time_scale` to accelerate time, e.g. 60.0 makes one minute pass every second.
+#[derive(Resource)]
+pub struct TimeOfDay {
+    pub day_of_year: u32,
+    pub latitude: f32,
+    pub hours: f32,
+    pub time_scale: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            day_of_year: 172,
+            latitude: 50.0,
+            hours: 12.0,
+            time_scale: 1.0,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Returns sun elevation and azimuth in radians.
+    pub fn solar_position(&self) -> (f32, f32) {
+        solar_position(self.day_of_year, self.latitude, self.hours)
+    }
+}
+
+/// Describes how the sky lights the world and how warm the air is.
+///
+/// Illuminances are in lux. Moonlight is brighter than the real one, so the night is visible with the default exposure.
+///
+/// Air temperature is the lowest before the sunrise and the highest in the afternoon.
+#[derive(Resource)]
+pub struct SkyLighting {
+    pub sun_illuminance: f32,
+    pub moon_illuminance: f32,
+    pub day_ambient_brightness: f32,
+    pub night_ambient_brightness: f32,
+    pub air_temperature: f32,
+    pub air_temperature_swing: f32,
+}
+
+impl Default for SkyLighting {
+    fn default() -> Self {
+        Self {
+            sun_illuminance: light_consts::lux::AMBIENT_DAYLIGHT,
+            moon_illuminance: 3.0,
+            day_ambient_brightness: 80.0,
+            night_ambient_brightness: 5.0,
+            air_temperature: 12.0,
+            air_temperature_swing: 5.0,
+        }
+    }
+}
+
+impl SkyLighting {
+    /// Returns air temperature at the given local solar time.
+    pub fn air_temperature_at(&self, hours: f32) -> f32 {
+        self.air_temperature + self.air_temperature_swing * ((hours - 15.0) / 24.0 * TAU).cos()
+    }
+}
+
+// components
+/// Describes the sun.
+#[derive(Component)]
+pub struct Sun;
+
+/// Describes the moon.
+#[derive(Component)]
+pub struct Moon;
+
+// functions
+/// Returns sun elevation and azimuth in radians for the given day of the year, latitude in degrees and local solar time.
+///
+/// Azimuth is measured from the north clockwise.
+pub fn solar_position(day_of_year: u32, latitude: f32, hours: f32) -> (f32, f32) {
+    let latitude = latitude.to_radians();
+    let declination = -23.44_f32.to_radians() * (TAU / 365.0 * (day_of_year as f32 + 10.0)).cos();
+    let hour_angle = (15.0 * (hours - 12.0)).to_radians();
+
+    let sin_elevation = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+
+    let cos_azimuth = (declination.sin() - sin_elevation * latitude.sin()) / (elevation.cos() * latitude.cos()).max(f32::EPSILON);
+    let azimuth = cos_azimuth.clamp(-1.0, 1.0).acos();
+
+    // in the afternoon the sun is in the west
+    let azimuth = if hour_angle > 0.0 { TAU - azimuth } else { azimuth };
+
+    (elevation, azimuth)
+}
+
+/// Returns direction to the celestial body. North is -Z, east is +X.
+fn direction_from(elevation: f32, azimuth: f32) -> Vec3 {
+    Vec3::new(
+        azimuth.sin() * elevation.cos(),
+        elevation.sin(),
+        -azimuth.cos() * elevation.cos(),
+    )
+}
+
+/// Returns transform of a directional light, which shines from the given direction.
+fn light_transform(direction: Vec3) -> Transform {
+    let up = if direction.y.abs() > 0.999 { Vec3::Z } else { Vec3::Y };
+    Transform::IDENTITY.looking_to(-direction, up)
+}
+
+// systems
+/// Query for the light of a celestial body `T`, which is not `U`.
+type CelestialLightQuery<'w, 's, T, U> = Query<'w, 's,
+    (&'static mut DirectionalLight, &'static mut Transform),
+    (With<T>, Without<U>),
+>;
+
+/// Spawns sun and moon.
+///
+/// It's important to mention, that light should be rendered in all layers. Use ` RenderLayers::all()`.
+fn spawn_sky(
+    mut commands: Commands,
+) {
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: true,
+                ..default()
+            },
+            ..default()
+        },
+        Sun,
+        Name::new("Sun"),
+        RenderLayers::all(),
+    ));
+
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                color: Color::rgb(0.75, 0.8, 1.0),
+                illuminance: 0.0,
+                ..default()
+            },
+            ..default()
+        },
+        Moon,
+        Name::new("Moon"),
+        RenderLayers::all(),
+    ));
+}
+
+/// System that advances time of day.
+pub fn advance_time_of_day(
+    time: Res<Time>,
+    mut time_of_day: ResMut<TimeOfDay>,
+) {
+    let hours = time_of_day.hours + time.delta_seconds() * time_of_day.time_scale / 3600.0;
+    let days = (hours / 24.0).floor();
+
+    time_of_day.hours = hours - days * 24.0;
+    time_of_day.day_of_year = (time_of_day.day_of_year as i64 - 1 + days as i64).rem_euclid(365) as u32 + 1;
+}
+
+/// System that moves sun and moon and sets ambient light.
+///
+/// Sunlight fades out during the twilight. The moon is treated as a full moon opposite to the sun.
+fn update_sky(
+    time_of_day: Res<TimeOfDay>,
+    sky_lighting: Res<SkyLighting>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut sun: CelestialLightQuery<Sun, Moon>,
+    mut moon: CelestialLightQuery<Moon, Sun>,
+) {
+    let (elevation, azimuth) = time_of_day.solar_position();
+    let sun_direction = direction_from(elevation, azimuth);
+
+    let daylight = daylight_factor(elevation);
+
+    if let Ok((mut light, mut transform)) = sun.get_single_mut() {
+        light.illuminance = sky_lighting.sun_illuminance * daylight;
+        *transform = light_transform(sun_direction);
+    }
+
+    if let Ok((mut light, mut transform)) = moon.get_single_mut() {
+        let moonlight = daylight_factor(-elevation) * (1.0 - daylight);
+        light.illuminance = sky_lighting.moon_illuminance * moonlight;
+        *transform = light_transform(-sun_direction);
+    }
+
+    ambient_light.brightness = sky_lighting.night_ambient_brightness
+        + (sky_lighting.day_ambient_brightness - sky_lighting.night_ambient_brightness) * daylight;
+}
+
+/// Returns how much of the sunlight reaches the ground, from 0.0 at night to 1.0 during the day.
+fn daylight_factor(elevation: f32) -> f32 {
+    let elevation = elevation.to_degrees();
+    ((elevation + 6.0) / 11.0).clamp(0.0, 1.0)
+}
+
+/// System that warms up `SolarHeating` entities in the sun and cools them down to the air temperature.
+///
+/// Temperature follows the equilibrium with a delay of `time_constant`, so surfaces stay warm after the sunset.
+/// 
+/// Precipitation of the `Weather` brings the equilibrium closer to the air temperature.
+pub fn solar_heating(
+    time: Res<Time>,
+    time_of_day: Res<TimeOfDay>,
+    sky_lighting: Res<SkyLighting>,
+    weather: Option<Res<Weather>>,
+    mut bodies: Query<(&mut Temperature, &SolarHeating)>,
+) {
+    let (elevation, _) = time_of_day.solar_position();
+    let insolation = elevation.sin().max(0.0);
+    let contrast = weather.map_or(1.0, |weather| weather.thermal_contrast());
+    let air_temperature = sky_lighting.air_temperature_at(time_of_day.hours);
+    let delta_seconds = time.delta_seconds() * time_of_day.time_scale;
+
+    for (mut temperature, heating) in &mut bodies {
+        let equilibrium = air_temperature + (heating.internal_heat + heating.solar_gain * insolation) * contrast;
+        let blend = 1.0 - (-delta_seconds / heating.time_constant.max(f32::EPSILON)).exp();
+
+        temperature.0 += (equilibrium - temperature.0) * blend;
+    }
+}
+
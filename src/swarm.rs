@@ -0,0 +1,736 @@
+use std::{
+    f32::consts::TAU,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arming::Arming,
+    autopilot::{AutopilotProgress, AutopilotStatus, DroneMission, Waypoint},
+    failsafe::{Failsafe, FailsafeState},
+    fleet::{Drone, DroneControl},
+    materials::{Temperature, Thermal},
+    player::{FlightController, FlightSetpoint, Motors, Player},
+    ui::menu::AppState,
+};
+
+/// Plugin for the swarm: drones of the fleet, which fly together.
+///
+/// Swarm members follow the leader in a formation, flock, or split an area between them and search it.
+/// They avoid each other and detect heat signatures with their thermal cameras.
+pub struct SwarmPlugin;
+
+impl Plugin for SwarmPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Swarm>()
+            .init_resource::<SwarmBindings>()
+            .init_resource::<HeatDetections>()
+            .add_systems(Startup, setup_swarm_hud)
+            .add_systems(Update, (
+                swarm_input.run_if(in_state(AppState::InFlight)),
+                engage_swarm,
+                fly_swarm,
+                avoid_collisions,
+                sense_heat,
+                update_swarm_hud,
+            ).chain());
+    }
+}
+
+/// Error that can happen while reading or writing a swarm file.
+#[derive(Debug)]
+pub enum SwarmError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for SwarmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't access swarm file: {error}"),
+            Self::Parse(error) => write!(f, "can't parse swarm file: {error}"),
+            Self::Serialize(error) => write!(f, "can't serialize swarm: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SwarmError {}
+
+impl From<io::Error> for SwarmError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for SwarmError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<ron::Error> for SwarmError {
+    fn from(error: ron::Error) -> Self {
+        Self::Serialize(error)
+    }
+}
+
+// resources
+/// Shape of the formation.
+///
+/// Slots are placed behind the leader, looking where it looks: `Line` abreast, `Column` in a file,
+/// `Wedge` in a V, `Circle` around the leader and `Grid` in rows of `columns` drones.
+/// `Custom` slots are offsets from the leader, where -Z is forward and +X is right.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FormationShape {
+    Line,
+    Column,
+    Wedge,
+    Circle,
+    Grid { columns: usize },
+    Custom(Vec<Vec3>),
+}
+
+impl FormationShape {
+    /// Returns offsets of `count` followers from the leader, `spacing` meters apart.
+    pub fn slots(&self, count: usize, spacing: f32) -> Vec<Vec3> {
+        (0..count)
+            .map(|index| {
+                // followers take both sides in turns
+                let rank = (index / 2 + 1) as f32;
+                let side = if index % 2 == 0 { 1.0 } else { -1.0 };
+
+                match self {
+                    Self::Line => Vec3::new(side * rank * spacing, 0.0, 0.0),
+                    Self::Column => Vec3::new(0.0, 0.0, (index + 1) as f32 * spacing),
+                    Self::Wedge => Vec3::new(side * rank * spacing, 0.0, rank * spacing),
+                    Self::Circle => {
+                        let angle = TAU * index as f32 / count as f32;
+                        Vec3::new(angle.sin() * spacing, 0.0, angle.cos() * spacing)
+                    },
+                    Self::Grid { columns } => {
+                        let columns = (*columns).max(1);
+                        let column = (index % columns) as f32 - (columns - 1) as f32 / 2.0;
+                        Vec3::new(column * spacing, 0.0, (index / columns + 1) as f32 * spacing)
+                    },
+                    Self::Custom(offsets) => offsets
+                        .get(index)
+                        .copied()
+                        .unwrap_or(Vec3::new(0.0, 0.0, (index + 1) as f32 * spacing)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Describes flocking.
+///
+/// Drones see neighbours closer than `radius` meters. They keep away from them with `separation` weight,
+/// match their velocity with `alignment` weight and fly to their center with `cohesion` weight.
+/// `migration` weight pulls them to the leader. They hold `altitude` meters and fly up to `max_speed` meters per second.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlockingSettings {
+    pub radius: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    pub migration: f32,
+    pub altitude: f32,
+    pub max_speed: f32,
+}
+
+impl Default for FlockingSettings {
+    fn default() -> Self {
+        Self {
+            radius: 20.0,
+            separation: 30.0,
+            alignment: 0.5,
+            cohesion: 0.2,
+            migration: 0.3,
+            altitude: 20.0,
+            max_speed: 8.0,
+        }
+    }
+}
+
+/// Rectangle of the XZ plane, which is searched by the swarm.
+///
+/// Every drone searches its own strip of the area in lanes `lane_spacing` meters apart,
+/// at `altitude` meters and `speed` meters per second.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageArea {
+    pub center: Vec2,
+    pub size: Vec2,
+    pub altitude: f32,
+    pub lane_spacing: f32,
+    pub speed: f32,
+}
+
+impl Default for CoverageArea {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            size: Vec2::new(200.0, 200.0),
+            altitude: 30.0,
+            lane_spacing: 30.0,
+            speed: 6.0,
+        }
+    }
+}
+
+impl CoverageArea {
+    /// Splits the area into `count` strips along X and returns lawnmower waypoints of every strip.
+    pub fn partition(&self, count: usize) -> Vec<Vec<Waypoint>> {
+        let min = self.center - self.size / 2.0;
+        let strip_width = self.size.x / count.max(1) as f32;
+        let lane_spacing = self.lane_spacing.max(1.0);
+
+        (0..count)
+            .map(|strip| {
+                let left = min.x + strip as f32 * strip_width;
+                let lanes = (strip_width / lane_spacing).ceil().max(1.0) as usize;
+
+                (0..lanes)
+                    .flat_map(|lane| {
+                        let x = (left + (lane as f32 + 0.5) * lane_spacing).min(left + strip_width);
+                        let (from, to) = if lane % 2 == 0 { (min.y, min.y + self.size.y) } else { (min.y + self.size.y, min.y) };
+
+                        [from, to].map(|z| Waypoint::new(Vec2::new(x, z), self.altitude).with_speed(self.speed))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// What the swarm does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SwarmBehaviour {
+    /// Drones in the air hold their position.
+    Hold,
+    /// Leader-follower formation, followers fly up to `max_speed` meters per second to their slots.
+    Formation { shape: FormationShape, spacing: f32, max_speed: f32 },
+    Flocking(FlockingSettings),
+    /// Drones search their parts of the area.
+    Coverage(CoverageArea),
+}
+
+impl SwarmBehaviour {
+    /// Returns the name of the behaviour.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hold => "hold",
+            Self::Formation { .. } => "formation",
+            Self::Flocking(_) => "flocking",
+            Self::Coverage(_) => "coverage",
+        }
+    }
+}
+
+/// Describes how drones avoid each other.
+///
+/// Drones closer than `safety_radius` meters are pushed apart at up to `max_speed` meters per second,
+/// `strength` is the speed per meter of intrusion.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollisionAvoidance {
+    pub safety_radius: f32,
+    pub strength: f32,
+    pub max_speed: f32,
+}
+
+impl Default for CollisionAvoidance {
+    fn default() -> Self {
+        Self {
+            safety_radius: 4.0,
+            strength: 2.0,
+            max_speed: 5.0,
+        }
+    }
+}
+
+/// Describes thermal cameras of the swarm members, which look down.
+///
+/// `Thermal` objects warmer than `detection_temperature` degrees are detected
+/// within `half_angle` radians of the camera axis and `max_range` meters.
+/// Detections closer than `merge_distance` meters are considered the same.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThermalSensing {
+    pub half_angle: f32,
+    pub max_range: f32,
+    pub detection_temperature: f32,
+    pub merge_distance: f32,
+}
+
+impl Default for ThermalSensing {
+    fn default() -> Self {
+        Self {
+            half_angle: 30.0_f32.to_radians(),
+            max_range: 80.0,
+            detection_temperature: 28.0,
+            merge_distance: 3.0,
+        }
+    }
+}
+
+/// Describes the swarm: its behaviours, collision avoidance and sensing.
+///
+/// Members are the drones with `DroneControl::Swarm`, which are not piloted. The leader is the drone with the `leader` ID
+/// or the `Player`. The swarm does the `current` behaviour of `behaviours`.
+///
+/// Swarms are stored in RON files.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Swarm {
+    pub name: String,
+    pub leader: Option<u32>,
+    pub behaviours: Vec<SwarmBehaviour>,
+    pub current: usize,
+    pub avoidance: CollisionAvoidance,
+    pub sensing: ThermalSensing,
+}
+
+impl Default for Swarm {
+    fn default() -> Self {
+        Self {
+            name: "default".to_owned(),
+            leader: None,
+            behaviours: vec![
+                SwarmBehaviour::Hold,
+                SwarmBehaviour::Formation { shape: FormationShape::Wedge, spacing: 8.0, max_speed: 10.0 },
+                SwarmBehaviour::Flocking(FlockingSettings::default()),
+                SwarmBehaviour::Coverage(CoverageArea::default()),
+            ],
+            current: 0,
+            avoidance: CollisionAvoidance::default(),
+            sensing: ThermalSensing::default(),
+        }
+    }
+}
+
+impl Swarm {
+    /// Reads the swarm file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SwarmError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the swarm file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SwarmError> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)?;
+        Ok(())
+    }
+
+    /// Returns the current behaviour.
+    pub fn behaviour(&self) -> &SwarmBehaviour {
+        self.behaviours.get(self.current).unwrap_or(&SwarmBehaviour::Hold)
+    }
+}
+
+/// Key bindings of the swarm.
+///
+/// `next` switches the swarm to the next behaviour, `load` loads the swarm from the `path` file.
+#[derive(Resource)]
+pub struct SwarmBindings {
+    pub next: KeyCode,
+    pub load: KeyCode,
+    pub path: PathBuf,
+}
+
+impl Default for SwarmBindings {
+    fn default() -> Self {
+        Self {
+            next: KeyCode::KeyB,
+            load: KeyCode::F10,
+            path: PathBuf::from("assets/swarms/default.ron"),
+        }
+    }
+}
+
+/// Heat signature detected by the swarm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeatDetection {
+    pub position: Vec3,
+    pub temperature: f32,
+    pub drone: u32,
+    /// Seconds since the start.
+    pub time: f32,
+}
+
+/// Heat signatures detected by the swarm.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct HeatDetections(pub Vec<HeatDetection>);
+
+// components
+/// Describes the swarm HUD text.
+#[derive(Component)]
+struct SwarmHudText;
+
+// systems
+/// System that switches swarm behaviours and loads the swarm file.
+fn swarm_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<SwarmBindings>,
+    mut swarm: ResMut<Swarm>,
+) {
+    if keys.just_pressed(bindings.next) && !swarm.behaviours.is_empty() {
+        swarm.current = (swarm.current + 1) % swarm.behaviours.len();
+        info!("swarm: {}", swarm.behaviour().name());
+    }
+
+    if keys.just_pressed(bindings.load) {
+        match Swarm::load(&bindings.path) {
+            Ok(loaded) => *swarm = loaded,
+            Err(error) => warn!("{error}"),
+        }
+    }
+}
+
+/// Query for the swarm members.
+type MemberQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static Drone,
+        &'static Transform,
+        Option<&'static Velocity>,
+        &'static mut FlightController,
+        Option<&'static mut DroneMission>,
+        Option<&'static Failsafe>,
+    ),
+    Without<Player>,
+>;
+
+/// Returns whether the drone is a swarm member, which is free to fly with the swarm.
+fn is_member(drone: &Drone, failsafe: Option<&Failsafe>) -> bool {
+    drone.control == DroneControl::Swarm && failsafe.map_or(true, |failsafe| failsafe.state == FailsafeState::Normal)
+}
+
+/// Query for the swarm members, which are engaged.
+type EngagedMemberQuery<'w, 's> = Query<'w, 's,
+    (
+        Entity,
+        &'static Drone,
+        Option<&'static mut DroneMission>,
+        Option<&'static mut Motors>,
+        Option<&'static mut Arming>,
+    ),
+    Without<Player>,
+>;
+
+/// System that starts the behaviour of the swarm, when it changes.
+///
+/// Members are armed, except for holding. Coverage gives every member a mission over its part of the area.
+pub fn engage_swarm(
+    mut commands: Commands,
+    swarm: Res<Swarm>,
+    mut members: EngagedMemberQuery,
+) {
+    if !swarm.is_changed() {
+        return;
+    }
+
+    let behaviour = swarm.behaviour();
+
+    let mut members: Vec<_> = members
+        .iter_mut()
+        .filter(|(_, drone, ..)| drone.control == DroneControl::Swarm)
+        .collect();
+    members.sort_unstable_by_key(|(_, drone, ..)| drone.id);
+
+    let mut partition = match behaviour {
+        SwarmBehaviour::Coverage(area) => area.partition(members.len()),
+        _ => Vec::new(),
+    }
+    .into_iter();
+
+    for (entity, _, mission, motors, arming) in members {
+        if *behaviour != SwarmBehaviour::Hold {
+            if let Some(mut motors) = motors.filter(|motors| !motors.is_armed) {
+                motors.is_armed = true;
+
+                if let Some(mut arming) = arming {
+                    *arming = Arming::default();
+                }
+            }
+        }
+
+        match (partition.next(), mission) {
+            (Some(waypoints), Some(mut mission)) => {
+                mission.waypoints = waypoints;
+                mission.progress = AutopilotProgress {
+                    status: AutopilotStatus::Flying,
+                    ..default()
+                };
+            },
+            (Some(waypoints), None) => {
+                let mut mission = DroneMission::new(waypoints);
+                mission.progress.status = AutopilotStatus::Flying;
+
+                commands.entity(entity).insert(mission);
+            },
+            // missions of the other behaviours are over
+            (None, Some(mut mission)) => mission.progress.status = AutopilotStatus::Idle,
+            (None, None) => {},
+        }
+    }
+}
+
+/// System that flies the swarm members.
+///
+/// Followers of the formation keep their slots around the leader and look where it looks.
+/// Flocking drones follow their neighbours and the leader. Holding drones keep their position.
+/// Coverage missions are flown by the autopilot.
+pub fn fly_swarm(
+    swarm: Res<Swarm>,
+    leaders: Query<(&Drone, &Transform, Option<&Velocity>, Has<Player>)>,
+    mut members: MemberQuery,
+) {
+    let leader = leaders
+        .iter()
+        .find(|(drone, _, _, is_player)| swarm.leader.map_or(*is_player, |leader| drone.id == leader))
+        .map(|(drone, transform, velocity, _)| (drone.id, *transform, velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel)));
+
+    // positions and velocities of the members and the leader, the flock sees all of them
+    let flock: Vec<(u32, Vec3, Vec3)> = members
+        .iter()
+        .filter(|(drone, _, _, _, _, failsafe)| is_member(drone, *failsafe))
+        .map(|(drone, transform, velocity, ..)| {
+            (drone.id, transform.translation, velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel))
+        })
+        .chain(leader.map(|(id, transform, velocity)| (id, transform.translation, velocity)))
+        .collect();
+
+    let mut followers: Vec<u32> = flock
+        .iter()
+        .map(|(id, ..)| *id)
+        .filter(|id| leader.map_or(true, |(leader, ..)| *id != leader))
+        .collect();
+    followers.sort_unstable();
+
+    for (drone, transform, velocity, mut controller, mission, failsafe) in &mut members {
+        if !is_member(drone, failsafe) || leader.is_some_and(|(leader, ..)| leader == drone.id) {
+            continue;
+        }
+
+        let position = transform.translation;
+        let velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+
+        controller.setpoint = match swarm.behaviour() {
+            SwarmBehaviour::Hold => Some(FlightSetpoint { velocity: Vec3::ZERO, yaw: None }),
+            SwarmBehaviour::Formation { shape, spacing, max_speed } => {
+                let Some((_, leader_transform, leader_velocity)) = leader else {
+                    controller.setpoint = Some(FlightSetpoint { velocity: Vec3::ZERO, yaw: None });
+                    continue;
+                };
+
+                let index = followers.iter().position(|id| *id == drone.id).unwrap_or(0);
+                let slots = shape.slots(followers.len(), *spacing);
+                let (yaw, _, _) = leader_transform.rotation.to_euler(EulerRot::YXZ);
+                let slot = leader_transform.translation + Quat::from_rotation_y(yaw) * slots[index];
+
+                Some(FlightSetpoint {
+                    velocity: (leader_velocity + (slot - position)).clamp_length_max(*max_speed),
+                    yaw: Some(yaw),
+                })
+            },
+            SwarmBehaviour::Flocking(settings) => {
+                let mut separation = Vec3::ZERO;
+                let mut neighbours = 0;
+                let mut center = Vec3::ZERO;
+                let mut average_velocity = Vec3::ZERO;
+
+                for (_, other, other_velocity) in flock.iter().filter(|(id, ..)| *id != drone.id) {
+                    let offset = position - *other;
+                    let distance = offset.length();
+
+                    if distance > settings.radius {
+                        continue;
+                    }
+
+                    separation += offset / distance.max(0.5).powi(2);
+                    center += *other;
+                    average_velocity += *other_velocity;
+                    neighbours += 1;
+                }
+
+                let mut desired = velocity + separation * settings.separation;
+
+                if neighbours > 0 {
+                    let count = neighbours as f32;
+                    desired += (average_velocity / count - velocity) * settings.alignment;
+                    desired += (center / count - position) * settings.cohesion;
+                }
+
+                if let Some((_, leader_transform, _)) = leader {
+                    desired += (leader_transform.translation - position) * settings.migration;
+                }
+
+                desired.y = settings.altitude - position.y;
+                let desired = desired.clamp_length_max(settings.max_speed);
+
+                let yaw = (desired.xz().length() > 0.5).then(|| (-desired.x).atan2(-desired.z));
+
+                Some(FlightSetpoint { velocity: desired, yaw })
+            },
+            SwarmBehaviour::Coverage(_) => {
+                // the autopilot flies the mission, the finished drones hold
+                if mission.is_some_and(|mission| mission.progress.status == AutopilotStatus::Flying) {
+                    continue;
+                }
+
+                Some(FlightSetpoint { velocity: Vec3::ZERO, yaw: None })
+            },
+        };
+    }
+}
+
+/// System that pushes drones, which are not piloted, away from the other drones.
+///
+/// Neighbours are found with a Rapier shape query of `CollisionAvoidance::safety_radius`.
+pub fn avoid_collisions(
+    swarm: Res<Swarm>,
+    rapier_context: Res<RapierContext>,
+    mut drones: Query<(Entity, &Transform, &mut FlightController, Has<Player>), With<Drone>>,
+) {
+    let avoidance = &swarm.avoidance;
+    let ball = Collider::ball(avoidance.safety_radius);
+
+    let positions: Vec<(Entity, Vec3)> = drones
+        .iter()
+        .map(|(entity, transform, ..)| (entity, transform.translation))
+        .collect();
+
+    for (entity, transform, mut controller, is_player) in &mut drones {
+        if is_player {
+            controller.avoidance = Vec3::ZERO;
+            continue;
+        }
+
+        let position = transform.translation;
+        let mut push = Vec3::ZERO;
+
+        rapier_context.intersections_with_shape(
+            position,
+            Quat::IDENTITY,
+            &ball,
+            QueryFilter::default().exclude_rigid_body(entity),
+            |other| {
+                let neighbour = positions.iter().find(|(drone, _)| *drone == other);
+
+                if let Some((_, other_position)) = neighbour {
+                    let offset = position - *other_position;
+                    let intrusion = avoidance.safety_radius - offset.length();
+                    // drones on top of each other go apart sideways
+                    let away = offset.try_normalize().unwrap_or(Vec3::X);
+
+                    push += away * intrusion.max(0.0) * avoidance.strength;
+                }
+
+                true
+            },
+        );
+
+        controller.avoidance = push.clamp_length_max(avoidance.max_speed);
+    }
+}
+
+/// Query for the objects with heat signatures.
+type HeatSourceQuery<'w, 's> = Query<'w, 's, (&'static GlobalTransform, &'static Temperature), (With<Thermal>, Without<Drone>)>;
+
+/// System that detects heat signatures under the swarm members.
+pub fn sense_heat(
+    time: Res<Time>,
+    swarm: Res<Swarm>,
+    mut detections: ResMut<HeatDetections>,
+    members: Query<(&Drone, &Transform), Without<Player>>,
+    objects: HeatSourceQuery,
+) {
+    let sensing = &swarm.sensing;
+    let min_cos = sensing.half_angle.cos();
+
+    for (drone, transform) in members.iter().filter(|(drone, _)| drone.control == DroneControl::Swarm) {
+        let position = transform.translation;
+
+        for (object, temperature) in &objects {
+            let target = object.translation();
+            let offset = target - position;
+            let distance = offset.length();
+
+            let is_in_view = distance > 0.0 && distance <= sensing.max_range && offset.dot(Vec3::NEG_Y) / distance >= min_cos;
+
+            if !is_in_view || temperature.0 < sensing.detection_temperature {
+                continue;
+            }
+
+            let is_known = detections.0.iter().any(|detection| detection.position.distance(target) < sensing.merge_distance);
+
+            if !is_known {
+                info!("drone {} detected heat signature of {:.0}°C", drone.id, temperature.0);
+
+                detections.0.push(HeatDetection {
+                    position: target,
+                    temperature: temperature.0,
+                    drone: drone.id,
+                    time: time.elapsed_seconds(),
+                });
+            }
+        }
+    }
+}
+
+/// Swarm HUD initialization.
+fn setup_swarm_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/Wellfleet-Regular.ttf"),
+                font_size: 15.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(240.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        SwarmHudText,
+    ));
+}
+
+/// System that shows the swarm behaviour and its detections in the HUD.
+fn update_swarm_hud(
+    swarm: Res<Swarm>,
+    detections: Res<HeatDetections>,
+    members: Query<&Drone, Without<Player>>,
+    mut query: Query<&mut Text, With<SwarmHudText>>,
+) {
+    let count = members.iter().filter(|drone| drone.control == DroneControl::Swarm).count();
+
+    let value = if count > 0 {
+        format!(
+            "Swarm: {} of {count}\nHeat signatures: {}",
+            swarm.behaviour().name(),
+            detections.0.len(),
+        )
+    } else {
+        String::new()
+    };
+
+    for mut text in &mut query {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+    utils::HashMap,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    materials::{TerrainMaterial, TerrainMaterialExtension},
+    player::Player,
+};
+
+/// Height maps read from images and raw elevation files.
+pub mod height_map;
+/// Procedural terrain noise.
+pub mod noise;
+
+use height_map::HeightMap;
+use noise::NoiseSettings;
+
+/// Plugin for the terrain.
+///
+/// Terrain is split into square chunks, which are loaded around the `Player` and unloaded when it flies away.
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Terrain>()
+            .init_resource::<LoadedTerrainChunks>()
+            .add_systems(Update, stream_terrain_chunks);
+    }
+}
+
+// resources
+/// Where the terrain heights come from.
+///
+/// `HeightMap` is stretched over `size` meters and centered at the origin, there is no terrain outside of it.
+/// `Procedural` terrain is endless.
+#[derive(Debug, Clone)]
+pub enum TerrainSource {
+    Procedural(NoiseSettings),
+    HeightMap {
+        map: HeightMap,
+        size: Vec2,
+    },
+}
+
+impl Default for TerrainSource {
+    fn default() -> Self {
+        Self::Procedural(NoiseSettings::default())
+    }
+}
+
+/// Describes the terrain.
+///
+/// Each chunk is `chunk_size` meters wide and has `chunk_resolution` quads along each side.
+/// Chunks closer than `load_radius` meters to the `Player` are loaded, not more than `chunks_per_frame` at once.
+///
+/// Terrain is shifted so the origin is at zero height, and it is flattened within `pad_radius` meters around it,
+/// which leaves a flat launch pad for the drone.
+///
+/// Changing the resource rebuilds all of the chunks.
+#[derive(Resource, Debug, Clone)]
+pub struct Terrain {
+    pub source: TerrainSource,
+    pub chunk_size: f32,
+    pub chunk_resolution: u32,
+    pub load_radius: f32,
+    pub chunks_per_frame: usize,
+    pub pad_radius: f32,
+    pub splatting: TerrainSplatting,
+}
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Self {
+            source: TerrainSource::default(),
+            chunk_size: 64.0,
+            chunk_resolution: 32,
+            load_radius: 400.0,
+            chunks_per_frame: 4,
+            pad_radius: 15.0,
+            splatting: TerrainSplatting::default(),
+        }
+    }
+}
+
+impl Terrain {
+    /// Returns terrain height in meters at the given point of the XZ plane.
+    pub fn height_at(&self, position: Vec2) -> f32 {
+        let height = self.source_height_at(position) - self.source_height_at(Vec2::ZERO);
+
+        // launch pad blends into the terrain over one more pad radius
+        let pad = smoothstep(self.pad_radius, self.pad_radius * 2.0, position.length());
+
+        height * pad
+    }
+
+    /// Returns terrain normal at the given point of the XZ plane.
+    pub fn normal_at(&self, position: Vec2) -> Vec3 {
+        let step = self.chunk_size / self.chunk_resolution.max(1) as f32;
+
+        let dx = self.height_at(position + Vec2::X * step) - self.height_at(position - Vec2::X * step);
+        let dz = self.height_at(position + Vec2::Y * step) - self.height_at(position - Vec2::Y * step);
+
+        Vec3::new(-dx, 2.0 * step, -dz).normalize()
+    }
+
+    /// Returns coordinates of the chunk, which contains the given point of the XZ plane.
+    pub fn chunk_at(&self, position: Vec2) -> IVec2 {
+        (position / self.chunk_size).round().as_ivec2()
+    }
+
+    /// Returns position of the chunk center in the XZ plane.
+    pub fn chunk_center(&self, chunk: IVec2) -> Vec2 {
+        chunk.as_vec2() * self.chunk_size
+    }
+
+    /// Whether the chunk has any terrain in it.
+    pub fn has_chunk(&self, chunk: IVec2) -> bool {
+        match &self.source {
+            TerrainSource::Procedural(_) => true,
+            TerrainSource::HeightMap { size, .. } => {
+                let offset = (self.chunk_center(chunk).abs() - self.chunk_size / 2.0).max(Vec2::ZERO);
+                offset.cmplt(*size / 2.0).all()
+            },
+        }
+    }
+
+    /// Builds the render mesh and the heightfield collider of the chunk.
+    ///
+    /// Both of them are centered at `Terrain::chunk_center`, so the chunk entity should be placed there.
+    pub fn build_chunk(&self, chunk: IVec2) -> (Mesh, Collider) {
+        let resolution = self.chunk_resolution.max(1) as usize;
+        let vertices_per_side = resolution + 1;
+        let step = self.chunk_size / resolution as f32;
+        let center = self.chunk_center(chunk);
+        let corner = -Vec2::splat(self.chunk_size / 2.0);
+
+        let mut positions = Vec::with_capacity(vertices_per_side * vertices_per_side);
+        let mut normals = Vec::with_capacity(positions.capacity());
+        let mut uvs = Vec::with_capacity(positions.capacity());
+        let mut colors = Vec::with_capacity(positions.capacity());
+
+        // vertices go row by row along Z
+        for z in 0..vertices_per_side {
+            for x in 0..vertices_per_side {
+                let local = corner + Vec2::new(x as f32, z as f32) * step;
+                let world = center + local;
+
+                let height = self.height_at(world);
+                let normal = self.normal_at(world);
+
+                positions.push([local.x, height, local.y]);
+                normals.push(normal.to_array());
+                uvs.push(world.to_array());
+                colors.push(self.splatting.weights(height, normal.y.clamp(-1.0, 1.0).acos()).to_array());
+            }
+        }
+
+        let mut indices = Vec::with_capacity(resolution * resolution * 6);
+
+        for z in 0..resolution {
+            for x in 0..resolution {
+                let north_west = (z * vertices_per_side + x) as u32;
+                let north_east = north_west + 1;
+                let south_west = north_west + vertices_per_side as u32;
+                let south_east = south_west + 1;
+
+                indices.extend([north_west, south_west, north_east, north_east, south_west, south_east]);
+            }
+        }
+
+        // heightfield is column-major: rows go along Z and columns along X
+        let mut heights = Vec::with_capacity(positions.len());
+
+        for x in 0..vertices_per_side {
+            for z in 0..vertices_per_side {
+                heights.push(positions[z * vertices_per_side + x][1]);
+            }
+        }
+
+        let collider = Collider::heightfield(
+            heights,
+            vertices_per_side,
+            vertices_per_side,
+            Vec3::new(self.chunk_size, 1.0, self.chunk_size),
+        );
+
+        let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+            .with_inserted_indices(Indices::U32(indices));
+
+        (mesh, collider)
+    }
+
+    /// Returns height of the source without the launch pad.
+    fn source_height_at(&self, position: Vec2) -> f32 {
+        match &self.source {
+            TerrainSource::Procedural(noise) => noise.height_at(position),
+            TerrainSource::HeightMap { map, size } => map.sample(position / *size + 0.5),
+        }
+    }
+}
+
+/// Describes how terrain layers are splatted by height and slope.
+///
+/// Sand lies below `shore_height`, snow lies above `snow_height` and everything steeper than `rock_slope` is rock.
+/// Grass covers the rest. Layers blend over `height_blend` meters and `slope_blend` radians.
+///
+/// `layers` are the colors and textures of sand, grass, rock and snow.
+#[derive(Debug, Clone)]
+pub struct TerrainSplatting {
+    pub shore_height: f32,
+    pub snow_height: f32,
+    pub rock_slope: f32,
+    pub height_blend: f32,
+    pub slope_blend: f32,
+    pub layers: TerrainMaterialExtension,
+}
+
+impl Default for TerrainSplatting {
+    fn default() -> Self {
+        Self {
+            shore_height: -12.0,
+            snow_height: 22.0,
+            rock_slope: 35.0_f32.to_radians(),
+            height_blend: 2.0,
+            slope_blend: 5.0_f32.to_radians(),
+            layers: TerrainMaterialExtension {
+                layer_colors: [
+                    Vec4::new(0.76, 0.7, 0.5, 1.0),
+                    Vec4::new(0.3, 0.5, 0.2, 1.0),
+                    Vec4::new(0.45, 0.42, 0.4, 1.0),
+                    Vec4::new(0.95, 0.95, 0.97, 1.0),
+                ],
+                ..default()
+            },
+        }
+    }
+}
+
+impl TerrainSplatting {
+    /// Returns weights of sand, grass, rock and snow at the given height in meters and slope in radians.
+    ///
+    /// Weights always sum up to 1.0.
+    pub fn weights(&self, height: f32, slope: f32) -> Vec4 {
+        let sand = 1.0 - smoothstep(self.shore_height - self.height_blend, self.shore_height + self.height_blend, height);
+        let snow = smoothstep(self.snow_height - self.height_blend, self.snow_height + self.height_blend, height);
+        let rock = smoothstep(self.rock_slope - self.slope_blend, self.rock_slope + self.slope_blend, slope);
+
+        // rock covers snow, snow covers sand and grass
+        let ground = (1.0 - snow) * (1.0 - rock);
+
+        Vec4::new(sand * ground, (1.0 - sand) * ground, rock, snow * (1.0 - rock))
+    }
+}
+
+/// Chunks of the terrain, which are loaded now.
+#[derive(Resource, Default)]
+pub struct LoadedTerrainChunks {
+    pub chunks: HashMap<IVec2, Entity>,
+}
+
+// components
+/// Describes a chunk of the terrain.
+#[derive(Component)]
+pub struct TerrainChunk {
+    pub coordinates: IVec2,
+}
+
+// functions
+/// Hermite interpolation from 0.0 to 1.0 between `edge0` and `edge1`.
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0).max(f32::EPSILON)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// systems
+/// System that loads terrain chunks around the `Player` and unloads the distant ones.
+///
+/// Nearest chunks are loaded first. Without the `Player` chunks are loaded around the origin.
+pub fn stream_terrain_chunks(
+    mut commands: Commands,
+    terrain: Res<Terrain>,
+    mut loaded: ResMut<LoadedTerrainChunks>,
+    players: Query<&Transform, With<Player>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut terrain_materials: ResMut<Assets<TerrainMaterial>>,
+    mut material: Local<Option<Handle<TerrainMaterial>>>,
+) {
+    if terrain.is_changed() {
+        for (_, entity) in loaded.chunks.drain() {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        *material = Some(terrain_materials.add(TerrainMaterial {
+            base: StandardMaterial {
+                perceptual_roughness: 0.9,
+                ..default()
+            },
+            extension: terrain.splatting.layers.clone(),
+        }));
+    }
+
+    let Some(material) = material.clone() else {
+        return;
+    };
+
+    let focus = players
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.xz());
+
+    // chunk is kept while any part of it is in range
+    let reach = terrain.load_radius + terrain.chunk_size * std::f32::consts::FRAC_1_SQRT_2;
+    let is_in_range = |chunk: IVec2| terrain.chunk_center(chunk).distance(focus) <= reach;
+
+    loaded.chunks.retain(|chunk, entity| {
+        let keep = is_in_range(*chunk);
+        if !keep {
+            commands.entity(*entity).despawn_recursive();
+        }
+        keep
+    });
+
+    let center = terrain.chunk_at(focus);
+    let radius = (reach / terrain.chunk_size).ceil() as i32;
+
+    let mut missing: Vec<IVec2> = (-radius..=radius)
+        .flat_map(|z| (-radius..=radius).map(move |x| center + IVec2::new(x, z)))
+        .filter(|chunk| is_in_range(*chunk) && terrain.has_chunk(*chunk) && !loaded.chunks.contains_key(chunk))
+        .collect();
+
+    missing.sort_by(|a, b| {
+        let a = terrain.chunk_center(*a).distance_squared(focus);
+        let b = terrain.chunk_center(*b).distance_squared(focus);
+        a.total_cmp(&b)
+    });
+
+    for chunk in missing.into_iter().take(terrain.chunks_per_frame) {
+        let (mesh, collider) = terrain.build_chunk(chunk);
+        let center = terrain.chunk_center(chunk);
+
+        let entity = commands
+            .spawn((
+                MaterialMeshBundle {
+                    mesh: meshes.add(mesh),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(center.x, 0.0, center.y),
+                    ..default()
+                },
+                collider,
+                TerrainChunk { coordinates: chunk },
+                Name::new(format!("Terrain chunk {} {}", chunk.x, chunk.y)),
+            ))
+            .id();
+
+        loaded.chunks.insert(chunk, entity);
+    }
+}
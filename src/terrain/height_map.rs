@@ -0,0 +1,220 @@
+use std::{fmt, fs, io, path::Path};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::TextureFormat,
+        texture::{CompressedImageFormats, ImageSampler, ImageType, TextureError},
+    },
+};
+
+/// Error that can happen while reading a height map.
+#[derive(Debug)]
+pub enum TerrainError {
+    Io(io::Error),
+    Image(TextureError),
+    UnsupportedFormat(TextureFormat),
+    SizeMismatch { expected: usize, actual: usize },
+    Empty { width: usize, depth: usize },
+}
+
+impl fmt::Display for TerrainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "can't read height map: {error}"),
+            Self::Image(error) => write!(f, "can't decode height map image: {error}"),
+            Self::UnsupportedFormat(format) => write!(f, "height map image format {format:?} is not supported"),
+            Self::SizeMismatch { expected, actual } => {
+                write!(f, "height map should have {expected} bytes, but it has {actual}")
+            },
+            Self::Empty { width, depth } => write!(f, "height map of {width}x{depth} samples has no heights"),
+        }
+    }
+}
+
+impl std::error::Error for TerrainError {}
+
+impl From<io::Error> for TerrainError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<TextureError> for TerrainError {
+    fn from(error: TextureError) -> Self {
+        Self::Image(error)
+    }
+}
+
+/// Sample format of a raw elevation file.
+///
+/// Samples are stored row by row, from the north-west corner, in little-endian byte order.
+/// That's how GeoTIFF and SRTM rasters look after `gdal_translate -of ENVI`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElevationFormat {
+    Int16,
+    UInt16,
+    Float32,
+}
+
+impl ElevationFormat {
+    /// Returns size of a single sample in bytes.
+    pub fn sample_size(&self) -> usize {
+        match self {
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Float32 => 4,
+        }
+    }
+
+    fn read(&self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+            Self::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+            Self::Float32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+/// Grid of heights in meters.
+///
+/// `heights` are stored row by row, `width` samples along X in each of the `depth` rows along Z.
+/// There is at least one sample, so the map always has a height. The map is only built by its checked
+/// constructors, so the samples always match the size.
+#[derive(Debug, Clone)]
+pub struct HeightMap {
+    width: usize,
+    depth: usize,
+    heights: Vec<f32>,
+}
+
+impl HeightMap {
+    /// Returns height of the sample at the given column and row. Coordinates are clamped to the grid.
+    pub fn height(&self, x: usize, z: usize) -> f32 {
+        let x = x.min(self.width - 1);
+        let z = z.min(self.depth - 1);
+
+        self.heights[z * self.width + x]
+    }
+
+    /// Returns bilinearly interpolated height, where `uv` goes from 0.0 to 1.0 across the grid.
+    pub fn sample(&self, uv: Vec2) -> f32 {
+        let position = uv.clamp(Vec2::ZERO, Vec2::ONE)
+            * Vec2::new((self.width - 1) as f32, (self.depth - 1) as f32);
+        let cell = position.floor();
+        let fraction = position - cell;
+
+        let (x, z) = (cell.x as usize, cell.y as usize);
+
+        let north = self.height(x, z) + (self.height(x + 1, z) - self.height(x, z)) * fraction.x;
+        let south = self.height(x, z + 1) + (self.height(x + 1, z + 1) - self.height(x, z + 1)) * fraction.x;
+
+        north + (south - north) * fraction.y
+    }
+
+    /// Creates height map from a grayscale image, where black is 0.0 and white is `max_height` meters.
+    ///
+    /// Only the first channel of the image is used. 16-bit images keep their precision.
+    pub fn from_grayscale(image: &Image, max_height: f32) -> Result<Self, TerrainError> {
+        let format = image.texture_descriptor.format;
+
+        let (pixel_size, read): (usize, fn(&[u8]) -> f32) = match format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => (4, |bytes| bytes[0] as f32 / u8::MAX as f32),
+            TextureFormat::R8Unorm => (1, |bytes| bytes[0] as f32 / u8::MAX as f32),
+            TextureFormat::R16Uint => (2, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32),
+            TextureFormat::Rg16Uint => (4, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32),
+            TextureFormat::Rgba16Unorm => (8, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32),
+            TextureFormat::Rgba32Float => (16, |bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            _ => return Err(TerrainError::UnsupportedFormat(format)),
+        };
+
+        let size = image.size();
+        let (width, depth) = (size.x as usize, size.y as usize);
+
+        if width == 0 || depth == 0 {
+            return Err(TerrainError::Empty { width, depth });
+        }
+
+        if image.data.len() != width * depth * pixel_size {
+            return Err(TerrainError::SizeMismatch { expected: width * depth * pixel_size, actual: image.data.len() });
+        }
+
+        Ok(Self {
+            width,
+            depth,
+            heights: image.data
+                .chunks_exact(pixel_size)
+                .map(|pixel| read(pixel) * max_height)
+                .collect(),
+        })
+    }
+
+    /// Reads grayscale height map from an image file. See `HeightMap::from_grayscale`.
+    pub fn from_grayscale_file(path: impl AsRef<Path>, max_height: f32) -> Result<Self, TerrainError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("png");
+
+        let image = Image::from_buffer(
+            &fs::read(path)?,
+            ImageType::Extension(extension),
+            CompressedImageFormats::NONE,
+            false,
+            ImageSampler::Default,
+            RenderAssetUsages::MAIN_WORLD,
+        )?;
+
+        Self::from_grayscale(&image, max_height)
+    }
+
+    /// Creates height map from raw elevation samples.
+    ///
+    /// Samples equal to `no_data` are holes in the data, they are filled with the lowest valid height.
+    pub fn from_raw_elevation(
+        bytes: &[u8],
+        width: usize,
+        depth: usize,
+        format: ElevationFormat,
+        no_data: Option<f32>,
+    ) -> Result<Self, TerrainError> {
+        if width == 0 || depth == 0 {
+            return Err(TerrainError::Empty { width, depth });
+        }
+
+        let expected = width * depth * format.sample_size();
+
+        if bytes.len() != expected {
+            return Err(TerrainError::SizeMismatch { expected, actual: bytes.len() });
+        }
+
+        let mut heights: Vec<f32> = bytes
+            .chunks_exact(format.sample_size())
+            .map(|sample| format.read(sample))
+            .collect();
+
+        let is_hole = |height: f32| no_data == Some(height) || !height.is_finite();
+
+        let lowest = heights
+            .iter()
+            .copied()
+            .filter(|height| !is_hole(*height))
+            .fold(f32::INFINITY, f32::min);
+        let lowest = if lowest.is_finite() { lowest } else { 0.0 };
+
+        for height in heights.iter_mut().filter(|height| is_hole(**height)) {
+            *height = lowest;
+        }
+
+        Ok(Self { width, depth, heights })
+    }
+
+    /// Reads raw elevation file. See `HeightMap::from_raw_elevation`.
+    pub fn from_raw_elevation_file(
+        path: impl AsRef<Path>,
+        width: usize,
+        depth: usize,
+        format: ElevationFormat,
+        no_data: Option<f32>,
+    ) -> Result<Self, TerrainError> {
+        Self::from_raw_elevation(&fs::read(path)?, width, depth, format, no_data)
+    }
+}
@@ -0,0 +1,87 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+/// Settings of the procedural terrain noise.
+///
+/// Terrain is a sum of `octaves` layers of gradient noise. The first one has `wavelength` meters between hills,
+/// every next one is `lacunarity` times smaller and `persistence` times lower.
+///
+/// `amplitude` is the highest possible height in meters.
+#[derive(Debug, Clone)]
+pub struct NoiseSettings {
+    pub seed: u32,
+    pub amplitude: f32,
+    pub wavelength: f32,
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub persistence: f32,
+}
+
+impl Default for NoiseSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            amplitude: 30.0,
+            wavelength: 250.0,
+            octaves: 5,
+            lacunarity: 2.0,
+            persistence: 0.45,
+        }
+    }
+}
+
+impl NoiseSettings {
+    /// Returns height in meters at the given point, from `-amplitude` to `amplitude`.
+    pub fn height_at(&self, position: Vec2) -> f32 {
+        let mut frequency = 1.0 / self.wavelength.max(f32::EPSILON);
+        let mut weight = 1.0;
+        let mut height = 0.0;
+        let mut total_weight = 0.0;
+
+        for octave in 0..self.octaves {
+            height += gradient_noise(position * frequency, self.seed.wrapping_add(octave)) * weight;
+            total_weight += weight;
+
+            frequency *= self.lacunarity;
+            weight *= self.persistence;
+        }
+
+        // gradient noise rarely goes beyond ±0.7, so it is stretched to the full amplitude
+        (height / total_weight.max(f32::EPSILON) / 0.7).clamp(-1.0, 1.0) * self.amplitude
+    }
+}
+
+/// Returns gradient noise at the given point.
+fn gradient_noise(position: Vec2, seed: u32) -> f32 {
+    let cell = position.floor();
+    let fraction = position - cell;
+    let (x, z) = (cell.x as i32, cell.y as i32);
+
+    let dot = |dx: i32, dz: i32| {
+        gradient(x + dx, z + dz, seed).dot(fraction - Vec2::new(dx as f32, dz as f32))
+    };
+
+    // quintic fade, so the terrain normals are continuous
+    let fade = fraction * fraction * fraction * (fraction * (fraction * 6.0 - 15.0) + 10.0);
+
+    let north = dot(0, 0) + (dot(1, 0) - dot(0, 0)) * fade.x;
+    let south = dot(0, 1) + (dot(1, 1) - dot(0, 1)) * fade.x;
+
+    north + (south - north) * fade.y
+}
+
+/// Returns pseudo-random unit gradient of the lattice point.
+fn gradient(x: i32, z: i32, seed: u32) -> Vec2 {
+    let mut hash = seed
+        ^ (x as u32).wrapping_mul(0x27d4_eb2d)
+        ^ (z as u32).wrapping_mul(0x1656_67b1);
+
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^= hash >> 12;
+    hash = hash.wrapping_mul(0x297a_2d39);
+    hash ^= hash >> 15;
+
+    Vec2::from_angle(hash as f32 / u32::MAX as f32 * TAU)
+}
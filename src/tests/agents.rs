@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    agents::{animate_agents, move_agents, Agent, AgentBehaviour, AgentKind, BodyPart, BodyPartKind},
+    player::Player,
+    world::generator::GeneratedObject,
+};
+
+#[test]
+fn did_react_to_drone() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.add_systems(Update, move_agents);
+
+    let wander = AgentBehaviour::Wander { center: Vec2::ZERO, radius: 5.0 };
+
+    app.world.spawn((Player, TransformBundle::from(Transform::from_xyz(0.0, 10.0, 0.0))));
+    app.world.spawn((GeneratedObject::Tree, TransformBundle::from(Transform::from_xyz(15.0, 0.0, 0.0))));
+
+    let pedestrian_id = app.world
+        .spawn((Agent::new(AgentKind::Pedestrian, wander.clone(), 1), TransformBundle::default()))
+        .id();
+    let animal_id = app.world
+        .spawn((Agent::new(AgentKind::Animal, wander, 2), TransformBundle::from(Transform::from_xyz(-3.0, 0.0, 0.0))))
+        .id();
+
+    for _ in 0..100 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_millis(100));
+        app.update();
+    }
+
+    let pedestrian = app.world.get::<Transform>(pedestrian_id).unwrap().translation;
+    let animal = app.world.get::<Transform>(animal_id).unwrap().translation;
+
+    // pedestrian hides under the tree, animal runs away
+    assert!(app.world.get::<Agent>(pedestrian_id).unwrap().is_alarmed());
+    assert!(pedestrian.distance(Vec3::new(15.0, 0.0, 0.0)) < 1.0);
+    assert!(animal.x < -30.0);
+}
+
+#[test]
+fn did_animate_body_parts() {
+    let mut app = App::new();
+
+    app.add_systems(Update, animate_agents);
+
+    let mut agent = Agent::new(AgentKind::Car, AgentBehaviour::FollowRoad, 0);
+    agent.travelled = 1.0;
+
+    let wheel_rest = Transform::from_xyz(0.9, 0.35, 1.35).with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+
+    let wheel_id = app.world
+        .spawn((BodyPart { kind: BodyPartKind::Wheel, rest: wheel_rest }, TransformBundle::from(wheel_rest)))
+        .id();
+    let body_id = app.world
+        .spawn((BodyPart { kind: BodyPartKind::Body, rest: Transform::IDENTITY }, TransformBundle::default()))
+        .id();
+
+    app.world
+        .spawn((agent, TransformBundle::default()))
+        .push_children(&[wheel_id, body_id]);
+
+    app.update();
+
+    let wheel = app.world.get::<Transform>(wheel_id).unwrap();
+
+    assert_eq!(wheel.translation, wheel_rest.translation);
+    assert!(wheel.rotation.angle_between(wheel_rest.rotation) > 0.5);
+    assert_eq!(app.world.get::<Transform>(body_id).unwrap().rotation, Quat::IDENTITY);
+}
+
+#[test]
+fn did_stop_at_the_end_of_path() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.add_systems(Update, move_agents);
+
+    let path = AgentBehaviour::FollowPath { waypoints: vec![Vec2::new(1.0, 0.0), Vec2::new(3.0, 0.0)], looped: false };
+
+    let agent_id = app.world
+        .spawn((Agent::new(AgentKind::Pedestrian, path, 1), TransformBundle::default()))
+        .id();
+
+    for _ in 0..100 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_millis(100));
+        app.update();
+    }
+
+    let position = app.world.get::<Transform>(agent_id).unwrap().translation;
+
+    assert_eq!(app.world.get::<Agent>(agent_id).unwrap().waypoint, 1);
+    assert!(position.distance(Vec3::new(3.0, 0.0, 0.0)) < 0.1);
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    arming::{detect_landing, process_arming_commands, Arming, ArmingCommand, ArmingSettings, PreflightFailure},
+    failsafe::FailureInjection,
+    player::{Battery, Motors, Player},
+};
+
+#[test]
+fn did_arm_after_preflight_checks_and_disarm_after_landing() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<ArmingSettings>();
+    app.insert_resource(FailureInjection {
+        gps_lost: true,
+        ..default()
+    });
+    app.add_event::<ArmingCommand>();
+
+    app.add_systems(Update, (process_arming_commands, detect_landing).chain());
+
+    let drone_id = app.world
+        .spawn((
+            Player,
+            Battery::default(),
+            Motors::default(),
+            Arming::default(),
+            TransformBundle::from(Transform::from_xyz(0.0, 0.55, 0.0)),
+        ))
+        .id();
+
+    // unhealthy GPS blocks arming
+    app.world.send_event(ArmingCommand::Arm);
+    app.update();
+
+    assert!(!app.world.get::<Motors>(drone_id).unwrap().is_armed);
+    assert_eq!(app.world.get::<Arming>(drone_id).unwrap().failures, vec![PreflightFailure::GpsUnhealthy]);
+
+    app.world.resource_mut::<FailureInjection>().gps_lost = false;
+    app.world.send_event(ArmingCommand::Arm);
+    app.update();
+
+    assert!(app.world.get::<Motors>(drone_id).unwrap().is_armed);
+
+    // take off, drones in the air are not disarmed
+    app.world.get_mut::<Transform>(drone_id).unwrap().translation.y = 10.0;
+    app.update();
+    app.world.send_event(ArmingCommand::Disarm);
+    app.update();
+
+    assert!(app.world.get::<Arming>(drone_id).unwrap().is_airborne);
+    assert!(app.world.get::<Motors>(drone_id).unwrap().is_armed);
+
+    // land and stay on the ground
+    app.world.get_mut::<Transform>(drone_id).unwrap().translation.y = 0.55;
+
+    for _ in 0..3 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_millis(600));
+        app.update();
+    }
+
+    assert!(!app.world.get::<Motors>(drone_id).unwrap().is_armed);
+    assert!(!app.world.get::<Arming>(drone_id).unwrap().is_airborne);
+}
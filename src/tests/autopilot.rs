@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    autopilot::{
+        navigate_waypoints, perform_waypoint_actions, plan::read_qgc_plan, start_waypoint_mission, AutopilotProgress,
+        AutopilotStatus, StartWaypointMission, StopWaypointMission, Waypoint, WaypointAction, WaypointActionStarted,
+        WaypointMission, YawMode,
+    },
+    player::{run_flight_controller, FlightController, Motors, Player},
+    tests::add_fixed_physics,
+};
+
+#[test]
+fn did_fly_waypoint_mission() {
+    let mut app = App::new();
+
+    add_fixed_physics(&mut app);
+
+    app.init_resource::<AutopilotProgress>();
+    app.insert_resource(WaypointMission {
+        waypoints: vec![
+            Waypoint::new(Vec2::new(10.0, 0.0), 12.0)
+                .with_hold(1.0)
+                .with_action(WaypointAction::TakePhoto),
+            Waypoint::new(Vec2::new(10.0, -10.0), 10.0).with_action(WaypointAction::Land),
+        ],
+        ..default()
+    });
+    app.add_event::<StartWaypointMission>();
+    app.add_event::<StopWaypointMission>();
+    app.add_event::<WaypointActionStarted>();
+
+    app.add_systems(Update, (
+        start_waypoint_mission,
+        navigate_waypoints,
+        perform_waypoint_actions,
+        run_flight_controller,
+    ).chain());
+
+    let player_id = app.world
+        .spawn((
+            Collider::cuboid(1.25, 0.5, 1.5),
+            Player,
+            RigidBody::Dynamic,
+            TransformBundle::from(Transform::from_xyz(0.0, 10.0, 0.0)),
+            ExternalForce {
+                force: Vec3::new(0.0, 73.8, 0.0),
+                ..default()
+            },
+            Velocity::default(),
+            FlightController::default(),
+            Motors::default(),
+        ))
+        .id();
+
+    // the disarmed drone doesn't start the mission
+    app.world.send_event_default::<StartWaypointMission>();
+    app.update();
+
+    assert_eq!(app.world.resource::<AutopilotProgress>().status, AutopilotStatus::Disarmed);
+
+    app.world.entity_mut(player_id).remove::<Motors>();
+    app.world.send_event_default::<StartWaypointMission>();
+
+    for _ in 0..60 * 60 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(1.0 / 60.0));
+        app.update();
+
+        if app.world.resource::<AutopilotProgress>().status != AutopilotStatus::Flying {
+            break;
+        }
+    }
+
+    let progress = app.world.resource::<AutopilotProgress>();
+    let position = app.world.get::<Transform>(player_id).unwrap().translation;
+
+    assert_eq!(progress.status, AutopilotStatus::Completed);
+    assert_eq!(progress.photos, 1);
+    assert!(position.xz().distance(Vec2::new(10.0, -10.0)) < 1.5);
+    assert!(position.y < 1.0);
+    assert!(app.world.get::<FlightController>(player_id).unwrap().setpoint.is_none());
+}
+
+#[test]
+fn did_read_qgc_plan() {
+    let plan = r#"{
+        "fileType": "Plan",
+        "groundStation": "QGroundControl",
+        "mission": {
+            "hoverSpeed": 4,
+            "plannedHomePosition": [47.0, 8.0, 500],
+            "items": [
+                { "type": "SimpleItem", "command": 22, "frame": 3, "params": [0, 0, 0, null, 47.0, 8.0, 20] },
+                { "type": "SimpleItem", "command": 178, "frame": 2, "params": [1, 8, -1, 0, 0, 0, 0] },
+                { "type": "SimpleItem", "command": 16, "frame": 0, "params": [3, 0, 0, 90, 47.0009, 8.0, 530] },
+                { "type": "SimpleItem", "command": 2000, "frame": 2, "params": [0, 0, 1, 0, 0, 0, 0] },
+                {
+                    "type": "ComplexItem",
+                    "complexItemType": "survey",
+                    "TransectStyleComplexItem": {
+                        "Items": [
+                            { "type": "SimpleItem", "command": 16, "frame": 3, "params": [0, 0, 0, null, 47.0, 8.001, 25] }
+                        ]
+                    }
+                },
+                { "type": "SimpleItem", "command": 20, "frame": 2, "params": [0, 0, 0, 0, 0, 0, 0] }
+            ]
+        }
+    }"#;
+
+    let waypoints = read_qgc_plan(plan).unwrap();
+
+    assert_eq!(waypoints.len(), 4);
+    assert_eq!(waypoints[0].target(), Vec3::new(0.0, 20.0, 0.0));
+    assert_eq!(waypoints[0].speed, 4.0);
+
+    // 0.0009 degrees to the north, 30 meters above home
+    let second = &waypoints[1];
+    assert!((second.position.y + 100.0).abs() < 0.5);
+    assert_eq!(second.altitude, 30.0);
+    assert_eq!(second.speed, 8.0);
+    assert_eq!(second.hold, 3.0);
+    assert_eq!(second.yaw, YawMode::Fixed(-90.0_f32.to_radians()));
+    assert_eq!(second.actions, vec![WaypointAction::TakePhoto]);
+
+    assert!(waypoints[2].position.x > 70.0);
+    assert_eq!(waypoints[3].target(), Vec3::new(0.0, 25.0, 0.0));
+    assert_eq!(waypoints[3].actions, vec![WaypointAction::Land]);
+}
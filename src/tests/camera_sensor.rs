@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera::VisionMode,
+    camera_sensor::{
+        update_camera_sensor_effects, update_thermal_sensor_effects, AnalogVideoLink, CameraSensor, ThermalSensor
+    },
+    player::Player,
+    post_processing::{
+        effects::{
+            AnalogStaticSettings, MotionBlurSettings, PostProcessEffect, PostProcessEffectKind, ThermalSensorSettings
+        },
+        PostProcessStack
+    }
+};
+
+#[test]
+fn did_follow_drone_state() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+
+    app.add_systems(Update, update_camera_sensor_effects);
+
+    let player_id = app.world
+        .spawn((
+            Player,
+            Transform::from_xyz(0.0, 10.0, 0.0),
+            Velocity::default(),
+        ))
+        .id();
+
+    let camera_id = app.world
+        .spawn((
+            PostProcessStack::default()
+                .with(PostProcessEffect::MotionBlur(MotionBlurSettings::default()))
+                .with(PostProcessEffect::AnalogStatic(AnalogStaticSettings::default())),
+            CameraSensor::default(),
+            AnalogVideoLink::default(),
+            Transform::from_xyz(0.0, 10.0, 15.0),
+            Projection::default(),
+        ))
+        .id();
+
+    app.update();
+
+    let stack = app.world.get::<PostProcessStack>(camera_id).unwrap();
+    assert_eq!(stack.get(PostProcessEffectKind::MotionBlur).unwrap().intensity(), 0.0);
+    assert_eq!(stack.get(PostProcessEffectKind::AnalogStatic).unwrap().intensity(), 0.0);
+
+    app.world.get_mut::<Velocity>(player_id).unwrap().linvel = Vec3::new(20.0, 0.0, 0.0);
+    app.world.get_mut::<Transform>(player_id).unwrap().translation = Vec3::new(650.0, 10.0, 0.0);
+
+    app.update();
+
+    let stack = app.world.get::<PostProcessStack>(camera_id).unwrap();
+    assert_eq!(stack.get(PostProcessEffectKind::MotionBlur).unwrap().intensity(), 1.0);
+
+    let analog_static = stack.get(PostProcessEffectKind::AnalogStatic).unwrap().intensity();
+    assert!(analog_static > 0.0 && analog_static < 1.0);
+}
+
+#[test]
+fn did_freeze_during_nuc() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+
+    app.add_systems(Update, update_thermal_sensor_effects);
+
+    app.world.spawn(VisionMode::Thermal);
+
+    let camera_id = app.world
+        .spawn((
+            PostProcessStack::default()
+                .with(PostProcessEffect::ThermalSensor(ThermalSensorSettings::default())),
+            ThermalSensor {
+                nuc_interval: 10.0,
+                nuc_duration: 1.0,
+                ..default()
+            },
+        ))
+        .id();
+
+    let is_frozen = |app: &App| {
+        app.world.get::<PostProcessStack>(camera_id).unwrap()
+            .get(PostProcessEffectKind::ThermalSensor).unwrap()
+            .is_frozen()
+    };
+
+    app.world.resource_mut::<Time>().advance_by(Duration::from_secs(5));
+    app.update();
+
+    assert!(!is_frozen(&app));
+
+    app.world.resource_mut::<Time>().advance_by(Duration::from_millis(5500));
+    app.update();
+
+    assert!(is_frozen(&app));
+
+    app.world.resource_mut::<Time>().advance_by(Duration::from_secs(1));
+    app.update();
+
+    assert!(!is_frozen(&app));
+    assert_eq!(app.world.get::<ThermalSensor>(camera_id).unwrap().since_nuc, 0.0);
+}
@@ -0,0 +1,132 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    time::{TimePlugin, TimeSystem},
+};
+use bevy_rapier3d::prelude::*;
+use serde_json::{json, Value};
+
+use crate::{
+    control_server::{process_control_requests, ControlServer, ControlServerSettings, RpcResponse},
+    fleet::{fly_unpiloted_drones, Drone, DroneControl, DroneSetpoint, SpawnDrone},
+    player::{FlightController, Motors},
+    sim_clock::{advance_sim_step, apply_sim_clock, SimClock},
+};
+
+#[test]
+fn did_control_simulation_over_socket() {
+    let mut app = App::new();
+
+    app.add_plugins(TimePlugin);
+    app.init_resource::<RapierConfiguration>();
+    app.insert_resource(ControlServerSettings::default());
+    app.insert_resource(ControlServer::listen("127.0.0.1:0".parse().unwrap()).unwrap());
+    app.add_event::<SpawnDrone>();
+    app.add_event::<DroneSetpoint>();
+    app.init_resource::<SimClock>();
+    app.add_systems(First, (
+        process_control_requests.before(apply_sim_clock),
+        apply_sim_clock.before(TimeSystem),
+        advance_sim_step.after(TimeSystem),
+    ));
+    app.add_systems(Update, fly_unpiloted_drones);
+
+    let drone = app.world
+        .spawn((
+            Drone {
+                id: 3,
+                airframe: "scout".to_owned(),
+                control: DroneControl::Autopilot,
+            },
+            Transform::from_xyz(1.0, 5.0, 0.0),
+            Motors::default(),
+            FlightController::default(),
+        ))
+        .id();
+
+    let address = app.world.resource::<ControlServer>().address;
+
+    // the loopback client
+    let client = thread::spawn(move || {
+        let stream = TcpStream::connect(address).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut call = |id: u32, method: &str, params: Value| {
+            let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+            writeln!(writer, "{request}").unwrap();
+
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            serde_json::from_str::<RpcResponse>(&line).unwrap()
+        };
+
+        let responses = vec![
+            call(1, "get_state", Value::Null),
+            call(2, "set_setpoint", json!({ "id": 3, "velocity": [0.0, 1.0, 0.0], "yaw": 0.5 })),
+            call(3, "pause", Value::Null),
+            call(4, "step", json!({ "frames": 2 })),
+            call(5, "spawn_object", json!({
+                "shape": { "box": { "size": [1.0, 2.0, 1.0] } },
+                "position": [0.0, 1.0, 0.0],
+                "temperature": 36.6,
+            })),
+            call(6, "spawn_drone", json!({ "id": 3, "airframe": "scout", "position": [0.0, 0.0, 0.0] })),
+            call(7, "fly", Value::Null),
+            call(8, "get_state", Value::Null),
+            call(9, "despawn_drone", json!({ "id": 3 })),
+        ];
+
+        writeln!(writer, "not json").unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+
+        (responses, serde_json::from_str::<RpcResponse>(&line).unwrap())
+    });
+
+    while !client.is_finished() {
+        app.update();
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let (responses, parse_error) = client.join().unwrap();
+    let result = |index: usize| responses[index].result.clone().unwrap();
+
+    assert_eq!(result(0)["drones"][0]["id"], 3);
+    assert_eq!(result(0)["drones"][0]["position"], json!([1.0, 5.0, 0.0]));
+
+    // stepping two frames of the paused simulation
+    assert_eq!(result(3)["frame"].as_u64(), result(2)["frame"].as_u64().map(|frame| frame + 2));
+    assert!(app.world.resource::<Time<Virtual>>().is_paused());
+    assert!(!app.world.resource::<RapierConfiguration>().physics_pipeline_active);
+
+    assert!(result(4)["entity"].is_u64());
+    assert_eq!(responses[5].error.as_ref().unwrap().code, -32000);
+    assert_eq!(responses[6].error.as_ref().unwrap().code, -32601);
+    assert_eq!(responses[6].id, json!(7));
+    assert_eq!(parse_error.error.unwrap().code, -32700);
+
+    // the drone held the setpoint, before it was despawned
+    assert_eq!(result(7)["drones"][0]["control"], "External");
+    assert_eq!(result(7)["drones"][0]["setpoint_velocity"], json!([0.0, 1.0, 0.0]));
+    assert!(app.world.get_entity(drone).is_none());
+    assert_eq!(app.world.query::<&Drone>().iter(&app.world).count(), 0);
+}
+
+#[test]
+fn did_read_control_server_address() {
+    let args = |args: &[&str]| {
+        ControlServerSettings::address_from_args(args.iter().map(|arg| arg.to_string())).map(Result::ok)
+    };
+
+    assert_eq!(args(&["supersonic"]), None);
+    assert_eq!(args(&["supersonic", "--control-server"]), Some("127.0.0.1:7700".parse().ok()));
+    assert_eq!(args(&["supersonic", "--control-server", "0.0.0.0:7800"]), Some("0.0.0.0:7800".parse().ok()));
+    assert_eq!(args(&["supersonic", "--control-server", "localhost"]), Some(None));
+}
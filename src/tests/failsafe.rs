@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::{
+    autopilot::StopWaypointMission,
+    failsafe::{
+        run_failsafes, trigger_failsafes, Failsafe, FailsafeAction, FailsafeSettings, FailsafeState, FailsafeTrigger,
+        FailureInjection, ReturnPhase,
+    },
+    player::{Battery, FlightController, HomePosition, Motors, Player},
+};
+
+fn failsafe_app(settings: FailsafeSettings) -> (App, Entity) {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<ButtonInput<KeyCode>>();
+    app.init_resource::<FailureInjection>();
+    app.insert_resource(settings);
+    app.add_event::<StopWaypointMission>();
+
+    app.add_systems(Update, (trigger_failsafes, run_failsafes).chain());
+
+    let drone_id = app.world
+        .spawn((
+            Player,
+            Battery::default(),
+            Motors {
+                is_armed: true,
+                ..default()
+            },
+            FlightController::default(),
+            Failsafe::default(),
+            HomePosition(Vec3::ZERO),
+            TransformBundle::from(Transform::from_xyz(50.0, 10.0, 0.0)),
+        ))
+        .id();
+
+    (app, drone_id)
+}
+
+#[test]
+fn did_return_to_launch_on_low_battery() {
+    let (mut app, drone_id) = failsafe_app(FailsafeSettings::default());
+
+    let battery = Battery::default();
+    app.world.get_mut::<Battery>(drone_id).unwrap().remaining = battery.capacity * 0.15;
+    app.update();
+
+    let failsafe = app.world.get::<Failsafe>(drone_id).unwrap();
+
+    assert_eq!(failsafe.state, FailsafeState::ReturnToLaunch(ReturnPhase::Climb));
+    assert_eq!(failsafe.trigger, Some(FailsafeTrigger::LowBattery));
+    assert!(app.world.get::<FlightController>(drone_id).unwrap().setpoint.unwrap().velocity.y > 0.0);
+
+    // at the return altitude it flies home
+    app.world.get_mut::<Transform>(drone_id).unwrap().translation.y = 30.0;
+    app.update();
+    app.update();
+
+    assert_eq!(app.world.get::<Failsafe>(drone_id).unwrap().state, FailsafeState::ReturnToLaunch(ReturnPhase::Return));
+    assert!(app.world.get::<FlightController>(drone_id).unwrap().setpoint.unwrap().velocity.x < 0.0);
+
+    // without GPS it can't find home, so it lands where it is
+    app.world.resource_mut::<FailureInjection>().gps_lost = true;
+    app.update();
+
+    let failsafe = app.world.get::<Failsafe>(drone_id).unwrap();
+
+    assert_eq!(failsafe.state, FailsafeState::Land);
+    assert!(app.world.get::<FlightController>(drone_id).unwrap().setpoint.unwrap().velocity.y < 0.0);
+
+    app.world.get_mut::<Transform>(drone_id).unwrap().translation.y = 0.5;
+    app.update();
+
+    assert_eq!(app.world.get::<Failsafe>(drone_id).unwrap().state, FailsafeState::Disarmed);
+    assert!(!app.world.get::<Motors>(drone_id).unwrap().is_armed);
+    assert!(app.world.get::<FlightController>(drone_id).unwrap().setpoint.is_none());
+}
+
+#[test]
+fn did_hover_while_rc_link_is_lost() {
+    let (mut app, drone_id) = failsafe_app(FailsafeSettings {
+        rc_loss_action: FailsafeAction::Hover,
+        ..default()
+    });
+
+    app.world.resource_mut::<FailureInjection>().rc_link_lost = true;
+    app.update();
+
+    assert_eq!(
+        app.world.get::<Failsafe>(drone_id).unwrap().state,
+        FailsafeState::Hover { point: Vec3::new(50.0, 10.0, 0.0) },
+    );
+
+    // the drone drifted away and is brought back
+    app.world.get_mut::<Transform>(drone_id).unwrap().translation.x = 52.0;
+    app.update();
+
+    assert!(app.world.get::<FlightController>(drone_id).unwrap().setpoint.unwrap().velocity.x < 0.0);
+
+    app.world.resource_mut::<FailureInjection>().rc_link_lost = false;
+    app.update();
+
+    assert_eq!(app.world.get::<Failsafe>(drone_id).unwrap().state, FailsafeState::Normal);
+    assert!(app.world.get::<FlightController>(drone_id).unwrap().setpoint.is_none());
+    assert!(app.world.get::<Motors>(drone_id).unwrap().is_armed);
+}
+
+#[test]
+fn did_climb_above_raised_launch_pad() {
+    let (mut app, drone_id) = failsafe_app(FailsafeSettings::default());
+
+    // the launch pad is on a hill, 20 meters high, and the drone is 35 meters high
+    app.world.get_mut::<HomePosition>(drone_id).unwrap().0 = Vec3::new(0.0, 20.0, 0.0);
+    app.world.get_mut::<Transform>(drone_id).unwrap().translation.y = 35.0;
+
+    let battery = Battery::default();
+    app.world.get_mut::<Battery>(drone_id).unwrap().remaining = battery.capacity * 0.15;
+    app.update();
+    app.update();
+
+    assert_eq!(app.world.get::<Failsafe>(drone_id).unwrap().state, FailsafeState::ReturnToLaunch(ReturnPhase::Climb));
+    assert!(app.world.get::<FlightController>(drone_id).unwrap().setpoint.unwrap().velocity.y > 0.0);
+
+    app.world.get_mut::<Transform>(drone_id).unwrap().translation.y = 50.0;
+    app.update();
+
+    assert_eq!(app.world.get::<Failsafe>(drone_id).unwrap().state, FailsafeState::ReturnToLaunch(ReturnPhase::Return));
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::{
+    autopilot::StopWaypointMission,
+    fleet::{
+        fly_unpiloted_drones, record_telemetry, select_drones, Airframe, Drone, DroneControl, DroneSetpoint,
+        SelectDrone, Telemetry,
+    },
+    player::{FlightController, FlightSetpoint, Motors, Player},
+    search_and_rescue::{track_search_progress, MissionStatus, SearchMission, SearchMissionProgress},
+};
+
+fn spawn_drone(app: &mut App, id: u32, control: DroneControl, position: Vec3) -> Entity {
+    app.world
+        .spawn((
+            Drone {
+                id,
+                airframe: "quadcopter".to_owned(),
+                control,
+            },
+            FlightController::default(),
+            Motors {
+                is_armed: true,
+                ..default()
+            },
+            Telemetry::default(),
+            TransformBundle::from(Transform::from_translation(position)),
+        ))
+        .id()
+}
+
+#[test]
+fn did_switch_piloted_drone() {
+    assert_eq!(Airframe::load(Airframe::file_path("quadcopter")).unwrap(), Airframe::default());
+
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.add_event::<SelectDrone>();
+    app.add_event::<DroneSetpoint>();
+    app.add_event::<StopWaypointMission>();
+
+    app.add_systems(Update, (select_drones, fly_unpiloted_drones, record_telemetry).chain());
+
+    let first_id = spawn_drone(&mut app, 0, DroneControl::Autopilot, Vec3::new(0.0, 10.0, 0.0));
+    let second_id = spawn_drone(&mut app, 1, DroneControl::External, Vec3::new(6.0, 10.0, 0.0));
+    app.world.entity_mut(first_id).insert(Player);
+
+    let climb = Some(FlightSetpoint { velocity: Vec3::Y, yaw: None });
+
+    app.world.send_event(DroneSetpoint { id: 1, setpoint: climb });
+    app.update();
+
+    assert_eq!(app.world.get::<FlightController>(second_id).unwrap().setpoint, climb);
+    assert!(app.world.get::<FlightController>(first_id).unwrap().setpoint.is_none());
+    assert!(app.world.get::<Telemetry>(second_id).unwrap().latest().is_some_and(|sample| sample.is_armed));
+
+    // the operator takes over the second drone, the autopilot holds the first one
+    app.world.send_event(SelectDrone(1));
+    app.update();
+
+    assert!(app.world.get::<Player>(second_id).is_some());
+    assert!(app.world.get::<Player>(first_id).is_none());
+    assert!(app.world.get::<FlightController>(second_id).unwrap().setpoint.is_none());
+    assert_eq!(
+        app.world.get::<FlightController>(first_id).unwrap().setpoint,
+        Some(FlightSetpoint { velocity: Vec3::ZERO, yaw: None }),
+    );
+
+    // external programs can't fly the piloted drone
+    app.world.send_event(DroneSetpoint { id: 1, setpoint: climb });
+    app.update();
+
+    assert!(app.world.get::<FlightController>(second_id).unwrap().setpoint.is_none());
+}
+
+#[test]
+fn did_search_area_cooperatively() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<SearchMission>();
+
+    let mission = app.world.resource::<SearchMission>().clone();
+    app.insert_resource(SearchMissionProgress {
+        status: MissionStatus::Active,
+        area_cells: mission.area_cells(),
+        ..default()
+    });
+
+    app.add_systems(Update, track_search_progress);
+
+    let player_id = spawn_drone(&mut app, 0, DroneControl::Autopilot, Vec3::new(-60.0, 30.0, 0.0));
+    app.world.entity_mut(player_id).insert(Player);
+    spawn_drone(&mut app, 1, DroneControl::Autopilot, Vec3::new(60.0, 30.0, 0.0));
+
+    app.update();
+
+    let progress = app.world.resource::<SearchMissionProgress>();
+
+    assert!(progress.covered_cells.contains(&mission.cell_at(Vec2::new(-60.0, 0.0))));
+    assert!(progress.covered_cells.contains(&mission.cell_at(Vec2::new(60.0, 0.0))));
+    assert!(!progress.covered_cells.contains(&mission.cell_at(Vec2::ZERO)));
+}
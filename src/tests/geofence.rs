@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    geofence::{check_geofence, BreachAction, BreachKind, FenceShape, FenceZone, Geofence, GeofenceBreach, GeofenceStatus},
+    player::Player,
+};
+
+#[test]
+fn did_detect_geofence_breaches() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<GeofenceStatus>();
+    app.insert_resource(Geofence {
+        inclusion: Some(FenceZone {
+            name: "Flight area".to_owned(),
+            shape: FenceShape::Cylinder { center: Vec2::ZERO, radius: 50.0 },
+            min_altitude: 0.0,
+            max_altitude: 30.0,
+        }),
+        exclusions: vec![FenceZone {
+            name: "Hospital".to_owned(),
+            shape: FenceShape::Polygon(vec![
+                Vec2::new(10.0, 10.0),
+                Vec2::new(20.0, 10.0),
+                Vec2::new(20.0, 20.0),
+                Vec2::new(10.0, 20.0),
+            ]),
+            min_altitude: 0.0,
+            max_altitude: 100.0,
+        }],
+        action: BreachAction::Hold,
+        ..default()
+    });
+    app.add_event::<GeofenceBreach>();
+
+    app.add_systems(Update, check_geofence);
+
+    let player_id = app.world
+        .spawn((Player, TransformBundle::from(Transform::from_xyz(0.0, 10.0, 0.0))))
+        .id();
+
+    app.update();
+
+    assert!(app.world.resource::<GeofenceStatus>().breach.is_none());
+
+    // fly into the restricted zone
+    app.world.get_mut::<Transform>(player_id).unwrap().translation = Vec3::new(15.0, 10.0, 15.0);
+    app.world.resource_mut::<Time>().advance_by(Duration::from_millis(100));
+    app.update();
+
+    let status = app.world.resource::<GeofenceStatus>();
+
+    assert_eq!(status.breach, Some(BreachKind::RestrictedZone("Hospital".to_owned())));
+    assert_eq!(status.log.len(), 1);
+    assert_eq!(status.log[0].action, BreachAction::Hold);
+    assert_eq!(status.last_inside, Some(Vec3::new(0.0, 10.0, 0.0)));
+
+    // the breach goes on, so it is not logged again
+    app.update();
+
+    assert_eq!(app.world.resource::<GeofenceStatus>().log.len(), 1);
+
+    // above the ceiling
+    app.world.get_mut::<Transform>(player_id).unwrap().translation = Vec3::new(0.0, 40.0, 0.0);
+    app.world.resource_mut::<Time>().advance_by(Duration::from_secs(1));
+    app.update();
+
+    let status = app.world.resource::<GeofenceStatus>();
+
+    assert_eq!(status.breach, Some(BreachKind::OutsideFence));
+    assert_eq!(status.log.len(), 2);
+}
@@ -0,0 +1,100 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use crate::{
+    camera_sensor::RayCastCamera,
+    gym::{
+        Action, DroneEnv, DroneState, FrameKind, GymSettings, HoverReward, RewardFunction, SearchReward, VecDroneEnv,
+        OBSERVATION_SIZE,
+    },
+};
+
+#[test]
+fn did_fly_drone_with_actions() {
+    let hover = || Box::new(HoverReward { target: Vec3::new(0.0, 10.0, 0.0), crash_penalty: 100.0 });
+    let mut env = DroneEnv::new(GymSettings::default(), hover());
+
+    assert_eq!(env.reset(7).len(), OBSERVATION_SIZE);
+
+    // rotors at half hold the drone in the air
+    for _ in 0..20 {
+        let result = env.step(Action::Rotors([0.5; 4]));
+
+        assert!(!result.is_done);
+    }
+
+    assert!((env.state().position.y - 10.0).abs() < 0.05);
+    assert!(env.state().battery_charge < 1.0);
+
+    let result = env.step(Action::Sticks { throttle: 1.0, roll: 0.0, pitch: 0.5, yaw: 0.0 });
+
+    assert!(result.state.velocity.y > 0.0 && result.state.velocity.z < 0.0);
+    assert!(result.reward < 0.0);
+
+    // stopped rotors drop the drone
+    let result = (0..100)
+        .map(|_| env.step(Action::Rotors([0.0; 4])))
+        .find(|result| result.is_done)
+        .unwrap();
+
+    assert!(result.state.is_crashed);
+    assert!(result.reward < -100.0);
+
+    // independent environments give the same results for the same seed
+    let mut envs = VecDroneEnv::new(GymSettings::default(), 3, || hover());
+    envs.reset(7);
+    envs.envs[2].reset(7);
+
+    let actions = [Action::Rotors([0.6, 0.6, 0.5, 0.5]), Action::default(), Action::Rotors([0.6, 0.6, 0.5, 0.5])];
+
+    for _ in 0..10 {
+        envs.step(&actions);
+    }
+
+    let states: Vec<_> = envs.envs.iter().map(|env| env.state().clone()).collect();
+
+    assert_eq!(states[0], states[2]);
+    assert_ne!(states[0], states[1]);
+    assert!(states[0].angular_velocity.x > 0.0);
+}
+
+#[test]
+fn did_render_frames_and_find_people() {
+    let settings = GymSettings {
+        targets: 1,
+        area: 0.0,
+        camera: RayCastCamera {
+            width: 33,
+            height: 25,
+            pitch: FRAC_PI_2,
+            ..default()
+        },
+        frame: Some(FrameKind::Thermal),
+        ..default()
+    };
+
+    let mut env = DroneEnv::new(settings, Box::new(SearchReward::default()));
+    env.reset(1);
+
+    let result = env.step(Action::default());
+    let frame = result.frame.unwrap();
+
+    // the person is right under the drone
+    assert_eq!(result.state.visible_targets, vec![0]);
+    assert!((frame.pixel(16, 12) - 36.6).abs() < 1e-4);
+    assert_eq!(frame.pixel(0, 0), 15.0);
+    assert!(result.is_done);
+
+    // it was seen from the start
+    let mut reward = SearchReward::default();
+
+    assert!((result.reward + 0.01).abs() < 1e-4);
+    assert!((reward.reward(&DroneState::default(), &result.state) - 0.99).abs() < 1e-4);
+
+    let depth = env.render(FrameKind::Depth);
+    let height = env.state().position.y;
+
+    assert!((depth.pixel(16, 12) - (height - 1.7)).abs() < 0.1);
+    assert!(depth.pixel(0, 0) > height);
+}
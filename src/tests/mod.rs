@@ -1,2 +1,44 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
 mod post_processing;
-mod player;
\ No newline at end of file
+mod player;
+mod camera_sensor;
+mod sky;
+mod weather;
+mod terrain;
+mod world;
+mod agents;
+mod search_and_rescue;
+mod racing;
+mod autopilot;
+mod geofence;
+mod failsafe;
+mod arming;
+mod fleet;
+mod swarm;
+mod recorder;
+mod replay;
+mod gym;
+mod control_server;
+mod ros_bridge;
+mod sim_clock;
+mod ui;
+
+/// Adds Rapier physics to the test app, with the assets and scenes it needs.
+fn add_physics(app: &mut App) {
+    app.init_resource::<Assets<Mesh>>();
+    app.init_resource::<Assets<Scene>>();
+    app.init_resource::<SceneSpawner>();
+    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default());
+}
+
+/// Adds physics, which steps 1/60 of a second every update, and `Time`, which the test advances itself.
+fn add_fixed_physics(app: &mut App) {
+    app.init_resource::<Time>();
+    add_physics(app);
+    app.insert_resource(RapierConfiguration {
+        timestep_mode: TimestepMode::Fixed { dt: 1.0 / 60.0, substeps: 1 },
+        ..RapierConfiguration::new(1.0)
+    });
+}
@@ -1,13 +1,16 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
-use crate::player::{player_movement, Player};
+use crate::{
+    player::{drain_battery, player_movement, Battery, Player},
+    tests::add_fixed_physics,
+};
 
 #[test]
 fn did_change_height() {
     let mut app = App::new();
 
-    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default());
+    add_fixed_physics(&mut app);
 
     app.add_systems(Update, player_movement);
 
@@ -52,4 +55,32 @@ fn did_change_height() {
     app.update();
 
     assert_ne!(app.world.get::<Transform>(player_id).unwrap().translation, current_position);
-}
\ No newline at end of file
+}
+
+#[test]
+fn did_drain_battery() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.add_systems(Update, drain_battery);
+
+    let battery = Battery::default();
+
+    let drone_id = app.world
+        .spawn((
+            ExternalForce {
+                force: Vec3::new(0.0, battery.hover_thrust, 0.0),
+                ..default()
+            },
+            battery.clone(),
+        ))
+        .id();
+
+    app.world.resource_mut::<Time>().advance_by(std::time::Duration::from_secs(60));
+    app.update();
+
+    let used = app.world.get::<Battery>(drone_id).unwrap().used();
+
+    // a minute of hover
+    assert!((used - battery.hover_power / 60.0).abs() < 0.01);
+}
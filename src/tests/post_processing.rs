@@ -1,47 +1,127 @@
 use bevy::prelude::*;
 
 use crate::{
-    camera::{update_post_processing, IsPostProcessingActive}, 
-    post_processing::PostProcessSettings
+    camera::{update_post_processing, VisionMode, VisionModeBindings}, 
+    post_processing::{
+        effects::{
+            GrayscaleSettings, NightVisionSettings, NoiseSettings, PostProcessEffect, PostProcessEffectKind, 
+            VignetteSettings
+        }, 
+        PostProcessStack
+    }
 };
 
+/// Presses and releases the key, then updates the app.
+fn tap(app: &mut App, key: KeyCode) {
+    let mut input = ButtonInput::<KeyCode>::default();
+    input.press(key);
+    app.insert_resource(input);
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().release(key);
+
+    app.update();
+
+    app.world.resource_mut::<ButtonInput<KeyCode>>().clear();
+}
+
 #[test]
 fn did_switch_camera_mode() {
     let mut app = App::new();
 
+    app.init_resource::<VisionModeBindings>();
     app.add_systems(Update, update_post_processing);
 
     let camera_id = app.world
         .spawn((
-            IsPostProcessingActive(false),
-            PostProcessSettings {
-                intensity: 0.0,
-            },
+            VisionMode::Day,
+            PostProcessStack::default()
+                .with(PostProcessEffect::Grayscale(GrayscaleSettings {
+                    intensity: 0.0,
+                    ..default()
+                })),
         ))
         .id();
 
-    assert!(app.world.get::<IsPostProcessingActive>(camera_id).is_some());
-    
-    let mut input = ButtonInput::<KeyCode>::default();
-    input.press(KeyCode::KeyJ);
-    app.insert_resource(input.clone());
+    let thermal_camera_id = app.world
+        .spawn(
+            PostProcessStack::default()
+                .with(PostProcessEffect::NightVision(NightVisionSettings {
+                    intensity: 0.0,
+                    ..default()
+                })),
+        )
+        .id();
 
-    app.world.resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::KeyJ);
+    let intensity = |app: &App, entity: Entity, kind: PostProcessEffectKind| {
+        app.world.get::<PostProcessStack>(entity).unwrap()
+            .get(kind).unwrap()
+            .intensity()
+    };
 
-    app.update();
+    assert!(app.world.get::<VisionMode>(camera_id).is_some());
 
-    assert_eq!(app.world.get::<IsPostProcessingActive>(camera_id).unwrap().0, true);
+    tap(&mut app, KeyCode::KeyJ);
 
-    app.world.resource_mut::<ButtonInput<KeyCode>>().clear();
+    assert_eq!(*app.world.get::<VisionMode>(camera_id).unwrap(), VisionMode::Thermal);
+    assert_eq!(intensity(&app, camera_id, PostProcessEffectKind::Grayscale), 1.0);
+    assert_eq!(intensity(&app, thermal_camera_id, PostProcessEffectKind::NightVision), 0.0);
 
-    app.update();
+    tap(&mut app, KeyCode::KeyJ);
 
-    input.press(KeyCode::KeyJ);
-    app.insert_resource(input.clone());
+    assert_eq!(*app.world.get::<VisionMode>(camera_id).unwrap(), VisionMode::NightVision);
+    assert_eq!(intensity(&app, camera_id, PostProcessEffectKind::Grayscale), 0.0);
+    assert_eq!(intensity(&app, thermal_camera_id, PostProcessEffectKind::NightVision), 1.0);
 
-    app.world.resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::KeyJ);
+    tap(&mut app, KeyCode::KeyJ);
 
-    app.update();
+    assert_eq!(*app.world.get::<VisionMode>(camera_id).unwrap(), VisionMode::Day);
+    assert_eq!(intensity(&app, thermal_camera_id, PostProcessEffectKind::NightVision), 0.0);
+}
+
+#[test]
+fn did_use_custom_vision_mode_bindings() {
+    let mut app = App::new();
+
+    app.insert_resource(VisionModeBindings {
+        cycle: KeyCode::KeyN,
+        modes: vec![VisionMode::Day, VisionMode::NightVision],
+    });
+    app.add_systems(Update, update_post_processing);
+
+    let camera_id = app.world
+        .spawn((VisionMode::Day, PostProcessStack::default()))
+        .id();
+
+    tap(&mut app, KeyCode::KeyJ);
+
+    assert_eq!(*app.world.get::<VisionMode>(camera_id).unwrap(), VisionMode::Day);
+
+    tap(&mut app, KeyCode::KeyN);
+
+    assert_eq!(*app.world.get::<VisionMode>(camera_id).unwrap(), VisionMode::NightVision);
+
+    tap(&mut app, KeyCode::KeyN);
+
+    assert_eq!(*app.world.get::<VisionMode>(camera_id).unwrap(), VisionMode::Day);
+}
+
+#[test]
+fn did_reorder_effects_stack() {
+    let mut stack = PostProcessStack::default()
+        .with(PostProcessEffect::Grayscale(GrayscaleSettings::default()))
+        .with(PostProcessEffect::Vignette(VignetteSettings::default()));
+
+    stack.insert(0, PostProcessEffect::Noise(NoiseSettings::default()));
+
+    assert_eq!(stack.position(PostProcessEffectKind::Noise), Some(0));
+    assert_eq!(stack.position(PostProcessEffectKind::Vignette), Some(2));
+
+    assert!(stack.move_to(PostProcessEffectKind::Noise, 10));
+    assert_eq!(stack.position(PostProcessEffectKind::Noise), Some(2));
+
+    assert!(stack.remove(PostProcessEffectKind::Grayscale).is_some());
+    assert!(stack.get(PostProcessEffectKind::Grayscale).is_none());
+    assert!(!stack.move_to(PostProcessEffectKind::Grayscale, 0));
 
-    assert_eq!(app.world.get::<IsPostProcessingActive>(camera_id).unwrap().0, false);
-}
\ No newline at end of file
+    assert_eq!(stack.effects.len(), 2);
+}
@@ -0,0 +1,101 @@
+use std::{env, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::racing::{
+    process_gate_passes, update_race_hud, GateDefinition, GatePassed, RaceHudText, RaceState, RaceTrack, TrackEditor,
+};
+
+#[test]
+fn did_time_laps() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<RaceState>();
+    app.insert_resource(RaceTrack {
+        // the best lap file should not be written next to the real tracks
+        path: Some(env::temp_dir().join("supersonic_did_time_laps.ron")),
+        ..default()
+    });
+    app.add_event::<GatePassed>();
+
+    app.add_systems(Update, process_gate_passes);
+
+    let pass = |app: &mut App, gate: usize, seconds: u64| {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(seconds));
+        app.world.send_event(GatePassed { gate });
+        app.update();
+    };
+
+    // clean lap
+    for gate in [0, 1, 2, 3] {
+        pass(&mut app, gate, 2);
+    }
+    pass(&mut app, 0, 2);
+
+    let state = app.world.resource::<RaceState>();
+
+    assert_eq!(state.laps, 1);
+    assert_eq!(state.last_lap, Some(8.0));
+    assert_eq!(state.best_lap.as_ref().unwrap().splits, vec![2.0, 4.0, 6.0]);
+
+    // faster lap with a missed gate is not the best one
+    pass(&mut app, 2, 1);
+    pass(&mut app, 3, 1);
+    pass(&mut app, 0, 1);
+
+    let state = app.world.resource::<RaceState>();
+
+    assert_eq!(state.laps, 2);
+    assert_eq!(state.last_lap, Some(3.0));
+    assert_eq!(state.missed_gates, 1);
+    assert_eq!(state.best_lap.as_ref().unwrap().time, 8.0);
+}
+
+#[test]
+fn did_show_split_after_lap_restart() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<TrackEditor>();
+    app.insert_resource(RaceTrack {
+        gates: vec![GateDefinition::new(Vec3::ZERO, 0.0); 3],
+        path: None,
+        ..default()
+    });
+
+    let mut state = RaceState::default();
+    for (gate, now) in [(0, 0.0), (1, 2.0), (2, 4.0), (0, 6.0)] {
+        state.pass_gate(gate, now, 3);
+    }
+    app.insert_resource(state);
+
+    app.add_systems(Update, update_race_hud);
+
+    let hud_id = app.world.spawn((Text::from_section("", default()), RaceHudText)).id();
+
+    // the lap has just restarted, so there are no splits to compare yet
+    app.update();
+
+    assert!(!app.world.get::<Text>(hud_id).unwrap().sections[0].value.contains("Split"));
+
+    app.world.resource_mut::<RaceState>().pass_gate(1, 7.5, 3);
+    app.update();
+
+    assert!(app.world.get::<Text>(hud_id).unwrap().sections[0].value.contains("Split: -0.50"));
+}
+
+#[test]
+fn did_save_and_load_track() {
+    let path = env::temp_dir().join("supersonic_did_save_and_load_track.ron");
+
+    let mut track = RaceTrack::default();
+    track.gates.push(GateDefinition::new(Vec3::new(1.0, 2.0, 3.0), 0.5));
+
+    track.save(&path).unwrap();
+    let loaded = RaceTrack::load(&path).unwrap();
+
+    assert_eq!(loaded.gates, track.gates);
+    assert_eq!(loaded.name, track.name);
+    assert_eq!(loaded.path, Some(path));
+}
@@ -0,0 +1,256 @@
+use std::{env, f32::consts::FRAC_PI_2, fs, time::Duration};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    camera::VisionMode,
+    fleet::{Drone, DroneControl},
+    player::{FlightController, FlightSetpoint, Motors},
+    recorder::{
+        export::{export_betaflight_csv, export_ulog, read_betaflight_csv, read_ulog},
+        flight_log::{
+            BinaryLogWriter, CsvLogWriter, FlightLog, FlightRecord, LogError, LogWriter, SessionMetadata,
+            FORMAT_VERSION,
+        },
+        process_recorder_commands, record_flight, FlightRecorder, RecorderSettings,
+        StartFlightRecording, StopFlightRecording,
+    },
+};
+
+#[test]
+fn did_record_and_rotate_flight_logs() {
+    let directory = env::temp_dir().join("supersonic_did_record_and_rotate_flight_logs");
+    let _ = fs::remove_dir_all(&directory);
+
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<FlightRecorder>();
+    app.insert_resource(RecorderSettings {
+        directory: directory.clone(),
+        records_per_file: 6,
+        max_parts: 2,
+        ..default()
+    });
+    app.add_event::<StartFlightRecording>();
+    app.add_event::<StopFlightRecording>();
+    app.add_systems(Update, (process_recorder_commands, record_flight).chain());
+
+    for (id, setpoint) in [(1, Some(Vec3::X)), (2, None)] {
+        app.world.spawn((
+            Drone {
+                id,
+                airframe: "scout".to_owned(),
+                control: DroneControl::Autopilot,
+            },
+            Transform::from_xyz(id as f32, 5.0, 0.0),
+            Velocity::linear(Vec3::Y),
+            Motors {
+                is_armed: true,
+                applied: 73.8,
+                ..default()
+            },
+            FlightController {
+                setpoint: setpoint.map(|velocity| FlightSetpoint { velocity, yaw: Some(0.5) }),
+                ..default()
+            },
+        ));
+    }
+
+    app.world.send_event(StartFlightRecording);
+
+    // 10 samples of 2 drones in parts of 6 records
+    for _ in 0..10 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_millis(100));
+        app.update();
+    }
+
+    app.world.send_event(StopFlightRecording);
+    app.update();
+
+    assert!(!app.world.resource::<FlightRecorder>().is_recording());
+
+    let mut files: Vec<_> = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+
+    // the first two parts are rotated away
+    assert_eq!(files.len(), 4);
+    assert!(files[0].ends_with("_002.bin"));
+    assert!(files[3].ends_with("_003.csv"));
+
+    let full = FlightLog::read(directory.join(&files[1])).unwrap();
+    let last = FlightLog::read(directory.join(&files[2])).unwrap();
+
+    assert_eq!(full, FlightLog::read(directory.join(&files[0])).unwrap());
+    assert_eq!(last, FlightLog::read(directory.join(&files[3])).unwrap());
+
+    assert_eq!(full.metadata.part, 2);
+    assert_eq!(full.metadata.drones, vec![(1, "scout".to_owned()), (2, "scout".to_owned())]);
+    assert_eq!(full.records.len(), 6);
+    assert_eq!(last.records.len(), 2);
+
+    let record = full.drone_records(1).next().unwrap();
+
+    assert_eq!(record.position, Vec3::new(1.0, 5.0, 0.0));
+    assert_eq!(record.velocity_error, Some(Vec3::new(1.0, -1.0, 0.0)));
+    assert_eq!(record.setpoint_yaw, Some(0.5));
+    assert_eq!(record.motor_thrust, 73.8);
+    assert!(record.is_armed);
+    assert!(full.drone_records(2).all(|record| record.setpoint_velocity.is_none()));
+}
+
+#[test]
+fn did_export_ulog_and_betaflight_logs() {
+    let record = |time: f32| FlightRecord {
+        time,
+        drone: 1,
+        position: Vec3::new(3.0, 10.0, -4.0),
+        // facing west
+        rotation: Quat::from_rotation_y(FRAC_PI_2),
+        velocity: Vec3::new(1.0, 0.0, -2.0),
+        angular_velocity: Vec3::new(0.0, 0.5, 0.0),
+        motor_thrust: 73.8,
+        thrust: Vec3::ZERO,
+        setpoint_velocity: Some(Vec3::new(1.0, 0.0, -2.0)),
+        setpoint_yaw: Some(FRAC_PI_2),
+        velocity_error: Some(Vec3::ZERO),
+        altitude: 9.5,
+        temperature: 20.0,
+        video_breakup: 0.0,
+        battery_remaining: 77.0,
+        battery_charge: 1.0,
+        is_armed: true,
+        vision_mode: VisionMode::Day,
+    };
+
+    let log = FlightLog {
+        metadata: SessionMetadata {
+            version: FORMAT_VERSION,
+            session: "flight_1".to_owned(),
+            part: 0,
+            created: 1,
+            rate: 10.0,
+            drones: vec![(1, "quadcopter".to_owned())],
+        },
+        records: vec![record(0.0), record(0.1), record(0.2)],
+    };
+
+    let mut bytes = Vec::new();
+    export_ulog(&log, 1, &mut bytes).unwrap();
+    let ulog = read_ulog(&mut bytes.as_slice()).unwrap();
+
+    assert_eq!(ulog.timestamp, 1_000_000);
+    assert_eq!(ulog.info["sys_name"], "supersonic");
+    assert_eq!(ulog.info["ver_hw"], "quadcopter");
+
+    let position = &ulog.topics["vehicle_local_position"];
+    let close = |values: Option<Vec<f64>>, expected: f64| {
+        values.unwrap().iter().all(|value| (value - expected).abs() < 1e-4)
+    };
+
+    assert_eq!(position.column("timestamp"), Some(vec![0.0, 100_000.0, 200_000.0]));
+    assert!(close(position.column("x"), 4.0));
+    assert!(close(position.column("y"), 3.0));
+    assert!(close(position.column("z"), -10.0));
+    assert!(close(position.column("vx"), 2.0));
+    assert!(close(position.column("heading"), -f64::from(FRAC_PI_2)));
+
+    let attitude = &ulog.topics["vehicle_attitude"];
+    let q: Vec<_> = (0..4).map(|index| attitude.column(&format!("q[{index}]")).unwrap()[0] as f32).collect();
+
+    // yaw to the left around the down axis
+    assert!(Quat::from_xyzw(q[1], q[2], q[3], q[0]).abs_diff_eq(Quat::from_rotation_z(-FRAC_PI_2), 1e-5));
+    assert!(close(ulog.topics["vehicle_local_position_setpoint"].column("yaw"), -f64::from(FRAC_PI_2)));
+    assert!(close(ulog.topics["vehicle_angular_velocity"].column("xyz[2]"), -0.5));
+    assert!(close(ulog.topics["sensor_combined"].column("accelerometer_m_s2[2]"), -9.81));
+    assert!(close(ulog.topics["vehicle_status"].column("arming_state"), 2.0));
+
+    let mut bytes = Vec::new();
+    export_betaflight_csv(&log, 1, &mut bytes).unwrap();
+    let blackbox = read_betaflight_csv(bytes.as_slice()).unwrap();
+
+    assert_eq!(blackbox.rows.len(), 3);
+    assert_eq!(blackbox.column("time (us)"), Some(vec![0.0, 100_000.0, 200_000.0]));
+    assert!(close(blackbox.column("gyroADC[2] (deg/s)"), 28.65));
+    assert!(close(blackbox.column("accSmooth[2]"), 2048.0));
+    assert!(close(blackbox.column("motor[0]"), 1048.0));
+    assert!(close(blackbox.column("vbatLatest (V)"), 16.8));
+    assert!(close(blackbox.column("stateFlags"), 1.0));
+}
+
+#[test]
+fn did_read_cut_flight_logs() {
+    let directory = env::temp_dir().join("supersonic_did_read_cut_flight_logs");
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    let metadata = SessionMetadata {
+        version: FORMAT_VERSION,
+        session: "flight_1".to_owned(),
+        part: 0,
+        created: 1,
+        rate: 10.0,
+        drones: vec![(1, "quadcopter".to_owned())],
+    };
+
+    let record = FlightRecord {
+        time: 0.0,
+        drone: 1,
+        position: Vec3::new(3.0, 10.0, -4.0),
+        rotation: Quat::IDENTITY,
+        velocity: Vec3::X,
+        angular_velocity: Vec3::ZERO,
+        motor_thrust: 73.8,
+        thrust: Vec3::ZERO,
+        setpoint_velocity: None,
+        setpoint_yaw: None,
+        velocity_error: None,
+        altitude: 9.5,
+        temperature: 20.0,
+        video_breakup: 0.0,
+        battery_remaining: 77.0,
+        battery_charge: 1.0,
+        is_armed: true,
+        vision_mode: VisionMode::Day,
+    };
+    let records: Vec<_> = (0..3).map(|index| FlightRecord { time: index as f32 * 0.1, ..record }).collect();
+
+    let csv = directory.join("flight_1.csv");
+    let binary = directory.join("flight_1.bin");
+    let mut writers: [Box<dyn LogWriter>; 2] = [
+        Box::new(CsvLogWriter::create(&csv, &metadata).unwrap()),
+        Box::new(BinaryLogWriter::create(&binary, &metadata).unwrap()),
+    ];
+
+    for writer in &mut writers {
+        for record in &records {
+            writer.write_record(record).unwrap();
+        }
+
+        writer.flush().unwrap();
+    }
+
+    drop(writers);
+
+    // the recording stopped in the middle of the last record, which is dropped
+    for path in [&csv, &binary] {
+        let bytes = fs::read(path).unwrap();
+        fs::write(path, &bytes[..bytes.len() - 5]).unwrap();
+
+        let log = FlightLog::read(path).unwrap();
+
+        assert_eq!(log.metadata, metadata);
+        assert_eq!(log.records, records[..2]);
+    }
+
+    // the broken length of the metadata is longer than the file
+    let mut bytes = fs::read(&binary).unwrap();
+    bytes[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+    fs::write(&binary, bytes).unwrap();
+
+    assert!(matches!(FlightLog::read(&binary), Err(LogError::Format(_))));
+}
@@ -0,0 +1,313 @@
+use std::{env, fs, thread, time::Duration};
+
+use bevy::{app::AppExit, input::InputPlugin, prelude::*, time::TimePlugin};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    fleet::{fly_unpiloted_drones, Drone, DroneControl, DroneSetpoint},
+    player::{player_movement, run_flight_controller, run_motors, FlightController, FlightSetpoint, Motors, Player},
+    recorder::RecorderSettings,
+    replay::{
+        install_replay_step, record_commands, record_input, replay_input, rewind_replay, save_input_log,
+        schedule_replay_frame, InputFrame, InputLog, InputRecorder, Replay,
+    },
+    search_and_rescue::TargetMark,
+    sim_clock::{SimClock, SimClockPlugin},
+    tests::add_physics,
+    ui::menu::AppState,
+};
+
+/// Creates the app, which loads the piloted drone and the drone of an external program above the ground
+/// and flies them.
+fn flight_app(replay: Option<Replay>) -> App {
+    let mut app = App::new();
+
+    app.add_plugins((TimePlugin, InputPlugin, SimClockPlugin));
+    add_physics(&mut app);
+
+    if let Some(replay) = replay {
+        app.insert_resource(replay);
+    }
+
+    install_replay_step(&mut app);
+    app.insert_state(AppState::Loading);
+    app.init_resource::<InputRecorder>();
+    app.init_resource::<MarkCount>();
+    app.add_event::<TargetMark>();
+    app.add_event::<DroneSetpoint>();
+    app.add_event::<AppExit>();
+    app.add_systems(OnEnter(AppState::Loading), load_drone);
+    app.add_systems(OnEnter(AppState::InFlight), (resume_flight, replay_input, record_input));
+    app.add_systems(PreUpdate, (
+        rewind_replay,
+        (replay_input, record_input).run_if(in_state(AppState::InFlight)),
+    ).chain().after(bevy::input::InputSystem));
+    app.add_systems(Update, (
+        finish_loading.run_if(in_state(AppState::Loading)),
+        (player_movement, fly_unpiloted_drones, run_flight_controller, run_motors).chain(),
+        count_marks,
+    ));
+    app.add_systems(Last, (schedule_replay_frame, record_commands));
+
+    app.world.spawn((Collider::cuboid(50.0, 0.5, 50.0), TransformBundle::from(Transform::from_xyz(0.0, -0.5, 0.0))));
+
+    app
+}
+
+/// Spawns the drones again and pauses the simulation, like the loading of the scenario.
+fn load_drone(
+    mut commands: Commands,
+    mut clock: ResMut<SimClock>,
+    drones: Query<Entity, With<ExternalForce>>,
+) {
+    for entity in &drones {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    clock.pause();
+
+    commands.spawn((
+        Player,
+        Collider::cuboid(1.25, 0.5, 1.5),
+        RigidBody::Dynamic,
+        TransformBundle::from(Transform::from_xyz(0.0, 0.55, 0.0)),
+        ExternalForce::default(),
+        ExternalImpulse::default(),
+        Velocity::default(),
+        Motors {
+            is_armed: true,
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        Drone {
+            id: 2,
+            airframe: "scout".to_owned(),
+            control: DroneControl::Autopilot,
+        },
+        Collider::cuboid(1.25, 0.5, 1.5),
+        RigidBody::Dynamic,
+        TransformBundle::from(Transform::from_xyz(10.0, 0.55, 0.0)),
+        ExternalForce::default(),
+        Velocity::default(),
+        Motors {
+            is_armed: true,
+            ..default()
+        },
+        FlightController::default(),
+    ));
+}
+
+/// Starts the flight after the first loading frame.
+fn finish_loading(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::InFlight);
+}
+
+/// Resumes the simulation, like the start of the flight.
+fn resume_flight(mut clock: ResMut<SimClock>) {
+    clock.resume();
+}
+
+/// Returns the transform of the drone.
+fn drone_transform(app: &mut App) -> Transform {
+    *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world)
+}
+
+/// Returns the transform of the drone of the external program.
+fn external_transform(app: &mut App) -> Transform {
+    *app.world.query_filtered::<&Transform, With<Drone>>().single(&app.world)
+}
+
+/// Number of the `TargetMark`s.
+#[derive(Resource, Default)]
+struct MarkCount(usize);
+
+/// Counts the `TargetMark`s.
+fn count_marks(
+    mut marks: EventReader<TargetMark>,
+    mut count: ResMut<MarkCount>,
+) {
+    count.0 += marks.read().count();
+}
+
+/// Gives the drone to the external program or takes it back, like the control server.
+fn send_setpoint(app: &mut App, setpoint: Option<FlightSetpoint>) {
+    let mut drones = app.world.query::<(&mut Drone, &mut FlightController)>();
+    let (mut drone, mut controller) = drones.single_mut(&mut app.world);
+
+    if setpoint.is_some() {
+        drone.control = DroneControl::External;
+    } else {
+        drone.control = DroneControl::Autopilot;
+        controller.setpoint = None;
+    }
+
+    app.world.send_event(DroneSetpoint { id: 2, setpoint });
+}
+
+#[test]
+fn did_replay_recorded_flight() {
+    let mut app = flight_app(None);
+
+    for frame in 0..120 {
+        let mut keys = app.world.resource_mut::<ButtonInput<KeyCode>>();
+
+        match frame {
+            10 => keys.press(KeyCode::ArrowUp),
+            40 => keys.release(KeyCode::ArrowUp),
+            70 => keys.press(KeyCode::ArrowDown),
+            80 => keys.release(KeyCode::ArrowDown),
+            _ => {},
+        }
+
+        let mut clock = app.world.resource_mut::<SimClock>();
+
+        match frame {
+            50 => clock.set_speed(4.0),
+            60 => clock.set_speed(1.0),
+            90 => clock.step_frames(2),
+            100 => clock.resume(),
+            _ => {},
+        }
+
+        // frame times of the real app differ
+        thread::sleep(Duration::from_millis(2 + frame % 5));
+        app.update();
+    }
+
+    let recorded = drone_transform(&mut app);
+    let log = app.world.resource::<InputRecorder>().log.clone();
+
+    // the loading isn't recorded
+    assert_eq!(log.frames.len(), 119);
+    assert_eq!(log.timestep, SimClock::default().physics_step);
+    assert!(log.frames.iter().any(|frame| frame.speed == 4.0) && log.frames.iter().any(|frame| frame.speed == 0.0));
+    assert!(recorded.translation.y > 1.0);
+
+    let path = std::env::temp_dir().join("supersonic_did_replay_recorded_flight.replay.ron");
+    log.save(&path).unwrap();
+    let log = InputLog::load(&path).unwrap();
+
+    let mut replay = Replay::new(log);
+    replay.seek = Some(replay.log.frames.len());
+    let mut app = flight_app(Some(replay));
+
+    while !app.world.resource::<Replay>().is_finished() {
+        app.update();
+    }
+
+    // the replay reproduces the exact flight
+    assert_eq!(drone_transform(&mut app), recorded);
+    assert!(app.world.resource::<InputRecorder>().log.frames.is_empty());
+
+    // seeking back loads the drone again
+    app.world.resource_mut::<Replay>().seek = Some(65);
+    app.update();
+
+    assert_eq!(app.world.resource::<Replay>().frame, 0);
+    assert_eq!(*app.world.resource::<State<AppState>>().get(), AppState::Loading);
+
+    while !app.world.resource::<Replay>().is_finished() {
+        app.update();
+    }
+
+    assert!(drone_transform(&mut app).translation.distance(recorded.translation) < 0.01);
+
+    // paused replay holds the simulation
+    let mut replay = app.world.resource_mut::<Replay>();
+    replay.frame = 0;
+    replay.is_paused = true;
+    app.update();
+    app.update();
+
+    assert_eq!(app.world.resource::<Replay>().frame, 0);
+}
+
+#[test]
+fn did_replay_external_setpoints_and_marks() {
+    let directory = env::temp_dir().join("supersonic_did_replay_external_setpoints_and_marks");
+    let _ = fs::remove_dir_all(&directory);
+
+    let mut app = flight_app(None);
+
+    app.insert_resource(RecorderSettings {
+        directory: directory.clone(),
+        ..default()
+    });
+    app.add_systems(Last, save_input_log.after(record_commands));
+
+    for frame in 0..90 {
+        match frame {
+            20 => send_setpoint(&mut app, Some(FlightSetpoint { velocity: Vec3::new(3.0, 1.0, 0.0), yaw: Some(1.0) })),
+            // the operator clicks the target
+            30 => {
+                app.world.send_event(TargetMark { point: Vec3::new(5.0, 0.0, 5.0) });
+            },
+            60 => send_setpoint(&mut app, None),
+            _ => {},
+        }
+
+        thread::sleep(Duration::from_millis(2 + frame % 5));
+        app.update();
+    }
+
+    // the frame, which pauses the flight, is its last one
+    app.world.resource_mut::<NextState<AppState>>().set(AppState::Paused);
+    app.update();
+
+    let recorded = external_transform(&mut app);
+
+    // the external program flew the drone east and up
+    assert!(recorded.translation.x > 10.1 && recorded.translation.y > 0.6);
+    assert_eq!(app.world.resource::<MarkCount>().0, 1);
+
+    // restarting ends the flight, which saves its input log without a flight log
+    app.world.resource_mut::<NextState<AppState>>().set(AppState::Loading);
+    app.update();
+
+    let files: Vec<_> = fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+
+    assert_eq!(files.len(), 1);
+    assert!(files[0].to_string_lossy().ends_with(".replay.ron"));
+
+    let log = InputLog::load(&files[0]).unwrap();
+
+    assert_eq!(log.frames.iter().map(|frame| frame.setpoints.len()).sum::<usize>(), 2);
+    assert_eq!(log.frames.iter().map(|frame| frame.marks.len()).sum::<usize>(), 1);
+
+    let mut replay = Replay::new(log);
+    replay.seek = Some(replay.log.frames.len());
+    let mut app = flight_app(Some(replay));
+
+    while !app.world.resource::<Replay>().is_finished() {
+        app.update();
+    }
+
+    // the replay reproduces the flight of the external program and the marks of the operator
+    assert_eq!(external_transform(&mut app), recorded);
+    assert_eq!(app.world.resource::<MarkCount>().0, 1);
+}
+
+#[test]
+fn did_restore_keyboard_state() {
+    let mut keys = ButtonInput::<KeyCode>::default();
+    keys.press(KeyCode::ArrowUp);
+    keys.press(KeyCode::KeyK);
+    keys.clear();
+    keys.press(KeyCode::KeyJ);
+    keys.release(KeyCode::KeyJ);
+    keys.release(KeyCode::KeyK);
+    keys.press(KeyCode::KeyC);
+
+    let frame = InputFrame::capture(Duration::from_millis(16), &keys);
+
+    let mut restored = ButtonInput::<KeyCode>::default();
+    restored.press(KeyCode::KeyB);
+    frame.restore(&mut restored, &[KeyCode::KeyC]);
+
+    assert_eq!(InputFrame::capture(Duration::from_millis(16), &restored).pressed, vec![KeyCode::ArrowUp]);
+    assert!(restored.just_pressed(KeyCode::KeyJ) && restored.just_released(KeyCode::KeyJ));
+    assert!(restored.just_released(KeyCode::KeyK) && !restored.pressed(KeyCode::KeyK));
+    assert!(!restored.just_pressed(KeyCode::ArrowUp) && !restored.pressed(KeyCode::KeyC));
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, PI},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::TimePlugin};
+use bevy_rapier3d::prelude::*;
+use serde_json::{json, Value};
+use tungstenite::{
+    protocol::frame::{
+        coding::{CloseCode, Data, OpCode},
+        Frame,
+    },
+    Message,
+};
+
+use crate::{
+    fleet::{fly_unpiloted_drones, Drone, DroneControl, DroneSetpoint},
+    materials::Temperature,
+    player::FlightController,
+    ros_bridge::{
+        fly_to_ros_setpoints,
+        messages::{from_enu, to_enu, to_enu_rotation, to_flu},
+        publish_ros_topics,
+        receive_ros_messages,
+        RosBridge,
+        RosBridgeSettings,
+        MAX_MESSAGE_SIZE,
+    },
+    tests::add_physics,
+};
+
+#[test]
+fn did_bridge_topics_over_websocket() {
+    let mut app = App::new();
+
+    app.add_plugins((TaskPoolPlugin::default(), TimePlugin));
+    add_physics(&mut app);
+    app.init_resource::<RosBridgeSettings>();
+    app.insert_resource(RosBridge::listen("127.0.0.1:0".parse().unwrap()).unwrap());
+    app.add_event::<DroneSetpoint>();
+    app.add_systems(Update, (receive_ros_messages, fly_to_ros_setpoints, fly_unpiloted_drones).chain());
+    app.add_systems(Last, publish_ros_topics);
+
+    app.world.spawn((
+        Collider::cuboid(200.0, 0.5, 200.0),
+        TransformBundle::from(Transform::from_xyz(0.0, -0.5, 0.0)),
+        Temperature(20.0),
+    ));
+
+    let drone = app.world
+        .spawn((
+            Drone {
+                id: 2,
+                airframe: "scout".to_owned(),
+                control: DroneControl::Autopilot,
+            },
+            TransformBundle::from(Transform::from_xyz(1.0, 10.0, -3.0)),
+            Velocity::linear(Vec3::new(0.0, 0.0, -2.0)),
+            FlightController::default(),
+        ))
+        .id();
+
+    let address = app.world.resource::<RosBridge>().address;
+
+    // the loopback client
+    let client = thread::spawn(move || {
+        let stream = TcpStream::connect(address).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{address}"), stream).unwrap();
+        let topics = ["/drone2/odom", "/tf", "/drone2/thermal/image_raw", "/drone2/lidar/points"];
+
+        for topic in topics {
+            socket.send(Message::Text(json!({ "op": "subscribe", "topic": topic }).to_string())).unwrap();
+        }
+
+        let twist = json!({ "linear": { "x": 1.0, "y": 0.0, "z": 0.0 }, "angular": { "x": 0.0, "y": 0.0, "z": 0.4 } });
+        let publish = json!({ "op": "publish", "topic": "/drone2/cmd_vel", "msg": twist });
+        socket.send(Message::Text(publish.to_string())).unwrap();
+
+        let mut messages = HashMap::new();
+
+        while messages.len() < topics.len() {
+            let Message::Text(text) = socket.read().unwrap() else {
+                panic!("rosbridge messages should be text");
+            };
+
+            let message: Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(message["op"], "publish");
+            messages.insert(message["topic"].as_str().unwrap().to_owned(), message["msg"].clone());
+        }
+
+        // the bridge confirms the close
+        socket.close(None).unwrap();
+        while socket.read().is_ok() {}
+
+        messages
+    });
+
+    while !client.is_finished() {
+        app.update();
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let messages = client.join().unwrap();
+
+    let odometry = &messages["/drone2/odom"];
+    assert_eq!(odometry["header"]["frame_id"], "map");
+    assert_eq!(odometry["pose"]["pose"]["position"], json!({ "x": 1.0, "y": 3.0, "z": 10.0 }));
+    assert_eq!(odometry["twist"]["twist"]["linear"]["x"], 2.0);
+    assert_eq!(messages["/tf"]["transforms"][1]["child_frame_id"], "drone2/thermal");
+
+    let thermal = &messages["/drone2/thermal/image_raw"];
+    assert_eq!(thermal["encoding"], "32FC1");
+    assert_eq!(thermal["width"], 64);
+    assert_eq!(thermal["step"], 64 * 4);
+
+    // the rings from -15° to -7° hit the ground within the range
+    let lidar = &messages["/drone2/lidar/points"];
+    assert_eq!(lidar["width"], 180 * 5);
+    assert_eq!(lidar["row_step"], 180 * 5 * 16);
+
+    // the drone flies forward and turns left at the commanded rate
+    let (drone, controller) = app.world.query::<(&Drone, &FlightController)>().get(&app.world, drone).unwrap();
+    let setpoint = controller.setpoint.unwrap();
+
+    assert_eq!(drone.control, DroneControl::External);
+    assert!(setpoint.velocity.abs_diff_eq(Vec3::new(0.0, 0.0, -1.0), 1e-6));
+    assert!((setpoint.yaw.unwrap() - 0.2).abs() < 1e-6);
+}
+
+#[test]
+fn did_convert_frames() {
+    let point = Vec3::new(1.0, 2.0, -3.0);
+
+    assert_eq!(to_enu(point), Vec3::new(1.0, 3.0, 2.0));
+    assert_eq!(from_enu(to_enu(point)), point);
+    assert_eq!(to_flu(Vec3::NEG_Z), Vec3::X);
+
+    // the drone looks north, which is 90° from the east
+    assert!(to_enu_rotation(Quat::IDENTITY).abs_diff_eq(Quat::from_rotation_z(FRAC_PI_2), 1e-6));
+    // turning left faces west
+    let west = to_enu_rotation(Quat::from_rotation_y(FRAC_PI_2));
+    assert!(west.abs_diff_eq(Quat::from_rotation_z(PI), 1e-6) || west.abs_diff_eq(-Quat::from_rotation_z(PI), 1e-6));
+}
+
+#[test]
+fn did_close_on_too_large_message() {
+    let bridge = RosBridge::listen("127.0.0.1:0".parse().unwrap()).unwrap();
+
+    let stream = TcpStream::connect(bridge.address).unwrap();
+    let (mut socket, _) = tungstenite::client(format!("ws://{}", bridge.address), stream).unwrap();
+
+    // every fragment fits, the message doesn't
+    let fragment = vec![b'a'; MAX_MESSAGE_SIZE / 2 + 1];
+
+    socket.write(Message::Frame(Frame::message(fragment.clone(), OpCode::Data(Data::Text), false))).unwrap();
+    socket.send(Message::Frame(Frame::message(fragment, OpCode::Data(Data::Continue), true))).unwrap();
+
+    let Message::Close(Some(close)) = socket.read().unwrap() else {
+        panic!("the bridge should close the connection");
+    };
+
+    assert_eq!(close.code, CloseCode::Size);
+}
+
+#[test]
+fn did_read_ros_bridge_address() {
+    let args = |args: &[&str]| {
+        RosBridgeSettings::address_from_args(args.iter().map(|arg| arg.to_string())).map(Result::ok)
+    };
+
+    assert_eq!(args(&["supersonic"]), None);
+    assert_eq!(args(&["supersonic", "--ros-bridge"]), Some("127.0.0.1:9090".parse().ok()));
+    assert_eq!(args(&["supersonic", "--ros-bridge", "0.0.0.0:9091"]), Some("0.0.0.0:9091".parse().ok()));
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    materials::ThermalMaterial,
+    player::{Battery, Player},
+    search_and_rescue::{
+        process_target_marks, start_search_mission, track_search_progress, MissionStatus, SearchMission,
+        SearchMissionProgress, SearchTarget, StartSearchMission, TargetMark,
+    },
+};
+
+#[test]
+fn did_score_search_mission() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<Assets<Mesh>>();
+    app.init_resource::<Assets<StandardMaterial>>();
+    app.init_resource::<Assets<ThermalMaterial>>();
+    app.init_resource::<SearchMissionProgress>();
+    app.insert_resource(SearchMission {
+        target_count: 3,
+        ..default()
+    });
+    app.add_event::<StartSearchMission>();
+    app.add_event::<TargetMark>();
+
+    app.add_systems(Update, (start_search_mission, process_target_marks, track_search_progress).chain());
+
+    let player_id = app.world
+        .spawn((Player, Battery::default(), TransformBundle::from(Transform::from_xyz(0.0, 40.0, 0.0))))
+        .id();
+
+    app.world.send_event_default::<StartSearchMission>();
+    app.update();
+
+    assert_eq!(app.world.resource::<SearchMissionProgress>().status, MissionStatus::Active);
+
+    let targets: Vec<Vec3> = app.world
+        .query_filtered::<&Transform, With<SearchTarget>>()
+        .iter(&app.world)
+        .map(|transform| transform.translation)
+        .collect();
+    assert_eq!(targets.len(), 3);
+
+    // fly to the first target, mark it and mark an empty place
+    app.world.get_mut::<Transform>(player_id).unwrap().translation = targets[0] + Vec3::Y * 40.0;
+    app.world.get_mut::<Battery>(player_id).unwrap().remaining -= 2.0;
+    app.world.resource_mut::<Time>().advance_by(Duration::from_secs(30));
+
+    app.world.send_event(TargetMark { point: targets[0] + Vec3::X });
+    app.world.send_event(TargetMark { point: Vec3::new(500.0, 0.0, 500.0) });
+    app.update();
+
+    let score = app.world.resource::<SearchMissionProgress>().score();
+
+    assert_eq!(score.found, 1);
+    assert_eq!(score.false_marks, 1);
+    assert_eq!(score.battery_used, 2.0);
+    assert!(score.area_covered > 0.0);
+    assert!(score.path_efficiency > 0.99);
+
+    // the same target can't be found twice
+    app.world.send_event(TargetMark { point: targets[0] });
+    app.update();
+
+    assert_eq!(app.world.resource::<SearchMissionProgress>().score().false_marks, 2);
+
+    for target in &targets[1..] {
+        app.world.send_event(TargetMark { point: *target });
+    }
+    app.update();
+
+    let progress = app.world.resource::<SearchMissionProgress>();
+
+    assert_eq!(progress.status, MissionStatus::Debrief);
+    assert_eq!(progress.score().found, 3);
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    time::{TimePlugin, TimeSystem, TimeUpdateStrategy},
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::sim_clock::{advance_sim_step, apply_sim_clock, SimClock, MAX_SPEED, MIN_SPEED};
+
+#[test]
+fn did_pause_speed_up_and_step_simulation() {
+    let mut app = App::new();
+
+    app.add_plugins(TimePlugin);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(10)));
+    app.init_resource::<RapierConfiguration>();
+    app.init_resource::<SimClock>();
+    app.add_systems(First, (apply_sim_clock.before(TimeSystem), advance_sim_step.after(TimeSystem)));
+
+    let elapsed = |app: &App| app.world.resource::<Time<Virtual>>().elapsed();
+
+    app.update();
+    app.world.resource_mut::<SimClock>().set_speed(2.0);
+    app.update();
+
+    // fast-forward takes more physics steps
+    let start = elapsed(&app);
+    app.update();
+
+    assert_eq!(elapsed(&app) - start, Duration::from_millis(20));
+    assert_eq!(app.world.resource::<RapierConfiguration>().timestep_mode, TimestepMode::Variable {
+        max_dt: 2.0 / 60.0,
+        time_scale: 1.0,
+        substeps: 2,
+    });
+
+    app.world.resource_mut::<SimClock>().pause();
+    app.update();
+    let paused = elapsed(&app);
+    app.update();
+
+    assert_eq!(elapsed(&app), paused);
+    assert!(!app.world.resource::<RapierConfiguration>().physics_pipeline_active);
+
+    // single steps last their own duration, whatever the speed
+    let frame = app.world.resource::<SimClock>().frame;
+    app.world.resource_mut::<SimClock>().step_frames(2);
+
+    for _ in 0..4 {
+        app.update();
+    }
+
+    let clock = app.world.resource::<SimClock>();
+
+    assert_eq!(elapsed(&app) - paused, clock.step * 2);
+    assert_eq!(clock.frame, frame + 2);
+    assert!(clock.is_paused && !clock.is_running());
+    assert!(!app.world.resource::<RapierConfiguration>().physics_pipeline_active);
+
+    let mut clock = SimClock::default();
+
+    clock.set_speed(100.0);
+    assert_eq!(clock.speed, MAX_SPEED);
+    clock.slower();
+    assert_eq!(clock.speed, 4.0);
+    clock.set_speed(0.0);
+    assert_eq!(clock.speed, MIN_SPEED);
+    clock.faster();
+    assert_eq!(clock.speed, 0.25);
+}
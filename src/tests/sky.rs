@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    materials::{SolarHeating, Temperature},
+    sky::{advance_time_of_day, solar_heating, solar_position, SkyLighting, TimeOfDay},
+};
+
+#[test]
+fn did_find_sun_position() {
+    let (noon_elevation, noon_azimuth) = solar_position(172, 50.0, 12.0);
+    assert!((noon_elevation.to_degrees() - 63.44).abs() < 0.5);
+    assert!((noon_azimuth.to_degrees() - 180.0).abs() < 1.0);
+
+    let (midnight_elevation, _) = solar_position(172, 50.0, 0.0);
+    assert!(midnight_elevation < 0.0);
+
+    let (_, morning_azimuth) = solar_position(172, 50.0, 9.0);
+    let (_, evening_azimuth) = solar_position(172, 50.0, 15.0);
+    assert!(morning_azimuth.to_degrees() < 180.0);
+    assert!(evening_azimuth.to_degrees() > 180.0);
+}
+
+#[test]
+fn did_warm_up_in_the_sun() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<SkyLighting>();
+    app.insert_resource(TimeOfDay {
+        hours: 10.0,
+        time_scale: 3600.0,
+        ..default()
+    });
+
+    app.add_systems(Update, (advance_time_of_day, solar_heating).chain());
+
+    let body_id = app.world
+        .spawn((Temperature(10.0), SolarHeating::default()))
+        .id();
+
+    // four hours of the day
+    for _ in 0..4 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs(1));
+        app.update();
+    }
+
+    let day_temperature = app.world.get::<Temperature>(body_id).unwrap().0;
+    let air_temperature = app.world.resource::<SkyLighting>().air_temperature_at(14.0);
+    assert!(day_temperature > air_temperature);
+
+    // an hour after the sunset
+    app.world.resource_mut::<TimeOfDay>().hours = 21.5;
+    app.world.resource_mut::<Time>().advance_by(Duration::from_secs(1));
+    app.update();
+
+    let evening_temperature = app.world.get::<Temperature>(body_id).unwrap().0;
+    let air_temperature = app.world.resource::<SkyLighting>().air_temperature_at(22.5);
+    assert!(evening_temperature < day_temperature);
+    assert!(evening_temperature > air_temperature);
+    assert_eq!(app.world.resource::<TimeOfDay>().hours, 22.5);
+}
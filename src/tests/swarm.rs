@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    fleet::{Drone, DroneControl},
+    materials::{Temperature, Thermal},
+    player::{run_flight_controller, run_motors, FlightController, Motors, Player},
+    swarm::{
+        avoid_collisions, engage_swarm, fly_swarm, sense_heat, CoverageArea, FormationShape, HeatDetections, Swarm,
+        SwarmBehaviour,
+    },
+    tests::add_fixed_physics,
+};
+
+fn spawn_drone(app: &mut App, id: u32, control: DroneControl, position: Vec3) -> Entity {
+    app.world
+        .spawn((
+            Drone {
+                id,
+                airframe: "quadcopter".to_owned(),
+                control,
+            },
+            Collider::cuboid(1.25, 0.5, 1.5),
+            RigidBody::Dynamic,
+            TransformBundle::from(Transform::from_translation(position)),
+            ExternalForce::default(),
+            Velocity::default(),
+            Motors {
+                is_armed: true,
+                ..default()
+            },
+            FlightController::default(),
+        ))
+        .id()
+}
+
+#[test]
+fn did_fly_in_formation() {
+    let mut app = App::new();
+
+    add_fixed_physics(&mut app);
+
+    app.init_resource::<HeatDetections>();
+    app.insert_resource(Swarm {
+        behaviours: vec![SwarmBehaviour::Formation { shape: FormationShape::Wedge, spacing: 8.0, max_speed: 10.0 }],
+        ..default()
+    });
+
+    app.add_systems(Update, (engage_swarm, fly_swarm, avoid_collisions, run_motors, run_flight_controller).chain());
+
+    let leader_id = spawn_drone(&mut app, 0, DroneControl::Autopilot, Vec3::new(0.0, 10.0, 0.0));
+    app.world.entity_mut(leader_id).insert((Player, RigidBody::Fixed));
+
+    // the followers start too close to each other
+    let right_id = spawn_drone(&mut app, 1, DroneControl::Swarm, Vec3::new(1.0, 10.0, 6.0));
+    let left_id = spawn_drone(&mut app, 2, DroneControl::Swarm, Vec3::new(-1.0, 10.0, 6.0));
+
+    for _ in 0..2 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(1.0 / 60.0));
+        app.update();
+    }
+
+    assert!(app.world.get::<FlightController>(right_id).unwrap().avoidance.x > 0.0);
+    assert!(app.world.get::<FlightController>(left_id).unwrap().avoidance.x < 0.0);
+
+    for _ in 0..60 * 10 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(1.0 / 60.0));
+        app.update();
+    }
+
+    // wedge behind the leader, which looks along -Z
+    let right = app.world.get::<Transform>(right_id).unwrap().translation;
+    let left = app.world.get::<Transform>(left_id).unwrap().translation;
+
+    assert!(right.distance(Vec3::new(8.0, 10.0, 8.0)) < 1.0, "{right}");
+    assert!(left.distance(Vec3::new(-8.0, 10.0, 8.0)) < 1.0, "{left}");
+}
+
+#[test]
+fn did_split_area_and_detect_heat() {
+    let swarm = Swarm::load("assets/swarms/default.ron").unwrap();
+
+    assert_eq!(swarm.behaviours.len(), 5);
+    assert_eq!(swarm.behaviour(), &SwarmBehaviour::Hold);
+
+    let area = CoverageArea {
+        center: Vec2::new(100.0, 0.0),
+        size: Vec2::new(180.0, 100.0),
+        lane_spacing: 20.0,
+        ..default()
+    };
+    let strips = area.partition(3);
+
+    assert_eq!(strips.len(), 3);
+
+    for (index, strip) in strips.iter().enumerate() {
+        let left = 10.0 + index as f32 * 60.0;
+
+        // three lanes there and back
+        assert_eq!(strip.len(), 6);
+        assert!(strip.iter().all(|waypoint| waypoint.position.x > left && waypoint.position.x < left + 60.0));
+        assert_eq!(strip[0].position.y, -50.0);
+        assert_eq!(strip[1].position.y, 50.0);
+    }
+
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.init_resource::<Swarm>();
+    app.init_resource::<HeatDetections>();
+
+    app.add_systems(Update, sense_heat);
+
+    app.world.spawn((
+        Drone {
+            id: 1,
+            airframe: "scout".to_owned(),
+            control: DroneControl::Swarm,
+        },
+        Transform::from_xyz(0.0, 30.0, 0.0),
+    ));
+    app.world.spawn((Thermal, Temperature(36.0), TransformBundle::from(Transform::from_xyz(5.0, 0.0, 5.0))));
+    app.world.spawn((Thermal, Temperature(15.0), TransformBundle::from(Transform::from_xyz(0.0, 0.0, 0.0))));
+    // out of the camera view
+    app.world.spawn((Thermal, Temperature(36.0), TransformBundle::from(Transform::from_xyz(60.0, 0.0, 0.0))));
+
+    // global transforms are not propagated without the transform plugin
+    app.world
+        .query::<(&Transform, &mut GlobalTransform)>()
+        .iter_mut(&mut app.world)
+        .for_each(|(transform, mut global)| *global = GlobalTransform::from(*transform));
+
+    app.update();
+    app.update();
+
+    let detections = &app.world.resource::<HeatDetections>().0;
+
+    assert_eq!(detections.len(), 1);
+    assert_eq!(detections[0].drone, 1);
+    assert_eq!(detections[0].position, Vec3::new(5.0, 0.0, 5.0));
+}
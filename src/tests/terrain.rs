@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_rapier3d::parry::{
+    math::{Point, Vector},
+    query::Ray,
+};
+
+use crate::{
+    materials::TerrainMaterial,
+    player::Player,
+    terrain::{
+        height_map::{ElevationFormat, HeightMap},
+        stream_terrain_chunks, LoadedTerrainChunks, Terrain, TerrainChunk,
+    },
+};
+
+#[test]
+fn did_match_collider_with_mesh() {
+    let terrain = Terrain::default();
+    let chunk = IVec2::new(2, -1);
+
+    let (mesh, collider) = terrain.build_chunk(chunk);
+    let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap();
+
+    assert_eq!(positions.len(), (terrain.chunk_resolution as usize + 1).pow(2));
+
+    for position in positions.iter().step_by(7) {
+        let ray = Ray::new(Point::new(position[0], 1000.0, position[2]), Vector::new(0.0, -1.0, 0.0));
+        let toi = collider.raw.cast_local_ray(&ray, 2000.0, true).unwrap();
+
+        assert!((1000.0 - toi - position[1]).abs() < 0.01);
+
+        let world = terrain.chunk_center(chunk) + Vec2::new(position[0], position[2]);
+        assert!((terrain.height_at(world) - position[1]).abs() < 0.001);
+    }
+
+    // launch pad is flat
+    assert_eq!(terrain.height_at(Vec2::ZERO), 0.0);
+    assert_eq!(terrain.height_at(Vec2::new(5.0, -5.0)), 0.0);
+}
+
+#[test]
+fn did_read_raw_elevation() {
+    let samples: [i16; 6] = [100, 120, -32768, 140, 160, 180];
+    let bytes: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+
+    let map = HeightMap::from_raw_elevation(&bytes, 3, 2, ElevationFormat::Int16, Some(-32768.0)).unwrap();
+
+    assert_eq!(map.height(2, 0), 100.0);
+    assert_eq!(map.height(1, 1), 160.0);
+    assert_eq!(map.sample(Vec2::new(0.25, 1.0)), 150.0);
+
+    assert!(HeightMap::from_raw_elevation(&bytes, 4, 2, ElevationFormat::Int16, None).is_err());
+    assert!(HeightMap::from_raw_elevation(&[], 0, 0, ElevationFormat::Int16, None).is_err());
+}
+
+#[test]
+fn did_stream_chunks_around_player() {
+    let mut app = App::new();
+
+    app.init_resource::<Assets<Mesh>>();
+    app.init_resource::<Assets<TerrainMaterial>>();
+    app.init_resource::<LoadedTerrainChunks>();
+    app.insert_resource(Terrain {
+        load_radius: 100.0,
+        chunks_per_frame: 100,
+        ..default()
+    });
+
+    app.add_systems(Update, stream_terrain_chunks);
+
+    let player_id = app.world
+        .spawn((Player, TransformBundle::default()))
+        .id();
+
+    app.update();
+
+    let chunks_near_origin = app.world.query::<&TerrainChunk>().iter(&app.world).count();
+
+    assert!(chunks_near_origin > 0);
+    assert!(app.world.query::<&TerrainChunk>().iter(&app.world).any(|chunk| chunk.coordinates == IVec2::ZERO));
+
+    app.world.get_mut::<Transform>(player_id).unwrap().translation = Vec3::new(1024.0, 50.0, 0.0);
+    app.update();
+
+    let chunks: Vec<IVec2> = app.world
+        .query::<&TerrainChunk>()
+        .iter(&app.world)
+        .map(|chunk| chunk.coordinates)
+        .collect();
+
+    assert_eq!(chunks.len(), chunks_near_origin);
+    assert!(!chunks.contains(&IVec2::ZERO));
+    assert!(chunks.contains(&app.world.resource::<Terrain>().chunk_at(Vec2::new(1024.0, 0.0))));
+}
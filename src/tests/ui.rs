@@ -0,0 +1,238 @@
+use std::{env, fs};
+
+use bevy::prelude::*;
+
+use crate::{
+    agents::AgentPopulation,
+    autopilot::{AutopilotProgress, AutopilotStatus},
+    failsafe::FailureInjection,
+    fleet::SpawnDrone,
+    geofence::{BreachAction, BreachKind, BreachRecord, Geofence, GeofenceStatus},
+    racing::{BestLap, RaceState},
+    recorder::{
+        process_recorder_commands, FlightRecorder, RecorderSettings, StartFlightRecording, StopFlightRecording,
+    },
+    replay::{InputLog, Replay, ScenarioSeed},
+    sim_clock::SimClock,
+    swarm::{HeatDetection, HeatDetections, Swarm},
+    ui::menu::{scenario_thumbnail, AppState, MenuFocus, MenuItem, MenuPlugin, Scenarios},
+    world::generator::EnvironmentSettings,
+};
+
+/// Presses the key for one frame.
+fn press(app: &mut App, key: KeyCode) {
+    app.world.resource_mut::<ButtonInput<KeyCode>>().press(key);
+    app.update();
+
+    let mut keys = app.world.resource_mut::<ButtonInput<KeyCode>>();
+    keys.release(key);
+    keys.clear();
+
+    // the state changes in the next frame
+    app.update();
+}
+
+/// Creates the app with the menus.
+fn menu_app(replay: Option<Replay>) -> App {
+    let mut app = App::new();
+
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.init_asset::<Font>();
+    app.init_asset::<Image>();
+    app.init_resource::<ButtonInput<KeyCode>>();
+    app.init_resource::<SimClock>();
+    app.add_event::<SpawnDrone>();
+    app.add_event::<StopFlightRecording>();
+
+    if let Some(replay) = replay {
+        app.insert_resource(replay);
+    }
+
+    app.add_plugins(MenuPlugin);
+    app
+}
+
+#[test]
+fn did_navigate_menus_into_flight_and_back() {
+    let mut app = menu_app(None);
+
+    let state = |app: &App| *app.world.resource::<State<AppState>>().get();
+    let is_paused = |app: &App| app.world.resource::<SimClock>().is_paused;
+    let item_count = |app: &mut App| app.world.query::<&MenuItem>().iter(&app.world).count();
+
+    app.update();
+    assert_eq!(state(&app), AppState::MainMenu);
+    assert_eq!(item_count(&mut app), 2);
+    assert!(is_paused(&app));
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(state(&app), AppState::ScenarioPicker);
+    // the scenarios and the back button
+    assert_eq!(item_count(&mut app), 5);
+
+    press(&mut app, KeyCode::ArrowRight);
+    assert_eq!(app.world.resource::<MenuFocus>().0, 1);
+
+    // detections of an earlier flight
+    app.insert_resource(HeatDetections(vec![HeatDetection {
+        position: Vec3::ZERO,
+        temperature: 37.0,
+        drone: 1,
+        time: 0.0,
+    }]));
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(state(&app), AppState::Loading);
+    assert_eq!(app.world.resource::<Scenarios>().selected, 1);
+    assert_eq!(app.world.resource::<EnvironmentSettings>().seed, 7);
+    assert_eq!(app.world.resource::<Events<SpawnDrone>>().len(), 3);
+    assert!(app.world.contains_resource::<Geofence>());
+    assert!(app.world.contains_resource::<Swarm>());
+    assert!(app.world.resource::<HeatDetections>().0.is_empty());
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    assert_eq!(state(&app), AppState::InFlight);
+    assert_eq!(item_count(&mut app), 0);
+    assert!(!is_paused(&app));
+
+    press(&mut app, KeyCode::Escape);
+    assert_eq!(state(&app), AppState::Paused);
+    assert!(is_paused(&app));
+
+    // the focus starts at Resume
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(state(&app), AppState::InFlight);
+    assert!(!is_paused(&app));
+}
+
+#[test]
+fn did_load_replayed_scenario() {
+    let log = InputLog {
+        scenario: "Foggy morning".to_owned(),
+        seed: ScenarioSeed {
+            environment: 5,
+            agents: 6,
+            search: 0,
+        },
+        ..default()
+    };
+
+    let mut app = menu_app(Some(Replay::new(log)));
+    let state = |app: &App| *app.world.resource::<State<AppState>>().get();
+
+    // the scenario without a fence removes the fence of the previous one
+    app.world.resource_mut::<Scenarios>().scenarios[2].geofence = None;
+    app.insert_resource(Geofence::default());
+
+    app.update();
+    assert_eq!(state(&app), AppState::Loading);
+    assert_eq!(app.world.resource::<Scenarios>().selected, 2);
+    assert_eq!(app.world.resource::<EnvironmentSettings>().seed, 5);
+    assert_eq!(app.world.resource::<AgentPopulation>().seed, 6);
+    assert!(!app.world.contains_resource::<Geofence>());
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    // the replayed flight goes on
+    press(&mut app, KeyCode::Escape);
+    assert_eq!(state(&app), AppState::InFlight);
+}
+
+#[test]
+fn did_restart_with_clean_state() {
+    let directory = env::temp_dir().join("supersonic_did_restart_with_clean_state");
+    let _ = fs::remove_dir_all(&directory);
+
+    let mut app = menu_app(None);
+
+    app.init_resource::<FlightRecorder>();
+    app.insert_resource(RecorderSettings {
+        directory,
+        ..default()
+    });
+    app.add_event::<StartFlightRecording>();
+    app.add_systems(Update, process_recorder_commands);
+
+    let state = |app: &App| *app.world.resource::<State<AppState>>().get();
+
+    app.update();
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+
+    for _ in 0..10 {
+        app.update();
+    }
+
+    assert_eq!(state(&app), AppState::InFlight);
+
+    // the flight finished the mission, breached the fence, lost the RC link and flew laps, while it was recorded
+    app.insert_resource(AutopilotProgress {
+        status: AutopilotStatus::Completed,
+        current: 3,
+        ..default()
+    });
+    app.insert_resource(GeofenceStatus {
+        breach: Some(BreachKind::OutsideFence),
+        last_inside: Some(Vec3::ZERO),
+        log: vec![BreachRecord {
+            time: 1.0,
+            kind: BreachKind::OutsideFence,
+            position: Vec3::X,
+            action: BreachAction::Warn,
+        }],
+    });
+    app.insert_resource(FailureInjection {
+        rc_link_lost: true,
+        gps_lost: false,
+    });
+    app.insert_resource(RaceState {
+        laps: 2,
+        missed_gates: 1,
+        best_lap: Some(BestLap {
+            time: 30.0,
+            splits: vec![10.0, 20.0],
+            samples: Vec::new(),
+        }),
+        ..default()
+    });
+    app.world.send_event(StartFlightRecording);
+    app.update();
+
+    assert!(app.world.resource::<FlightRecorder>().is_recording());
+
+    // Restart is the second item of the pause menu
+    press(&mut app, KeyCode::Escape);
+    press(&mut app, KeyCode::ArrowDown);
+    press(&mut app, KeyCode::Enter);
+
+    assert_eq!(state(&app), AppState::Loading);
+    assert_eq!(app.world.resource::<AutopilotProgress>().status, AutopilotStatus::Idle);
+    assert_eq!(app.world.resource::<AutopilotProgress>().current, 0);
+    assert!(app.world.resource::<GeofenceStatus>().breach.is_none());
+    assert!(app.world.resource::<GeofenceStatus>().log.is_empty());
+    assert!(!app.world.resource::<FailureInjection>().rc_link_lost);
+    assert!(!app.world.resource::<FlightRecorder>().is_recording());
+
+    let race = app.world.resource::<RaceState>();
+
+    assert_eq!(race.laps, 0);
+    assert_eq!(race.missed_gates, 0);
+    assert_eq!(race.best_lap.as_ref().map(|best_lap| best_lap.time), Some(30.0));
+}
+
+#[test]
+fn did_draw_scenario_thumbnails() {
+    let scenarios = Scenarios::default();
+    let brightness = |image: &Image| image.data.iter().map(|channel| u64::from(*channel)).sum::<u64>();
+
+    let day = scenario_thumbnail(&scenarios.scenarios[0]);
+    let night = scenario_thumbnail(&scenarios.scenarios[1]);
+
+    assert_eq!(day.size(), UVec2::new(160, 90));
+    assert!(brightness(&night) < brightness(&day) / 2);
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::weather::{apply_wind, Weather, WindDrag};
+
+#[test]
+fn did_attenuate_sensors() {
+    let clear = Weather::clear();
+    let fog = Weather::fog();
+    let rain = Weather::rain();
+
+    assert!(fog.lidar_return_probability(100.0) < clear.lidar_return_probability(100.0));
+    assert!(fog.visible_extinction() > fog.ir_extinction());
+
+    assert!(rain.ir_extinction() > clear.ir_extinction());
+    assert!(rain.thermal_contrast() < clear.thermal_contrast());
+    assert!(rain.lidar_range_noise() > clear.lidar_range_noise());
+}
+
+#[test]
+fn did_push_with_wind() {
+    let mut app = App::new();
+
+    app.init_resource::<Time>();
+    app.insert_resource(Weather {
+        wind: Vec3::new(5.0, 0.0, 0.0),
+        gust_strength: 0.0,
+        ..Weather::clear()
+    });
+
+    app.add_systems(Update, apply_wind);
+
+    let hover_force = Vec3::new(0.0, 73.8, 0.0);
+
+    let body_id = app.world
+        .spawn((
+            ExternalForce {
+                force: hover_force,
+                ..default()
+            },
+            WindDrag::default(),
+            Velocity::default(),
+        ))
+        .id();
+
+    app.update();
+    app.update();
+
+    let force = app.world.get::<ExternalForce>(body_id).unwrap().force;
+    let wind_force = app.world.get::<WindDrag>(body_id).unwrap().applied;
+
+    assert!(wind_force.x > 0.0);
+    assert_eq!(force, hover_force + wind_force);
+}
@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    materials::{Temperature, ThermalMaterial},
+    world::generator::{generate_environment, EnvironmentSettings, GeneratedObject},
+};
+
+/// Returns generated objects sorted by their position.
+fn generated_objects(app: &mut App) -> Vec<(GeneratedObject, [i32; 3])> {
+    let mut objects: Vec<(GeneratedObject, [i32; 3])> = app.world
+        .query::<(&GeneratedObject, &Transform)>()
+        .iter(&app.world)
+        .map(|(object, transform)| (*object, (transform.translation * 100.0).as_ivec3().to_array()))
+        .collect();
+
+    objects.sort_by_key(|(_, position)| *position);
+    objects
+}
+
+#[test]
+fn did_generate_environment_from_seed() {
+    let mut app = App::new();
+
+    app.init_resource::<Assets<Mesh>>();
+    app.init_resource::<Assets<StandardMaterial>>();
+    app.init_resource::<Assets<ThermalMaterial>>();
+    app.insert_resource(EnvironmentSettings {
+        seed: 7,
+        ..default()
+    });
+
+    app.add_systems(Update, generate_environment);
+
+    app.update();
+
+    let objects = generated_objects(&mut app);
+
+    for kind in [
+        GeneratedObject::Road,
+        GeneratedObject::Building,
+        GeneratedObject::Tree,
+        GeneratedObject::Fence,
+        GeneratedObject::PowerLine,
+        GeneratedObject::Vehicle,
+    ] {
+        assert!(objects.iter().any(|(object, _)| *object == kind), "{kind:?} is missing");
+    }
+
+    let without_collider = app.world
+        .query_filtered::<&GeneratedObject, Without<Collider>>()
+        .iter(&app.world)
+        .count();
+    assert_eq!(without_collider, 0);
+
+    // running engines are hotter than anything else
+    let hottest = app.world
+        .query::<(&Name, &Temperature)>()
+        .iter(&app.world)
+        .max_by(|(_, a), (_, b)| a.0.total_cmp(&b.0))
+        .map(|(name, _)| name.as_str().to_owned());
+    assert_eq!(hottest.as_deref(), Some("Running vehicle"));
+
+    // same seed gives the same environment
+    app.world.resource_mut::<EnvironmentSettings>().set_changed();
+    app.update();
+
+    assert_eq!(generated_objects(&mut app), objects);
+
+    // other seed gives another one
+    app.world.resource_mut::<EnvironmentSettings>().seed = 8;
+    app.update();
+
+    assert_ne!(generated_objects(&mut app), objects);
+}
@@ -1,14 +1,21 @@
 use bevy::{app::AppExit, diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin}, prelude::*, ui::FocusPolicy};
 
+use crate::sim_clock::SimClock;
+
+/// Main menu, scenario picker, pause menu and debrief.
+pub mod menu;
+
+use menu::{MenuItem, MenuPlugin};
+
 /// Plugin for User Interface.
 pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_plugins(FrameTimeDiagnosticsPlugin)
+            .add_plugins((FrameTimeDiagnosticsPlugin, MenuPlugin))
             .add_systems(Startup, setup_ui)
-            .add_systems(Update, (fps_update, button_interaction_system));
+            .add_systems(Update, (fps_update, sim_time_update, button_interaction_system));
     }
 }
 
@@ -26,6 +33,10 @@ const DIALOG_MENU_BACKGROUND: Color = Color::rgb(0.35, 0.35, 0.35);
 #[derive(Component)]
 struct FpsText;
 
+/// Component that describes `TextBundle` for the simulation time.
+#[derive(Component)]
+struct SimTimeText;
+
 /// Describes dialog menu.
 #[derive(Component)]
 struct DialogMenu;
@@ -45,21 +56,20 @@ fn setup_ui(
                 "FPS: ",
                 TextStyle {
                     font: font.clone(),
-                    font_size: font_size,
+                    font_size,
                     color: font_color,
-                    ..default()
                 },
             ),
             TextSection::from_style(if cfg!(feature = "default_font") {
                 TextStyle {
-                    font_size: font_size,
+                    font_size,
                     color: font_color,
                     ..default()
                 }
             } else {
                 TextStyle {
                     font: font.clone(),
-                    font_size: font_size,
+                    font_size,
                     color: font_color,
                 }
             }),
@@ -73,6 +83,34 @@ fn setup_ui(
         FpsText,
     ));
 
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Sim: ",
+                TextStyle {
+                    font: font.clone(),
+                    font_size,
+                    color: font_color,
+                },
+            ),
+            TextSection::new(
+                "",
+                TextStyle {
+                    font: font.clone(),
+                    font_size,
+                    color: font_color,
+                },
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(25.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+        SimTimeText,
+    ));
+
     commands
         .spawn((
             NodeBundle {
@@ -105,7 +143,7 @@ fn setup_ui(
                         "Quit",
                         TextStyle {
                             font: font.clone(),
-                            font_size: font_size,
+                            font_size,
                             color: font_color,
                         },
                     ));
@@ -144,7 +182,7 @@ fn setup_ui(
                         "Info",
                         TextStyle {
                             font: font.clone(),
-                            font_size: font_size,
+                            font_size,
                             color: font_color,
                         },
                     ));
@@ -224,10 +262,10 @@ fn build_dialog_menu(
                 .spawn(
                     TextBundle::from_sections([
                         TextSection::new(
-                            "Controls:\n^ ArrowUp for Up\nv ArrowDown for Down\n[ 0¯] J to switch camera mode\n\n",
+                            "Controls:\n^ ArrowUp for Up\nv ArrowDown for Down\n[ 0¯] J to cycle vision modes\n[+] N to start search mission\n[x] M or click to mark target\n[#] G to edit race track\n[o] O to add waypoint, F6 to fly mission\n[=] F3 to toggle map\n[*] K or hold ArrowDown to arm/disarm\n[>] C or 1-9 to switch drone\n[%] B to switch swarm behaviour\n[@] L to record flight log\n[!] F7/F8 to lose RC link/GPS\n[||] Space to pause, . to step, -/= for speed\n\n",
                             TextStyle {
                                 font: font.clone(),
-                                font_size: font_size,
+                                font_size,
                                 color: font_color,
                             },
                         ),
@@ -235,7 +273,7 @@ fn build_dialog_menu(
                             "Created by:\nAlexander V. Trotsky",
                             TextStyle {
                                 font: font.clone(),
-                                font_size: font_size,
+                                font_size,
                                 color: font_color,
                             },
                         ),
@@ -262,19 +300,44 @@ fn fps_update(
     }
 }
 
+/// System that updates the simulation time, its speed and pause.
+fn sim_time_update(
+    time: Res<Time<Virtual>>,
+    clock: Option<Res<SimClock>>,
+    mut query: Query<&mut Text, With<SimTimeText>>,
+) {
+    let seconds = time.elapsed_seconds_f64();
+    let mut value = format!("{:02}:{:05.2}", (seconds / 60.0).floor(), seconds % 60.0);
+
+    if let Some(clock) = clock {
+        value += &format!(" x{}", clock.speed);
+
+        if clock.is_paused {
+            value += " paused";
+        }
+    }
+
+    for mut text in &mut query {
+        text.sections[1].value.clone_from(&value);
+    }
+}
+
+/// Query for the buttons, which interaction state has changed.
+type ButtonInteractionQuery<'w, 's> = Query<'w, 's,
+    (
+        &'static Interaction,
+        &'static mut BackgroundColor,
+        &'static mut BorderColor,
+        &'static Children,
+    ),
+    (Changed<Interaction>, With<Button>, Without<MenuItem>),
+>;
+
 /// System responsible for all buttons logic.
 /// 
 /// Currently matches button label to it's specific logic.
 fn button_interaction_system(
-    mut interaction_query: Query<
-        (
-            &Interaction,
-            &mut BackgroundColor,
-            &mut BorderColor,
-            &Children,
-        ),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut interaction_query: ButtonInteractionQuery,
     dialog_menu_query: Query<Entity, With<DialogMenu>>,
     mut exit: EventWriter<AppExit>,
     mut text_query: Query<&mut Text>,
@@ -316,7 +379,7 @@ fn button_interaction_system(
                 }
                 Interaction::None => {
                     *color = NORMAL_BUTTON.into();
-                    border_color.0 = NORMAL_BUTTON.into();
+                    border_color.0 = NORMAL_BUTTON;
                 }
             }
         };        
@@ -0,0 +1,851 @@
+use bevy::{
+    app::AppExit,
+    asset::LoadState,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use crate::{
+    agents::AgentPopulation,
+    autopilot::AutopilotProgress,
+    failsafe::FailureInjection,
+    fleet::{Drone, Fleet, SpawnDrone},
+    geofence::{Geofence, GeofenceStatus},
+    racing::RaceState,
+    recorder::StopFlightRecording,
+    replay::Replay,
+    search_and_rescue::{MissionEntity, MissionStatus, SearchMissionProgress},
+    sim_clock::SimClock,
+    sky::TimeOfDay,
+    swarm::{HeatDetections, Swarm},
+    weather::{Precipitation, Weather},
+    world::generator::{EnvironmentSettings, GeneratorRng},
+};
+
+use super::{HOVERED_BUTTON, NORMAL_BUTTON};
+
+/// Plugin for the app states and their menus.
+///
+/// The app starts in the main menu, the operator picks a scenario and flies it. The simulation is paused
+/// everywhere but in the flight. Menus are navigated with the `MenuBindings`, the mouse or a gamepad:
+/// the D-pad moves, South confirms, East goes back and Start pauses the flight.
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_state::<AppState>()
+            .init_resource::<Scenarios>()
+            .init_resource::<MenuBindings>()
+            .init_resource::<MenuFocus>()
+            .init_resource::<Flight>()
+            .add_systems(Startup, load_replayed_scenario)
+            .add_systems(OnEnter(AppState::MainMenu), (pause_simulation, spawn_main_menu))
+            .add_systems(OnEnter(AppState::ScenarioPicker), spawn_scenario_picker)
+            .add_systems(OnEnter(AppState::Loading), (load_scenario, spawn_loading_screen))
+            .add_systems(OnEnter(AppState::InFlight), resume_simulation)
+            .add_systems(OnEnter(AppState::Paused), (pause_simulation, spawn_pause_menu))
+            .add_systems(OnEnter(AppState::Debrief), (pause_simulation, spawn_debrief))
+            .add_systems(OnExit(AppState::MainMenu), despawn_menu_screens)
+            .add_systems(OnExit(AppState::ScenarioPicker), despawn_menu_screens)
+            .add_systems(OnExit(AppState::Loading), despawn_menu_screens)
+            .add_systems(OnExit(AppState::Paused), despawn_menu_screens)
+            .add_systems(OnExit(AppState::Debrief), despawn_menu_screens)
+            .add_systems(Update, (
+                navigate_menu,
+                highlight_menu_items,
+                update_scenario_description,
+                finish_loading.run_if(in_state(AppState::Loading)),
+                end_flight_with_mission.run_if(in_state(AppState::InFlight)),
+            ).chain());
+    }
+}
+
+/// Frames, which the loading lasts at least, so the environment and the drones are spawned.
+const LOADING_FRAMES: u32 = 10;
+
+/// Size of the scenario thumbnails in pixels.
+const THUMBNAIL_SIZE: UVec2 = UVec2::new(160, 90);
+
+/// Describes the focused menu item.
+const FOCUSED_BORDER: Color = Color::WHITE;
+
+/// State of the app.
+#[derive(States, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    ScenarioPicker,
+    /// The scenario is being spawned.
+    Loading,
+    InFlight,
+    Paused,
+    Debrief,
+}
+
+/// Describes a scenario: the world, its weather, time, the fleet, its swarm and the geofence.
+///
+/// `hours` is the local solar time of the start. Scenarios without `geofence` are flown without a fence.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: String,
+    pub description: String,
+    pub environment: EnvironmentSettings,
+    pub agents: AgentPopulation,
+    pub weather: Weather,
+    pub hours: f32,
+    pub fleet: Fleet,
+    pub swarm: Swarm,
+    pub geofence: Option<Geofence>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            name: "Town patrol".to_owned(),
+            description: "Sunny noon over the town. Learn the controls, then start the search with N.".to_owned(),
+            environment: EnvironmentSettings::default(),
+            agents: AgentPopulation::default(),
+            weather: Weather::clear(),
+            hours: 12.0,
+            fleet: Fleet::default(),
+            swarm: Swarm::default(),
+            geofence: Some(Geofence::default()),
+        }
+    }
+}
+
+/// Action of a menu item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    /// Opens the scenario picker.
+    Play,
+    /// Loads the scenario with the index.
+    Scenario(usize),
+    Pause,
+    Resume,
+    /// Loads the current scenario again.
+    Restart,
+    EndFlight,
+    MainMenu,
+    Quit,
+}
+
+// resources
+/// Scenarios of the scenario picker, `selected` is the one being flown.
+#[derive(Resource, Debug, Clone)]
+pub struct Scenarios {
+    pub scenarios: Vec<Scenario>,
+    pub selected: usize,
+}
+
+impl Default for Scenarios {
+    fn default() -> Self {
+        let patrol = Scenario::default();
+
+        let night = Scenario {
+            name: "Night search".to_owned(),
+            description: "Lost hikers in the countryside after dark. The thermal camera sees them, the eyes don't."
+                .to_owned(),
+            environment: EnvironmentSettings {
+                seed: 7,
+                urban_radius: 40.0,
+                tree_density: 0.006,
+                ..default()
+            },
+            agents: AgentPopulation {
+                seed: 7,
+                pedestrians: 4,
+                animals: 10,
+                cars: 1,
+            },
+            hours: 23.0,
+            ..patrol.clone()
+        };
+
+        let fog = Scenario {
+            name: "Foggy morning".to_owned(),
+            description: "Thick fog at dawn, no wind. Fly by the instruments and keep clear of power lines."
+                .to_owned(),
+            environment: EnvironmentSettings {
+                seed: 3,
+                ..default()
+            },
+            weather: Weather::fog(),
+            hours: 6.5,
+            ..patrol.clone()
+        };
+
+        let storm = Scenario {
+            name: "Winter storm".to_owned(),
+            description: "Snow and gusty wind over a dense town. Gusts push the drone around the buildings.".to_owned(),
+            environment: EnvironmentSettings {
+                seed: 11,
+                urban_radius: 160.0,
+                building_density: 0.9,
+                ..default()
+            },
+            weather: Weather {
+                wind: Vec3::new(8.0, 0.0, 0.0),
+                gust_strength: 6.0,
+                ..Weather::snow()
+            },
+            hours: 15.0,
+            ..patrol.clone()
+        };
+
+        Self {
+            scenarios: vec![patrol, night, fog, storm],
+            selected: 0,
+        }
+    }
+}
+
+impl Scenarios {
+    /// Returns the scenario being flown.
+    pub fn current(&self) -> Option<&Scenario> {
+        self.scenarios.get(self.selected)
+    }
+}
+
+/// Key bindings of the menus.
+#[derive(Resource, Debug, Clone)]
+pub struct MenuBindings {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub confirm: KeyCode,
+    pub back: KeyCode,
+}
+
+impl Default for MenuBindings {
+    fn default() -> Self {
+        Self {
+            up: KeyCode::ArrowUp,
+            down: KeyCode::ArrowDown,
+            left: KeyCode::ArrowLeft,
+            right: KeyCode::ArrowRight,
+            confirm: KeyCode::Enter,
+            back: KeyCode::Escape,
+        }
+    }
+}
+
+/// Index of the focused menu item.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MenuFocus(pub usize);
+
+/// Current flight: when it started in seconds of `Time::elapsed_seconds`.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct Flight {
+    pub started_at: f32,
+}
+
+// components
+/// Describes a screen of the menus, which is removed when the state changes.
+#[derive(Component)]
+pub struct MenuScreen;
+
+/// Describes a menu item, `index` is its place in the navigation order.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MenuItem {
+    pub index: usize,
+    pub action: MenuAction,
+}
+
+/// Describes the text with the description of the focused scenario.
+#[derive(Component)]
+struct ScenarioDescription;
+
+/// Returns the thumbnail of the scenario: its sky at the start time, weather and town silhouette.
+pub fn scenario_thumbnail(scenario: &Scenario) -> Image {
+    let (width, height) = (THUMBNAIL_SIZE.x, THUMBNAIL_SIZE.y);
+    let horizon = height * 2 / 3;
+
+    let (elevation, _) = TimeOfDay {
+        hours: scenario.hours,
+        ..default()
+    }.solar_position();
+
+    let daylight = (elevation / 0.3).clamp(0.0, 1.0);
+    let light = 0.15 + 0.85 * daylight;
+    // fog hides the sky and the town
+    let haze = (1.0 - scenario.weather.visibility / 2_000.0).clamp(0.0, 0.85);
+
+    let night_sky = Vec3::new(0.02, 0.03, 0.08);
+    let day_sky = Vec3::new(0.3, 0.55, 0.9);
+    let fog = Vec3::splat(0.6 * light);
+    let ground = match scenario.weather.precipitation {
+        Precipitation::Snow => Vec3::splat(0.85),
+        _ => Vec3::new(0.25, 0.45, 0.2),
+    } * light;
+
+    // buildings stand closer together in denser towns
+    let environment = &scenario.environment;
+    let urban_share = (environment.urban_radius / environment.radius).clamp(0.0, 1.0);
+    let town_width = (width as f32 * urban_share) as u32;
+    let town_start = (width - town_width) / 2;
+
+    let mut rng = GeneratorRng::new(environment.seed);
+    let mut skyline = vec![0; width as usize];
+    let mut x = town_start;
+
+    while x < town_start + town_width {
+        let building_width = rng.range(4.0, 10.0) as u32;
+        let building_height = if rng.chance(environment.building_density) { rng.range(6.0, 30.0) as u32 } else { 0 };
+
+        for column in x..(x + building_width).min(width) {
+            skyline[column as usize] = building_height;
+        }
+
+        x += building_width + 1;
+    }
+
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let is_building = y < horizon && horizon - y <= skyline[x as usize];
+
+            let color = if is_building {
+                // lit windows at night
+                let is_window = x % 3 == 1 && y % 4 == 1 && rng.chance(0.4 * (1.0 - daylight));
+                if is_window { Vec3::new(0.9, 0.8, 0.4) } else { Vec3::splat(0.3 * light) }
+            } else if y < horizon {
+                night_sky.lerp(day_sky, daylight).lerp(Vec3::splat(0.8) * light, y as f32 / horizon as f32 * 0.5)
+            } else {
+                ground
+            };
+
+            let mut color = color.lerp(fog, haze);
+
+            let precipitation = match scenario.weather.precipitation {
+                Precipitation::Rain => 0.02 * scenario.weather.precipitation_rate.sqrt(),
+                Precipitation::Snow => 0.06,
+                Precipitation::None => 0.0,
+            };
+
+            if rng.chance(precipitation) {
+                color = color.lerp(Vec3::ONE, 0.6);
+            }
+
+            data.extend(color.to_array().map(|channel| (channel.clamp(0.0, 1.0) * 255.0) as u8));
+            data.push(255);
+        }
+    }
+
+    Image::new(
+        Extent3d { width, height, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+// systems
+/// System that loads the scenario of the replayed input log at once, with the recorded seeds.
+fn load_replayed_scenario(
+    replay: Option<Res<Replay>>,
+    mut scenarios: ResMut<Scenarios>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(replay) = replay else {
+        return;
+    };
+
+    let log = &replay.log;
+
+    match scenarios.scenarios.iter().position(|scenario| scenario.name == log.scenario) {
+        Some(index) => scenarios.selected = index,
+        None => warn!("replayed scenario {:?} is unknown", log.scenario),
+    }
+
+    let selected = scenarios.selected;
+
+    if let Some(scenario) = scenarios.scenarios.get_mut(selected) {
+        scenario.environment.seed = log.seed.environment;
+        scenario.agents.seed = log.seed.agents;
+    }
+
+    next_state.set(AppState::Loading);
+}
+
+/// System that pauses the simulation behind the menus.
+fn pause_simulation(clock: Option<ResMut<SimClock>>) {
+    if let Some(mut clock) = clock {
+        clock.pause();
+    }
+}
+
+/// System that resumes the simulation, when the flight starts or goes on.
+fn resume_simulation(clock: Option<ResMut<SimClock>>) {
+    if let Some(mut clock) = clock {
+        clock.resume();
+    }
+}
+
+/// System that removes the screens of the left state.
+fn despawn_menu_screens(
+    mut commands: Commands,
+    mut focus: ResMut<MenuFocus>,
+    screens: Query<Entity, With<MenuScreen>>,
+) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    focus.0 = 0;
+}
+
+/// Returns the node, which covers the window and centers its children in a column.
+fn screen_node(opacity: f32) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(10.0),
+            ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, opacity).into(),
+        z_index: ZIndex::Global(10),
+        ..default()
+    }
+}
+
+/// Returns the text of the menus.
+fn menu_text(text: impl Into<String>, font: &Handle<Font>, font_size: f32) -> TextBundle {
+    TextBundle::from_section(
+        text,
+        TextStyle {
+            font: font.clone(),
+            font_size,
+            color: Color::WHITE,
+        },
+    )
+}
+
+/// Spawns the button of the menu item.
+fn spawn_menu_button(parent: &mut ChildBuilder, font: &Handle<Font>, label: &str, item: MenuItem) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(220.0),
+                    height: Val::Px(40.0),
+                    border: UiRect::all(Val::Px(2.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                border_color: BorderColor(Color::BLACK),
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+            },
+            item,
+        ))
+        .with_children(|parent| {
+            parent.spawn(menu_text(label, font, 20.0));
+        });
+}
+
+/// Spawns the screen with the title, the text and the buttons of the menu items.
+fn spawn_menu_screen(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    opacity: f32,
+    title: &str,
+    text: Option<String>,
+    items: &[(&str, MenuAction)],
+) {
+    commands
+        .spawn((screen_node(opacity), MenuScreen))
+        .with_children(|parent| {
+            parent.spawn(menu_text(title, font, 40.0));
+
+            if let Some(text) = text {
+                parent.spawn(menu_text(text, font, 18.0));
+            }
+
+            for (index, (label, action)) in items.iter().enumerate() {
+                spawn_menu_button(parent, font, label, MenuItem { index, action: *action });
+            }
+        });
+}
+
+/// System that shows the main menu.
+fn spawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let font = asset_server.load("fonts/Wellfleet-Regular.ttf");
+
+    spawn_menu_screen(&mut commands, &font, 0.6, "Supersonic", None, &[
+        ("Fly", MenuAction::Play),
+        ("Quit", MenuAction::Quit),
+    ]);
+}
+
+/// System that shows the scenarios with their thumbnails and the description of the focused one.
+fn spawn_scenario_picker(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scenarios: Res<Scenarios>,
+    mut focus: ResMut<MenuFocus>,
+    mut images: ResMut<Assets<Image>>,
+    mut thumbnails: Local<Vec<Handle<Image>>>,
+) {
+    let font = asset_server.load("fonts/Wellfleet-Regular.ttf");
+
+    if thumbnails.len() != scenarios.scenarios.len() {
+        *thumbnails = scenarios.scenarios.iter().map(|scenario| images.add(scenario_thumbnail(scenario))).collect();
+    }
+
+    focus.0 = scenarios.selected.min(scenarios.scenarios.len().saturating_sub(1));
+
+    commands
+        .spawn((screen_node(0.7), MenuScreen))
+        .with_children(|parent| {
+            parent.spawn(menu_text("Pick a scenario", &font, 40.0));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        column_gap: Val::Px(10.0),
+                        row_gap: Val::Px(10.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let cards = scenarios.scenarios.iter().zip(thumbnails.iter()).enumerate();
+
+                    for (index, (scenario, thumbnail)) in cards {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        flex_direction: FlexDirection::Column,
+                                        align_items: AlignItems::Center,
+                                        padding: UiRect::all(Val::Px(6.0)),
+                                        border: UiRect::all(Val::Px(2.0)),
+                                        row_gap: Val::Px(4.0),
+                                        ..default()
+                                    },
+                                    border_color: BorderColor(Color::BLACK),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                MenuItem { index, action: MenuAction::Scenario(index) },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(ImageBundle {
+                                    style: Style {
+                                        width: Val::Px(THUMBNAIL_SIZE.x as f32),
+                                        height: Val::Px(THUMBNAIL_SIZE.y as f32),
+                                        ..default()
+                                    },
+                                    image: UiImage::new(thumbnail.clone()),
+                                    ..default()
+                                });
+                                parent.spawn(menu_text(&scenario.name, &font, 18.0));
+                            });
+                    }
+                });
+
+            parent.spawn((
+                menu_text("", &font, 18.0).with_style(Style {
+                    max_width: Val::Px(600.0),
+                    min_height: Val::Px(50.0),
+                    ..default()
+                }),
+                ScenarioDescription,
+            ));
+
+            let back = MenuItem { index: scenarios.scenarios.len(), action: MenuAction::MainMenu };
+            spawn_menu_button(parent, &font, "Back", back);
+        });
+}
+
+/// System that spawns the scenario: its world, weather, time and fleet. The drones of the previous flight
+/// and its mission are removed, its progress, breaches, failures and laps are reset and its recording stops.
+/// The best lap of the track is kept.
+#[allow(clippy::too_many_arguments)]
+fn load_scenario(
+    mut commands: Commands,
+    time: Res<Time>,
+    scenarios: Res<Scenarios>,
+    time_of_day: Option<ResMut<TimeOfDay>>,
+    race: Option<ResMut<RaceState>>,
+    mut flight: ResMut<Flight>,
+    mut spawn: EventWriter<SpawnDrone>,
+    mut stop_recording: EventWriter<StopFlightRecording>,
+    drones: Query<Entity, With<Drone>>,
+    mission_entities: Query<Entity, With<MissionEntity>>,
+) {
+    let Some(scenario) = scenarios.current() else {
+        return;
+    };
+
+    for entity in drones.iter().chain(&mission_entities) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // changed settings regenerate the environment and respawn the agents
+    commands.insert_resource(scenario.environment.clone());
+    commands.insert_resource(scenario.agents.clone());
+    commands.insert_resource(scenario.weather.clone());
+    commands.insert_resource(scenario.fleet.clone());
+    commands.insert_resource(scenario.swarm.clone());
+    commands.insert_resource(SearchMissionProgress::default());
+    commands.insert_resource(HeatDetections::default());
+    commands.insert_resource(AutopilotProgress::default());
+    commands.insert_resource(GeofenceStatus::default());
+    commands.insert_resource(FailureInjection::default());
+    stop_recording.send(StopFlightRecording);
+
+    if let Some(mut race) = race {
+        *race = RaceState {
+            best_lap: race.best_lap.take(),
+            ..default()
+        };
+    }
+
+    match &scenario.geofence {
+        Some(geofence) => commands.insert_resource(geofence.clone()),
+        None => commands.remove_resource::<Geofence>(),
+    }
+
+    if let Some(mut time_of_day) = time_of_day {
+        time_of_day.hours = scenario.hours;
+    }
+
+    for definition in &scenario.fleet.drones {
+        spawn.send(SpawnDrone(definition.clone()));
+    }
+
+    flight.started_at = time.elapsed_seconds();
+}
+
+/// System that shows the loading screen.
+fn spawn_loading_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scenarios: Res<Scenarios>,
+) {
+    let font = asset_server.load("fonts/Wellfleet-Regular.ttf");
+    let name = scenarios.current().map_or("", |scenario| &scenario.name);
+
+    spawn_menu_screen(&mut commands, &font, 0.9, &format!("Loading {name}..."), None, &[]);
+}
+
+/// System that starts the flight, when the drone models are loaded.
+fn finish_loading(
+    asset_server: Res<AssetServer>,
+    meshes: Query<&Handle<Mesh>, With<Drone>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut frames: Local<u32>,
+) {
+    *frames += 1;
+
+    let is_loaded = meshes
+        .iter()
+        .all(|mesh| asset_server.get_load_state(mesh) != Some(LoadState::Loading));
+
+    if *frames >= LOADING_FRAMES && is_loaded {
+        *frames = 0;
+        next_state.set(AppState::InFlight);
+    }
+}
+
+/// System that shows the pause menu.
+fn spawn_pause_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let font = asset_server.load("fonts/Wellfleet-Regular.ttf");
+
+    spawn_menu_screen(&mut commands, &font, 0.5, "Paused", None, &[
+        ("Resume", MenuAction::Resume),
+        ("Restart", MenuAction::Restart),
+        ("End flight", MenuAction::EndFlight),
+        ("Main menu", MenuAction::MainMenu),
+        ("Quit", MenuAction::Quit),
+    ]);
+}
+
+/// System that shows the summary of the flight.
+fn spawn_debrief(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    scenarios: Res<Scenarios>,
+    flight: Res<Flight>,
+    progress: Option<Res<SearchMissionProgress>>,
+) {
+    let font = asset_server.load("fonts/Wellfleet-Regular.ttf");
+    let name = scenarios.current().map_or("", |scenario| &scenario.name);
+
+    let mut summary = format!("{name}\nFlight time: {:.0} s", time.elapsed_seconds() - flight.started_at);
+
+    if let Some(progress) = progress.filter(|progress| progress.status == MissionStatus::Debrief) {
+        let score = progress.score();
+        summary += &format!("\nTargets found: {}/{}", score.found, score.target_count);
+        summary += &format!("\nScore: {:.0}/100", score.points());
+    }
+
+    spawn_menu_screen(&mut commands, &font, 0.6, "Debrief", Some(summary), &[
+        ("Fly again", MenuAction::Restart),
+        ("Main menu", MenuAction::MainMenu),
+        ("Quit", MenuAction::Quit),
+    ]);
+}
+
+/// System that shows the debrief, when the search mission is over.
+fn end_flight_with_mission(
+    progress: Option<Res<SearchMissionProgress>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if progress.is_some_and(|progress| progress.is_changed() && progress.status == MissionStatus::Debrief) {
+        next_state.set(AppState::Debrief);
+    }
+}
+
+/// Whether the gamepad button was just pressed on any gamepad.
+fn is_gamepad_pressed(
+    gamepads: Option<&Gamepads>,
+    buttons: Option<&ButtonInput<GamepadButton>>,
+    button: GamepadButtonType,
+) -> bool {
+    let (Some(gamepads), Some(buttons)) = (gamepads, buttons) else {
+        return false;
+    };
+
+    gamepads.iter().any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button)))
+}
+
+/// System that moves the focus between the menu items and runs their actions.
+///
+/// Going back leaves the scenario picker for the main menu, pauses the flight and resumes it. The replayed flight
+/// isn't paused, its input log has no frames behind the pause menu.
+#[allow(clippy::too_many_arguments)]
+fn navigate_menu(
+    keys: Res<ButtonInput<KeyCode>>,
+    replay: Option<Res<Replay>>,
+    gamepads: Option<Res<Gamepads>>,
+    gamepad_buttons: Option<Res<ButtonInput<GamepadButton>>>,
+    bindings: Res<MenuBindings>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut focus: ResMut<MenuFocus>,
+    mut scenarios: ResMut<Scenarios>,
+    mut exit: EventWriter<AppExit>,
+    items: Query<(Ref<Interaction>, &MenuItem)>,
+) {
+    let gamepad = |button| is_gamepad_pressed(gamepads.as_deref(), gamepad_buttons.as_deref(), button);
+    let count = items.iter().count();
+
+    let mut action = None;
+
+    if count > 0 {
+        if keys.any_just_pressed([bindings.up, bindings.left]) || gamepad(GamepadButtonType::DPadUp)
+            || gamepad(GamepadButtonType::DPadLeft)
+        {
+            focus.0 = (focus.0 + count - 1) % count;
+        }
+
+        if keys.any_just_pressed([bindings.down, bindings.right]) || gamepad(GamepadButtonType::DPadDown)
+            || gamepad(GamepadButtonType::DPadRight)
+        {
+            focus.0 = (focus.0 + 1) % count;
+        }
+
+        for (interaction, item) in &items {
+            if !interaction.is_changed() {
+                continue;
+            }
+
+            match *interaction {
+                Interaction::Hovered => focus.0 = item.index,
+                Interaction::Pressed => action = Some(item.action),
+                Interaction::None => {},
+            }
+        }
+
+        if keys.just_pressed(bindings.confirm) || gamepad(GamepadButtonType::South) {
+            action = items.iter().find(|(_, item)| item.index == focus.0).map(|(_, item)| item.action);
+        }
+    }
+
+    if keys.just_pressed(bindings.back) || gamepad(GamepadButtonType::East) || gamepad(GamepadButtonType::Start) {
+        action = match state.get() {
+            AppState::ScenarioPicker => Some(MenuAction::MainMenu),
+            AppState::InFlight if replay.is_none() => Some(MenuAction::Pause),
+            AppState::Paused => Some(MenuAction::Resume),
+            _ => action,
+        };
+    }
+
+    match action {
+        Some(MenuAction::Play) => next_state.set(AppState::ScenarioPicker),
+        Some(MenuAction::Scenario(index)) => {
+            scenarios.selected = index;
+            next_state.set(AppState::Loading);
+        },
+        Some(MenuAction::Pause) => next_state.set(AppState::Paused),
+        Some(MenuAction::Resume) => next_state.set(AppState::InFlight),
+        Some(MenuAction::Restart) => next_state.set(AppState::Loading),
+        Some(MenuAction::EndFlight) => next_state.set(AppState::Debrief),
+        Some(MenuAction::MainMenu) => next_state.set(AppState::MainMenu),
+        Some(MenuAction::Quit) => {
+            exit.send(AppExit);
+        },
+        None => {},
+    }
+}
+
+/// System that highlights the focused menu item.
+fn highlight_menu_items(
+    focus: Res<MenuFocus>,
+    mut items: Query<(&MenuItem, &mut BackgroundColor, &mut BorderColor)>,
+) {
+    for (item, mut background, mut border) in &mut items {
+        let is_focused = item.index == focus.0;
+
+        *background = if is_focused { HOVERED_BUTTON } else { NORMAL_BUTTON }.into();
+        border.0 = if is_focused { FOCUSED_BORDER } else { Color::BLACK };
+    }
+}
+
+/// System that shows the description of the focused scenario.
+fn update_scenario_description(
+    focus: Res<MenuFocus>,
+    scenarios: Res<Scenarios>,
+    items: Query<&MenuItem>,
+    mut texts: Query<&mut Text, With<ScenarioDescription>>,
+) {
+    let description = items
+        .iter()
+        .find(|item| item.index == focus.0)
+        .and_then(|item| match item.action {
+            MenuAction::Scenario(index) => scenarios.scenarios.get(index),
+            _ => None,
+        })
+        .map_or("", |scenario| &scenario.description);
+
+    for mut text in &mut texts {
+        if text.sections[0].value != description {
+            description.clone_into(&mut text.sections[0].value);
+        }
+    }
+}
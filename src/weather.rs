@@ -0,0 +1,427 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::{ExternalForce, Velocity};
+
+use crate::{geofence::GeofenceMapCamera, materials::ThermalMaterial};
+
+/// Plugin for the weather: fog, precipitation, wind and their influence on sensors.
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Weather>()
+            .init_resource::<PrecipitationRng>()
+            .add_systems(Startup, setup_precipitation)
+            .add_systems(Update, (
+                apply_fog,
+                apply_ir_extinction,
+                apply_wind,
+                (update_precipitation_count, move_precipitation).chain(),
+            ));
+    }
+}
+
+/// Most of the precipitation particles that can be around the camera at once.
+const MAX_PRECIPITATION_PARTICLES: usize = 1500;
+/// Radius around the camera in which the precipitation is simulated.
+const PRECIPITATION_RADIUS: f32 = 25.0;
+/// Height above the camera at which precipitation particles appear.
+const PRECIPITATION_HEIGHT: f32 = 15.0;
+
+/// Kind of precipitation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Precipitation {
+    #[default]
+    None,
+    Rain,
+    Snow,
+}
+
+// resources
+/// Describes weather of the scenario.
+///
+/// `precipitation_rate` is in millimeters per hour, `visibility` is a meteorological visibility in meters.
+/// `wind` is a mean wind velocity in m/s and `gust_strength` is how much faster it can blow during gusts.
+///
+/// Insert this resource with one of the presets or custom values to configure a scenario.
+#[derive(Resource, Debug, Clone)]
+pub struct Weather {
+    pub precipitation: Precipitation,
+    pub precipitation_rate: f32,
+    pub visibility: f32,
+    pub wind: Vec3,
+    pub gust_strength: f32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self::clear()
+    }
+}
+
+impl Weather {
+    /// Clear sky with a light breeze.
+    pub fn clear() -> Self {
+        Self {
+            precipitation: Precipitation::None,
+            precipitation_rate: 0.0,
+            visibility: 20_000.0,
+            wind: Vec3::new(2.0, 0.0, 0.0),
+            gust_strength: 1.0,
+        }
+    }
+
+    /// Moderate rain.
+    pub fn rain() -> Self {
+        Self {
+            precipitation: Precipitation::Rain,
+            precipitation_rate: 5.0,
+            visibility: 5_000.0,
+            wind: Vec3::new(4.0, 0.0, 0.0),
+            gust_strength: 3.0,
+        }
+    }
+
+    /// Thick fog without wind.
+    pub fn fog() -> Self {
+        Self {
+            precipitation: Precipitation::None,
+            precipitation_rate: 0.0,
+            visibility: 150.0,
+            wind: Vec3::ZERO,
+            gust_strength: 0.0,
+        }
+    }
+
+    /// Moderate snowfall.
+    pub fn snow() -> Self {
+        Self {
+            precipitation: Precipitation::Snow,
+            precipitation_rate: 2.0,
+            visibility: 1_500.0,
+            wind: Vec3::new(3.0, 0.0, 0.0),
+            gust_strength: 2.0,
+        }
+    }
+
+    /// Heavy rain with strong gusty wind.
+    pub fn storm() -> Self {
+        Self {
+            precipitation: Precipitation::Rain,
+            precipitation_rate: 25.0,
+            visibility: 1_500.0,
+            wind: Vec3::new(10.0, 0.0, 0.0),
+            gust_strength: 8.0,
+        }
+    }
+
+    /// Extinction coefficient of the visible light per meter (Koschmieder's law).
+    pub fn visible_extinction(&self) -> f32 {
+        3.912 / self.visibility.max(1.0)
+    }
+
+    /// Extinction coefficient of the long-wave infrared per meter.
+    ///
+    /// Infrared goes through fog better than the visible light, but it is absorbed by the rain and snow.
+    pub fn ir_extinction(&self) -> f32 {
+        let precipitation = match self.precipitation {
+            Precipitation::None => 0.0,
+            Precipitation::Rain => 0.0002 * self.precipitation_rate.powf(0.63),
+            Precipitation::Snow => 0.0006 * self.precipitation_rate.powf(0.63),
+        };
+
+        precipitation + 0.3 * self.visible_extinction()
+    }
+
+    /// Returns how much of the thermal contrast is left.
+    ///
+    /// Wet surfaces get closer to the air temperature, so the rain and snow lower the contrast.
+    pub fn thermal_contrast(&self) -> f32 {
+        match self.precipitation {
+            Precipitation::None => 1.0,
+            Precipitation::Rain | Precipitation::Snow => 1.0 / (1.0 + 0.1 * self.precipitation_rate),
+        }
+    }
+
+    /// Returns probability that the lidar pulse comes back from the given distance in meters.
+    pub fn lidar_return_probability(&self, distance: f32) -> f32 {
+        (-2.0 * self.visible_extinction() * distance.max(0.0)).exp()
+    }
+
+    /// Returns standard deviation of the lidar range noise in meters.
+    ///
+    /// Raindrops and snowflakes scatter the pulse, so the noise grows with the precipitation rate.
+    pub fn lidar_range_noise(&self) -> f32 {
+        let scattering = match self.precipitation {
+            Precipitation::None => 0.0,
+            Precipitation::Rain => 0.01,
+            Precipitation::Snow => 0.03,
+        };
+
+        0.02 + scattering * self.precipitation_rate
+    }
+
+    /// Returns wind velocity at the given time in seconds.
+    ///
+    /// Gusts blow along the mean wind and come from a sum of sines, so they are smooth and not periodic.
+    pub fn wind_at(&self, seconds: f32) -> Vec3 {
+        let gust = (0.5 * (TAU * seconds / 7.3).sin()
+            + 0.3 * (TAU * seconds / 3.1 + 1.7).sin()
+            + 0.2 * (TAU * seconds / 1.3 + 0.4).sin())
+            .max(0.0);
+
+        let direction = self.wind.try_normalize().unwrap_or(Vec3::X);
+
+        self.wind + direction * self.gust_strength * gust
+    }
+}
+
+/// Random number generator for the precipitation particles.
+#[derive(Resource)]
+struct PrecipitationRng(u64);
+
+impl Default for PrecipitationRng {
+    fn default() -> Self {
+        Self(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl PrecipitationRng {
+    /// Returns random number in [0, 1).
+    fn next(&mut self) -> f32 {
+        // xorshift64
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Meshes and materials of the precipitation particles.
+#[derive(Resource)]
+struct PrecipitationAssets {
+    rain_mesh: Handle<Mesh>,
+    snow_mesh: Handle<Mesh>,
+    rain_material: Handle<StandardMaterial>,
+    snow_material: Handle<StandardMaterial>,
+}
+
+// components
+/// Describes how the wind pushes the entity.
+///
+/// Drag force is `coefficient` multiplied by the squared speed of the air relative to the entity.
+#[derive(Component)]
+pub struct WindDrag {
+    pub coefficient: f32,
+    /// Wind force, which is currently added to the `ExternalForce`.
+    pub applied: Vec3,
+}
+
+impl Default for WindDrag {
+    fn default() -> Self {
+        Self {
+            coefficient: 0.1,
+            applied: Vec3::ZERO,
+        }
+    }
+}
+
+/// Describes a raindrop or a snowflake.
+#[derive(Component)]
+struct PrecipitationParticle {
+    kind: Precipitation,
+}
+
+// systems
+/// Creates meshes and materials for the precipitation.
+fn setup_precipitation(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(PrecipitationAssets {
+        rain_mesh: meshes.add(Cuboid::new(0.01, 0.4, 0.01)),
+        snow_mesh: meshes.add(Cuboid::new(0.04, 0.04, 0.04)),
+        rain_material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.7, 0.75, 0.8, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        snow_material: materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+/// Query for the cameras, which are fogged.
+type FogCameraQuery<'w, 's> = Query<'w, 's,
+    (Entity, Option<&'static mut FogSettings>),
+    (With<Camera3d>, Without<GeofenceMapCamera>),
+>;
+
+/// System that applies weather fog to the cameras.
+///
+/// Fog gets darker with the ambient light, so it doesn't glow at night. The map is not fogged.
+fn apply_fog(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    ambient_light: Res<AmbientLight>,
+    mut cameras: FogCameraQuery,
+) {
+    // default ambient brightness, which corresponds to the daylight
+    let daylight = (ambient_light.brightness / AmbientLight::default().brightness).min(1.0);
+    let color = Color::rgb(0.7 * daylight, 0.72 * daylight, 0.75 * daylight);
+    let falloff = FogFalloff::from_visibility(weather.visibility);
+
+    for (entity, fog) in &mut cameras {
+        match fog {
+            Some(mut fog) => {
+                fog.color = color;
+                fog.falloff = falloff.clone();
+            },
+            None => {
+                commands.entity(entity).insert(FogSettings {
+                    color,
+                    falloff: falloff.clone(),
+                    ..default()
+                });
+            },
+        }
+    }
+}
+
+/// System that sends infrared extinction to the thermal materials.
+fn apply_ir_extinction(
+    weather: Res<Weather>,
+    mut ext_materials: ResMut<Assets<ThermalMaterial>>,
+) {
+    let ir_extinction = weather.ir_extinction();
+
+    let is_outdated = ext_materials
+        .iter()
+        .any(|(_, material)| material.extension.ir_extinction != ir_extinction);
+
+    if is_outdated {
+        for (_, material) in ext_materials.iter_mut() {
+            material.extension.ir_extinction = ir_extinction;
+        }
+    }
+}
+
+/// System that pushes `WindDrag` entities with the wind.
+///
+/// Wind force is added on top of the other forces of the `ExternalForce`.
+pub fn apply_wind(
+    time: Res<Time>,
+    weather: Res<Weather>,
+    mut bodies: Query<(&mut ExternalForce, &mut WindDrag, &Velocity)>,
+) {
+    let wind = weather.wind_at(time.elapsed_seconds());
+
+    for (mut external_force, mut drag, velocity) in &mut bodies {
+        let relative = wind - velocity.linvel;
+        let force = drag.coefficient * relative * relative.length();
+
+        external_force.force += force - drag.applied;
+        drag.applied = force;
+    }
+}
+
+/// System that spawns and despawns precipitation particles, so their amount follows the precipitation rate.
+fn update_precipitation_count(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    assets: Option<Res<PrecipitationAssets>>,
+    particles: Query<(Entity, &PrecipitationParticle)>,
+) {
+    let Some(assets) = assets else {
+        return;
+    };
+
+    let target = match weather.precipitation {
+        Precipitation::None => 0,
+        _ => ((weather.precipitation_rate * 100.0) as usize).min(MAX_PRECIPITATION_PARTICLES),
+    };
+
+    let mut count = 0;
+
+    for (entity, particle) in &particles {
+        if particle.kind != weather.precipitation || count >= target {
+            commands.entity(entity).despawn();
+        } else {
+            count += 1;
+        }
+    }
+
+    let (mesh, material) = match weather.precipitation {
+        Precipitation::None => return,
+        Precipitation::Rain => (&assets.rain_mesh, &assets.rain_material),
+        Precipitation::Snow => (&assets.snow_mesh, &assets.snow_material),
+    };
+
+    for _ in count..target {
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                // particles are placed around the camera on the first move
+                transform: Transform::from_xyz(0.0, -1000.0, 0.0),
+                ..default()
+            },
+            PrecipitationParticle {
+                kind: weather.precipitation,
+            },
+        ));
+    }
+}
+
+/// System that moves precipitation particles with gravity and wind.
+///
+/// Particle that falls below the camera area is placed back at the top of it.
+fn move_precipitation(
+    time: Res<Time>,
+    weather: Res<Weather>,
+    mut rng: ResMut<PrecipitationRng>,
+    cameras: Query<&GlobalTransform, (With<Camera3d>, Without<GeofenceMapCamera>)>,
+    mut particles: Query<(&mut Transform, &PrecipitationParticle)>,
+) {
+    let Some(camera) = cameras.iter().next() else {
+        return;
+    };
+    let center = camera.translation();
+
+    let wind = weather.wind_at(time.elapsed_seconds());
+    let delta_seconds = time.delta_seconds();
+
+    for (mut transform, particle) in &mut particles {
+        let fall_speed = match particle.kind {
+            Precipitation::Snow => 1.0,
+            _ => 9.0,
+        };
+
+        let velocity = Vec3::new(wind.x, -fall_speed, wind.z);
+        transform.translation += velocity * delta_seconds;
+
+        let offset = transform.translation - center;
+        let is_outside = offset.y < -PRECIPITATION_HEIGHT || offset.xz().length() > PRECIPITATION_RADIUS;
+
+        if is_outside {
+            let angle = rng.next() * TAU;
+            let radius = rng.next().sqrt() * PRECIPITATION_RADIUS;
+            let height = rng.next() * PRECIPITATION_HEIGHT;
+
+            transform.translation = center + Vec3::new(angle.cos() * radius, height, angle.sin() * radius);
+        }
+
+        // raindrops are stretched along their velocity
+        if particle.kind == Precipitation::Rain {
+            transform.rotation = Quat::from_rotation_arc(Vec3::Y, -velocity.normalize());
+        }
+    }
+}
@@ -1,16 +1,23 @@
 use bevy::{ pbr::ExtendedMaterial, prelude::*, render::view::RenderLayers};
-use bevy_rapier3d::prelude::*;
 
-use crate::materials::{Thermal, ThermalMaterialExtension};
+use crate::materials::{SolarHeating, Temperature, Thermal, ThermalMaterialExtension};
+
+/// Procedural generator of urban and rural environments.
+pub mod generator;
+
+use generator::{generate_environment, EnvironmentSettings};
 
 /// Plugin responsible for World.
+/// 
+/// Generates the environment from `EnvironmentSettings` on top of the terrain.
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Startup, (spawn_light, spawn_floor, spawn_cubes))
-            .add_systems(Update, rotate);
+            .init_resource::<EnvironmentSettings>()
+            .add_systems(Startup, spawn_cubes)
+            .add_systems(Update, (rotate, generate_environment));
     }
 }
 
@@ -48,12 +55,15 @@ fn spawn_cubes(
                     temperature: 15.0,
                     intensity: 1.0,
                     is_infrared_mode_active: 0,
+                    ..default()
                 },
             }),
             transform: cube1_position,
             ..default()
         },
         Thermal,
+        Temperature(15.0),
+        SolarHeating::default(),
         Rotates,
         thermal_render_layer,
     ));
@@ -79,53 +89,6 @@ fn spawn_cubes(
     ));
 }
 
-/// Spawns light.
-/// 
-/// It's important to mention, that light should be rendered in all layers. Use ` RenderLayers::all()`.
-fn spawn_light(
-    mut commands: Commands,
-) {
-    let light = (
-        PointLightBundle {
-            point_light: PointLight {
-                shadows_enabled: true,
-                intensity: 150_000_000.,
-                range: 100.0,
-                ..default()
-            },
-            transform: Transform::from_xyz(0.0, 75.0, 0.0),
-            ..default()
-        },
-        Name::new("Light"),
-        RenderLayers::all(),
-    );
-
-    commands.spawn(light);
-}
-
-/// Spawns floor.
-/// 
-/// Floor has a collider, so it should interact with physical objects.
-fn spawn_floor(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let floor_dimensions = Vec3::new(100.0, 1.0, 100.0);
-    let floor_position = Vec3::new(0.0, -1.0, 0.0);
-    let floor_name = "Floor";
-
-    commands
-        .spawn(Collider::cuboid(floor_dimensions.x / 2.0, floor_dimensions.y / 2.0, floor_dimensions.z / 2.0))
-        .insert(PbrBundle {
-            mesh: meshes.add(Cuboid::from_size(floor_dimensions)),
-            material: materials.add(Color::LIME_GREEN),
-            transform: Transform::from_translation(floor_position),
-            ..default()
-        })
-        .insert(Name::new(floor_name));
-}
-
 /// Describes the rotation of an entity.
 fn rotate(
     time: Res<Time>, 
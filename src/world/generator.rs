@@ -0,0 +1,665 @@
+use bevy::{prelude::*, render::view::RenderLayers};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    materials::{SolarHeating, Temperature, Thermal, ThermalMaterial, ThermalMaterialExtension},
+    sky::{SkyLighting, TimeOfDay},
+    terrain::Terrain,
+};
+
+/// Describes the generated environment.
+///
+/// Roads form a grid of `block_size` meters within `radius` meters around the origin.
+/// Blocks closer than `urban_radius` meters are the town with buildings, the rest is the countryside
+/// with trees, fences and power lines. Nothing is placed within `clearing_radius` meters around the origin.
+///
+/// Densities are:
+/// - `building_density` is the probability that a lot of the town block is built up;
+/// - `tree_density` is how many trees grow on a square meter of the countryside;
+/// - `fence_density` is the probability that a countryside road side has a fence along the block;
+/// - `power_line_density` is the probability that a road has a power line;
+/// - `vehicle_density` is how many vehicles are on 100 meters of a road,
+///   `running_vehicles` of them have their engines running.
+///
+/// The same `seed` always gives the same environment. Changing the resource regenerates it.
+#[derive(Resource, Debug, Clone)]
+pub struct EnvironmentSettings {
+    pub seed: u64,
+    pub radius: f32,
+    pub clearing_radius: f32,
+    pub urban_radius: f32,
+    pub block_size: f32,
+    pub road_width: f32,
+    pub building_density: f32,
+    pub tree_density: f32,
+    pub fence_density: f32,
+    pub power_line_density: f32,
+    pub vehicle_density: f32,
+    pub running_vehicles: f32,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            radius: 250.0,
+            clearing_radius: 20.0,
+            urban_radius: 110.0,
+            block_size: 60.0,
+            road_width: 6.0,
+            building_density: 0.7,
+            tree_density: 0.003,
+            fence_density: 0.3,
+            power_line_density: 0.5,
+            vehicle_density: 0.5,
+            running_vehicles: 0.3,
+        }
+    }
+}
+
+impl EnvironmentSettings {
+    /// Returns distance between the parallel roads.
+    pub fn road_spacing(&self) -> f32 {
+        self.block_size.max(self.road_width * 2.0)
+    }
+
+    /// Returns intersection of the roads nearest to the given point.
+    pub fn nearest_intersection(&self, position: Vec2) -> Vec2 {
+        let spacing = self.road_spacing();
+        ((position / spacing - 0.5).round() + 0.5) * spacing
+    }
+}
+
+/// Describes an entity created by the environment generator.
+///
+/// Only the root entities of the objects have it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratedObject {
+    Road,
+    Building,
+    Tree,
+    Fence,
+    PowerLine,
+    Vehicle,
+}
+
+/// Random number generator of the environment.
+pub(crate) struct GeneratorRng(u64);
+
+impl GeneratorRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        // splitmix64, so close seeds give different environments
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state ^= state >> 31;
+
+        Self(state.max(1))
+    }
+
+    /// Returns random number in [0, 1).
+    pub(crate) fn next(&mut self) -> f32 {
+        // xorshift64
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns random number in [min, max).
+    pub(crate) fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+    /// Returns `true` with the given probability.
+    pub(crate) fn chance(&mut self, probability: f32) -> bool {
+        self.next() < probability
+    }
+}
+
+/// Road segments are this long, so roads follow the terrain.
+const ROAD_SEGMENT_LENGTH: f32 = 10.0;
+/// Distance between power line poles.
+const POWER_LINE_SPAN: f32 = 40.0;
+
+/// Spawns objects of the environment.
+pub struct EnvironmentBuilder<'a, 'w, 's> {
+    pub commands: &'a mut Commands<'w, 's>,
+    pub meshes: &'a mut Assets<Mesh>,
+    pub materials: &'a mut Assets<StandardMaterial>,
+    pub thermal_materials: &'a mut Assets<ThermalMaterial>,
+    pub settings: &'a EnvironmentSettings,
+    pub terrain: Option<&'a Terrain>,
+    pub air_temperature: f32,
+    rng: GeneratorRng,
+}
+
+impl<'a, 'w, 's> EnvironmentBuilder<'a, 'w, 's> {
+    pub fn new(
+        commands: &'a mut Commands<'w, 's>,
+        meshes: &'a mut Assets<Mesh>,
+        materials: &'a mut Assets<StandardMaterial>,
+        thermal_materials: &'a mut Assets<ThermalMaterial>,
+        settings: &'a EnvironmentSettings,
+        terrain: Option<&'a Terrain>,
+        air_temperature: f32,
+    ) -> Self {
+        Self {
+            commands,
+            meshes,
+            materials,
+            thermal_materials,
+            settings,
+            terrain,
+            air_temperature,
+            rng: GeneratorRng::new(settings.seed),
+        }
+    }
+
+    /// Generates the whole environment.
+    pub fn build(&mut self) {
+        let roads = self.road_lines();
+
+        for road in &roads {
+            self.spawn_road(road);
+        }
+
+        self.spawn_buildings();
+        self.spawn_trees();
+
+        for road in &roads {
+            self.spawn_fences(road);
+            self.spawn_power_line(road);
+            self.spawn_vehicles(road);
+        }
+    }
+
+    /// Returns roads as pairs of their ends.
+    ///
+    /// Roads go between the blocks, so the origin is in the middle of a block.
+    fn road_lines(&self) -> Vec<(Vec2, Vec2)> {
+        let radius = self.settings.radius;
+        let block_size = self.settings.road_spacing();
+        let count = (radius / block_size).ceil() as i32;
+
+        let mut roads = Vec::new();
+
+        for index in -count..count {
+            let offset = (index as f32 + 0.5) * block_size;
+
+            if offset.abs() >= radius {
+                continue;
+            }
+
+            let half_length = (radius * radius - offset * offset).sqrt();
+
+            roads.push((Vec2::new(offset, -half_length), Vec2::new(offset, half_length)));
+            roads.push((Vec2::new(-half_length, offset), Vec2::new(half_length, offset)));
+        }
+
+        roads
+    }
+
+    /// Returns terrain height at the given point.
+    fn ground(&self, position: Vec2) -> f32 {
+        self.terrain.map_or(0.0, |terrain| terrain.height_at(position))
+    }
+
+    /// Returns position on the ground.
+    fn on_ground(&self, position: Vec2) -> Vec3 {
+        Vec3::new(position.x, self.ground(position), position.y)
+    }
+
+    /// Whether the point is far enough from the launch pad.
+    fn is_clear(&self, position: Vec2, margin: f32) -> bool {
+        position.length() > self.settings.clearing_radius + margin
+    }
+
+    /// Whether the point is closer to a road than `margin` meters from its side.
+    fn is_on_road(&self, position: Vec2, margin: f32) -> bool {
+        let distance = (self.settings.nearest_intersection(position) - position).abs();
+        let reach = self.settings.road_width / 2.0 + margin;
+
+        distance.x < reach || distance.y < reach
+    }
+
+    /// Returns the road split into segments, which follow the terrain.
+    fn segments(&self, (start, end): &(Vec2, Vec2), length: f32) -> Vec<(Vec2, Vec2)> {
+        let count = (start.distance(*end) / length).ceil().max(1.0) as usize;
+
+        (0..count)
+            .map(|index| (
+                start.lerp(*end, index as f32 / count as f32),
+                start.lerp(*end, (index + 1) as f32 / count as f32),
+            ))
+            .collect()
+    }
+
+    /// Returns transform of a box, which lies on the ground between two points.
+    fn between(&self, start: Vec2, end: Vec2, lift: f32) -> (Transform, f32) {
+        let start = self.on_ground(start);
+        let end = self.on_ground(end);
+        let direction = (end - start).normalize_or_zero();
+
+        let transform = Transform::from_translation((start + end) / 2.0 + Vec3::Y * lift)
+            .looking_to(direction, Vec3::Y);
+
+        (transform, start.distance(end))
+    }
+
+    /// Returns thermal material for objects with the given heating.
+    ///
+    /// Objects with the same heating have the same temperature, so they can share the material.
+    fn thermal_material(&mut self, color: Color, emissive: Color, heating: &SolarHeating) -> Handle<ThermalMaterial> {
+        self.thermal_materials.add(ThermalMaterial {
+            base: StandardMaterial {
+                base_color: color,
+                emissive,
+                ..default()
+            },
+            extension: ThermalMaterialExtension {
+                temperature: self.air_temperature + heating.internal_heat,
+                intensity: 1.0,
+                ..default()
+            },
+        })
+    }
+
+    /// Spawns thermal mesh, which starts at the air temperature plus its internal heat.
+    fn spawn_thermal(
+        &mut self,
+        mesh: Mesh,
+        transform: Transform,
+        material: Handle<ThermalMaterial>,
+        heating: SolarHeating,
+    ) -> Entity {
+        let mesh = self.meshes.add(mesh);
+
+        self.commands
+            .spawn((
+                MaterialMeshBundle {
+                    mesh,
+                    material,
+                    transform,
+                    ..default()
+                },
+                Thermal,
+                Temperature(self.air_temperature + heating.internal_heat),
+                heating,
+                RenderLayers::layer(1),
+            ))
+            .id()
+    }
+
+    /// Spawns road segments. Asphalt gets hot in the sun.
+    fn spawn_road(&mut self, road: &(Vec2, Vec2)) {
+        let road_width = self.settings.road_width;
+        let heating = || SolarHeating { solar_gain: 25.0, time_constant: 2400.0, ..default() };
+
+        // all of the segments have the same temperature, so they share the material
+        let material = self.thermal_material(Color::rgb(0.15, 0.15, 0.16), Color::BLACK, &heating());
+
+        for (start, end) in self.segments(road, ROAD_SEGMENT_LENGTH) {
+            let (transform, length) = self.between(start, end, 0.05);
+            let size = Vec3::new(road_width, 0.2, length + 0.5);
+
+            let entity = self.spawn_thermal(Cuboid::from_size(size).into(), transform, material.clone(), heating());
+
+            self.commands.entity(entity).insert((
+                Collider::cuboid(size.x / 2.0, size.y / 2.0, size.z / 2.0),
+                GeneratedObject::Road,
+                Name::new("Road"),
+            ));
+        }
+    }
+
+    /// Spawns buildings in the town blocks. Buildings are heated and some of their windows are lit.
+    fn spawn_buildings(&mut self) {
+        let settings = self.settings.clone();
+        let block_size = settings.road_spacing();
+        let count = (settings.urban_radius / block_size).ceil() as i32 + 1;
+
+        // every block has 2 by 2 lots
+        let lot_size = (block_size - settings.road_width) / 2.0;
+
+        let window_heating = || SolarHeating { solar_gain: 5.0, internal_heat: 12.0, time_constant: 1800.0 };
+        let window = self.thermal_material(Color::rgb(0.9, 0.8, 0.5), Color::rgb(0.6, 0.5, 0.25), &window_heating());
+
+        for block_x in -count..=count {
+            for block_z in -count..=count {
+                for lot in 0..4 {
+                    let block_center = Vec2::new(block_x as f32, block_z as f32) * block_size;
+                    let lot_offset = Vec2::new((lot % 2) as f32 - 0.5, (lot / 2) as f32 - 0.5) * lot_size;
+                    let center = block_center + lot_offset;
+
+                    let distance = center.length();
+                    let is_built_up = self.rng.chance(settings.building_density);
+
+                    if distance > settings.urban_radius || !is_built_up || !self.is_clear(center, lot_size) {
+                        continue;
+                    }
+
+                    // buildings are taller closer to the center of the town
+                    let max_floors = 2.0 + 8.0 * (1.0 - distance / settings.urban_radius.max(f32::EPSILON));
+                    let floors = self.rng.range(1.0, max_floors).round().max(1.0);
+                    let footprint = Vec2::new(
+                        self.rng.range(lot_size * 0.4, lot_size - 2.0),
+                        self.rng.range(lot_size * 0.4, lot_size - 2.0),
+                    );
+
+                    let corners = [
+                        center - footprint / 2.0,
+                        center + footprint / 2.0,
+                        center + Vec2::new(footprint.x, -footprint.y) / 2.0,
+                        center + Vec2::new(-footprint.x, footprint.y) / 2.0,
+                        center,
+                    ];
+                    let heights = corners.map(|corner| self.ground(corner));
+                    let lowest = heights.into_iter().fold(f32::INFINITY, f32::min);
+                    let highest = heights.into_iter().fold(f32::NEG_INFINITY, f32::max);
+
+                    // building goes into the ground on the slopes
+                    let height = floors * 3.0 + (highest - lowest);
+                    let size = Vec3::new(footprint.x, height, footprint.y);
+                    let transform = Transform::from_xyz(center.x, lowest + height / 2.0, center.y);
+
+                    let shade = self.rng.range(0.45, 0.8);
+                    let heating = SolarHeating { solar_gain: 8.0, internal_heat: 4.0, time_constant: 7200.0 };
+                    let material = self.thermal_material(Color::rgb(shade, shade * 0.95, shade * 0.9), Color::BLACK, &heating);
+
+                    let building = self.spawn_thermal(Cuboid::from_size(size).into(), transform, material, heating);
+
+                    self.commands.entity(building).insert((
+                        Collider::cuboid(size.x / 2.0, size.y / 2.0, size.z / 2.0),
+                        GeneratedObject::Building,
+                        Name::new("Building"),
+                    ));
+
+                    // window strips of the lit floors on every facade
+                    for floor in 0..floors as u32 {
+                        let floor_height = highest - lowest + floor as f32 * 3.0 + 1.5 - height / 2.0;
+
+                        for facade in 0..4 {
+                            if !self.rng.chance(0.5) {
+                                continue;
+                            }
+
+                            let (offset, strip) = match facade {
+                                0 => (Vec3::new(0.0, floor_height, size.z / 2.0), Vec3::new(size.x * 0.8, 1.2, 0.1)),
+                                1 => (Vec3::new(0.0, floor_height, -size.z / 2.0), Vec3::new(size.x * 0.8, 1.2, 0.1)),
+                                2 => (Vec3::new(size.x / 2.0, floor_height, 0.0), Vec3::new(0.1, 1.2, size.z * 0.8)),
+                                _ => (Vec3::new(-size.x / 2.0, floor_height, 0.0), Vec3::new(0.1, 1.2, size.z * 0.8)),
+                            };
+
+                            let strip = self.spawn_thermal(
+                                Cuboid::from_size(strip).into(),
+                                Transform::from_translation(offset),
+                                window.clone(),
+                                window_heating(),
+                            );
+
+                            self.commands.entity(building).add_child(strip);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Spawns trees in the countryside. Leaves are cooler than the air.
+    fn spawn_trees(&mut self) {
+        let settings = self.settings.clone();
+        let area = std::f32::consts::PI * (settings.radius.powi(2) - settings.urban_radius.min(settings.radius).powi(2));
+        let count = (area * settings.tree_density).round() as u32;
+
+        let crown_heating = || SolarHeating { solar_gain: 4.0, internal_heat: -2.0, time_constant: 900.0 };
+        let crown = self.thermal_material(Color::rgb(0.2, 0.42, 0.15), Color::BLACK, &crown_heating());
+        let trunk_material = self.materials.add(Color::rgb(0.35, 0.25, 0.15));
+
+        for _ in 0..count {
+            let angle = self.rng.range(0.0, std::f32::consts::TAU);
+            let distance = self.rng.range(settings.urban_radius, settings.radius);
+            let position = Vec2::from_angle(angle) * distance;
+
+            let trunk_height = self.rng.range(2.0, 4.0);
+            let crown_radius = self.rng.range(1.5, 3.0);
+
+            if !self.is_clear(position, crown_radius) || self.is_on_road(position, crown_radius) {
+                continue;
+            }
+
+            let trunk = self.commands
+                .spawn(PbrBundle {
+                    mesh: self.meshes.add(Cylinder::new(0.2, trunk_height)),
+                    material: trunk_material.clone(),
+                    transform: Transform::from_xyz(0.0, trunk_height / 2.0, 0.0),
+                    ..default()
+                })
+                .id();
+
+            let crown = self.spawn_thermal(
+                Sphere::new(crown_radius).into(),
+                Transform::from_xyz(0.0, trunk_height + crown_radius * 0.8, 0.0),
+                crown.clone(),
+                crown_heating(),
+            );
+
+            let collider = Collider::compound(vec![
+                (Vec3::Y * trunk_height / 2.0, Quat::IDENTITY, Collider::cylinder(trunk_height / 2.0, 0.2)),
+                (Vec3::Y * (trunk_height + crown_radius * 0.8), Quat::IDENTITY, Collider::ball(crown_radius)),
+            ]);
+
+            self.commands
+                .spawn((
+                    SpatialBundle::from_transform(Transform::from_translation(self.on_ground(position))),
+                    collider,
+                    GeneratedObject::Tree,
+                    Name::new("Tree"),
+                ))
+                .push_children(&[trunk, crown]);
+        }
+    }
+
+    /// Spawns fences along the countryside road sides.
+    fn spawn_fences(&mut self, road: &(Vec2, Vec2)) {
+        let settings = self.settings.clone();
+        let block_size = settings.road_spacing();
+        let direction = (road.1 - road.0).normalize_or_zero();
+        let side = direction.perp() * (settings.road_width / 2.0 + 2.0);
+
+        let material = self.materials.add(Color::rgb(0.45, 0.35, 0.25));
+
+        for (block_start, block_end) in self.segments(road, block_size) {
+            for side in [side, -side] {
+                let middle = (block_start + block_end) / 2.0 + side;
+
+                if middle.length() < settings.urban_radius || !self.rng.chance(settings.fence_density) {
+                    continue;
+                }
+
+                let span = (block_start + side, block_end + side);
+
+                for (start, end) in self.segments(&span, ROAD_SEGMENT_LENGTH) {
+                    if !self.is_clear(start, 0.0) || !self.is_clear(end, 0.0) {
+                        continue;
+                    }
+
+                    let (transform, length) = self.between(start, end, 0.6);
+                    let size = Vec3::new(0.1, 1.2, length);
+
+                    self.commands.spawn((
+                        PbrBundle {
+                            mesh: self.meshes.add(Cuboid::from_size(size)),
+                            material: material.clone(),
+                            transform,
+                            ..default()
+                        },
+                        Collider::cuboid(size.x / 2.0, size.y / 2.0, size.z / 2.0),
+                        GeneratedObject::Fence,
+                        Name::new("Fence"),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Spawns poles and wires of a power line along the road. Wires are warmed by the current.
+    fn spawn_power_line(&mut self, road: &(Vec2, Vec2)) {
+        if !self.rng.chance(self.settings.power_line_density) {
+            return;
+        }
+
+        let direction = (road.1 - road.0).normalize_or_zero();
+        let side = direction.perp() * (self.settings.road_width / 2.0 + 3.0);
+        let pole_height = 9.0;
+
+        let pole_material = self.materials.add(Color::rgb(0.3, 0.25, 0.2));
+        let wire_heating = || SolarHeating { solar_gain: 10.0, internal_heat: 3.0, time_constant: 300.0 };
+        let wire = self.thermal_material(Color::rgb(0.1, 0.1, 0.1), Color::BLACK, &wire_heating());
+
+        let span = (road.0 + side, road.1 + side);
+        let poles: Vec<Vec2> = self.segments(&span, POWER_LINE_SPAN)
+            .into_iter()
+            .map(|(start, _)| start)
+            .chain(std::iter::once(span.1))
+            .filter(|pole| self.is_clear(*pole, 0.0))
+            .collect();
+
+        for pole in &poles {
+            self.commands.spawn((
+                PbrBundle {
+                    mesh: self.meshes.add(Cylinder::new(0.15, pole_height)),
+                    material: pole_material.clone(),
+                    transform: Transform::from_translation(self.on_ground(*pole) + Vec3::Y * pole_height / 2.0),
+                    ..default()
+                },
+                Collider::cylinder(pole_height / 2.0, 0.15),
+                GeneratedObject::PowerLine,
+                Name::new("Power line pole"),
+            ));
+        }
+
+        for pair in poles.windows(2) {
+            // poles around the launch pad are skipped, there are no wires over it
+            if pair[0].distance(pair[1]) > POWER_LINE_SPAN * 1.5 {
+                continue;
+            }
+
+            let start = self.on_ground(pair[0]) + Vec3::Y * (pole_height - 0.3);
+            let end = self.on_ground(pair[1]) + Vec3::Y * (pole_height - 0.3);
+            let length = start.distance(end);
+
+            let transform = Transform::from_translation((start + end) / 2.0)
+                .with_rotation(Quat::from_rotation_arc(Vec3::Y, (end - start).normalize_or_zero()));
+
+            let entity = self.spawn_thermal(
+                Cylinder::new(0.03, length).into(),
+                transform,
+                wire.clone(),
+                wire_heating(),
+            );
+
+            self.commands.entity(entity).insert((
+                Collider::capsule_y(length / 2.0, 0.03),
+                GeneratedObject::PowerLine,
+                Name::new("Power line wire"),
+            ));
+        }
+    }
+
+    /// Spawns vehicles on the road. Running engines are hot, parked vehicles only warm up in the sun.
+    fn spawn_vehicles(&mut self, road: &(Vec2, Vec2)) {
+        let settings = self.settings.clone();
+        let probability = settings.vehicle_density * ROAD_SEGMENT_LENGTH / 100.0;
+        let direction = (road.1 - road.0).normalize_or_zero();
+        let colors = [Color::WHITE, Color::BLACK, Color::rgb(0.6, 0.05, 0.05), Color::rgb(0.1, 0.2, 0.5)];
+
+        for (start, end) in self.segments(road, ROAD_SEGMENT_LENGTH) {
+            if !self.rng.chance(probability) {
+                continue;
+            }
+
+            // vehicles keep to the right
+            let lane = if self.rng.chance(0.5) { 1.0 } else { -1.0 };
+            let offset = direction.perp() * lane * settings.road_width / 4.0;
+            let (start, end) = if lane > 0.0 { (start, end) } else { (end, start) };
+
+            if !self.is_clear(start + offset, 2.0) || !self.is_clear(end + offset, 2.0) {
+                continue;
+            }
+
+            let is_running = self.rng.chance(settings.running_vehicles);
+            let color = colors[(self.rng.next() * colors.len() as f32) as usize % colors.len()];
+
+            let heating = if is_running {
+                SolarHeating { solar_gain: 20.0, internal_heat: 40.0, time_constant: 600.0 }
+            } else {
+                SolarHeating { solar_gain: 20.0, internal_heat: 0.0, time_constant: 1800.0 }
+            };
+
+            let size = Vec3::new(1.8, 1.0, 4.2);
+            let (transform, _) = self.between(start + offset, end + offset, 0.1 + size.y / 2.0 + 0.3);
+
+            let material = self.thermal_material(color, Color::BLACK, &heating);
+            let body = self.spawn_thermal(Cuboid::from_size(size).into(), transform, material, heating);
+
+            let cabin = self.commands
+                .spawn(PbrBundle {
+                    mesh: self.meshes.add(Cuboid::new(1.6, 0.7, 2.2)),
+                    material: self.materials.add(color),
+                    transform: Transform::from_xyz(0.0, 0.85, 0.3),
+                    ..default()
+                })
+                .id();
+
+            self.commands
+                .entity(body)
+                .insert((
+                    Collider::cuboid(size.x / 2.0, size.y / 2.0, size.z / 2.0),
+                    GeneratedObject::Vehicle,
+                    Name::new(if is_running { "Running vehicle" } else { "Parked vehicle" }),
+                ))
+                .add_child(cabin);
+        }
+    }
+}
+
+/// System that regenerates the environment when `EnvironmentSettings` or `Terrain` change.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_environment(
+    mut commands: Commands,
+    settings: Res<EnvironmentSettings>,
+    terrain: Option<Res<Terrain>>,
+    sky: Option<Res<SkyLighting>>,
+    time_of_day: Option<Res<TimeOfDay>>,
+    generated: Query<Entity, With<GeneratedObject>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut thermal_materials: ResMut<Assets<ThermalMaterial>>,
+) {
+    let is_terrain_changed = terrain.as_ref().is_some_and(|terrain| terrain.is_changed());
+
+    if !settings.is_changed() && !is_terrain_changed {
+        return;
+    }
+
+    for entity in &generated {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let air_temperature = match (sky, time_of_day) {
+        (Some(sky), Some(time_of_day)) => sky.air_temperature_at(time_of_day.hours),
+        _ => 15.0,
+    };
+
+    EnvironmentBuilder::new(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &mut thermal_materials,
+        &settings,
+        terrain.as_deref(),
+        air_temperature,
+    ).build();
+}